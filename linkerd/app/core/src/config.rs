@@ -2,7 +2,7 @@ pub use crate::exp_backoff::ExponentialBackoff;
 use crate::{
     proxy::http::{self, h1, h2},
     svc::{stack::CloneParam, Param},
-    transport::{Keepalive, ListenAddr},
+    transport::{proxy_protocol, Keepalive, ListenAddr, UnixListenPath},
 };
 use std::time::Duration;
 
@@ -11,6 +11,8 @@ pub struct ServerConfig {
     pub addr: ListenAddr,
     pub keepalive: Keepalive,
     pub h2_settings: h2::Settings,
+    pub proxy_protocol: Option<proxy_protocol::Config>,
+    pub unix_listen_path: Option<UnixListenPath>,
 }
 
 #[derive(Clone, Debug)]
@@ -54,3 +56,15 @@ impl Param<Keepalive> for ServerConfig {
         self.keepalive
     }
 }
+
+impl Param<Option<proxy_protocol::Config>> for ServerConfig {
+    fn param(&self) -> Option<proxy_protocol::Config> {
+        self.proxy_protocol.clone()
+    }
+}
+
+impl Param<Option<UnixListenPath>> for ServerConfig {
+    fn param(&self) -> Option<UnixListenPath> {
+        self.unix_listen_path.clone()
+    }
+}
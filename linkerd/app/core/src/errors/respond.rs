@@ -3,11 +3,13 @@ use http::header::{HeaderValue, LOCATION};
 use linkerd_error::{Error, Result};
 use linkerd_error_respond as respond;
 pub use linkerd_proxy_http::{ClientHandle, HasH2Reason};
+use linkerd_server_policy::http::filter::ModifyHeader;
 use linkerd_stack::ExtractParam;
 use pin_project::pin_project;
 use std::{
     borrow::Cow,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 use tracing::{debug, info_span, warn};
@@ -37,6 +39,7 @@ pub struct SyntheticHttpResponse {
     close_connection: bool,
     message: Cow<'static, str>,
     location: Option<HeaderValue>,
+    modify_headers: Vec<Arc<ModifyHeader>>,
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -101,6 +104,7 @@ impl SyntheticHttpResponse {
             grpc_status: tonic::Code::Internal,
             message: msg.into(),
             location: None,
+            modify_headers: Vec::new(),
         }
     }
 
@@ -111,6 +115,7 @@ impl SyntheticHttpResponse {
             grpc_status: tonic::Code::Unavailable,
             message: Cow::Owned(msg.to_string()),
             location: None,
+            modify_headers: Vec::new(),
         }
     }
 
@@ -121,6 +126,18 @@ impl SyntheticHttpResponse {
             grpc_status: tonic::Code::Unavailable,
             message: Cow::Owned(msg.to_string()),
             location: None,
+            modify_headers: Vec::new(),
+        }
+    }
+
+    pub fn deadline_exceeded(msg: impl ToString) -> Self {
+        Self {
+            http_status: http::StatusCode::GATEWAY_TIMEOUT,
+            grpc_status: tonic::Code::DeadlineExceeded,
+            close_connection: false,
+            message: Cow::Owned(msg.to_string()),
+            location: None,
+            modify_headers: Vec::new(),
         }
     }
 
@@ -131,6 +148,7 @@ impl SyntheticHttpResponse {
             close_connection: false,
             message: Cow::Owned(msg.to_string()),
             location: None,
+            modify_headers: Vec::new(),
         }
     }
 
@@ -141,6 +159,18 @@ impl SyntheticHttpResponse {
             close_connection: false,
             message: Cow::Owned(msg.to_string()),
             location: None,
+            modify_headers: Vec::new(),
+        }
+    }
+
+    pub fn rate_limited(msg: impl ToString) -> Self {
+        Self {
+            http_status: http::StatusCode::TOO_MANY_REQUESTS,
+            grpc_status: tonic::Code::ResourceExhausted,
+            close_connection: false,
+            message: Cow::Owned(msg.to_string()),
+            location: None,
+            modify_headers: Vec::new(),
         }
     }
 
@@ -151,6 +181,7 @@ impl SyntheticHttpResponse {
             close_connection: true,
             message: Cow::Owned(msg.to_string()),
             location: None,
+            modify_headers: Vec::new(),
         }
     }
 
@@ -161,6 +192,7 @@ impl SyntheticHttpResponse {
             close_connection: false,
             message: Cow::Owned(msg.to_string()),
             location: None,
+            modify_headers: Vec::new(),
         }
     }
 
@@ -174,6 +206,7 @@ impl SyntheticHttpResponse {
                 HeaderValue::try_from(location.to_string())
                     .expect("location must be a valid header value"),
             ),
+            modify_headers: Vec::new(),
         }
     }
 
@@ -184,9 +217,20 @@ impl SyntheticHttpResponse {
             grpc_status: tonic::Code::FailedPrecondition,
             close_connection: false,
             message: message.into(),
+            modify_headers: Vec::new(),
         }
     }
 
+    /// Applies a route's response header modifiers to the synthesized
+    /// response.
+    pub fn with_modified_headers(
+        mut self,
+        modify: impl IntoIterator<Item = Arc<ModifyHeader>>,
+    ) -> Self {
+        self.modify_headers.extend(modify);
+        self
+    }
+
     #[inline]
     fn message(&self) -> HeaderValue {
         match self.message {
@@ -218,8 +262,13 @@ impl SyntheticHttpResponse {
             rsp = rsp.header(L5D_PROXY_CONNECTION, "close");
         }
 
-        rsp.body(B::default())
-            .expect("error response must be valid")
+        let mut rsp = rsp
+            .body(B::default())
+            .expect("error response must be valid");
+        for modify in self.modify_headers.iter() {
+            modify.apply(rsp.headers_mut());
+        }
+        rsp
     }
 
     #[inline]
@@ -261,8 +310,13 @@ impl SyntheticHttpResponse {
             rsp = rsp.header(LOCATION, loc);
         }
 
-        rsp.body(B::default())
-            .expect("error response must be valid")
+        let mut rsp = rsp
+            .body(B::default())
+            .expect("error response must be valid");
+        for modify in self.modify_headers.iter() {
+            modify.apply(rsp.headers_mut());
+        }
+        rsp
     }
 }
 
@@ -213,4 +213,26 @@ mod tests {
             srv_group=\"policy.linkerd.io\",srv_kind=\"server\",srv_name=\"testserver\""
         );
     }
+
+    #[test]
+    fn server_labels_spiffe() {
+        use linkerd_server_policy::Meta;
+        use std::sync::Arc;
+
+        let labels = ServerLabels::inbound(
+            tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                client_id: Some("spiffe://example.org/ns/default/sa/web".parse().unwrap()),
+                negotiated_protocol: None,
+            }),
+            ([192, 0, 2, 4], 40000).into(),
+            PolicyServerLabel(Meta::new_default("default")),
+        );
+        assert_eq!(
+            labels.to_string(),
+            "direction=\"inbound\",peer=\"src\",\
+            target_addr=\"192.0.2.4:40000\",target_ip=\"192.0.2.4\",target_port=\"40000\",\
+            tls=\"true\",client_id=\"spiffe://example.org/ns/default/sa/web\",\
+            srv_group=\"\",srv_kind=\"default\",srv_name=\"default\""
+        );
+    }
 }
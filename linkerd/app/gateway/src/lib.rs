@@ -255,7 +255,7 @@ impl Param<http::normalize_uri::DefaultAuthority> for HttpTransportHeader {
 
 impl Param<Option<identity::Name>> for HttpTransportHeader {
     fn param(&self) -> Option<identity::Name> {
-        Some(self.client.client_id.clone().0)
+        self.client.client_id.dns_name().cloned()
     }
 }
 
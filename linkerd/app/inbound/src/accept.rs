@@ -140,6 +140,7 @@ mod tests {
                     kind: "server".into(),
                     name: "testsrv".into(),
                 }),
+                audit: false,
             },
             None,
         );
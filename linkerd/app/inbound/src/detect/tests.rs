@@ -35,6 +35,7 @@ fn allow(protocol: Protocol) -> AllowPolicy {
                 kind: "server".into(),
                 name: "testsrv".into(),
             }),
+            audit: false,
         },
     );
     allow
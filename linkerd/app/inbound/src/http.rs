@@ -1,9 +1,13 @@
+mod mirror;
 mod router;
 mod server;
 mod set_identity_header;
 #[cfg(test)]
 mod tests;
 
+pub use self::mirror::MirrorTarget;
+pub(crate) use self::mirror::Mirrors;
+
 fn trace_labels() -> std::collections::HashMap<String, String> {
     let mut l = std::collections::HashMap::new();
     l.insert("direction".to_string(), "inbound".to_string());
@@ -228,6 +232,7 @@ pub mod fuzz {
                         kind: "server".into(),
                         name: "testsrv".into(),
                     }),
+                    audit: false,
                 },
             );
             policy
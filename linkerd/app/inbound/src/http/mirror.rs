@@ -0,0 +1,352 @@
+use futures::FutureExt;
+use linkerd_app_core::{
+    proxy::http::{self, HttpBody},
+    svc::{self, Param, ServiceExt},
+    Addr, Error, NameAddr,
+};
+use linkerd_http_retry::{replay, ReplayBody};
+use linkerd_server_policy::http::filter::RequestMirror;
+use std::{
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::sync::{oneshot, Semaphore};
+use tracing::{debug, Instrument};
+
+/// Describes a mirror to which copies of inbound requests are sent.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MirrorTarget {
+    pub addr: Addr,
+    pub version: http::Version,
+}
+
+/// Builds `Mirror` services.
+#[derive(Clone, Debug)]
+pub(super) struct NewMirror<N> {
+    inner: N,
+    mirrors: Option<Mirrors>,
+}
+
+/// State shared by all of a stack's mirrors.
+#[derive(Clone, Debug)]
+pub(crate) struct Mirrors {
+    new_mirror: svc::ArcNewHttp<MirrorTarget>,
+    max_buffered_bytes: usize,
+    in_flight: Arc<Semaphore>,
+}
+
+/// Sends a copy of each request that has been marked with a `RequestMirror`
+/// (by route policy) to the mirror's authority, discarding the response.
+///
+/// Mirrored requests are dispatched through the outbound proxy's stack, so
+/// that mirrors are discovered, balanced, and secured with mTLS just like the
+/// application's own outbound traffic. They are sent in the background once
+/// the original request's body has been released, so that the original
+/// request is never delayed by the mirror.
+#[derive(Clone, Debug)]
+pub(super) struct Mirror<S> {
+    version: http::Version,
+    mirrors: Option<Mirrors>,
+    inner: S,
+}
+
+/// Notifies a mirrored request when the original request's body is dropped, at
+/// which point any buffered data may be replayed.
+#[derive(Debug)]
+struct ReleaseBody {
+    inner: ReplayBody<http::BoxBody>,
+    _released: oneshot::Sender<()>,
+}
+
+// === impl MirrorTarget ===
+
+impl Param<Addr> for MirrorTarget {
+    #[inline]
+    fn param(&self) -> Addr {
+        self.addr.clone()
+    }
+}
+
+impl Param<http::Version> for MirrorTarget {
+    #[inline]
+    fn param(&self) -> http::Version {
+        self.version
+    }
+}
+
+// === impl Mirrors ===
+
+impl Mirrors {
+    pub(crate) fn new(
+        new_mirror: svc::ArcNewHttp<MirrorTarget>,
+        max_buffered_bytes: usize,
+        max_in_flight: usize,
+    ) -> Self {
+        Self {
+            new_mirror,
+            max_buffered_bytes,
+            in_flight: Arc::new(Semaphore::new(max_in_flight)),
+        }
+    }
+}
+
+// === impl NewMirror ===
+
+impl<N> NewMirror<N> {
+    /// Mirrors requests through the given mirror stack, if one is configured.
+    /// Otherwise, mirror filters are ignored.
+    pub(super) fn layer(
+        mirrors: Option<Mirrors>,
+    ) -> impl svc::layer::Layer<N, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            mirrors: mirrors.clone(),
+        })
+    }
+}
+
+impl<T, N> svc::NewService<T> for NewMirror<N>
+where
+    T: Param<http::Version>,
+    N: svc::NewService<T>,
+{
+    type Service = Mirror<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        Mirror {
+            version: target.param(),
+            mirrors: self.mirrors.clone(),
+            inner: self.inner.new_service(target),
+        }
+    }
+}
+
+// === impl Mirror ===
+
+impl<S> svc::Service<http::Request<http::BoxBody>> for Mirror<S>
+where
+    S: svc::Service<http::Request<http::BoxBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<http::BoxBody>) -> Self::Future {
+        let mirror = match req.extensions().get::<RequestMirror>() {
+            Some(mirror) => mirror.authority.clone(),
+            None => return self.inner.call(req),
+        };
+
+        let mirrors = match self.mirrors.as_ref() {
+            Some(mirrors) => mirrors,
+            None => {
+                debug!("Mirroring is not configured");
+                return self.inner.call(req);
+            }
+        };
+
+        let addr = {
+            let port = mirror.port_u16().unwrap_or(80);
+            let host = mirror.host().trim_start_matches('[').trim_end_matches(']');
+            match host.parse::<IpAddr>() {
+                Ok(ip) => Addr::Socket(SocketAddr::new(ip, port)),
+                Err(_) => match NameAddr::from_str_and_port(host, port) {
+                    Ok(name) => Addr::Name(name),
+                    Err(error) => {
+                        debug!(%error, %mirror, "Invalid mirror authority");
+                        return self.inner.call(req);
+                    }
+                },
+            }
+        };
+
+        let permit = match mirrors.in_flight.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                debug!("Too many mirrored requests in flight");
+                return self.inner.call(req);
+            }
+        };
+
+        let (head, body) = req.into_parts();
+        let body = match ReplayBody::try_new(body, mirrors.max_buffered_bytes) {
+            Ok(body) => body,
+            Err(body) => {
+                debug!(
+                    size = body.size_hint().lower(),
+                    "Body is too large to mirror"
+                );
+                return self.inner.call(http::Request::from_parts(head, body));
+            }
+        };
+
+        let mut mirror_req = http::Request::new(http::BoxBody::new(body.clone()));
+        *mirror_req.method_mut() = head.method.clone();
+        *mirror_req.version_mut() = head.version;
+        *mirror_req.headers_mut() = head.headers.clone();
+        *mirror_req.uri_mut() = {
+            let mut parts = head.uri.clone().into_parts();
+            if parts.authority.is_some() {
+                parts.authority = Some(mirror.clone());
+            }
+            http::uri::Uri::from_parts(parts).expect("URI must be valid")
+        };
+        if let Ok(host) = http::HeaderValue::from_str(mirror.as_str()) {
+            mirror_req.headers_mut().insert(http::header::HOST, host);
+        }
+
+        let (released_tx, released_rx) = oneshot::channel();
+        let body = http::BoxBody::new(ReleaseBody {
+            inner: body,
+            _released: released_tx,
+        });
+
+        let target = MirrorTarget {
+            addr,
+            version: self.version,
+        };
+        let new_mirror = mirrors.new_mirror.clone();
+        tokio::spawn(
+            async move {
+                // The permit is held until the mirrored request completes.
+                let _permit = permit;
+
+                // The mirror may only read the body once the original request
+                // has released it.
+                let _ = released_rx.await;
+                new_mirror.new_service(target).oneshot(mirror_req).await?;
+                debug!("Mirrored request completed");
+                Ok::<_, Error>(())
+            }
+            .map(|res| {
+                if let Err(error) = res {
+                    debug!(%error, "Mirrored request failed");
+                }
+            })
+            .in_current_span(),
+        );
+
+        self.inner.call(http::Request::from_parts(head, body))
+    }
+}
+
+// === impl ReleaseBody ===
+
+impl HttpBody for ReleaseBody {
+    type Data = replay::Data;
+    type Error = Error;
+
+    #[inline]
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Pin::new(&mut self.inner).poll_data(cx)
+    }
+
+    #[inline]
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::header::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::svc::{layer::Layer, NewService};
+    use tokio::sync::mpsc;
+
+    #[derive(Clone, Debug)]
+    struct Target;
+
+    impl Param<http::Version> for Target {
+        fn param(&self) -> http::Version {
+            http::Version::Http1
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn mirrors_request() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let inner = {
+            let tx = tx.clone();
+            move |_: Target| {
+                let tx = tx.clone();
+                svc::mk(move |req: http::Request<http::BoxBody>| {
+                    let tx = tx.clone();
+                    async move {
+                        let (head, body) = req.into_parts();
+                        let body = hyper::body::to_bytes(body).await?;
+                        tx.send((None, head.uri, body)).unwrap();
+                        Ok::<_, Error>(http::Response::new(http::BoxBody::default()))
+                    }
+                })
+            }
+        };
+        let new_mirror = svc::ArcNewService::new(move |target: MirrorTarget| {
+            let tx = tx.clone();
+            svc::BoxService::new(svc::mk(move |req: http::Request<http::BoxBody>| {
+                let tx = tx.clone();
+                let target = target.clone();
+                async move {
+                    let (head, body) = req.into_parts();
+                    let body = hyper::body::to_bytes(body).await?;
+                    tx.send((Some(target), head.uri, body)).unwrap();
+                    Ok::<_, Error>(http::Response::new(http::BoxBody::default()))
+                }
+            }))
+        });
+        let mirrors = Mirrors::new(new_mirror, 64 * 1024, 100);
+        let svc = NewMirror::layer(Some(mirrors))
+            .layer(inner)
+            .new_service(Target);
+
+        let mut req = http::Request::builder()
+            .uri("http://foo.ns.svc.cluster.local:8080/bar")
+            .body(http::BoxBody::new(hyper::Body::from("hello")))
+            .unwrap();
+        req.extensions_mut().insert(RequestMirror {
+            authority: "mirror.ns.svc.cluster.local:8081".parse().unwrap(),
+            distribution: Default::default(),
+        });
+        svc.oneshot(req).await.expect("request must succeed");
+
+        let (target, uri, body) = rx.recv().await.expect("original request");
+        assert_eq!(target, None);
+        assert_eq!(uri, "http://foo.ns.svc.cluster.local:8080/bar");
+        assert_eq!(body, "hello");
+
+        let (target, uri, body) = rx.recv().await.expect("mirrored request");
+        assert_eq!(
+            target,
+            Some(MirrorTarget {
+                addr: Addr::Name(
+                    NameAddr::from_str_and_port("mirror.ns.svc.cluster.local", 8081).unwrap()
+                ),
+                version: http::Version::Http1,
+            })
+        );
+        assert_eq!(uri, "http://mirror.ns.svc.cluster.local:8081/bar");
+        assert_eq!(body, "hello");
+    }
+}
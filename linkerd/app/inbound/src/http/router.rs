@@ -1,3 +1,4 @@
+use super::mirror::NewMirror;
 use crate::{policy, stack_labels, Inbound};
 use linkerd_app_core::{
     classify, errors, http_tracing, metrics,
@@ -234,8 +235,11 @@ impl<C> Inbound<C> {
                 // dispatches the request. NewRouter moves the NewService into the service type, so
                 // minimize it's type footprint with a Box.
                 .push(svc::ArcNewService::layer())
+                // Sends copies of requests to mirrors configured by route policy.
+                .push(NewMirror::layer(rt.mirrors.clone()))
                 .push(svc::NewRouter::layer(LogicalPerRequest::from))
                 .push(policy::NewHttpPolicy::layer(rt.metrics.http_authz.clone()))
+                .push_on_service(http::BoxResponse::layer())
                 // Used by tap.
                 .push_http_insert_target::<tls::ConditionalServerTls>()
                 .push_http_insert_target::<Remote<ClientAddr>>()
@@ -366,6 +370,12 @@ impl Param<u16> for Logical {
     }
 }
 
+impl Param<http::Version> for Logical {
+    fn param(&self) -> http::Version {
+        self.http
+    }
+}
+
 impl Param<transport::labels::Key> for Logical {
     fn param(&self) -> transport::labels::Key {
         transport::labels::Key::InboundClient
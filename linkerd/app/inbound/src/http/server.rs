@@ -127,6 +127,20 @@ impl<T: Param<tls::ConditionalServerTls>> ExtractParam<errors::respond::EmitHead
 
 impl errors::HttpRescue<Error> for ServerRescue {
     fn rescue(&self, error: Error) -> Result<errors::SyntheticHttpResponse> {
+        // Responses synthesized for errors on a route carry the route's
+        // response header modifiers.
+        let modify = errors::cause_ref::<policy::HttpRouteResponseHeaders>(&*error)
+            .map(|e| e.modify_headers().to_vec());
+        let rsp = Self::synthesize(error)?;
+        Ok(match modify {
+            Some(modify) => rsp.with_modified_headers(modify),
+            None => rsp,
+        })
+    }
+}
+
+impl ServerRescue {
+    fn synthesize(error: Error) -> Result<errors::SyntheticHttpResponse> {
         if let Some(cause) = errors::cause_ref::<policy::HttpRouteNotFound>(&*error) {
             return Ok(errors::SyntheticHttpResponse::not_found(cause));
         }
@@ -135,10 +149,25 @@ impl errors::HttpRescue<Error> for ServerRescue {
             return Ok(errors::SyntheticHttpResponse::permission_denied(cause));
         }
 
+        if let Some(cause) = errors::cause_ref::<policy::HttpRouteRateLimited>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::rate_limited(cause));
+        }
+
+        if let Some(cause) = errors::cause_ref::<policy::HttpRouteRequestTimeout>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::deadline_exceeded(cause));
+        }
+        if let Some(cause) = errors::cause_ref::<policy::HttpRouteIdleTimeout>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::deadline_exceeded(cause));
+        }
+
         if let Some(error) = errors::cause_ref::<policy::HttpRouteInvalidRedirect>(&*error) {
             tracing::warn!(%error);
             return Ok(errors::SyntheticHttpResponse::unexpected_error());
         }
+        if let Some(error) = errors::cause_ref::<policy::HttpRouteInvalidRewrite>(&*error) {
+            tracing::warn!(%error);
+            return Ok(errors::SyntheticHttpResponse::unexpected_error());
+        }
         if let Some(policy::HttpRouteRedirect { status, location }) =
             errors::cause_ref::<policy::HttpRouteRedirect>(&*error)
         {
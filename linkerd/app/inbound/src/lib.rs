@@ -16,7 +16,7 @@ mod server;
 #[cfg(any(test, fuzzing))]
 pub(crate) mod test_util;
 
-pub use self::{metrics::Metrics, policy::DefaultPolicy};
+pub use self::{http::MirrorTarget, metrics::Metrics, policy::DefaultPolicy};
 use linkerd_app_core::{
     config::{ConnectConfig, ProxyConfig},
     drain,
@@ -41,6 +41,16 @@ pub struct Config {
     pub policy: policy::Config,
     pub profile_idle_timeout: Duration,
     pub allowed_ips: transport::AllowIps,
+    pub unix_sockets: transport::UnixSockets,
+
+    /// The maximum size of a request body that is buffered so that the request
+    /// may be mirrored. Requests with larger bodies are not mirrored.
+    pub mirror_max_buffered_bytes: usize,
+
+    /// The maximum number of mirrored requests that may be in flight at once.
+    /// Requests are not mirrored while this limit is reached, so that a slow
+    /// mirror cannot accumulate tasks and buffered bodies.
+    pub mirror_max_in_flight: usize,
 }
 
 #[derive(Clone)]
@@ -57,6 +67,9 @@ struct Runtime {
     tap: tap::Registry,
     span_sink: OpenCensusSink,
     drain: drain::Watch,
+
+    /// Sends mirrored requests, if configured.
+    mirrors: Option<http::Mirrors>,
 }
 
 // The inbound HTTP server handles gateway traffic; so gateway error types are defined here (so that
@@ -133,6 +146,7 @@ impl Inbound<()> {
             tap: runtime.tap,
             span_sink: runtime.span_sink,
             drain: runtime.drain,
+            mirrors: None,
         };
         Self {
             config,
@@ -145,6 +159,19 @@ impl Inbound<()> {
         self.runtime.metrics.clone()
     }
 
+    /// Configures the stack used to send mirrored requests.
+    ///
+    /// Mirrored requests are sent through the outbound proxy, so this stack is
+    /// provided by the application; without it, requests are not mirrored.
+    pub fn with_mirrors(mut self, mirrors: svc::ArcNewHttp<MirrorTarget>) -> Self {
+        self.runtime.mirrors = Some(http::Mirrors::new(
+            mirrors,
+            self.config.mirror_max_buffered_bytes,
+            self.config.mirror_max_in_flight,
+        ));
+        self
+    }
+
     pub fn with_stack<S>(self, stack: S) -> Inbound<S> {
         self.map_stack(move |_, _, _| svc::stack(stack))
     }
@@ -179,7 +206,13 @@ impl Inbound<()> {
             #[error("inbound connection must not target port {0}")]
             struct Loop(u16);
 
-            svc::stack(transport::ConnectTcp::new(*keepalive))
+            // Connects to the application over a Unix domain socket when its
+            // port is mapped to one.
+            let connect = transport::ConnectUnixOrTcp::new(
+                transport::ConnectTcp::new(*keepalive),
+                config.unix_sockets.clone(),
+            );
+            svc::stack(connect)
                 // Limits the time we wait for a connection to be established.
                 .push_connect_timeout(*timeout)
                 // Prevent connections that would target the inbound proxy port from looping.
@@ -20,6 +20,12 @@ metrics! {
     inbound_http_route_not_found_total: Counter {
         "The total number of inbound HTTP requests that could not be associated with a route"
     },
+    inbound_http_authz_audit_deny_total: Counter {
+        "The total number of inbound HTTP requests that would have been denied by an audited policy"
+    },
+    inbound_http_route_rate_limited_total: Counter {
+        "The total number of inbound HTTP requests that were rejected by a route's rate limit"
+    },
 
     inbound_tcp_authz_allow_total: Counter {
         "The total number of inbound TCP connections that were authorized"
@@ -27,6 +33,9 @@ metrics! {
     inbound_tcp_authz_deny_total: Counter {
         "The total number of inbound TCP connections that were denied"
     },
+    inbound_tcp_authz_audit_deny_total: Counter {
+        "The total number of inbound TCP connections that would have been denied by an audited policy"
+    },
     inbound_tcp_authz_terminate_total: Counter {
         "The total number of inbound TCP connections that were terminated due to an authorization change"
     }
@@ -42,13 +51,16 @@ pub(crate) struct TcpAuthzMetrics(Arc<TcpInner>);
 struct HttpInner {
     allow: Mutex<HashMap<RouteAuthzKey, Counter>>,
     deny: Mutex<HashMap<RouteKey, Counter>>,
+    audit_deny: Mutex<HashMap<RouteKey, Counter>>,
     route_not_found: Mutex<HashMap<ServerKey, Counter>>,
+    rate_limit: Mutex<HashMap<RouteAuthzKey, Counter>>,
 }
 
 #[derive(Debug, Default)]
 struct TcpInner {
     allow: Mutex<HashMap<ServerAuthzKey, Counter>>,
     deny: Mutex<HashMap<ServerKey, Counter>>,
+    audit_deny: Mutex<HashMap<ServerKey, Counter>>,
     terminate: Mutex<HashMap<ServerKey, Counter>>,
 }
 
@@ -98,6 +110,29 @@ impl HttpAuthzMetrics {
             .or_default()
             .incr();
     }
+
+    pub fn audit_deny(
+        &self,
+        labels: RouteLabels,
+        dst: OrigDstAddr,
+        tls: tls::ConditionalServerTls,
+    ) {
+        self.0
+            .audit_deny
+            .lock()
+            .entry(RouteKey::new(labels, dst, tls))
+            .or_default()
+            .incr();
+    }
+
+    pub fn rate_limit(&self, permit: &HttpRoutePermit, tls: tls::ConditionalServerTls) {
+        self.0
+            .rate_limit
+            .lock()
+            .entry(RouteAuthzKey::from_permit(permit, tls))
+            .or_default()
+            .incr();
+    }
 }
 
 impl FmtMetrics for HttpAuthzMetrics {
@@ -127,6 +162,19 @@ impl FmtMetrics for HttpAuthzMetrics {
         }
         drop(deny);
 
+        let audit_deny = self.0.audit_deny.lock();
+        if !audit_deny.is_empty() {
+            inbound_http_authz_audit_deny_total.fmt_help(f)?;
+            inbound_http_authz_audit_deny_total.fmt_scopes(
+                f,
+                audit_deny
+                    .iter()
+                    .map(|(k, c)| ((k.target, (&k.labels, TlsAccept(&k.tls))), c)),
+                |c| c,
+            )?;
+        }
+        drop(audit_deny);
+
         let route_not_found = self.0.route_not_found.lock();
         if !route_not_found.is_empty() {
             inbound_http_route_not_found_total.fmt_help(f)?;
@@ -140,6 +188,19 @@ impl FmtMetrics for HttpAuthzMetrics {
         }
         drop(route_not_found);
 
+        let rate_limit = self.0.rate_limit.lock();
+        if !rate_limit.is_empty() {
+            inbound_http_route_rate_limited_total.fmt_help(f)?;
+            inbound_http_route_rate_limited_total.fmt_scopes(
+                f,
+                rate_limit
+                    .iter()
+                    .map(|(k, c)| ((k.target, (&k.labels, TlsAccept(&k.tls))), c)),
+                |c| c,
+            )?;
+        }
+        drop(rate_limit);
+
         Ok(())
     }
 }
@@ -165,6 +226,15 @@ impl TcpAuthzMetrics {
             .incr();
     }
 
+    pub fn audit_deny(&self, policy: &AllowPolicy, tls: tls::ConditionalServerTls) {
+        self.0
+            .audit_deny
+            .lock()
+            .entry(ServerKey::from_policy(policy, tls))
+            .or_default()
+            .incr();
+    }
+
     pub fn terminate(&self, policy: &AllowPolicy, tls: tls::ConditionalServerTls) {
         self.0
             .terminate
@@ -191,6 +261,13 @@ impl FmtMetrics for TcpAuthzMetrics {
         }
         drop(deny);
 
+        let audit_deny = self.0.audit_deny.lock();
+        if !audit_deny.is_empty() {
+            inbound_tcp_authz_audit_deny_total.fmt_help(f)?;
+            inbound_tcp_authz_audit_deny_total.fmt_scopes(f, &*audit_deny, |c| c)?;
+        }
+        drop(audit_deny);
+
         let terminate = self.0.terminate.lock();
         if !terminate.is_empty() {
             inbound_tcp_authz_terminate_total.fmt_help(f)?;
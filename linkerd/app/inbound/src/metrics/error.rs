@@ -3,7 +3,10 @@ mod tcp;
 
 pub(crate) use self::{http::HttpErrorMetrics, tcp::TcpErrorMetrics};
 use crate::{
-    policy::{HttpRouteNotFound, HttpRouteUnauthorized, ServerUnauthorized},
+    policy::{
+        HttpRouteIdleTimeout, HttpRouteNotFound, HttpRouteRateLimited, HttpRouteRequestTimeout,
+        HttpRouteUnauthorized, ServerUnauthorized,
+    },
     GatewayDomainInvalid, GatewayIdentityRequired, GatewayLoop,
 };
 use linkerd_app_core::{errors::FailFastError, metrics::FmtLabels, tls};
@@ -17,6 +20,8 @@ enum ErrorKind {
     GatewayIdentityRequired,
     GatewayLoop,
     Io,
+    RouteIdleTimeout,
+    RouteRequestTimeout,
     TlsDetectTimeout,
     Unexpected,
 }
@@ -29,6 +34,7 @@ impl ErrorKind {
         if err.is::<ServerUnauthorized>()
             || err.is::<HttpRouteUnauthorized>()
             || err.is::<HttpRouteNotFound>()
+            || err.is::<HttpRouteRateLimited>()
         {
             return None;
         }
@@ -37,6 +43,10 @@ impl ErrorKind {
             Some(ErrorKind::FailFast)
         } else if err.is::<std::io::Error>() {
             Some(ErrorKind::Io)
+        } else if err.is::<HttpRouteRequestTimeout>() {
+            Some(ErrorKind::RouteRequestTimeout)
+        } else if err.is::<HttpRouteIdleTimeout>() {
+            Some(ErrorKind::RouteIdleTimeout)
         } else if err.is::<tls::server::ServerTlsTimeoutError>() {
             Some(ErrorKind::TlsDetectTimeout)
         } else if err.is::<GatewayDomainInvalid>() {
@@ -65,6 +75,8 @@ impl FmtLabels for ErrorKind {
                 ErrorKind::GatewayLoop => "gateway loop",
                 ErrorKind::GatewayDomainInvalid => "gateway domain invalid",
                 ErrorKind::Io => "i/o",
+                ErrorKind::RouteIdleTimeout => "route idle timeout",
+                ErrorKind::RouteRequestTimeout => "route request timeout",
                 ErrorKind::Unexpected => "unexpected",
             }
         )
@@ -1,6 +1,7 @@
 mod api;
 mod config;
 pub mod defaults;
+mod file;
 mod http;
 mod store;
 mod tcp;
@@ -9,28 +10,30 @@ pub(crate) use self::store::Store;
 pub use self::{
     config::Config,
     http::{
-        HttpInvalidPolicy, HttpRouteInvalidRedirect, HttpRouteNotFound, HttpRouteRedirect,
-        HttpRouteUnauthorized, NewHttpPolicy,
+        HttpInvalidPolicy, HttpRouteIdleTimeout, HttpRouteInvalidRedirect, HttpRouteInvalidRewrite,
+        HttpRouteNotFound, HttpRouteRateLimited, HttpRouteRedirect, HttpRouteRequestTimeout,
+        HttpRouteResponseHeaders, HttpRouteUnauthorized, NewHttpPolicy,
     },
     tcp::NewTcpPolicy,
 };
 
 pub use linkerd_app_core::metrics::ServerLabel;
 use linkerd_app_core::{
+    identity,
     metrics::{RouteAuthzLabels, ServerAuthzLabels},
     tls,
     transport::{ClientAddr, OrigDstAddr, Remote},
 };
 use linkerd_cache::Cached;
 pub use linkerd_server_policy::{
-    authz::Suffix,
+    authz::{SpiffePrefix, Suffix},
     grpc::Route as GrpcRoute,
     http::{filter::Redirection, Route as HttpRoute},
-    route, Authentication, Authorization, Meta, Protocol, RoutePolicy, ServerPolicy,
+    route, Authentication, Authorization, Meta, Protocol, RoutePolicy, RouteTimeouts, ServerPolicy,
 };
 use std::sync::Arc;
 use thiserror::Error;
-use tokio::sync::watch;
+use tokio::{sync::watch, time::Duration};
 
 #[derive(Clone, Debug, Error)]
 #[error("unauthorized connection on {}/{}", server.kind(), server.name())]
@@ -77,6 +80,20 @@ pub enum Routes {
 
 // === impl DefaultPolicy ===
 
+impl DefaultPolicy {
+    /// The protocol detection timeout used for servers that do not configure
+    /// their own.
+    fn detect_timeout(&self) -> Duration {
+        match self {
+            DefaultPolicy::Allow(ServerPolicy {
+                protocol: Protocol::Detect { timeout, .. },
+                ..
+            }) => *timeout,
+            _ => Duration::from_secs(10),
+        }
+    }
+}
+
 impl From<ServerPolicy> for DefaultPolicy {
     fn from(p: ServerPolicy) -> Self {
         DefaultPolicy::Allow(p)
@@ -90,6 +107,7 @@ impl From<DefaultPolicy> for ServerPolicy {
             DefaultPolicy::Deny => ServerPolicy {
                 protocol: Protocol::Opaque(Arc::new([])),
                 meta: Meta::new_default("deny"),
+                audit: false,
             },
         }
     }
@@ -134,6 +152,13 @@ impl AllowPolicy {
         ServerLabel(self.server.borrow().meta.clone())
     }
 
+    /// Indicates whether the server's authorizations are audited rather than
+    /// enforced.
+    #[inline]
+    pub fn is_audit(&self) -> bool {
+        self.server.borrow().audit
+    }
+
     async fn changed(&mut self) {
         if self.server.changed().await.is_err() {
             // If the sender was dropped, then there can be no further changes.
@@ -153,10 +178,15 @@ impl AllowPolicy {
     }
 }
 
+/// Checks whether the authorization permits a client.
+///
+/// HTTP request headers are only provided when authorizing requests; JWT
+/// authentication never permits connections.
 fn is_authorized(
     authz: &Authorization,
     client_addr: Remote<ClientAddr>,
     tls: &tls::ConditionalServerTls,
+    headers: Option<&::http::HeaderMap>,
 ) -> bool {
     if !authz.networks.iter().any(|n| n.contains(&client_addr.ip())) {
         return false;
@@ -175,15 +205,40 @@ fn is_authorized(
         Authentication::TlsAuthenticated {
             ref identities,
             ref suffixes,
+            ref spiffe_prefixes,
         } => match tls {
             tls::ConditionalServerTls::Some(tls::ServerTls::Established {
                 client_id: Some(tls::server::ClientId(ref id)),
                 ..
             }) => {
-                identities.contains(id.as_str()) || suffixes.iter().any(|s| s.contains(id.as_str()))
+                if identities.contains(id.as_str()) {
+                    return true;
+                }
+                match id {
+                    identity::Id::Dns(name) => suffixes.iter().any(|s| s.contains(name.as_str())),
+                    // DNS suffixes are not meaningful for SPIFFE IDs, though
+                    // a suffix that matches all identities still applies.
+                    identity::Id::Spiffe(id) => {
+                        suffixes.iter().any(|s| s.is_any())
+                            || spiffe_prefixes
+                                .iter()
+                                .any(|p| id.has_path_prefix(&p.trust_domain, &p.path))
+                    }
+                }
             }
             _ => false,
         },
+
+        Authentication::Jwt(ref jwt) => match headers {
+            Some(headers) => match jwt.authenticate(headers) {
+                Ok(()) => true,
+                Err(error) => {
+                    tracing::debug!(%error, authz = %authz.meta.name(), "JWT authentication failed");
+                    false
+                }
+            },
+            None => false,
+        },
     }
 }
 
@@ -191,13 +246,28 @@ fn is_authorized(
 
 impl ServerPermit {
     fn new(dst: OrigDstAddr, server: &ServerPolicy, authz: &Authorization) -> Self {
+        Self::mk(dst, server, authz.meta.clone())
+    }
+
+    /// Permits a connection that no authorization allows on an audited server.
+    fn audit(dst: OrigDstAddr, server: &ServerPolicy) -> Self {
+        Self::mk(dst, server, audit_meta())
+    }
+
+    fn mk(dst: OrigDstAddr, server: &ServerPolicy, authz: Arc<Meta>) -> Self {
         Self {
             dst,
             protocol: server.protocol.clone(),
             labels: ServerAuthzLabels {
-                authz: authz.meta.clone(),
+                authz,
                 server: ServerLabel(server.meta.clone()),
             },
         }
     }
 }
+
+/// Describes the synthetic authorization that permits traffic that would
+/// otherwise be denied on an audited server.
+fn audit_meta() -> Arc<Meta> {
+    Meta::new_default("audit")
+}
@@ -77,17 +77,24 @@ where
         Box::pin(async move {
             let rsp = client.watch_port(tonic::Request::new(req)).await?;
             Ok(rsp.map(|updates| {
+                let mut prior = None::<ServerPolicy>;
                 updates
                     .map_ok(move |up| {
                         // If the server returned an invalid server policy, we
                         // default to using an invalid policy that causes all
                         // requests to report an internal error.
-                        let policy = ServerPolicy::try_from(up).unwrap_or_else(|error| {
+                        let mut policy = ServerPolicy::try_from(up).unwrap_or_else(|error| {
                             tracing::warn!(%error, "Server misconfigured");
                             INVALID_POLICY
                                 .get_or_init(|| ServerPolicy::invalid(detect_timeout))
                                 .clone()
                         });
+                        // Unchanged rate limits keep their buckets across
+                        // updates.
+                        if let Some(prior) = prior.as_ref() {
+                            policy.inherit_rate_limits(prior);
+                        }
+                        prior = Some(policy.clone());
                         tracing::debug!(?policy);
                         policy
                     })
@@ -1,12 +1,15 @@
-use super::{api::Api, DefaultPolicy, GetPolicy, Protocol, ServerPolicy, Store};
+use super::{api::Api, file, DefaultPolicy, GetPolicy, ServerPolicy, Store};
 use linkerd_app_core::{control, dns, identity, metrics, svc::NewService};
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 use tokio::time::Duration;
 
 /// Configures inbound policies.
 ///
 /// The proxy usually watches dynamic policies from the control plane, though it can also use
-/// 'fixed' policies configured at startup.
+/// 'fixed' policies configured at startup or policies read from a local file.
 #[derive(Clone, Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum Config {
@@ -22,6 +25,12 @@ pub enum Config {
         cache_max_idle_age: Duration,
         ports: HashMap<u16, ServerPolicy>,
     },
+    File {
+        path: PathBuf,
+        default: DefaultPolicy,
+        cache_max_idle_age: Duration,
+        reload_interval: Duration,
+    },
 }
 
 // === impl Config ===
@@ -40,6 +49,22 @@ impl Config {
                 cache_max_idle_age,
             } => Store::spawn_fixed(default, cache_max_idle_age, ports),
 
+            Self::File {
+                path,
+                default,
+                cache_max_idle_age,
+                reload_interval,
+            } => {
+                let detect_timeout = default.detect_timeout();
+                let watch = file::Watch::spawn(
+                    path,
+                    default.clone().into(),
+                    detect_timeout,
+                    reload_interval,
+                );
+                Store::spawn_file(default, cache_max_idle_age, watch)
+            }
+
             Self::Discover {
                 control,
                 ports,
@@ -50,14 +75,7 @@ impl Config {
                 let watch = {
                     let backoff = control.connect.backoff;
                     let client = control.build(dns, metrics, identity).new_service(());
-                    let detect_timeout = match default {
-                        DefaultPolicy::Allow(ServerPolicy {
-                            protocol: Protocol::Detect { timeout, .. },
-                            ..
-                        }) => timeout,
-                        _ => Duration::from_secs(10),
-                    };
-                    Api::new(workload, detect_timeout, client).into_watch(backoff)
+                    Api::new(workload, default.detect_timeout(), client).into_watch(backoff)
                 };
                 Store::spawn_discover(default, cache_max_idle_age, watch, ports)
             }
@@ -53,6 +53,7 @@ fn authenticated() -> Authentication {
     Authentication::TlsAuthenticated {
         identities: Default::default(),
         suffixes: vec![Suffix::from(vec![])],
+        spiffe_prefixes: vec![],
     }
 }
 
@@ -79,5 +80,6 @@ fn mk(
     ServerPolicy {
         meta: Meta::new_default(name),
         protocol,
+        audit: false,
     }
 }
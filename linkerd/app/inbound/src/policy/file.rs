@@ -0,0 +1,175 @@
+//! Inbound policies that are read from a local file.
+//!
+//! The file is polled for changes so that policies may be updated without
+//! restarting the proxy. Each port's policy is published on a watch, just as
+//! it would be when the policy is discovered from the control plane.
+
+use self::spec::PolicyFile;
+use linkerd_app_core::Error;
+use linkerd_server_policy::ServerPolicy;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+    time::SystemTime,
+};
+use tokio::{sync::watch, time::Duration};
+use tracing::{debug, info, warn, Instrument};
+
+mod spec;
+
+#[cfg(test)]
+mod tests;
+
+/// Publishes the policies described by a policy file.
+#[derive(Clone, Debug)]
+pub(super) struct Watch {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Used for ports that are not described by the file.
+    default: ServerPolicy,
+    policies: HashMap<u16, ServerPolicy>,
+    txs: HashMap<u16, watch::Sender<ServerPolicy>>,
+}
+
+// === impl Watch ===
+
+impl Watch {
+    /// Loads the policy file and spawns a task that reloads it whenever its
+    /// modification time changes.
+    ///
+    /// If the file cannot be loaded initially, all ports use the default
+    /// policy until the file is fixed. If a subsequent reload fails, the
+    /// previously-loaded policies continue to be used.
+    pub(super) fn spawn(
+        path: PathBuf,
+        default: ServerPolicy,
+        detect_timeout: Duration,
+        interval: Duration,
+    ) -> Self {
+        let path: Arc<Path> = path.into();
+        let modified = modified(&path);
+        let policies = load(&path, detect_timeout).unwrap_or_else(|error| {
+            warn!(path = %path.display(), %error, "Failed to load inbound policy file");
+            HashMap::new()
+        });
+        debug!(path = %path.display(), servers = policies.len(), "Loaded inbound policy file");
+
+        let state = Arc::new(Mutex::new(State {
+            default,
+            policies,
+            txs: HashMap::new(),
+        }));
+        tokio::spawn(
+            reload(
+                path,
+                detect_timeout,
+                interval,
+                modified,
+                Arc::downgrade(&state),
+            )
+            .in_current_span(),
+        );
+
+        Self { state }
+    }
+
+    /// Returns the ports that are currently described by the policy file.
+    pub(super) fn ports(&self) -> Vec<u16> {
+        self.state.lock().policies.keys().copied().collect()
+    }
+
+    /// Returns a watch of the given port's policy.
+    pub(super) fn spawn_with_init(&self, port: u16) -> watch::Receiver<ServerPolicy> {
+        let mut state = self.state.lock();
+        let policy = state.get(port);
+        let (tx, rx) = watch::channel(policy);
+        // Any prior sender for this port is replaced. Its receivers are no
+        // longer cached, so they need not be updated.
+        state.txs.insert(port, tx);
+        rx
+    }
+}
+
+async fn reload(
+    path: Arc<Path>,
+    detect_timeout: Duration,
+    interval: Duration,
+    mut modified: Option<SystemTime>,
+    state: Weak<Mutex<State>>,
+) {
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+
+        // Stop watching the file once all of the watches have been dropped.
+        let state = match state.upgrade() {
+            Some(state) => state,
+            None => return,
+        };
+
+        let m = self::modified(&path);
+        if m.is_none() || m == modified {
+            continue;
+        }
+        modified = m;
+
+        match load(&path, detect_timeout) {
+            Ok(policies) => {
+                info!(path = %path.display(), servers = policies.len(), "Reloaded inbound policy file");
+                state.lock().update(policies);
+            }
+            Err(error) => {
+                warn!(path = %path.display(), %error, "Failed to reload inbound policy file; using previous policies");
+            }
+        }
+    }
+}
+
+fn load(path: &Path, detect_timeout: Duration) -> Result<HashMap<u16, ServerPolicy>, Error> {
+    let buf = std::fs::read(path)?;
+    PolicyFile::parse(&buf)?.into_policies(detect_timeout)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// === impl State ===
+
+impl State {
+    fn get(&self, port: u16) -> ServerPolicy {
+        self.policies
+            .get(&port)
+            .cloned()
+            .unwrap_or_else(|| self.default.clone())
+    }
+
+    fn update(&mut self, mut policies: HashMap<u16, ServerPolicy>) {
+        // Unchanged rate limits keep their buckets across reloads.
+        for (port, policy) in policies.iter_mut() {
+            if let Some(prior) = self.policies.get(port) {
+                policy.inherit_rate_limits(prior);
+            }
+        }
+        self.policies = policies;
+
+        let Self {
+            ref default,
+            ref policies,
+            ref mut txs,
+        } = *self;
+        txs.retain(|port, tx| {
+            let policy = policies.get(port).unwrap_or(default);
+            if *tx.borrow() == *policy {
+                return !tx.is_closed();
+            }
+            debug!(%port, "Updating policy");
+            tx.send(policy.clone()).is_ok()
+        });
+    }
+}
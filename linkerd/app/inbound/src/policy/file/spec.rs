@@ -0,0 +1,880 @@
+//! The schema of an inbound policy file.
+//!
+//! Policy files are YAML (or JSON) documents that describe the servers on each
+//! port, in roughly the same shape as the policy controller's API:
+//!
+//! ```yaml
+//! servers:
+//!   - port: 8080
+//!     name: web
+//!     protocol: http1
+//!     authorizations:
+//!       - name: mesh
+//!         authentication:
+//!           tlsAuthenticated:
+//!             suffixes: ["ns.serviceaccount.identity.linkerd.cluster.local"]
+//!     httpRoutes:
+//!       - name: api
+//!         rules:
+//!           - matches:
+//!               - path: { prefix: /api }
+//!             timeouts: { request: 10s }
+//! ```
+
+use linkerd_app_core::Error;
+use linkerd_server_policy::{
+    authz::{ClaimMatch, Jwks, Jwt, Network, SpiffePrefix, Suffix},
+    grpc::{self, r#match as grpc_match},
+    http::{
+        self,
+        filter::{
+            redirect::AuthorityOverride, Delay, Distribution, FailureResponse, InjectDelay,
+            InjectFailure, ModifyHeader, ModifyPath, RedirectRequest, RequestMirror, RewriteUrl,
+        },
+        r#match::{MatchHeader, MatchHost, MatchPath, MatchQueryParam, MatchRequest},
+    },
+    Authentication, Authorization, Meta, Protocol, RateLimit, RateLimitKey, RoutePolicy,
+    RouteTimeouts, ServerPolicy,
+};
+use serde::{Deserialize, Deserializer};
+use std::{
+    collections::{BTreeMap, HashMap},
+    num::{NonZeroU16, NonZeroU32},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+const GROUP: &str = "policy.linkerd.io";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub(super) struct PolicyFile {
+    #[serde(default)]
+    servers: Vec<Server>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct Server {
+    port: u16,
+    name: String,
+    #[serde(default)]
+    protocol: ProxyProtocol,
+    #[serde(default, deserialize_with = "de_opt_duration")]
+    detect_timeout: Option<Duration>,
+    #[serde(default)]
+    audit: bool,
+    #[serde(default)]
+    authorizations: Vec<AuthorizationSpec>,
+    #[serde(default)]
+    http_routes: Vec<HttpRouteSpec>,
+    #[serde(default)]
+    grpc_routes: Vec<GrpcRouteSpec>,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ProxyProtocol {
+    Detect,
+    Http1,
+    Http2,
+    Grpc,
+    Opaque,
+    Tls,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct AuthorizationSpec {
+    name: String,
+    #[serde(default = "all_networks")]
+    networks: Vec<String>,
+    authentication: AuthenticationSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+enum AuthenticationSpec {
+    Unauthenticated,
+    TlsUnauthenticated,
+    #[serde(rename_all = "camelCase")]
+    TlsAuthenticated {
+        #[serde(default)]
+        identities: Vec<String>,
+        #[serde(default)]
+        suffixes: Vec<String>,
+        #[serde(default)]
+        spiffe_prefixes: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Jwt {
+        jwks: PathBuf,
+        #[serde(default)]
+        issuers: Vec<String>,
+        #[serde(default)]
+        audiences: Vec<String>,
+        #[serde(default)]
+        claims: BTreeMap<String, Vec<String>>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct HttpRouteSpec {
+    name: String,
+    #[serde(default)]
+    hosts: Vec<String>,
+    #[serde(default)]
+    authorizations: Vec<AuthorizationSpec>,
+    #[serde(default)]
+    rules: Vec<HttpRuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct HttpRuleSpec {
+    #[serde(default)]
+    matches: Vec<HttpMatchSpec>,
+    #[serde(default)]
+    filters: Vec<HttpFilterSpec>,
+    #[serde(default)]
+    timeouts: TimeoutsSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct HttpMatchSpec {
+    path: Option<PathMatchSpec>,
+    method: Option<String>,
+    #[serde(default)]
+    headers: Vec<ValueMatchSpec>,
+    #[serde(default)]
+    query_params: Vec<ValueMatchSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+enum PathMatchSpec {
+    Exact(String),
+    Prefix(String),
+    Regex(String),
+}
+
+/// Matches a named header or query parameter by value.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct ValueMatchSpec {
+    name: String,
+    exact: Option<String>,
+    regex: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+enum HttpFilterSpec {
+    RequestHeaders(ModifyHeaderSpec),
+    ResponseHeaders(ModifyHeaderSpec),
+    Redirect(RedirectSpec),
+    RewriteUrl(RewriteUrlSpec),
+    RequestMirror(RequestMirrorSpec),
+    InjectFailure(HttpFailureSpec),
+    InjectDelay(InjectDelaySpec),
+    RateLimit(RateLimitSpec),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct GrpcRouteSpec {
+    name: String,
+    #[serde(default)]
+    hosts: Vec<String>,
+    #[serde(default)]
+    authorizations: Vec<AuthorizationSpec>,
+    #[serde(default)]
+    rules: Vec<GrpcRuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct GrpcRuleSpec {
+    #[serde(default)]
+    matches: Vec<GrpcMatchSpec>,
+    #[serde(default)]
+    filters: Vec<GrpcFilterSpec>,
+    #[serde(default)]
+    timeouts: TimeoutsSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct GrpcMatchSpec {
+    service: Option<String>,
+    method: Option<String>,
+    #[serde(default)]
+    headers: Vec<ValueMatchSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+enum GrpcFilterSpec {
+    RequestHeaders(ModifyHeaderSpec),
+    ResponseHeaders(ModifyHeaderSpec),
+    InjectFailure(GrpcFailureSpec),
+    InjectDelay(InjectDelaySpec),
+    RateLimit(RateLimitSpec),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct ModifyHeaderSpec {
+    #[serde(default)]
+    add: BTreeMap<String, String>,
+    #[serde(default)]
+    set: BTreeMap<String, String>,
+    #[serde(default)]
+    remove: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct RedirectSpec {
+    scheme: Option<String>,
+    hostname: Option<String>,
+    port: Option<NonZeroU16>,
+    path: Option<ModifyPathSpec>,
+    status: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct RewriteUrlSpec {
+    hostname: Option<String>,
+    path: Option<ModifyPathSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+enum ModifyPathSpec {
+    ReplaceFullPath(String),
+    ReplacePrefixMatch(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct RequestMirrorSpec {
+    authority: String,
+    #[serde(default)]
+    ratio: RatioSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct HttpFailureSpec {
+    status: u16,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    ratio: RatioSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct GrpcFailureSpec {
+    code: u16,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    ratio: RatioSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct InjectDelaySpec {
+    #[serde(default, deserialize_with = "de_opt_duration")]
+    fixed: Option<Duration>,
+    #[serde(default, deserialize_with = "de_opt_duration")]
+    min: Option<Duration>,
+    #[serde(default, deserialize_with = "de_opt_duration")]
+    max: Option<Duration>,
+    #[serde(default)]
+    ratio: RatioSpec,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct RateLimitSpec {
+    per_second: NonZeroU32,
+    burst: Option<NonZeroU32>,
+    #[serde(default)]
+    key: RateLimitKeySpec,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum RateLimitKeySpec {
+    Route,
+    ClientIdentity,
+    ClientIp,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct RatioSpec {
+    numerator: u32,
+    denominator: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct TimeoutsSpec {
+    #[serde(default, deserialize_with = "de_opt_duration")]
+    request: Option<Duration>,
+    #[serde(default, deserialize_with = "de_opt_duration")]
+    idle: Option<Duration>,
+}
+
+// === impl PolicyFile ===
+
+impl PolicyFile {
+    pub(super) fn parse(buf: &[u8]) -> Result<Self, Error> {
+        // YAML is a superset of JSON, so JSON policy files are parsed as well.
+        let file = serde_yaml::from_slice(buf)?;
+        Ok(file)
+    }
+
+    /// Builds a policy for each of the described servers, keyed by port.
+    ///
+    /// Servers that do not specify a detection timeout use `detect_timeout`.
+    pub(super) fn into_policies(
+        self,
+        detect_timeout: Duration,
+    ) -> Result<HashMap<u16, ServerPolicy>, Error> {
+        let mut policies = HashMap::with_capacity(self.servers.len());
+        for server in self.servers {
+            let port = server.port;
+            let name = server.name.clone();
+            let policy = server
+                .into_policy(detect_timeout)
+                .map_err(|e| format!("invalid server {}: {}", name, e))?;
+            if policies.insert(port, policy).is_some() {
+                return Err(format!("multiple servers on port {}", port).into());
+            }
+        }
+        Ok(policies)
+    }
+}
+
+// === impl Server ===
+
+impl Server {
+    fn into_policy(self, detect_timeout: Duration) -> Result<ServerPolicy, Error> {
+        let authorizations = mk_authorizations(self.authorizations, &[])?;
+
+        let protocol = match self.protocol {
+            ProxyProtocol::Detect => Protocol::Detect {
+                http: mk_http_routes(self.http_routes, &authorizations)?,
+                timeout: self.detect_timeout.unwrap_or(detect_timeout),
+                tcp_authorizations: authorizations,
+            },
+            ProxyProtocol::Http1 => {
+                Protocol::Http1(mk_http_routes(self.http_routes, &authorizations)?)
+            }
+            ProxyProtocol::Http2 => {
+                Protocol::Http2(mk_http_routes(self.http_routes, &authorizations)?)
+            }
+            ProxyProtocol::Grpc => {
+                Protocol::Grpc(mk_grpc_routes(self.grpc_routes, &authorizations)?)
+            }
+            ProxyProtocol::Opaque => Protocol::Opaque(authorizations),
+            ProxyProtocol::Tls => Protocol::Tls(authorizations),
+        };
+
+        Ok(ServerPolicy {
+            protocol,
+            meta: mk_meta("server", self.name),
+            audit: self.audit,
+        })
+    }
+}
+
+impl Default for ProxyProtocol {
+    fn default() -> Self {
+        Self::Detect
+    }
+}
+
+// === Authorizations ===
+
+fn mk_authorizations(
+    authzs: Vec<AuthorizationSpec>,
+    inherited: &[Authorization],
+) -> Result<Arc<[Authorization]>, Error> {
+    authzs
+        .into_iter()
+        .map(AuthorizationSpec::into_authorization)
+        .chain(inherited.iter().cloned().map(Ok))
+        .collect()
+}
+
+impl AuthorizationSpec {
+    fn into_authorization(self) -> Result<Authorization, Error> {
+        let networks = self
+            .networks
+            .iter()
+            .map(|n| {
+                n.parse::<Network>()
+                    .map_err(|e| format!("invalid network {:?}: {}", n, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let authentication = match self.authentication {
+            AuthenticationSpec::Unauthenticated => Authentication::Unauthenticated,
+            AuthenticationSpec::TlsUnauthenticated => Authentication::TlsUnauthenticated,
+            AuthenticationSpec::TlsAuthenticated {
+                identities,
+                suffixes,
+                spiffe_prefixes,
+            } => Authentication::TlsAuthenticated {
+                identities: identities.into_iter().collect(),
+                suffixes: suffixes
+                    .into_iter()
+                    .map(|s| {
+                        let s = s.trim_start_matches("*.").trim_start_matches('.');
+                        if s.is_empty() {
+                            Suffix::from(vec![])
+                        } else {
+                            Suffix::from(s.split('.').map(String::from).collect::<Vec<_>>())
+                        }
+                    })
+                    .collect(),
+                spiffe_prefixes: spiffe_prefixes
+                    .iter()
+                    .map(|p| p.parse::<SpiffePrefix>())
+                    .collect::<Result<Vec<_>, _>>()?,
+            },
+            AuthenticationSpec::Jwt {
+                jwks,
+                issuers,
+                audiences,
+                claims,
+            } => Authentication::Jwt(Jwt {
+                jwks: Jwks::new(jwks),
+                issuers: issuers.into_iter().collect(),
+                audiences: audiences.into_iter().collect(),
+                claims: claims
+                    .into_iter()
+                    .map(|(name, values)| ClaimMatch {
+                        name,
+                        values: values.into_iter().collect(),
+                    })
+                    .collect(),
+            }),
+        };
+
+        Ok(Authorization {
+            networks,
+            authentication,
+            meta: mk_meta("authorizationpolicy", self.name),
+        })
+    }
+}
+
+fn all_networks() -> Vec<String> {
+    vec!["0.0.0.0/0".to_string(), "::/0".to_string()]
+}
+
+// === HTTP routes ===
+
+fn mk_http_routes(
+    routes: Vec<HttpRouteSpec>,
+    server_authzs: &Arc<[Authorization]>,
+) -> Result<Arc<[http::Route]>, Error> {
+    // As with the policy controller, a server without routes permits all
+    // requests with the server's authorizations.
+    if routes.is_empty() {
+        return Ok(Arc::new([http::default(server_authzs.clone())]));
+    }
+
+    routes
+        .into_iter()
+        .map(|route| {
+            let name = route.name.clone();
+            route
+                .into_route(server_authzs)
+                .map_err(|e| format!("invalid HTTP route {}: {}", name, e).into())
+        })
+        .collect()
+}
+
+impl HttpRouteSpec {
+    fn into_route(self, server_authzs: &[Authorization]) -> Result<http::Route, Error> {
+        let meta = mk_meta("httproute", self.name);
+        let authorizations = mk_authorizations(self.authorizations, server_authzs)?;
+        let hosts = mk_hosts(self.hosts)?;
+        let rules = self
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let matches = rule
+                    .matches
+                    .into_iter()
+                    .map(HttpMatchSpec::into_match)
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let filters = rule
+                    .filters
+                    .into_iter()
+                    .map(HttpFilterSpec::into_filter)
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(http::Rule {
+                    matches,
+                    policy: RoutePolicy {
+                        meta: meta.clone(),
+                        authorizations: authorizations.clone(),
+                        filters,
+                        timeouts: rule.timeouts.into(),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(http::Route { hosts, rules })
+    }
+}
+
+impl HttpMatchSpec {
+    fn into_match(self) -> Result<MatchRequest, Error> {
+        let path = match self.path {
+            None => None,
+            Some(PathMatchSpec::Exact(p)) => Some(MatchPath::Exact(p)),
+            Some(PathMatchSpec::Prefix(p)) => Some(MatchPath::Prefix(p)),
+            Some(PathMatchSpec::Regex(r)) => Some(MatchPath::Regex(r.parse()?)),
+        };
+        let method = self.method.map(|m| m.parse()).transpose()?;
+        let headers = mk_header_matches(self.headers)?;
+        let query_params = self
+            .query_params
+            .into_iter()
+            .map(
+                |ValueMatchSpec { name, exact, regex }| match (exact, regex) {
+                    (Some(v), None) => Ok(MatchQueryParam::Exact(name, v)),
+                    (None, Some(r)) => Ok(MatchQueryParam::Regex(name, r.parse()?)),
+                    _ => Err(value_match_error(&name)),
+                },
+            )
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(MatchRequest {
+            path,
+            headers,
+            query_params,
+            method,
+        })
+    }
+}
+
+impl HttpFilterSpec {
+    fn into_filter(self) -> Result<http::Filter, Error> {
+        let filter = match self {
+            Self::RequestHeaders(spec) => http::Filter::RequestHeaders(spec.into_filter()?),
+            Self::ResponseHeaders(spec) => {
+                http::Filter::ResponseHeaders(Arc::new(spec.into_filter()?))
+            }
+            Self::Redirect(RedirectSpec {
+                scheme,
+                hostname,
+                port,
+                path,
+                status,
+            }) => {
+                let authority = match (hostname, port) {
+                    (Some(h), Some(p)) => {
+                        Some(AuthorityOverride::Exact(format!("{}:{}", h, p).parse()?))
+                    }
+                    (Some(h), None) => Some(AuthorityOverride::Host(h.parse()?)),
+                    (None, Some(p)) => Some(AuthorityOverride::Port(p)),
+                    (None, None) => None,
+                };
+                http::Filter::Redirect(RedirectRequest {
+                    scheme: scheme.map(|s| s.parse()).transpose()?,
+                    authority,
+                    path: path.map(Into::into),
+                    status: status.map(::http::StatusCode::from_u16).transpose()?,
+                })
+            }
+            Self::RewriteUrl(RewriteUrlSpec { hostname, path }) => {
+                http::Filter::RewriteUrl(RewriteUrl {
+                    host: hostname.map(|h| h.parse()).transpose()?,
+                    path: path.map(Into::into),
+                })
+            }
+            Self::RequestMirror(RequestMirrorSpec { authority, ratio }) => {
+                http::Filter::RequestMirror(RequestMirror {
+                    authority: authority.parse()?,
+                    distribution: ratio.into_distribution()?,
+                })
+            }
+            Self::InjectFailure(HttpFailureSpec {
+                status,
+                message,
+                ratio,
+            }) => http::Filter::InjectFailure(InjectFailure {
+                response: FailureResponse {
+                    status: ::http::StatusCode::from_u16(status)?,
+                    message: message.into(),
+                },
+                distribution: ratio.into_distribution()?,
+            }),
+            Self::InjectDelay(spec) => http::Filter::InjectDelay(spec.into_filter()?),
+            Self::RateLimit(spec) => http::Filter::RateLimit(spec.into()),
+        };
+        Ok(filter)
+    }
+}
+
+// === gRPC routes ===
+
+fn mk_grpc_routes(
+    routes: Vec<GrpcRouteSpec>,
+    server_authzs: &Arc<[Authorization]>,
+) -> Result<Arc<[grpc::Route]>, Error> {
+    if routes.is_empty() {
+        return Ok(Arc::new([grpc::default(server_authzs.clone())]));
+    }
+
+    routes
+        .into_iter()
+        .map(|route| {
+            let name = route.name.clone();
+            route
+                .into_route(server_authzs)
+                .map_err(|e| format!("invalid gRPC route {}: {}", name, e).into())
+        })
+        .collect()
+}
+
+impl GrpcRouteSpec {
+    fn into_route(self, server_authzs: &[Authorization]) -> Result<grpc::Route, Error> {
+        let meta = mk_meta("grpcroute", self.name);
+        let authorizations = mk_authorizations(self.authorizations, server_authzs)?;
+        let hosts = mk_hosts(self.hosts)?;
+        let rules = self
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let matches = rule
+                    .matches
+                    .into_iter()
+                    .map(
+                        |GrpcMatchSpec {
+                             service,
+                             method,
+                             headers,
+                         }| {
+                            Ok(grpc_match::MatchRoute {
+                                rpc: grpc_match::MatchRpc { service, method },
+                                headers: mk_header_matches(headers)?,
+                            })
+                        },
+                    )
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let filters = rule
+                    .filters
+                    .into_iter()
+                    .map(GrpcFilterSpec::into_filter)
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(grpc::Rule {
+                    matches,
+                    policy: RoutePolicy {
+                        meta: meta.clone(),
+                        authorizations: authorizations.clone(),
+                        filters,
+                        timeouts: rule.timeouts.into(),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(grpc::Route { hosts, rules })
+    }
+}
+
+impl GrpcFilterSpec {
+    fn into_filter(self) -> Result<grpc::Filter, Error> {
+        let filter = match self {
+            Self::RequestHeaders(spec) => grpc::Filter::RequestHeaders(spec.into_filter()?),
+            Self::ResponseHeaders(spec) => {
+                grpc::Filter::ResponseHeaders(Arc::new(spec.into_filter()?))
+            }
+            Self::InjectFailure(GrpcFailureSpec {
+                code,
+                message,
+                ratio,
+            }) => grpc::Filter::InjectFailure(InjectFailure {
+                response: grpc::filter::FailureResponse {
+                    code,
+                    message: message.into(),
+                },
+                distribution: ratio.into_distribution()?,
+            }),
+            Self::InjectDelay(spec) => grpc::Filter::InjectDelay(spec.into_filter()?),
+            Self::RateLimit(spec) => grpc::Filter::RateLimit(spec.into()),
+        };
+        Ok(filter)
+    }
+}
+
+// === Shared route configuration ===
+
+fn mk_hosts(hosts: Vec<String>) -> Result<Vec<MatchHost>, Error> {
+    hosts
+        .into_iter()
+        .map(|h| h.parse().map_err(Into::into))
+        .collect()
+}
+
+fn mk_header_matches(headers: Vec<ValueMatchSpec>) -> Result<Vec<MatchHeader>, Error> {
+    headers
+        .into_iter()
+        .map(|ValueMatchSpec { name, exact, regex }| {
+            let header = name.parse()?;
+            match (exact, regex) {
+                (Some(v), None) => Ok(MatchHeader::Exact(header, v.parse()?)),
+                (None, Some(r)) => Ok(MatchHeader::Regex(header, r.parse()?)),
+                _ => Err(value_match_error(&name)),
+            }
+        })
+        .collect()
+}
+
+fn value_match_error(name: &str) -> Error {
+    format!(
+        "match on {:?} must specify exactly one of 'exact' or 'regex'",
+        name
+    )
+    .into()
+}
+
+impl ModifyHeaderSpec {
+    fn into_filter(self) -> Result<ModifyHeader, Error> {
+        fn mk_pairs(
+            headers: BTreeMap<String, String>,
+        ) -> Result<Vec<(::http::header::HeaderName, ::http::HeaderValue)>, Error> {
+            headers
+                .into_iter()
+                .map(|(n, v)| Ok((n.parse()?, v.parse()?)))
+                .collect()
+        }
+
+        Ok(ModifyHeader {
+            add: mk_pairs(self.add)?,
+            set: mk_pairs(self.set)?,
+            remove: self
+                .remove
+                .into_iter()
+                .map(|n| n.parse())
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
+impl From<ModifyPathSpec> for ModifyPath {
+    fn from(spec: ModifyPathSpec) -> Self {
+        match spec {
+            ModifyPathSpec::ReplaceFullPath(p) => ModifyPath::ReplaceFullPath(p),
+            ModifyPathSpec::ReplacePrefixMatch(p) => ModifyPath::ReplacePrefixMatch(p),
+        }
+    }
+}
+
+impl InjectDelaySpec {
+    fn into_filter(self) -> Result<InjectDelay, Error> {
+        let delay = match (self.fixed, self.min, self.max) {
+            (Some(d), None, None) => Delay::Fixed(d),
+            (None, Some(min), Some(max)) if min <= max => Delay::Uniform { min, max },
+            _ => {
+                return Err(
+                    "delay must specify either 'fixed' or a 'min' no greater than 'max'".into(),
+                )
+            }
+        };
+        Ok(InjectDelay {
+            delay,
+            distribution: self.ratio.into_distribution()?,
+        })
+    }
+}
+
+impl From<RateLimitSpec> for RateLimit {
+    fn from(spec: RateLimitSpec) -> Self {
+        let key = match spec.key {
+            RateLimitKeySpec::Route => RateLimitKey::Route,
+            RateLimitKeySpec::ClientIdentity => RateLimitKey::ClientIdentity,
+            RateLimitKeySpec::ClientIp => RateLimitKey::ClientIp,
+        };
+        RateLimit::new(spec.per_second, spec.burst.unwrap_or(spec.per_second), key)
+    }
+}
+
+impl Default for RateLimitKeySpec {
+    fn default() -> Self {
+        Self::Route
+    }
+}
+
+impl RatioSpec {
+    fn into_distribution(self) -> Result<Distribution, Error> {
+        let dist = Distribution::from_ratio(self.numerator, self.denominator)?;
+        Ok(dist)
+    }
+}
+
+impl Default for RatioSpec {
+    fn default() -> Self {
+        Self {
+            numerator: 1,
+            denominator: 1,
+        }
+    }
+}
+
+impl From<TimeoutsSpec> for RouteTimeouts {
+    fn from(spec: TimeoutsSpec) -> Self {
+        Self {
+            request: spec.request,
+            idle: spec.idle,
+        }
+    }
+}
+
+fn mk_meta(kind: &str, name: String) -> Arc<Meta> {
+    Arc::new(Meta::Resource {
+        group: GROUP.to_string(),
+        kind: kind.to_string(),
+        name,
+    })
+}
+
+// === Durations ===
+
+fn de_opt_duration<'de, D: Deserializer<'de>>(de: D) -> Result<Option<Duration>, D::Error> {
+    let s = match Option::<String>::deserialize(de)? {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    parse_duration(&s)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid duration: {:?}", s)))
+}
+
+/// Parses durations like `100ms`, `10s`, `5m`, or `1h`.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let (magnitude, unit) = s.split_at(split);
+    let magnitude = magnitude.parse::<u64>().ok()?;
+    match unit {
+        "ms" => Some(Duration::from_millis(magnitude)),
+        "s" => Some(Duration::from_secs(magnitude)),
+        "m" => Some(Duration::from_secs(magnitude.checked_mul(60)?)),
+        "h" => Some(Duration::from_secs(magnitude.checked_mul(60 * 60)?)),
+        _ => None,
+    }
+}
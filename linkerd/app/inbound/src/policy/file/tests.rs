@@ -0,0 +1,237 @@
+use super::*;
+use linkerd_server_policy::{
+    authz::Suffix, http, Authentication, Authorization, Meta, Protocol, RouteTimeouts,
+};
+
+const DETECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn meta(kind: &str, name: &str) -> Arc<Meta> {
+    Arc::new(Meta::Resource {
+        group: "policy.linkerd.io".into(),
+        kind: kind.into(),
+        name: name.into(),
+    })
+}
+
+fn parse(buf: &str) -> Result<HashMap<u16, ServerPolicy>, Error> {
+    PolicyFile::parse(buf.as_bytes())?.into_policies(DETECT_TIMEOUT)
+}
+
+#[test]
+fn parses_yaml() {
+    let policies = parse(
+        r#"
+servers:
+  - port: 8080
+    name: web
+    protocol: http1
+    audit: true
+    authorizations:
+      - name: mesh
+        networks: ["10.0.0.0/8"]
+        authentication:
+          tlsAuthenticated:
+            suffixes: ["ns.serviceaccount.identity.linkerd.cluster.local"]
+    httpRoutes:
+      - name: api
+        rules:
+          - matches:
+              - path: { prefix: /api }
+                method: GET
+            timeouts: { request: 10s, idle: 100ms }
+  - port: 5432
+    name: db
+    protocol: opaque
+    authorizations:
+      - name: all
+        authentication: unauthenticated
+"#,
+    )
+    .expect("policy file must be valid");
+    assert_eq!(policies.len(), 2);
+
+    let mesh = Authorization {
+        networks: vec!["10.0.0.0/8".parse().unwrap()],
+        authentication: Authentication::TlsAuthenticated {
+            identities: Default::default(),
+            suffixes: vec![Suffix::from(vec![
+                "ns".to_string(),
+                "serviceaccount".to_string(),
+                "identity".to_string(),
+                "linkerd".to_string(),
+                "cluster".to_string(),
+                "local".to_string(),
+            ])],
+            spiffe_prefixes: vec![],
+        },
+        meta: meta("authorizationpolicy", "mesh"),
+    };
+    assert_eq!(
+        policies[&8080],
+        ServerPolicy {
+            protocol: Protocol::Http1(Arc::new([http::Route {
+                hosts: vec![],
+                rules: vec![http::Rule {
+                    matches: vec![http::r#match::MatchRequest {
+                        path: Some(http::r#match::MatchPath::Prefix("/api".into())),
+                        method: Some(::http::Method::GET),
+                        ..Default::default()
+                    }],
+                    policy: http::Policy {
+                        meta: meta("httproute", "api"),
+                        authorizations: Arc::new([mesh]),
+                        filters: vec![],
+                        timeouts: RouteTimeouts {
+                            request: Some(Duration::from_secs(10)),
+                            idle: Some(Duration::from_millis(100)),
+                        },
+                    },
+                }],
+            }])),
+            meta: meta("server", "web"),
+            audit: true,
+        }
+    );
+
+    assert_eq!(
+        policies[&5432],
+        ServerPolicy {
+            protocol: Protocol::Opaque(Arc::new([Authorization {
+                networks: vec!["0.0.0.0/0".parse().unwrap(), "::/0".parse().unwrap()],
+                authentication: Authentication::Unauthenticated,
+                meta: meta("authorizationpolicy", "all"),
+            }])),
+            meta: meta("server", "db"),
+            audit: false,
+        }
+    );
+}
+
+#[test]
+fn parses_json() {
+    let policies = parse(
+        r#"{"servers": [{"port": 9090, "name": "admin", "detectTimeout": "1s", "authorizations": []}]}"#,
+    )
+    .expect("policy file must be valid");
+    assert_eq!(
+        policies[&9090],
+        ServerPolicy {
+            protocol: Protocol::Detect {
+                http: Arc::new([http::default(Arc::new([]))]),
+                timeout: Duration::from_secs(1),
+                tcp_authorizations: Arc::new([]),
+            },
+            meta: meta("server", "admin"),
+            audit: false,
+        }
+    );
+}
+
+#[test]
+fn rejects_invalid() {
+    // Unknown fields are rejected.
+    assert!(parse("servers: [{port: 80, name: web, protocl: opaque}]").is_err());
+    // Ports may only be described once.
+    assert!(parse("servers: [{port: 80, name: a}, {port: 80, name: b}]").is_err());
+    // Networks must be valid.
+    assert!(parse(
+        "servers: [{port: 80, name: a, authorizations: [{name: a, networks: [foo], authentication: unauthenticated}]}]"
+    )
+    .is_err());
+    // Durations must have units.
+    assert!(parse("servers: [{port: 80, name: a, detectTimeout: 10}]").is_err());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn reloads_on_change() {
+    let path = std::env::temp_dir().join(format!(
+        "linkerd-inbound-policy-{}.yaml",
+        std::process::id()
+    ));
+    std::fs::write(&path, "servers: [{port: 80, name: a, protocol: opaque}]").unwrap();
+
+    let default = ServerPolicy::invalid(DETECT_TIMEOUT);
+    let watch = Watch::spawn(
+        path.clone(),
+        default.clone(),
+        DETECT_TIMEOUT,
+        Duration::from_millis(10),
+    );
+    let mut rx80 = watch.spawn_with_init(80);
+    let mut rx81 = watch.spawn_with_init(81);
+    assert_eq!(rx80.borrow().meta, meta("server", "a"));
+    assert_eq!(*rx81.borrow(), default);
+
+    // Ensure that the modification time changes.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    std::fs::write(&path, "servers: [{port: 81, name: b, protocol: opaque}]").unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), rx80.changed())
+        .await
+        .expect("policy must be updated")
+        .unwrap();
+    assert_eq!(*rx80.borrow(), default);
+    tokio::time::timeout(Duration::from_secs(5), rx81.changed())
+        .await
+        .expect("policy must be updated")
+        .unwrap();
+    assert_eq!(rx81.borrow().meta, meta("server", "b"));
+
+    // Invalid updates are ignored.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    std::fs::write(&path, "servers: [{port: 81}]").unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(rx81.borrow().meta, meta("server", "b"));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn reloads_keep_rate_limits() {
+    fn rate_limit(policy: &ServerPolicy) -> linkerd_server_policy::RateLimit {
+        match &policy.protocol {
+            Protocol::Http1(routes) => match &routes[0].rules[0].policy.filters[0] {
+                http::Filter::RateLimit(rl) => rl.clone(),
+                f => panic!("unexpected filter: {:?}", f),
+            },
+            p => panic!("unexpected protocol: {:?}", p),
+        }
+    }
+
+    let policy = |name: &str, per_second: u32| {
+        parse(&format!(
+            r#"
+servers:
+  - port: 80
+    name: {name}
+    protocol: http1
+    httpRoutes:
+      - name: api
+        rules:
+          - filters: [{{ rateLimit: {{ perSecond: {per_second}, key: route }} }}]
+"#
+        ))
+        .expect("policy file must be valid")
+    };
+
+    let mut state = State {
+        default: ServerPolicy::invalid(DETECT_TIMEOUT),
+        policies: policy("a", 1),
+        txs: HashMap::new(),
+    };
+    let client = std::net::Ipv4Addr::LOCALHOST.into();
+    assert!(rate_limit(&state.get(80)).check(None, client));
+
+    // Another part of the server changes, but the limit does not.
+    state.update(policy("b", 1));
+    assert!(
+        !rate_limit(&state.get(80)).check(None, client),
+        "buckets must be kept"
+    );
+
+    state.update(policy("c", 2));
+    assert!(
+        rate_limit(&state.get(80)).check(None, client),
+        "buckets must be reset"
+    );
+}
@@ -3,16 +3,20 @@ use crate::{
     metrics::authz::HttpAuthzMetrics,
     policy::{AllowPolicy, HttpRoutePermit},
 };
-use futures::{future, TryFutureExt};
+use futures::{future, ready};
 use linkerd_app_core::{
+    errors,
     metrics::{RouteAuthzLabels, RouteLabels},
+    proxy::http::HttpBody,
     svc::{self, ServiceExt},
     tls,
     transport::{ClientAddr, OrigDstAddr, Remote},
     Error, Result,
 };
-use linkerd_server_policy::{grpc, http, route::RouteMatch};
-use std::{sync::Arc, task};
+use linkerd_server_policy::{grpc, http, route::RouteMatch, RateLimit, RouteTimeouts};
+use pin_project::pin_project;
+use std::{future::Future, pin::Pin, sync::Arc, task, time::Duration};
+use tokio::time::{self, Instant, Sleep};
 
 #[cfg(test)]
 mod tests;
@@ -39,6 +43,44 @@ pub struct HttpPolicyService<T, N> {
     inner: N,
 }
 
+/// Applies a route's injected delays, response header modifiers, and timeouts
+/// to the inner service's response.
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    #[pin]
+    delay: Option<Sleep>,
+    #[pin]
+    deadline: Option<Sleep>,
+    response_headers: Vec<Arc<http::filter::ModifyHeader>>,
+    timeouts: RouteTimeouts,
+}
+
+/// Fails a response stream that remains idle for longer than the route's idle
+/// timeout.
+///
+/// The stream is only idle while it is being polled and has not produced a
+/// frame, so time spent waiting for the client to read the body is not
+/// counted.
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseBody<B> {
+    #[pin]
+    inner: B,
+    #[pin]
+    idle: Option<Sleep>,
+    idle_timeout: Option<Duration>,
+    waiting: bool,
+}
+
+/// The delay injected by a route's filters before the request is dispatched.
+#[derive(Debug, Default)]
+struct Applied {
+    delay: Option<Duration>,
+}
+
 #[derive(Clone, Debug)]
 struct ConnectionMeta {
     dst: OrigDstAddr,
@@ -54,6 +96,10 @@ pub struct HttpRouteNotFound(());
 #[error("invalid redirect: {0}")]
 pub struct HttpRouteInvalidRedirect(#[from] pub http::filter::InvalidRedirect);
 
+#[derive(Debug, thiserror::Error)]
+#[error("invalid URL rewrite: {0}")]
+pub struct HttpRouteInvalidRewrite(#[from] pub http::filter::InvalidRewrite);
+
 #[derive(Debug, thiserror::Error)]
 #[error("request redirected to {location}")]
 pub struct HttpRouteRedirect {
@@ -65,6 +111,28 @@ pub struct HttpRouteRedirect {
 #[error("unauthorized request on route")]
 pub struct HttpRouteUnauthorized(());
 
+#[derive(Debug, thiserror::Error)]
+#[error("request rate limit exceeded on route")]
+pub struct HttpRouteRateLimited(());
+
+#[derive(Debug, thiserror::Error)]
+#[error("route request timed out after {0:?}")]
+pub struct HttpRouteRequestTimeout(Duration);
+
+#[derive(Debug, thiserror::Error)]
+#[error("route response stream idle for {0:?}")]
+pub struct HttpRouteIdleTimeout(Duration);
+
+/// Carries a route's response header modifiers with an error so that they are
+/// applied to the response synthesized for the error.
+#[derive(Debug, thiserror::Error)]
+#[error("{source}")]
+pub struct HttpRouteResponseHeaders {
+    modify: Vec<Arc<http::filter::ModifyHeader>>,
+    #[source]
+    source: Error,
+}
+
 #[derive(Debug, thiserror::Error, Clone, PartialEq)]
 #[error("HTTP request configured to fail with {status}: {message}")]
 pub struct HttpRouteInjectedFailure {
@@ -135,17 +203,17 @@ macro_rules! try_fut {
     };
 }
 
-impl<B, T, N, S> svc::Service<::http::Request<B>> for HttpPolicyService<T, N>
+impl<B, RspB, T, N, S> svc::Service<::http::Request<B>> for HttpPolicyService<T, N>
 where
     T: Clone,
     N: svc::NewService<(HttpRoutePermit, T), Service = S>,
-    S: svc::Service<::http::Request<B>>,
+    S: svc::Service<::http::Request<B>, Response = ::http::Response<RspB>>,
     S::Error: Into<Error>,
 {
-    type Response = S::Response;
+    type Response = ::http::Response<ResponseBody<RspB>>;
     type Error = Error;
     type Future = future::Either<
-        future::ErrInto<svc::stack::Oneshot<S, ::http::Request<B>>, Error>,
+        ResponseFuture<svc::stack::Oneshot<S, ::http::Request<B>>>,
         future::Ready<Result<Self::Response>>,
     >;
 
@@ -157,38 +225,69 @@ where
     fn call(&mut self, mut req: ::http::Request<B>) -> Self::Future {
         // Find an appropriate route for the request and ensure that it's
         // authorized.
-        let permit = match self.policy.routes() {
+        let (permit, applied, response_headers, timeouts) = match self.policy.routes() {
             None => err!(self.mk_route_not_found()),
             Some(Routes::Http(routes)) => {
-                let (permit, mtch, route) = try_fut!(self.authorize(&routes, &req));
-                try_fut!(apply_http_filters(mtch, route, &mut req));
-                permit
+                let (permit, mtch, route, audited) = try_fut!(self.authorize(&routes, &req));
+                let response_headers = route
+                    .filters
+                    .iter()
+                    .filter_map(|f| match f {
+                        http::Filter::ResponseHeaders(rh) => Some(rh.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let applied = self
+                    .apply_http_filters(mtch, &permit, route, &mut req)
+                    .map_err(|e| HttpRouteResponseHeaders::wrap(e, &response_headers));
+                let applied = try_fut!(self.record_allow(&permit, audited, applied));
+                (permit, applied, response_headers, route.timeouts)
             }
             Some(Routes::Grpc(routes)) => {
-                let (permit, _, route) = try_fut!(self.authorize(&routes, &req));
-                try_fut!(apply_grpc_filters(route, &mut req));
-                permit
+                let (permit, _, route, audited) = try_fut!(self.authorize(&routes, &req));
+                let response_headers = route
+                    .filters
+                    .iter()
+                    .filter_map(|f| match f {
+                        grpc::Filter::ResponseHeaders(rh) => Some(rh.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let applied = self
+                    .apply_grpc_filters(&permit, route, &mut req)
+                    .map_err(|e| HttpRouteResponseHeaders::wrap(e, &response_headers));
+                let applied = try_fut!(self.record_allow(&permit, audited, applied));
+                (permit, applied, response_headers, route.timeouts)
             }
         };
 
-        future::Either::Left(
-            self.inner
+        future::Either::Left(ResponseFuture {
+            inner: self
+                .inner
                 .new_service((permit, self.target.clone()))
-                .oneshot(req)
-                .err_into::<Error>(),
-        )
+                .oneshot(req),
+            delay: applied.delay.map(time::sleep),
+            deadline: timeouts.request.map(time::sleep),
+            response_headers,
+            timeouts,
+        })
     }
 }
 
 impl<T, N> HttpPolicyService<T, N> {
     /// Finds a matching route for the given request and checks that a
     /// sufficient authorization is present, returning a permit describing the
-    /// authorization.
+    /// authorization and whether the request was only allowed for audit.
     fn authorize<'m, M: super::route::Match + 'm, P, B>(
         &self,
         routes: &'m [super::route::Route<M, RoutePolicy<P>>],
         req: &::http::Request<B>,
-    ) -> Result<(HttpRoutePermit, RouteMatch<M::Summary>, &'m RoutePolicy<P>)> {
+    ) -> Result<(
+        HttpRoutePermit,
+        RouteMatch<M::Summary>,
+        &'m RoutePolicy<P>,
+        bool,
+    )> {
         let (r#match, route) =
             super::route::find(routes, req).ok_or_else(|| self.mk_route_not_found())?;
 
@@ -197,12 +296,34 @@ impl<T, N> HttpPolicyService<T, N> {
             server: self.policy.server_label(),
         };
 
-        let authz = match route
-            .authorizations
-            .iter()
-            .find(|a| super::is_authorized(a, self.connection.client, &self.connection.tls))
-        {
-            Some(authz) => authz,
+        let (authz, audited) = match route.authorizations.iter().find(|a| {
+            super::is_authorized(
+                a,
+                self.connection.client,
+                &self.connection.tls,
+                Some(req.headers()),
+            )
+        }) {
+            Some(authz) => (authz.meta.clone(), false),
+            None if self.policy.is_audit() => {
+                tracing::info!(
+                    server.group = %labels.server.0.group(),
+                    server.kind = %labels.server.0.kind(),
+                    server.name = %labels.server.0.name(),
+                    route.group = %labels.route.group(),
+                    route.kind = %labels.route.kind(),
+                    route.name = %labels.route.name(),
+                    client.tls = ?self.connection.tls,
+                    client.ip = %self.connection.client.ip(),
+                    "Request would be denied; allowing for audit",
+                );
+                self.metrics.audit_deny(
+                    labels.clone(),
+                    self.connection.dst,
+                    self.connection.tls.clone(),
+                );
+                (super::audit_meta(), true)
+            }
             None => {
                 tracing::info!(
                     server.group = %labels.server.0.group(),
@@ -224,7 +345,7 @@ impl<T, N> HttpPolicyService<T, N> {
         let permit = {
             let labels = RouteAuthzLabels {
                 route: labels,
-                authz: authz.meta.clone(),
+                authz,
             };
             tracing::debug!(
                 server.group = %labels.route.server.0.group(),
@@ -246,8 +367,24 @@ impl<T, N> HttpPolicyService<T, N> {
             }
         };
 
-        self.metrics.allow(&permit, self.connection.tls.clone());
-        Ok((permit, r#match, route))
+        Ok((permit, r#match, route, audited))
+    }
+
+    /// Records an authorized request, unless it was rejected by a rate limit or
+    /// only allowed for audit (which are recorded separately).
+    fn record_allow<A>(
+        &self,
+        permit: &HttpRoutePermit,
+        audited: bool,
+        applied: Result<A>,
+    ) -> Result<A> {
+        if audited {
+            return applied;
+        }
+        if !matches!(applied, Err(ref e) if errors::is_caused_by::<HttpRouteRateLimited>(&**e)) {
+            self.metrics.allow(permit, self.connection.tls.clone());
+        }
+        applied
     }
 
     fn mk_route_not_found(&self) -> Error {
@@ -256,67 +393,316 @@ impl<T, N> HttpPolicyService<T, N> {
             .route_not_found(labels, self.connection.dst, self.connection.tls.clone());
         HttpRouteNotFound(()).into()
     }
-}
 
-fn apply_http_filters<B>(
-    r#match: http::RouteMatch,
-    route: &http::Policy,
-    req: &mut ::http::Request<B>,
-) -> Result<()> {
-    // TODO Do any metrics apply here?
-    for filter in &route.filters {
-        match filter {
-            http::Filter::InjectFailure(fail) => {
-                if let Some(http::filter::FailureResponse { status, message }) = fail.apply() {
-                    return Err(HttpRouteInjectedFailure { status, message }.into());
+    /// Applies the route's filters to the request, returning the delay that
+    /// should be applied before the request is dispatched.
+    fn apply_http_filters<B>(
+        &self,
+        r#match: http::RouteMatch,
+        permit: &HttpRoutePermit,
+        route: &http::Policy,
+        req: &mut ::http::Request<B>,
+    ) -> Result<Applied> {
+        // TODO Do any metrics apply here?
+        let mut applied = Applied::default();
+        for filter in &route.filters {
+            match filter {
+                http::Filter::InjectDelay(delay) => applied.delay(delay.apply()),
+
+                http::Filter::InjectFailure(fail) => {
+                    if let Some(http::filter::FailureResponse { status, message }) = fail.apply() {
+                        return Err(HttpRouteInjectedFailure { status, message }.into());
+                    }
                 }
-            }
 
-            http::Filter::Redirect(redir) => match redir.apply(req.uri(), &r#match) {
-                Ok(Some(http::filter::Redirection { status, location })) => {
-                    return Err(HttpRouteRedirect { status, location }.into());
-                }
+                http::Filter::Redirect(redir) => match redir.apply(req.uri(), &r#match) {
+                    Ok(Some(http::filter::Redirection { status, location })) => {
+                        return Err(HttpRouteRedirect { status, location }.into());
+                    }
 
-                Err(invalid) => {
-                    return Err(HttpRouteInvalidRedirect(invalid).into());
+                    Err(invalid) => {
+                        return Err(HttpRouteInvalidRedirect(invalid).into());
+                    }
+
+                    Ok(None) => {
+                        tracing::debug!("Ignoring irrelevant redirect");
+                    }
+                },
+
+                http::Filter::RequestHeaders(rh) => {
+                    rh.apply(req.headers_mut());
                 }
 
-                Ok(None) => {
-                    tracing::debug!("Ignoring irrelevant redirect");
+                http::Filter::RewriteUrl(rewrite) => match rewrite.apply(req.uri(), &r#match) {
+                    Ok(Some(uri)) => {
+                        if uri.authority() != req.uri().authority() {
+                            if let Some(host) = uri
+                                .authority()
+                                .and_then(|a| ::http::HeaderValue::from_str(a.as_str()).ok())
+                            {
+                                req.headers_mut().insert(::http::header::HOST, host);
+                            }
+                        }
+                        *req.uri_mut() = uri;
+                    }
+
+                    Err(invalid) => {
+                        return Err(HttpRouteInvalidRewrite(invalid).into());
+                    }
+
+                    Ok(None) => {
+                        tracing::debug!("Ignoring irrelevant URL rewrite");
+                    }
+                },
+
+                http::Filter::RequestMirror(mirror) => {
+                    if mirror.apply() {
+                        req.extensions_mut().insert(mirror.clone());
+                    }
                 }
-            },
 
-            http::Filter::RequestHeaders(rh) => {
-                rh.apply(req.headers_mut());
+                // Response headers are modified when the response is received.
+                http::Filter::ResponseHeaders(_) => {}
+
+                http::Filter::RateLimit(rl) => self.check_rate_limit(rl, permit)?,
+
+                http::Filter::InternalError(msg) => {
+                    return Err(HttpInvalidPolicy(msg).into());
+                }
             }
+        }
+
+        Ok(applied)
+    }
+
+    /// Applies the route's filters to the request, returning the delay that
+    /// should be applied before the request is dispatched.
+    fn apply_grpc_filters<B>(
+        &self,
+        permit: &HttpRoutePermit,
+        route: &grpc::Policy,
+        req: &mut ::http::Request<B>,
+    ) -> Result<Applied> {
+        let mut applied = Applied::default();
+        for filter in &route.filters {
+            match filter {
+                grpc::Filter::InjectDelay(delay) => applied.delay(delay.apply()),
+
+                grpc::Filter::InjectFailure(fail) => {
+                    if let Some(grpc::filter::FailureResponse { code, message }) = fail.apply() {
+                        return Err(GrpcRouteInjectedFailure { code, message }.into());
+                    }
+                }
+
+                grpc::Filter::RequestHeaders(rh) => {
+                    rh.apply(req.headers_mut());
+                }
+
+                // Response headers are modified when the response is received.
+                grpc::Filter::ResponseHeaders(_) => {}
 
-            http::Filter::InternalError(msg) => {
-                return Err(HttpInvalidPolicy(msg).into());
+                grpc::Filter::RateLimit(rl) => self.check_rate_limit(rl, permit)?,
+
+                grpc::Filter::InternalError(msg) => {
+                    return Err(HttpInvalidPolicy(msg).into());
+                }
             }
         }
+
+        Ok(applied)
     }
 
-    Ok(())
+    fn check_rate_limit(&self, limit: &RateLimit, permit: &HttpRoutePermit) -> Result<()> {
+        let client_id = match self.connection.tls {
+            tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+                client_id: Some(tls::server::ClientId(ref id)),
+                ..
+            }) => Some(id.as_str()),
+            _ => None,
+        };
+        if limit.check(client_id, self.connection.client.ip()) {
+            return Ok(());
+        }
+
+        tracing::info!(
+            server.group = %permit.labels.route.server.0.group(),
+            server.kind = %permit.labels.route.server.0.kind(),
+            server.name = %permit.labels.route.server.0.name(),
+            route.group = %permit.labels.route.route.group(),
+            route.kind = %permit.labels.route.route.kind(),
+            route.name = %permit.labels.route.route.name(),
+            client.tls = ?self.connection.tls,
+            client.ip = %self.connection.client.ip(),
+            "Request rate limited",
+        );
+        self.metrics.rate_limit(permit, self.connection.tls.clone());
+        Err(HttpRouteRateLimited(()).into())
+    }
 }
 
-fn apply_grpc_filters<B>(route: &grpc::Policy, req: &mut ::http::Request<B>) -> Result<()> {
-    for filter in &route.filters {
-        match filter {
-            grpc::Filter::InjectFailure(fail) => {
-                if let Some(grpc::filter::FailureResponse { code, message }) = fail.apply() {
-                    return Err(GrpcRouteInjectedFailure { code, message }.into());
-                }
+// === impl Applied ===
+
+impl Applied {
+    fn delay(&mut self, delay: Option<Duration>) {
+        if let Some(delay) = delay {
+            tracing::debug!(?delay, "Injecting delay");
+            self.delay = Some(self.delay.unwrap_or_default() + delay);
+        }
+    }
+}
+
+// === impl HttpRouteResponseHeaders ===
+
+impl HttpRouteResponseHeaders {
+    /// Attaches response header modifiers to an error, unless there are none.
+    fn wrap(source: Error, modify: &[Arc<http::filter::ModifyHeader>]) -> Error {
+        if modify.is_empty() {
+            return source;
+        }
+        Self {
+            modify: modify.to_vec(),
+            source,
+        }
+        .into()
+    }
+
+    pub fn modify_headers(&self) -> &[Arc<http::filter::ModifyHeader>] {
+        &self.modify
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<::http::Response<B>, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<::http::Response<ResponseBody<B>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> task::Poll<Self::Output> {
+        let mut this = self.project();
+
+        // The request is not dispatched until the injected delay elapses.
+        let poll = match this.delay.as_mut().as_pin_mut().map(|d| d.poll(cx)) {
+            Some(task::Poll::Pending) => task::Poll::Pending,
+            Some(task::Poll::Ready(())) => {
+                this.delay.set(None);
+                this.inner.poll(cx)
             }
+            None => this.inner.poll(cx),
+        };
 
-            grpc::Filter::RequestHeaders(rh) => {
-                rh.apply(req.headers_mut());
+        let mut rsp = match poll {
+            task::Poll::Ready(res) => {
+                res.map_err(|e| HttpRouteResponseHeaders::wrap(e.into(), this.response_headers))?
             }
+            task::Poll::Pending => {
+                if let Some(deadline) = this.deadline.as_pin_mut() {
+                    ready!(deadline.poll(cx));
+                    let timeout = this.timeouts.request.expect("deadline must be configured");
+                    return task::Poll::Ready(Err(HttpRouteResponseHeaders::wrap(
+                        HttpRouteRequestTimeout(timeout).into(),
+                        this.response_headers,
+                    )));
+                }
+                return task::Poll::Pending;
+            }
+        };
+
+        for rh in this.response_headers.iter() {
+            rh.apply(rsp.headers_mut());
+        }
 
-            grpc::Filter::InternalError(msg) => {
-                return Err(HttpInvalidPolicy(msg).into());
+        let idle_timeout = this.timeouts.idle;
+        task::Poll::Ready(Ok(rsp.map(|inner| ResponseBody {
+            inner,
+            idle: idle_timeout.map(time::sleep),
+            idle_timeout,
+            waiting: false,
+        })))
+    }
+}
+
+// === impl ResponseBody ===
+
+impl<B> ResponseBody<B> {
+    /// Fails if the idle timeout has elapsed; otherwise, ensures the task is
+    /// notified when it elapses.
+    ///
+    /// The timeout is restarted by the first poll after a frame is produced,
+    /// so that it only measures time spent waiting on the inner body.
+    fn poll_idle(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Result<()> {
+        let this = self.project();
+        if let (Some(mut idle), Some(timeout)) = (this.idle.as_pin_mut(), *this.idle_timeout) {
+            if !*this.waiting {
+                *this.waiting = true;
+                idle.as_mut().reset(Instant::now() + timeout);
+            }
+            if idle.poll(cx).is_ready() {
+                return Err(HttpRouteIdleTimeout(timeout).into());
             }
         }
+        Ok(())
+    }
+}
+
+impl<B> HttpBody for ResponseBody<B>
+where
+    B: HttpBody,
+    B::Error: Into<Error>,
+{
+    type Data = B::Data;
+    type Error = Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Result<Self::Data>>> {
+        let res = match self.as_mut().project().inner.poll_data(cx) {
+            task::Poll::Ready(res) => res,
+            task::Poll::Pending => {
+                self.poll_idle(cx)?;
+                return task::Poll::Pending;
+            }
+        };
+        *self.project().waiting = false;
+        task::Poll::Ready(res.map(|r| r.map_err(Into::into)))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Result<Option<::http::HeaderMap>>> {
+        let res = match self.as_mut().project().inner.poll_trailers(cx) {
+            task::Poll::Ready(res) => res,
+            task::Poll::Pending => {
+                self.poll_idle(cx)?;
+                return task::Poll::Pending;
+            }
+        };
+        *self.project().waiting = false;
+        task::Poll::Ready(res.map_err(Into::into))
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
     }
 
-    Ok(())
+    #[inline]
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl<B: Default> Default for ResponseBody<B> {
+    fn default() -> Self {
+        Self {
+            inner: B::default(),
+            idle: None,
+            idle_timeout: None,
+            waiting: false,
+        }
+    }
 }
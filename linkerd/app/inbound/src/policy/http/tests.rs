@@ -1,5 +1,5 @@
 use super::*;
-use crate::policy::{Authentication, Authorization, Meta, Protocol, ServerPolicy};
+use crate::policy::{Authentication, Authorization, Meta, Protocol, RouteTimeouts, ServerPolicy};
 use linkerd_app_core::{svc::Service, Infallible};
 use std::sync::Arc;
 
@@ -30,6 +30,7 @@ macro_rules! new_svc {
                     kind: "Server".into(),
                     name: "testsrv".into(),
                 }),
+                audit: false,
             },
         );
         let svc = HttpPolicyService {
@@ -90,6 +91,7 @@ async fn http_route() {
                     }]),
                     filters: vec![],
                     meta: rmeta.clone(),
+                    timeouts: Default::default(),
                 },
             },
             Rule {
@@ -101,6 +103,7 @@ async fn http_route() {
                     authorizations: Arc::new([]),
                     filters: vec![],
                     meta: rmeta.clone(),
+                    timeouts: Default::default(),
                 },
             }
         ],
@@ -174,6 +177,7 @@ async fn http_route() {
                         }]),
                         filters: vec![],
                         meta: rmeta.clone(),
+                        timeouts: Default::default(),
                     },
                 },
                 Rule {
@@ -193,10 +197,12 @@ async fn http_route() {
                         }]),
                         filters: vec![],
                         meta: rmeta.clone(),
+                        timeouts: Default::default(),
                     },
                 },
             ],
         }])),
+        audit: false,
     })
     .expect("must send");
 
@@ -237,6 +243,72 @@ async fn http_route() {
     assert_eq!(permit.labels.route.route, rmeta);
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn http_route_audit() {
+    use linkerd_server_policy::http::{r#match::MatchRequest, Policy, Route, Rule};
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let proto = Protocol::Http1(Arc::new([Route {
+        hosts: vec![],
+        rules: vec![Rule {
+            matches: vec![MatchRequest::default()],
+            policy: Policy {
+                authorizations: Arc::new([]),
+                filters: vec![],
+                meta: rmeta.clone(),
+                timeouts: Default::default(),
+            },
+        }],
+    }]));
+    let (mut svc, tx) = new_svc!(proto.clone());
+
+    svc.call(
+        ::http::Request::builder()
+            .body(hyper::Body::default())
+            .unwrap(),
+    )
+    .await
+    .expect_err("fails");
+
+    // When the server is audited, requests that would be denied are permitted.
+    tx.send(ServerPolicy {
+        protocol: proto,
+        meta: Arc::new(Meta::Resource {
+            group: "policy.linkerd.io".into(),
+            kind: "Server".into(),
+            name: "testsrv".into(),
+        }),
+        audit: true,
+    })
+    .expect("must send");
+    let rsp = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect("serves");
+    let permit = rsp
+        .extensions()
+        .get::<HttpRoutePermit>()
+        .expect("permitted");
+    assert_eq!(permit.labels.route.route, rmeta);
+    assert_eq!(permit.labels.authz, Meta::new_default("audit"));
+
+    // Audited requests are only counted as audit denials.
+    let metrics = {
+        use linkerd_app_core::metrics::FmtMetrics;
+        svc.metrics.as_display().to_string()
+    };
+    assert!(metrics.contains("inbound_http_authz_audit_deny_total{"));
+    assert!(!metrics.contains("inbound_http_authz_allow_total{"));
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn http_filter_header() {
     use linkerd_server_policy::http::{filter, r#match::MatchRequest, Filter, Policy, Route, Rule};
@@ -268,6 +340,7 @@ async fn http_filter_header() {
                     ..filter::ModifyHeader::default()
                 })],
                 meta: rmeta.clone(),
+                timeouts: Default::default(),
             },
         }],
     }]));
@@ -300,6 +373,135 @@ async fn http_filter_header() {
     assert_eq!(permit.labels.route.route, rmeta);
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn http_filter_response_header() {
+    use linkerd_server_policy::http::{filter, r#match::MatchRequest, Filter, Policy, Route, Rule};
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let proto = Protocol::Http1(Arc::new([Route {
+        hosts: vec![],
+        rules: vec![Rule {
+            matches: vec![MatchRequest {
+                method: Some(::http::Method::GET),
+                ..MatchRequest::default()
+            }],
+            policy: Policy {
+                authorizations: Arc::new([Authorization {
+                    authentication: Authentication::Unauthenticated,
+                    networks: vec![std::net::IpAddr::from([192, 168, 3, 3]).into()],
+                    meta: Arc::new(Meta::Resource {
+                        group: "policy.linkerd.io".into(),
+                        kind: "AuthorizatoinPolicy".into(),
+                        name: "test".into(),
+                    }),
+                }]),
+                filters: vec![Filter::ResponseHeaders(Arc::new(filter::ModifyHeader {
+                    set: vec![(
+                        "x-content-type-options".parse().unwrap(),
+                        "nosniff".parse().unwrap(),
+                    )],
+                    remove: vec!["server".parse().unwrap()],
+                    ..filter::ModifyHeader::default()
+                }))],
+                meta: rmeta.clone(),
+                timeouts: Default::default(),
+            },
+        }],
+    }]));
+    let inner = |_: HttpRoutePermit, req: ::http::Request<hyper::Body>| -> Result<_> {
+        assert!(req.headers().is_empty());
+        let rsp = ::http::Response::builder()
+            .header("server", "testsrv")
+            .header("x-content-type-options", "sniff")
+            .body(hyper::Body::default())
+            .unwrap();
+        Ok(rsp)
+    };
+    let (mut svc, _tx) = new_svc!(proto, conn!(), inner);
+
+    let rsp = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect("serves");
+    assert_eq!(rsp.headers().len(), 1);
+    assert_eq!(
+        rsp.headers().get("x-content-type-options"),
+        Some(&"nosniff".parse().unwrap())
+    );
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_filter_rewrite_url() {
+    use linkerd_server_policy::http::{
+        filter,
+        r#match::{MatchPath, MatchRequest},
+        Filter, Policy, Route, Rule,
+    };
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let proto = Protocol::Http1(Arc::new([Route {
+        hosts: vec![],
+        rules: vec![Rule {
+            matches: vec![MatchRequest {
+                path: Some(MatchPath::Prefix("/v1".to_string())),
+                ..MatchRequest::default()
+            }],
+            policy: Policy {
+                authorizations: Arc::new([Authorization {
+                    authentication: Authentication::Unauthenticated,
+                    networks: vec![std::net::IpAddr::from([192, 168, 3, 3]).into()],
+                    meta: Arc::new(Meta::Resource {
+                        group: "policy.linkerd.io".into(),
+                        kind: "AuthorizatoinPolicy".into(),
+                        name: "test".into(),
+                    }),
+                }]),
+                filters: vec![Filter::RewriteUrl(filter::RewriteUrl {
+                    host: Some("bar.example.com".parse().unwrap()),
+                    path: Some(filter::ModifyPath::ReplacePrefixMatch(
+                        "/api/v1".to_string(),
+                    )),
+                })],
+                meta: rmeta.clone(),
+                timeouts: Default::default(),
+            },
+        }],
+    }]));
+    let inner = |_: HttpRoutePermit, req: ::http::Request<hyper::Body>| -> Result<_> {
+        assert_eq!(req.uri(), "http://bar.example.com:8080/api/v1/baz?qux=1");
+        assert_eq!(
+            req.headers().get(::http::header::HOST),
+            Some(&"bar.example.com:8080".parse().unwrap())
+        );
+        Ok(::http::Response::builder()
+            .body(hyper::Body::default())
+            .unwrap())
+    };
+    let (mut svc, _tx) = new_svc!(proto, conn!(), inner);
+
+    svc.call(
+        ::http::Request::builder()
+            .uri("http://foo.example.com:8080/v1/baz?qux=1")
+            .header(::http::header::HOST, "foo.example.com:8080")
+            .body(hyper::Body::default())
+            .unwrap(),
+    )
+    .await
+    .expect("serves");
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn http_filter_inject_failure() {
     use linkerd_server_policy::http::{filter, r#match::MatchRequest, Filter, Policy, Route, Rule};
@@ -334,6 +536,7 @@ async fn http_filter_inject_failure() {
                     },
                 })],
                 meta: rmeta.clone(),
+                timeouts: Default::default(),
             },
         }],
     }]));
@@ -359,6 +562,321 @@ async fn http_filter_inject_failure() {
     );
 }
 
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn http_filter_inject_delay() {
+    use linkerd_server_policy::http::{filter, r#match::MatchRequest, Filter, Policy, Route, Rule};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let proto = Protocol::Http1(Arc::new([Route {
+        hosts: vec![],
+        rules: vec![Rule {
+            matches: vec![MatchRequest::default()],
+            policy: Policy {
+                authorizations: Arc::new([Authorization {
+                    authentication: Authentication::Unauthenticated,
+                    networks: vec![std::net::IpAddr::from([192, 168, 3, 3]).into()],
+                    meta: Arc::new(Meta::Resource {
+                        group: "policy.linkerd.io".into(),
+                        kind: "AuthorizatoinPolicy".into(),
+                        name: "test".into(),
+                    }),
+                }]),
+                filters: vec![Filter::InjectDelay(filter::InjectDelay {
+                    delay: filter::Delay::Fixed(Duration::from_secs(3)),
+                    distribution: filter::Distribution::from_ratio(1, 1).unwrap(),
+                })],
+                meta: rmeta.clone(),
+                timeouts: Default::default(),
+            },
+        }],
+    }]));
+    let start = Instant::now();
+    let inner = move |_: HttpRoutePermit, _: ::http::Request<hyper::Body>| -> Result<_> {
+        assert!(Instant::now().saturating_duration_since(start) >= Duration::from_secs(3));
+        Ok(::http::Response::builder()
+            .body(hyper::Body::default())
+            .unwrap())
+    };
+    let (mut svc, _tx) = new_svc!(proto, conn!(), inner);
+
+    svc.call(
+        ::http::Request::builder()
+            .body(hyper::Body::default())
+            .unwrap(),
+    )
+    .await
+    .expect("serves");
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_filter_rate_limit() {
+    use linkerd_server_policy::{
+        http::{r#match::MatchRequest, Filter, Policy, Route, Rule},
+        RateLimit, RateLimitKey,
+    };
+    use std::num::NonZeroU32;
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let proto = Protocol::Http1(Arc::new([Route {
+        hosts: vec![],
+        rules: vec![Rule {
+            matches: vec![MatchRequest {
+                method: Some(::http::Method::GET),
+                ..MatchRequest::default()
+            }],
+            policy: Policy {
+                authorizations: Arc::new([Authorization {
+                    authentication: Authentication::Unauthenticated,
+                    networks: vec![std::net::IpAddr::from([192, 168, 3, 3]).into()],
+                    meta: Arc::new(Meta::Resource {
+                        group: "policy.linkerd.io".into(),
+                        kind: "AuthorizatoinPolicy".into(),
+                        name: "test".into(),
+                    }),
+                }]),
+                filters: vec![Filter::RateLimit(RateLimit::new(
+                    NonZeroU32::new(1).unwrap(),
+                    NonZeroU32::new(1).unwrap(),
+                    RateLimitKey::ClientIdentity,
+                ))],
+                meta: rmeta.clone(),
+                timeouts: Default::default(),
+            },
+        }],
+    }]));
+    let inner = |permit: HttpRoutePermit, _: ::http::Request<hyper::Body>| -> Result<_> {
+        let mut rsp = ::http::Response::builder()
+            .body(hyper::Body::default())
+            .unwrap();
+        rsp.extensions_mut().insert(permit);
+        Ok(rsp)
+    };
+    let (mut svc, _tx) = new_svc!(proto, conn!(), inner);
+
+    let rsp = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect("serves");
+    let permit = rsp
+        .extensions()
+        .get::<HttpRoutePermit>()
+        .expect("permitted");
+    assert_eq!(permit.labels.route.route, rmeta);
+
+    let err = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect_err("fails");
+    assert!(err.is::<HttpRouteRateLimited>());
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn http_filter_response_header_on_rate_limit() {
+    use linkerd_server_policy::{
+        http::{filter, r#match::MatchRequest, Filter, Policy, Route, Rule},
+        RateLimit, RateLimitKey,
+    };
+    use std::num::NonZeroU32;
+
+    let proto = Protocol::Http1(Arc::new([Route {
+        hosts: vec![],
+        rules: vec![Rule {
+            matches: vec![MatchRequest::default()],
+            policy: Policy {
+                authorizations: Arc::new([Authorization {
+                    authentication: Authentication::Unauthenticated,
+                    networks: vec![std::net::IpAddr::from([192, 168, 3, 3]).into()],
+                    meta: Arc::new(Meta::Resource {
+                        group: "policy.linkerd.io".into(),
+                        kind: "AuthorizationPolicy".into(),
+                        name: "test".into(),
+                    }),
+                }]),
+                // The rate limit precedes the response header filter, which
+                // must still be applied to the rate-limited response.
+                filters: vec![
+                    Filter::RateLimit(RateLimit::new(
+                        NonZeroU32::new(1).unwrap(),
+                        NonZeroU32::new(1).unwrap(),
+                        RateLimitKey::Route,
+                    )),
+                    Filter::ResponseHeaders(Arc::new(filter::ModifyHeader {
+                        set: vec![(
+                            "x-content-type-options".parse().unwrap(),
+                            "nosniff".parse().unwrap(),
+                        )],
+                        ..filter::ModifyHeader::default()
+                    })),
+                ],
+                meta: Arc::new(Meta::Resource {
+                    group: "gateway.networking.k8s.io".into(),
+                    kind: "httproute".into(),
+                    name: "testrt".into(),
+                }),
+                timeouts: Default::default(),
+            },
+        }],
+    }]));
+    let inner = |_: HttpRoutePermit, _: ::http::Request<hyper::Body>| -> Result<_> {
+        Ok(::http::Response::builder()
+            .body(hyper::Body::default())
+            .unwrap())
+    };
+    let (mut svc, _tx) = new_svc!(proto, conn!(), inner);
+
+    let rsp = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect("serves");
+    assert_eq!(
+        rsp.headers().get("x-content-type-options"),
+        Some(&"nosniff".parse().unwrap())
+    );
+
+    let err = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect_err("fails");
+    assert!(errors::is_caused_by::<HttpRouteRateLimited>(&*err));
+    let headers = errors::cause_ref::<HttpRouteResponseHeaders>(&*err)
+        .expect("response headers must be applied to the error response");
+    assert_eq!(headers.modify_headers().len(), 1);
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn http_route_request_timeout() {
+    use linkerd_server_policy::http::{r#match::MatchRequest, Policy, Route, Rule};
+
+    let rmeta = Arc::new(Meta::Resource {
+        group: "gateway.networking.k8s.io".into(),
+        kind: "httproute".into(),
+        name: "testrt".into(),
+    });
+    let (policy, _tx) = AllowPolicy::for_test(
+        conn!().dst,
+        ServerPolicy {
+            protocol: Protocol::Http1(Arc::new([Route {
+                hosts: vec![],
+                rules: vec![Rule {
+                    matches: vec![MatchRequest::default()],
+                    policy: Policy {
+                        authorizations: Arc::new([Authorization {
+                            authentication: Authentication::Unauthenticated,
+                            networks: vec![std::net::IpAddr::from([192, 168, 3, 3]).into()],
+                            meta: Arc::new(Meta::Resource {
+                                group: "policy.linkerd.io".into(),
+                                kind: "AuthorizationPolicy".into(),
+                                name: "test".into(),
+                            }),
+                        }]),
+                        filters: vec![],
+                        meta: rmeta.clone(),
+                        timeouts: RouteTimeouts {
+                            request: Some(std::time::Duration::from_secs(1)),
+                            idle: None,
+                        },
+                    },
+                }],
+            }])),
+            meta: Arc::new(Meta::Resource {
+                group: "policy.linkerd.io".into(),
+                kind: "Server".into(),
+                name: "testsrv".into(),
+            }),
+            audit: false,
+        },
+    );
+    let mut svc = HttpPolicyService {
+        target: (),
+        policy,
+        connection: conn!(),
+        metrics: HttpAuthzMetrics::default(),
+        inner: |(_, _): (HttpRoutePermit, ())| {
+            svc::mk(move |_: ::http::Request<hyper::Body>| {
+                futures::future::pending::<Result<::http::Response<hyper::Body>>>()
+            })
+        },
+    };
+
+    let err = svc
+        .call(
+            ::http::Request::builder()
+                .body(hyper::Body::default())
+                .unwrap(),
+        )
+        .await
+        .expect_err("request must time out");
+    assert!(err.is::<HttpRouteRequestTimeout>());
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn http_route_idle_timeout_ignores_slow_readers() {
+    use linkerd_app_core::proxy::http::HttpBody;
+
+    let timeout = std::time::Duration::from_secs(1);
+    let (mut tx, inner) = hyper::Body::channel();
+    let mut body = Box::pin(ResponseBody {
+        inner,
+        idle: Some(time::sleep(timeout)),
+        idle_timeout: Some(timeout),
+        waiting: false,
+    });
+
+    tx.send_data("hello".into()).await.unwrap();
+    body.data()
+        .await
+        .expect("body must yield a frame")
+        .expect("frame must not fail");
+
+    // The reader takes longer than the idle timeout to poll the body again,
+    // which must not count against the stream.
+    time::sleep(timeout * 5).await;
+    let (frame, sent) = tokio::join!(body.data(), async {
+        time::sleep(timeout / 2).await;
+        tx.send_data("world".into()).await
+    });
+    sent.unwrap();
+    frame
+        .expect("body must yield a frame")
+        .expect("slow reader must not time out");
+
+    // The stream times out once it has been polled without producing a frame
+    // for longer than the idle timeout.
+    let err = body
+        .data()
+        .await
+        .expect("body must fail")
+        .expect_err("idle stream must time out");
+    assert!(err.is::<HttpRouteIdleTimeout>());
+    drop(tx);
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn grpc_route() {
     use linkerd_server_policy::grpc::{
@@ -394,6 +912,7 @@ async fn grpc_route() {
                     }]),
                     filters: vec![],
                     meta: rmeta.clone(),
+                    timeouts: Default::default(),
                 },
             },
             Rule {
@@ -408,6 +927,7 @@ async fn grpc_route() {
                     authorizations: Arc::new([]),
                     filters: vec![],
                     meta: rmeta.clone(),
+                    timeouts: Default::default(),
                 },
             }
         ],
@@ -495,6 +1015,7 @@ async fn grpc_filter_header() {
                     ..http::filter::ModifyHeader::default()
                 })],
                 meta: rmeta.clone(),
+                timeouts: Default::default(),
             },
         }],
     }]));
@@ -571,6 +1092,7 @@ async fn grpc_filter_inject_failure() {
                     },
                 })],
                 meta: rmeta.clone(),
+                timeouts: Default::default(),
             },
         }],
     }]));
@@ -1,4 +1,4 @@
-use super::{api, AllowPolicy, DefaultPolicy, GetPolicy};
+use super::{api, file, AllowPolicy, DefaultPolicy, GetPolicy};
 use linkerd_app_core::{proxy::http, transport::OrigDstAddr, Error};
 use linkerd_cache::Cache;
 pub use linkerd_server_policy::{
@@ -16,6 +16,7 @@ pub struct Store<S> {
     cache: Cache<u16, Rx, BuildHasherDefault<PortHasher>>,
     default_rx: Rx,
     discover: Option<api::Watch<S>>,
+    file: Option<file::Watch>,
 }
 
 type Rx = watch::Receiver<ServerPolicy>;
@@ -49,6 +50,33 @@ impl<S> Store<S> {
         Self {
             cache,
             discover: None,
+            file: None,
+            default_rx: Self::spawn_default(default),
+        }
+    }
+
+    /// Watches policies from a local policy file.
+    ///
+    /// Ports that are described by the file when the proxy starts are cached
+    /// for as long as the `Store` is held. Policies for other ports are
+    /// watched as they are requested, and expire after `idle_timeout`.
+    pub(super) fn spawn_file(
+        default: DefaultPolicy,
+        idle_timeout: Duration,
+        file: file::Watch,
+    ) -> Self {
+        let cache = {
+            let rxs = file
+                .ports()
+                .into_iter()
+                .map(|port| (port, file.spawn_with_init(port)));
+            Cache::with_permanent_from_iter(idle_timeout, rxs)
+        };
+
+        Self {
+            cache,
+            discover: None,
+            file: Some(file),
             default_rx: Self::spawn_default(default),
         }
     }
@@ -89,6 +117,7 @@ impl<S> Store<S> {
         Self {
             cache,
             discover: Some(discover),
+            file: None,
             default_rx: Self::spawn_default(default),
         }
     }
@@ -114,25 +143,31 @@ where
 {
     fn get_policy(&self, dst: OrigDstAddr) -> AllowPolicy {
         // Lookup the polcify for the target port in the cache. If it doesn't
-        // already exist, we spawn a watch on the API (if it is configured). If
-        // no discovery API is configured we use the default policy.
-        let server =
-            self.cache
-                .get_or_insert_with(dst.port(), |port| match self.discover.clone() {
-                    Some(disco) => info_span!("watch", port).in_scope(|| {
-                        tracing::trace!(%port, "spawning policy discovery");
-                        disco.spawn_with_init(*port, self.default_rx.borrow().clone())
-                    }),
-
-                    // If no discovery API is configured, then we use the
-                    // default policy. Whlie it's a little wasteful to cache
-                    // these results separately, this case isn't expected to be
-                    // used outside of testing.
-                    None => {
-                        tracing::trace!(%port, "using the default policy");
-                        self.default_rx.clone()
-                    }
-                });
+        // already exist, we spawn a watch on the API (if it is configured) or
+        // on the policy file (if one is configured). Otherwise, we use the
+        // default policy.
+        let server = self.cache.get_or_insert_with(dst.port(), |port| {
+            match (self.discover.clone(), self.file.as_ref()) {
+                (Some(disco), _) => info_span!("watch", port).in_scope(|| {
+                    tracing::trace!(%port, "spawning policy discovery");
+                    disco.spawn_with_init(*port, self.default_rx.borrow().clone())
+                }),
+
+                (None, Some(file)) => {
+                    tracing::trace!(%port, "watching the policy file");
+                    file.spawn_with_init(*port)
+                }
+
+                // If no discovery API is configured, then we use the
+                // default policy. Whlie it's a little wasteful to cache
+                // these results separately, this case isn't expected to be
+                // used outside of testing.
+                (None, None) => {
+                    tracing::trace!(%port, "using the default policy");
+                    self.default_rx.clone()
+                }
+            }
+        });
 
         AllowPolicy { dst, server }
     }
@@ -69,8 +69,9 @@ where
             let p = policy.server.borrow();
             tracing::trace!(policy = ?p, "Authorizing connection");
             check_authorized(&*p, policy.dst, client, &tls)
+                .map_err(|deny| (deny, p.audit.then(|| ServerPermit::audit(policy.dst, &*p))))
         };
-        match authorized {
+        let permit = match authorized {
             Ok(permit) => {
                 tracing::debug!(?permit, ?tls, %client, "Connection authorized");
 
@@ -78,29 +79,45 @@ where
                 // connection. So we can just increment the counter here since the service can only
                 // be used at most once.
                 self.metrics.allow(&permit, tls.clone());
-
-                let inner = self.inner.new_service((permit, target));
-                TcpPolicy::Authorized(Authorized {
-                    inner,
-                    policy,
-                    client,
-                    tls,
-                    metrics: self.metrics.clone(),
-                })
+                permit
             }
-            Err(deny) => {
+            Err((deny, audit)) => {
                 let meta = policy.meta();
-                tracing::info!(
-                    server.group = %meta.group(),
-                    server.kind = %meta.kind(),
-                    server.name = %meta.name(),
-                    ?tls, %client,
-                    "Connection denied"
-                );
-                self.metrics.deny(&policy, tls);
-                TcpPolicy::Unauthorized(deny)
+                match audit {
+                    Some(permit) => {
+                        tracing::info!(
+                            server.group = %meta.group(),
+                            server.kind = %meta.kind(),
+                            server.name = %meta.name(),
+                            ?tls, %client,
+                            "Connection would be denied; allowing for audit"
+                        );
+                        self.metrics.audit_deny(&policy, tls.clone());
+                        permit
+                    }
+                    None => {
+                        tracing::info!(
+                            server.group = %meta.group(),
+                            server.kind = %meta.kind(),
+                            server.name = %meta.name(),
+                            ?tls, %client,
+                            "Connection denied"
+                        );
+                        self.metrics.deny(&policy, tls);
+                        return TcpPolicy::Unauthorized(deny);
+                    }
+                }
             }
-        }
+        };
+
+        let inner = self.inner.new_service((permit, target));
+        TcpPolicy::Authorized(Authorized {
+            inner,
+            policy,
+            client,
+            tls,
+            metrics: self.metrics.clone(),
+        })
     }
 }
 
@@ -157,8 +174,25 @@ where
                 tokio::select! {
                     res = &mut call => return res.map_err(Into::into),
                     _ = policy.changed() => {
-                        if let Err(denied) = check_authorized(&*policy.server.borrow(), policy.dst, client, &tls) {
+                        let authorized = {
+                            let p = policy.server.borrow();
+                            check_authorized(&*p, policy.dst, client, &tls).map_err(|deny| (deny, p.audit))
+                        };
+                        if let Err((denied, audit)) = authorized {
                             let meta = policy.meta();
+                            if audit {
+                                tracing::info!(
+                                    server.group = %meta.group(),
+                                    server.kind = %meta.kind(),
+                                    server.name = %meta.name(),
+                                    ?tls,
+                                    %client,
+                                    "Connection would be terminated due to policy change; allowing for audit",
+                                );
+                                metrics.audit_deny(&policy, tls.clone());
+                                continue;
+                            }
+
                             tracing::info!(
                                 server.group = %meta.group(),
                                 server.kind = %meta.kind(),
@@ -193,7 +227,7 @@ fn check_authorized(
     | Protocol::Opaque(authzs) = &server.protocol
     {
         for authz in &**authzs {
-            if super::is_authorized(authz, client_addr, tls) {
+            if super::is_authorized(authz, client_addr, tls, None) {
                 return Ok(ServerPermit::new(dst, &*server, authz));
             }
         }
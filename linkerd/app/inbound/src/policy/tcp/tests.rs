@@ -27,6 +27,7 @@ async fn unauthenticated_allowed() {
             kind: "server".into(),
             name: "test".into(),
         }),
+        audit: false,
     };
 
     let tls = tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello);
@@ -61,6 +62,7 @@ async fn authenticated_identity() {
                 authentication: Authentication::TlsAuthenticated {
                     suffixes: vec![],
                     identities: vec![client_id().to_string()].into_iter().collect(),
+                    spiffe_prefixes: vec![],
                 },
                 networks: vec!["192.0.2.0/24".parse().unwrap()],
                 meta: Arc::new(Meta::Resource {
@@ -76,6 +78,7 @@ async fn authenticated_identity() {
             kind: "server".into(),
             name: "test".into(),
         }),
+        audit: false,
     };
 
     let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
@@ -124,6 +127,7 @@ async fn authenticated_suffix() {
                 authentication: Authentication::TlsAuthenticated {
                     identities: BTreeSet::default(),
                     suffixes: vec![Suffix::from(vec!["cluster".into(), "local".into()])],
+                    spiffe_prefixes: vec![],
                 },
                 networks: vec!["192.0.2.0/24".parse().unwrap()],
                 meta: Arc::new(Meta::Resource {
@@ -139,6 +143,7 @@ async fn authenticated_suffix() {
             kind: "server".into(),
             name: "test".into(),
         }),
+        audit: false,
     };
 
     let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
@@ -178,6 +183,61 @@ async fn authenticated_suffix() {
         .expect_err("policy must require a client identity");
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn authenticated_spiffe() {
+    let policy = ServerPolicy {
+        protocol: Protocol::Opaque(
+            vec![Authorization {
+                authentication: Authentication::TlsAuthenticated {
+                    identities: vec!["spiffe://example.org/billing/api".to_string()]
+                        .into_iter()
+                        .collect(),
+                    suffixes: vec![Suffix::from(vec!["cluster".into(), "local".into()])],
+                    spiffe_prefixes: vec!["spiffe://example.org/ns/web".parse().unwrap()],
+                },
+                networks: vec!["192.0.2.0/24".parse().unwrap()],
+                meta: Arc::new(Meta::Resource {
+                    group: "policy.linkerd.io".into(),
+                    kind: "serverauthorization".into(),
+                    name: "spiffe".into(),
+                }),
+            }]
+            .into(),
+        ),
+        meta: Arc::new(Meta::Resource {
+            group: "policy.linkerd.io".into(),
+            kind: "server".into(),
+            name: "test".into(),
+        }),
+        audit: false,
+    };
+
+    let tls = |id: &str| {
+        tls::ConditionalServerTls::Some(tls::ServerTls::Established {
+            client_id: Some(id.parse().unwrap()),
+            negotiated_protocol: None,
+        })
+    };
+    for id in &[
+        "spiffe://example.org/billing/api",
+        "spiffe://example.org/ns/web",
+        "spiffe://example.org/ns/web/sa/frontend",
+    ] {
+        check_authorized(&policy, orig_dst_addr(), client_addr(), &tls(id))
+            .unwrap_or_else(|_| panic!("{} must be permitted", id));
+    }
+    for id in &[
+        "spiffe://example.org/billing/api/v2",
+        "spiffe://example.org/ns/webhooks",
+        "spiffe://example.com/ns/web/sa/frontend",
+        // DNS suffixes do not apply to SPIFFE IDs.
+        "spiffe://example.org/svc.cluster.local",
+    ] {
+        check_authorized(&policy, orig_dst_addr(), client_addr(), &tls(id))
+            .expect_err("policy must not permit the client identity");
+    }
+}
+
 #[tokio::test(flavor = "current_thread")]
 async fn tls_unauthenticated() {
     let policy = ServerPolicy {
@@ -198,6 +258,7 @@ async fn tls_unauthenticated() {
             kind: "server".into(),
             name: "test".into(),
         }),
+        audit: false,
     };
 
     let tls = tls::ConditionalServerTls::Some(tls::ServerTls::Established {
@@ -234,6 +295,70 @@ async fn tls_unauthenticated() {
         .expect_err("policy must require a TLS termination identity");
 }
 
+#[tokio::test(flavor = "current_thread")]
+async fn audit_allows_unauthorized() {
+    use linkerd_app_core::svc::{layer::Layer, NewService, ServiceExt};
+
+    #[derive(Clone)]
+    struct Target(AllowPolicy);
+
+    impl svc::Param<AllowPolicy> for Target {
+        fn param(&self) -> AllowPolicy {
+            self.0.clone()
+        }
+    }
+
+    impl svc::Param<Remote<ClientAddr>> for Target {
+        fn param(&self) -> Remote<ClientAddr> {
+            client_addr()
+        }
+    }
+
+    impl svc::Param<tls::ConditionalServerTls> for Target {
+        fn param(&self) -> tls::ConditionalServerTls {
+            tls::ConditionalServerTls::None(tls::NoServerTls::NoClientHello)
+        }
+    }
+
+    let (policy, _tx) = AllowPolicy::for_test(
+        orig_dst_addr(),
+        ServerPolicy {
+            protocol: Protocol::Opaque(Arc::new([])),
+            meta: Arc::new(Meta::Resource {
+                group: "policy.linkerd.io".into(),
+                kind: "server".into(),
+                name: "test".into(),
+            }),
+            audit: true,
+        },
+    );
+
+    let metrics = TcpAuthzMetrics::default();
+    let new_policy =
+        NewTcpPolicy::layer(metrics.clone()).layer(|(permit, _): (ServerPermit, Target)| {
+            svc::mk(move |_: ()| {
+                let permit = permit.clone();
+                async move {
+                    assert_eq!(permit.labels.authz, Meta::new_default("audit"));
+                    Ok::<_, Error>(())
+                }
+            })
+        });
+    new_policy
+        .new_service(Target(policy))
+        .oneshot(())
+        .await
+        .expect("audited connection must be allowed");
+
+    // Audited connections are only counted as audit denials.
+    let metrics = {
+        use linkerd_app_core::metrics::FmtMetrics;
+        metrics.as_display().to_string()
+    };
+    assert!(metrics.contains("inbound_tcp_authz_audit_deny_total{"));
+    assert!(!metrics.contains("inbound_tcp_authz_allow_total{"));
+}
+
 fn client_id() -> tls::ClientId {
     "testsa.testns.serviceaccount.identity.linkerd.cluster.local"
         .parse()
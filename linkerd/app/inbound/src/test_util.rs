@@ -44,6 +44,7 @@ pub fn default_config() -> Config {
                 kind: "server".into(),
                 name: "testsrv".into(),
             }),
+            audit: false,
         }
         .into(),
         ports: Default::default(),
@@ -56,6 +57,8 @@ pub fn default_config() -> Config {
                 addr: ListenAddr(([0, 0, 0, 0], 0).into()),
                 keepalive: Keepalive(None),
                 h2_settings: h2::Settings::default(),
+                proxy_protocol: None,
+                unix_listen_path: None,
             },
             connect: config::ConnectConfig {
                 keepalive: Keepalive(None),
@@ -81,6 +84,9 @@ pub fn default_config() -> Config {
         policy,
         profile_idle_timeout: Duration::from_millis(500),
         allowed_ips: Default::default(),
+        unix_sockets: Default::default(),
+        mirror_max_buffered_bytes: 64 * 1024,
+        mirror_max_in_flight: 100,
     }
 }
 
@@ -2,14 +2,19 @@ use crate::Outbound;
 use linkerd_app_core::{
     io, profiles,
     svc::{self, stack::Param},
+    tls,
     transport::OrigDstAddr,
-    Error,
+    Error, NameAddr,
 };
 use tracing::debug;
 
 impl<N> Outbound<N> {
     /// Discovers the profile for a TCP endpoint.
     ///
+    /// When the application originates TLS with an SNI in the discoverable
+    /// domains, the profile is discovered by name; otherwise, it is discovered
+    /// by the original destination address.
+    ///
     /// Resolved services are cached and buffered.
     pub fn push_discover<T, I, NSvc, P>(
         self,
@@ -21,7 +26,7 @@ impl<N> Outbound<N> {
         >,
     >
     where
-        T: Param<OrigDstAddr>,
+        T: Param<OrigDstAddr> + Param<Option<tls::ServerId>>,
         T: Clone + Eq + std::fmt::Debug + std::hash::Hash + Send + Sync + 'static,
         I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + std::fmt::Debug + Send + Unpin + 'static,
         N: svc::NewService<(Option<profiles::Receiver>, T), Service = NSvc>,
@@ -37,6 +42,14 @@ impl<N> Outbound<N> {
             inner
                 .push(profiles::discover::layer(profiles, move |t: T| {
                     let OrigDstAddr(addr) = t.param();
+                    if let Some(tls::ServerId(sni)) = t.param() {
+                        if allow.names().matches(&sni) {
+                            debug!(%sni, "Allowing profile lookup by SNI");
+                            let name = NameAddr::from(((*sni).clone(), addr.port()));
+                            return Ok(profiles::LookupAddr(name.into()));
+                        }
+                        debug!(%sni, "SNI is not in discoverable domains");
+                    }
                     if allow.matches_ip(addr.ip()) {
                         debug!("Allowing profile lookup");
                         return Ok(profiles::LookupAddr(addr.into()));
@@ -327,6 +340,48 @@ mod tests {
         spawn_conn(svc).await.unwrap().expect("must not fail");
     }
 
+    /// Tests that the discover stack resolves profiles by the SNI of application-originated TLS
+    /// when the name is in a discoverable domain.
+    #[tokio::test(flavor = "current_thread")]
+    async fn profiles_by_sni() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        let addr = SocketAddr::new([192, 0, 2, 22].into(), 443);
+        let name = NameAddr::from_str_and_port("api.example.com", 443).unwrap();
+        let logical_addr = profiles::LogicalAddr(name.clone());
+        let profiles = support::profile::resolver().profile(
+            name,
+            profiles::Profile {
+                addr: Some(logical_addr.clone()),
+                ..Default::default()
+            },
+        );
+
+        // Mock an inner stack with a service that asserts that the profile was discovered by name.
+        let stack = move |(profile, _): (Option<profiles::Receiver>, _)| {
+            let profile = profile.expect("profile must resolve");
+            assert_eq!(profile.logical_addr(), Some(logical_addr.clone()));
+            svc::mk(move |_: io::DuplexStream| future::ok::<(), Error>(()))
+        };
+
+        let cfg = {
+            let mut cfg = default_config();
+            cfg.allow_discovery = AddrMatch::new(Some("example.com".parse().unwrap()), None);
+            cfg
+        };
+        let (rt, _shutdown) = runtime();
+        let stack = Outbound::new(cfg, rt)
+            .with_stack(stack)
+            .push_discover(profiles)
+            .into_inner();
+
+        let svc = stack.new_service(tcp::Accept {
+            sni: Some(tls::ServerId("api.example.com".parse().unwrap())),
+            ..tcp::Accept::from(OrigDstAddr(addr))
+        });
+        spawn_conn(svc).await.unwrap().expect("must not fail");
+    }
+
     fn spawn_conn<S>(mut svc: S) -> tokio::task::JoinHandle<Result<(), Error>>
     where
         S: Service<io::DuplexStream, Response = (), Error = Error> + Send + 'static,
@@ -5,7 +5,7 @@ use linkerd_app_core::{
     proxy::{api_resolve::Metadata, resolve::map_endpoint::MapEndpoint},
     svc, tls,
     transport::{self, addrs::*},
-    transport_header, Conditional,
+    transport_header, Conditional, Error, Infallible,
 };
 use std::{
     collections::HashSet,
@@ -20,10 +20,14 @@ pub struct Endpoint<P> {
     pub tls: tls::ConditionalClientTls,
     pub metadata: Metadata,
     pub logical_addr: Option<LogicalAddr>,
+    pub server_name: Option<tcp::originate_tls::ServerName>,
     pub protocol: P,
     pub opaque_protocol: bool,
 }
 
+/// The destination label that describes the zone in which an endpoint runs.
+const ZONE_LABEL: &str = "zone";
+
 #[derive(Clone)]
 pub struct FromMetadata {
     pub inbound_ips: Arc<HashSet<IpAddr>>,
@@ -42,6 +46,7 @@ impl Endpoint<()> {
             metadata: Metadata::default(),
             tls: Conditional::None(reason),
             logical_addr: None,
+            server_name: None,
             opaque_protocol,
             protocol: (),
         }
@@ -68,6 +73,7 @@ impl Endpoint<()> {
             tls,
             metadata,
             logical_addr: None,
+            server_name: None,
             opaque_protocol,
             protocol: (),
         }
@@ -86,6 +92,18 @@ impl<P> svc::Param<tls::ConditionalClientTls> for Endpoint<P> {
     }
 }
 
+impl<P> svc::Param<Option<LogicalAddr>> for Endpoint<P> {
+    fn param(&self) -> Option<LogicalAddr> {
+        self.logical_addr.clone()
+    }
+}
+
+impl<P> svc::Param<Option<tcp::originate_tls::ServerName>> for Endpoint<P> {
+    fn param(&self) -> Option<tcp::originate_tls::ServerName> {
+        self.server_name.clone()
+    }
+}
+
 impl<P> svc::Param<Option<http::detect::Skip>> for Endpoint<P> {
     fn param(&self) -> Option<http::detect::Skip> {
         if self.opaque_protocol {
@@ -113,6 +131,21 @@ impl<P> svc::Param<Option<http::AuthorityOverride>> for Endpoint<P> {
     }
 }
 
+impl<P> svc::Param<Option<http::balance::Zone>> for Endpoint<P> {
+    fn param(&self) -> Option<http::balance::Zone> {
+        self.metadata
+            .labels()
+            .get(ZONE_LABEL)
+            .map(|zone| http::balance::Zone::from(zone.as_str()))
+    }
+}
+
+impl<P> svc::Param<http::balance::Weight> for Endpoint<P> {
+    fn param(&self) -> http::balance::Weight {
+        http::balance::Weight(self.metadata.weight())
+    }
+}
+
 impl<P> svc::Param<transport::labels::Key> for Endpoint<P> {
     fn param(&self) -> transport::labels::Key {
         transport::labels::Key::OutboundClient(self.param())
@@ -190,6 +223,7 @@ impl<P: Copy + std::fmt::Debug> MapEndpoint<Concrete<P>, Metadata> for FromMetad
             tls,
             metadata,
             logical_addr: Some(concrete.logical.logical_addr.clone()),
+            server_name: None,
             protocol: concrete.logical.protocol,
             // XXX We never do protocol detection after resolving a concrete address to endpoints.
             // We should differentiate these target types statically.
@@ -215,6 +249,34 @@ impl<S> Outbound<S> {
             .clone()
             .push_tcp_endpoint::<http::Connect>()
             .push_http_endpoint()
+            .map_stack(|config, _, endpoint| {
+                // Unmeshed destinations are not discovered, so TLS may only be
+                // originated to them by the name in each request's authority.
+                // When TLS origination is configured, these requests are routed
+                // to an endpoint stack for their server name. As with ingress
+                // forwarding, these stacks are not cached.
+                let originate_tls = config.tls_origination.is_some();
+                endpoint
+                    .clone()
+                    .push(svc::NewRouter::layer(|ep: http::Endpoint| {
+                        move |req: &http::Request<http::BoxBody>| {
+                            Ok::<_, Error>(http::Endpoint {
+                                server_name: tcp::originate_tls::ServerName::from_request(req),
+                                ..ep.clone()
+                            })
+                        }
+                    }))
+                    .push_switch(
+                        move |ep: http::Endpoint| -> Result<_, Infallible> {
+                            if originate_tls && ep.logical_addr.is_none() && ep.tls.is_none() {
+                                return Ok(svc::Either::A(ep));
+                            }
+                            Ok(svc::Either::B(ep))
+                        },
+                        endpoint.into_inner(),
+                    )
+                    .push(svc::ArcNewService::layer())
+            })
             .push_http_server()
             .into_inner();
 
@@ -1,5 +1,6 @@
 pub mod detect;
 mod endpoint;
+mod hedge;
 pub mod logical;
 mod proxy_connection_close;
 mod require_id_header;
@@ -11,7 +12,9 @@ use self::{
     proxy_connection_close::ProxyConnectionClose, require_id_header::NewRequireIdentity,
     strip_proxy_error::NewStripProxyError,
 };
-pub(crate) use self::{require_id_header::IdentityRequired, server::ServerRescue};
+pub(crate) use self::{
+    require_id_header::IdentityRequired, retry::TryTimeoutError, server::ServerRescue,
+};
 use crate::tcp;
 pub use linkerd_app_core::proxy::http::*;
 use linkerd_app_core::{
@@ -110,6 +113,7 @@ impl From<(Version, tcp::Endpoint)> for Endpoint {
             tls: ep.tls,
             metadata: ep.metadata,
             logical_addr: ep.logical_addr,
+            server_name: ep.server_name,
             // If we know an HTTP version, the protocol must not be opaque.
             opaque_protocol: false,
         }
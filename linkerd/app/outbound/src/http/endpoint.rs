@@ -1,11 +1,15 @@
 use super::{NewRequireIdentity, NewStripProxyError, ProxyConnectionClose};
-use crate::{tcp::opaque_transport, Outbound};
+use crate::{
+    tcp::{opaque_transport, originate_tls::ServerName},
+    Outbound,
+};
 use linkerd_app_core::{
     classify, config, errors, http_tracing, metrics,
+    profiles::LogicalAddr,
     proxy::{http, tap},
     svc::{self, ExtractParam},
     tls,
-    transport::{self, Remote, ServerAddr},
+    transport::{self, ClientAddr, Remote, ServerAddr},
     transport_header::SessionProtocol,
     Error, Result, CANONICAL_DST_HEADER,
 };
@@ -188,6 +192,20 @@ impl<T: svc::Param<Option<http::AuthorityOverride>>> svc::Param<Option<http::Aut
     }
 }
 
+impl<T: svc::Param<Option<LogicalAddr>>> svc::Param<Option<LogicalAddr>> for Connect<T> {
+    #[inline]
+    fn param(&self) -> Option<LogicalAddr> {
+        self.inner.param()
+    }
+}
+
+impl<T: svc::Param<Option<ServerName>>> svc::Param<Option<ServerName>> for Connect<T> {
+    #[inline]
+    fn param(&self) -> Option<ServerName> {
+        self.inner.param()
+    }
+}
+
 impl<T: svc::Param<transport::labels::Key>> svc::Param<transport::labels::Key> for Connect<T> {
     #[inline]
     fn param(&self) -> transport::labels::Key {
@@ -195,6 +213,15 @@ impl<T: svc::Param<transport::labels::Key>> svc::Param<transport::labels::Key> f
     }
 }
 
+/// HTTP connections are pooled and shared by all clients, so they are never
+/// associated with a single client.
+impl<T> svc::Param<Option<Remote<ClientAddr>>> for Connect<T> {
+    #[inline]
+    fn param(&self) -> Option<Remote<ClientAddr>> {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -232,6 +259,7 @@ mod test {
             addr: Remote(ServerAddr(addr)),
             protocol: http::Version::Http1,
             logical_addr: None,
+            server_name: None,
             opaque_protocol: false,
             tls: tls::ConditionalClientTls::None(tls::NoClientTls::Disabled),
             metadata: Metadata::default(),
@@ -269,6 +297,7 @@ mod test {
             addr: Remote(ServerAddr(addr)),
             protocol: http::Version::H2,
             logical_addr: None,
+            server_name: None,
             opaque_protocol: false,
             tls: tls::ConditionalClientTls::None(tls::NoClientTls::Disabled),
             metadata: Metadata::default(),
@@ -308,6 +337,7 @@ mod test {
             addr: Remote(ServerAddr(addr)),
             protocol: http::Version::Http1,
             logical_addr: None,
+            server_name: None,
             opaque_protocol: false,
             tls: tls::ConditionalClientTls::None(tls::NoClientTls::Disabled),
             metadata: Metadata::new(None, ProtocolHint::Http2, None, None, None),
@@ -365,6 +395,7 @@ mod test {
             addr: Remote(ServerAddr(addr)),
             protocol: http::Version::Http1,
             logical_addr: None,
+            server_name: None,
             opaque_protocol: false,
             tls: tls::ConditionalClientTls::None(tls::NoClientTls::Disabled),
             metadata: Metadata::new(None, ProtocolHint::Http2, None, None, None),
@@ -408,6 +439,7 @@ mod test {
             addr: Remote(ServerAddr(addr)),
             protocol: http::Version::H2,
             logical_addr: None,
+            server_name: None,
             opaque_protocol: false,
             tls: tls::ConditionalClientTls::None(tls::NoClientTls::Disabled),
             metadata: Metadata::new(None, ProtocolHint::Http2, None, None, None),
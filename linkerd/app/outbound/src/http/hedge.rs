@@ -0,0 +1,251 @@
+use super::ProfileRoute;
+use futures::{future, FutureExt};
+use linkerd_app_core::{
+    is_caused_by, metrics, profiles,
+    proxy::http::{
+        balance::{DistinctEndpoint, SameEndpoint},
+        ClientHandle, HttpBody,
+    },
+    svc::{layer, NewService, Param, Service, ServiceExt},
+    Error,
+};
+use linkerd_retry as retry;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Weak,
+    },
+    task::{Context, Poll},
+};
+use tokio::time;
+
+/// The minimum number of responses that must be observed on a route before
+/// its latency distribution is used to hedge requests.
+const MIN_OBSERVATIONS: u64 = 100;
+
+/// How often each route's hedging delay is recomputed from its latencies.
+const DELAY_UPDATE_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+/// The number of times a hedged request is dispatched before it is abandoned,
+/// if the balancer keeps choosing the original request's endpoint.
+const MAX_HEDGE_DISPATCHES: usize = 3;
+
+/// Applies a route's hedging policy, if it has one.
+///
+/// Requests that have not received a response within the route's latency at
+/// the configured percentile are sent a second time. The second request is
+/// dispatched through the same balancer, but is never sent to the endpoint
+/// that is already processing the first request. Whichever response is
+/// received first is returned and the other request is canceled.
+///
+/// The route's latency at the percentile is computed periodically in the
+/// background, so requests do not read the route's latency histograms.
+pub fn layer<N>(
+    metrics: metrics::HttpProfileRoute,
+) -> impl layer::Layer<N, Service = NewHedge<N>> + Clone {
+    layer::mk(move |inner| NewHedge {
+        inner,
+        metrics: metrics.clone(),
+    })
+}
+
+#[derive(Clone, Debug)]
+pub struct NewHedge<N> {
+    inner: N,
+    metrics: metrics::HttpProfileRoute,
+}
+
+#[derive(Clone, Debug)]
+pub struct Hedge<S> {
+    inner: S,
+    policy: Option<Policy>,
+}
+
+#[derive(Clone, Debug)]
+struct Policy {
+    hedge: profiles::http::Hedge,
+    budget: Arc<retry::Budget>,
+    /// The route's latency at the hedge percentile, in milliseconds, or zero
+    /// until enough latencies have been observed.
+    delay_ms: Arc<AtomicU64>,
+}
+
+// === impl NewHedge ===
+
+impl<N> NewService<ProfileRoute> for NewHedge<N>
+where
+    N: NewService<ProfileRoute>,
+{
+    type Service = Hedge<N::Service>;
+
+    fn new_service(&self, route: ProfileRoute) -> Self::Service {
+        let policy = route.route.retries().and_then(|retries| {
+            let hedge = retries.hedge()?;
+            let delay_ms = Arc::new(AtomicU64::new(0));
+            tokio::spawn(update_delay(
+                Arc::downgrade(&delay_ms),
+                self.metrics.clone(),
+                route.param(),
+                hedge.percentile() / 100.0,
+            ));
+            Some(Policy {
+                hedge: hedge.clone(),
+                budget: retries.budget().clone(),
+                delay_ms,
+            })
+        });
+        Hedge {
+            inner: self.inner.new_service(route),
+            policy,
+        }
+    }
+}
+
+// === impl Hedge ===
+
+impl<S, B> Service<http::Request<B>> for Hedge<S>
+where
+    S: Service<http::Request<B>, Error = Error> + Clone + Send + 'static,
+    S::Response: Send + 'static,
+    S::Future: Send + 'static,
+    B: HttpBody + Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        S::Future,
+        Pin<Box<dyn Future<Output = Result<S::Response, Error>> + Send + 'static>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let Policy {
+            hedge,
+            budget,
+            delay_ms,
+        } = match self.policy.as_ref() {
+            Some(policy) => policy,
+            None => return future::Either::Left(self.inner.call(req)),
+        };
+
+        // Sending a request twice is only safe if it is idempotent.
+        if !hedge.is_hedgeable(req.method()) {
+            tracing::trace!(method = %req.method(), "Request is not idempotent; not hedging");
+            return future::Either::Left(self.inner.call(req));
+        }
+
+        // Only requests without a body are hedged, since a body cannot be
+        // replayed to a second request while the first is still in flight.
+        if !req.body().is_end_stream() {
+            tracing::trace!("Request has a body; not hedging");
+            return future::Either::Left(self.inner.call(req));
+        }
+
+        // Until the route has enough latency observations, its requests are
+        // not hedged.
+        let delay = match delay_ms.load(Ordering::Relaxed) {
+            0 => return future::Either::Left(self.inner.call(req)),
+            ms => time::Duration::from_millis(ms),
+        };
+
+        // The request and its hedge share an extension that prevents the
+        // balancer from dispatching the hedge to the request's endpoint.
+        let (original, distinct) = DistinctEndpoint::pair();
+        let mut hedge = clone_request(&req);
+        hedge.extensions_mut().insert(distinct);
+        let mut req = req;
+        req.extensions_mut().insert(original);
+        let primary = self.inner.call(req);
+        let inner = self.inner.clone();
+        let budget = budget.clone();
+        future::Either::Right(Box::pin(async move {
+            let primary = match future::select(primary.boxed(), Box::pin(time::sleep(delay))).await
+            {
+                future::Either::Left((rsp, _)) => return rsp,
+                future::Either::Right(((), primary)) => primary,
+            };
+
+            if budget.withdraw().is_err() {
+                tracing::debug!("Retry budget exhausted; not hedging");
+                return primary.await;
+            }
+
+            tracing::debug!(?delay, "Hedging request");
+            let mut primary = primary;
+            for _ in 0..MAX_HEDGE_DISPATCHES {
+                let rsp = inner.clone().oneshot(clone_request(&hedge)).boxed();
+                // Use the first successful response, dropping (and thereby
+                // canceling) the other request.
+                match future::select(primary, rsp).await {
+                    future::Either::Left((Ok(rsp), _)) | future::Either::Right((Ok(rsp), _)) => {
+                        return Ok(rsp)
+                    }
+                    future::Either::Left((Err(_), rsp)) => return rsp.await,
+                    // The balancer chose the original request's endpoint, so
+                    // the hedge was not sent and may be dispatched again.
+                    future::Either::Right((Err(e), p)) if is_caused_by::<SameEndpoint>(&*e) => {
+                        primary = p;
+                    }
+                    future::Either::Right((Err(_), primary)) => return primary.await,
+                }
+            }
+            tracing::debug!("No other endpoint is available; not hedging");
+            primary.await
+        }))
+    }
+}
+
+fn clone_request<B: Default>(req: &http::Request<B>) -> http::Request<B> {
+    let mut clone = http::Request::new(B::default());
+    *clone.method_mut() = req.method().clone();
+    *clone.uri_mut() = req.uri().clone();
+    *clone.headers_mut() = req.headers().clone();
+    *clone.version_mut() = req.version();
+
+    // The HTTP server sets a ClientHandle with the client's address and a means to close the
+    // server-side connection.
+    if let Some(client_handle) = req.extensions().get::<ClientHandle>().cloned() {
+        clone.extensions_mut().insert(client_handle);
+    }
+
+    if let Some(distinct) = req.extensions().get::<DistinctEndpoint>().cloned() {
+        clone.extensions_mut().insert(distinct);
+    }
+
+    clone
+}
+
+/// Periodically updates a route's hedging delay from its latencies until the
+/// route's hedge policy is dropped.
+///
+/// The route's latencies are looked up on each update, so that the delay
+/// reflects the metrics that are currently registered for the route.
+async fn update_delay(
+    delay_ms: Weak<AtomicU64>,
+    metrics: metrics::HttpProfileRoute,
+    labels: metrics::ProfileRouteLabels,
+    quantile: f64,
+) {
+    let mut interval = time::interval(DELAY_UPDATE_INTERVAL);
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let delay_ms = match delay_ms.upgrade() {
+            Some(delay_ms) => delay_ms,
+            None => return,
+        };
+        let delay = metrics
+            .latencies(labels.clone())
+            .quantile(quantile, MIN_OBSERVATIONS);
+        // A zero delay is rounded up so that it is not mistaken for an
+        // unknown delay.
+        let ms = delay.map_or(0, |d| (d.as_millis() as u64).max(1));
+        delay_ms.store(ms, Ordering::Relaxed);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{retry, CanonicalDstHeader, Concrete, Endpoint, Logical, ProfileRoute};
+use super::{hedge, retry, CanonicalDstHeader, Concrete, Endpoint, Logical, ProfileRoute};
 use crate::{endpoint, resolve, stack_labels, Outbound};
 use linkerd_app_core::{
     classify, config, profiles,
@@ -64,6 +64,8 @@ impl<E> Outbound<E> {
                     ),
                 )
                 .check_new_service::<Endpoint, http::Request<_>>()
+                .push(http::balance::NewWeighted::layer())
+                .push(http::balance::NewLocality::layer(config.zone.clone()))
                 // Resolve the service to its endpoints and balance requests over them.
                 //
                 // If the balancer has been empty/unavailable, eagerly fail requests.
@@ -75,13 +77,33 @@ impl<E> Outbound<E> {
                 // consulting discovery to see whether the endpoint has been removed. Instead, the
                 // endpoint layer spawns each _connection_ attempt on a background task, but the
                 // decision to attempt the connection must be driven by the balancer.
+                //
+                // Endpoints in the proxy's zone are preferred. Requests are only
+                // balanced over endpoints in other zones when too few local
+                // endpoints are healthy or when no local endpoint is ready.
+                //
+                // Destinations whose profile configures a sticky cookie pin each
+                // client to the endpoint that first served it, while the endpoint
+                // remains in discovery. Destinations whose profile configures a
+                // hash policy are instead balanced by consistent hashing.
+                // Otherwise, the profile may override the proxy's default
+                // balancer strategy. Sticky and hash balancers do not prefer
+                // local endpoints, since a client must remain pinned to its
+                // endpoint as local endpoints come and go. The split rebuilds
+                // its balancers when the profile's balancer configuration
+                // changes.
                 .push(resolve::layer(resolve, watchdog))
+                .push(http::balance::MakeBalance::layer(
+                    http::balance::layer(crate::EWMA_DEFAULT_RTT, crate::EWMA_DECAY)
+                        .with_outlier_detection(
+                            config.http_outlier_detection,
+                            rt.metrics.http_ejections.clone(),
+                        )
+                        .prefer_local(config.locality_min_healthy_percent),
+                    config.balancer_strategy,
+                ))
                 .push_on_service(
                     svc::layers()
-                        .push(http::balance::layer(
-                            crate::EWMA_DEFAULT_RTT,
-                            crate::EWMA_DECAY,
-                        ))
                         .push(
                             rt.metrics
                                 .proxy
@@ -89,8 +111,7 @@ impl<E> Outbound<E> {
                                 .layer(stack_labels("http", "balancer")),
                         )
                         .push(svc::layer::mk(svc::SpawnReady::new))
-                        .push(svc::FailFast::layer("HTTP Balancer", dispatch_timeout))
-                        .push(http::BoxResponse::layer()),
+                        .push(svc::FailFast::layer("HTTP Balancer", dispatch_timeout)),
                 )
                 .check_make_service::<Concrete, http::Request<_>>()
                 .push(svc::MapErr::layer(Into::into))
@@ -104,14 +125,15 @@ impl<E> Outbound<E> {
                 .push(svc::ArcNewService::layer());
 
             // Distribute requests over a distribution of balancers via a
-            // traffic split.
+            // traffic split. Requests that match one of the profile's split
+            // rules are always dispatched to the rule's balancer.
             //
             // If the traffic split is empty/unavailable, eagerly fail requests.
             // When the split is in failfast, spawn the service in a background
             // task so it becomes ready without new requests.
             let logical = concrete
                 .check_new_service::<(ConcreteAddr, Logical), _>()
-                .push(profiles::split::layer())
+                .push(profiles::split::http_layer())
                 .push_on_service(
                     svc::layers()
                         .push(svc::layer::mk(svc::SpawnReady::new))
@@ -151,8 +173,18 @@ impl<E> Outbound<E> {
                         // layer unifies any `Body` type into `BoxBody`.
                         .push_on_service(http::BoxRequest::erased())
                         .push_http_insert_target::<profiles::http::Route>()
+                        // Sets an optional timeout on each attempt.
+                        .push(retry::NewTryTimeout::layer())
                         // Sets an optional retry policy.
-                        .push(retry::layer(rt.metrics.proxy.http_profile_route_retry.clone()))
+                        .push(retry::layer(
+                            rt.metrics.proxy.http_profile_route_retry.clone(),
+                            config.retry_max_buffered_bytes,
+                        ))
+                        // Sets an optional hedging policy, which is informed by
+                        // the latencies of individual requests.
+                        .push(hedge::layer(
+                            rt.metrics.proxy.http_profile_route_actual.clone(),
+                        ))
                         // Sets an optional request timeout.
                         .push(http::NewTimeout::layer())
                         // Records per-route metrics.
@@ -2,10 +2,11 @@ use super::ProfileRoute;
 use futures::{future, FutureExt};
 use linkerd_app_core::{
     classify,
+    errors::is_caused_by,
     http_metrics::retries::Handle,
     metrics, profiles,
-    proxy::http::{ClientHandle, EraseResponse, HttpBody},
-    svc::{layer, Either, Param},
+    proxy::http::{balance::DistinctEndpoint, ClientHandle, EraseResponse, HttpBody},
+    svc::{layer, stack, Either, MapErr, NewService, Param},
     Error,
 };
 use linkerd_http_classify::{Classify, ClassifyEos, ClassifyResponse};
@@ -14,12 +15,14 @@ use linkerd_http_retry::{
     ReplayBody,
 };
 use linkerd_retry as retry;
-use std::sync::Arc;
+use std::{future::Future, pin::Pin};
+use tokio::time::Duration;
 
 pub fn layer<N>(
     metrics: metrics::HttpProfileRouteRetry,
+    max_buffered_bytes: usize,
 ) -> impl layer::Layer<N, Service = retry::NewRetry<NewRetryPolicy, N, EraseResponse<()>>> + Clone {
-    retry::layer(NewRetryPolicy::new(metrics))
+    retry::layer(NewRetryPolicy::new(metrics, max_buffered_bytes))
         // Because we wrap the response body type on retries, we must include a
         // `Proxy` middleware for unifying the response body types of the retry
         // and non-retry services.
@@ -29,23 +32,43 @@ pub fn layer<N>(
 #[derive(Clone, Debug)]
 pub struct NewRetryPolicy {
     metrics: metrics::HttpProfileRouteRetry,
+    /// Used for routes that do not set their own buffering limit.
+    max_buffered_bytes: usize,
 }
 
 #[derive(Clone, Debug)]
 pub struct RetryPolicy {
     metrics: Handle,
-    budget: Arc<retry::Budget>,
+    retries: profiles::http::Retries,
     response_classes: profiles::http::ResponseClasses,
+    /// The maximum size of a request body that may be buffered so that the
+    /// request can be retried.
+    max_buffered_bytes: usize,
+    /// The number of attempts that have been made, including the original
+    /// request.
+    attempts: usize,
 }
 
-/// Allow buffering requests up to 64 kb
-const MAX_BUFFERED_BYTES: usize = 64 * 1024;
+/// Applies a route's per-try timeout to each attempt of a request, so that an
+/// attempt that times out may be retried before the route's overall timeout
+/// elapses.
+#[derive(Clone, Debug)]
+pub struct NewTryTimeout<N> {
+    inner: N,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("HTTP request attempt timed out after {0:?}")]
+pub struct TryTimeoutError(Duration);
 
 // === impl NewRetryPolicy ===
 
 impl NewRetryPolicy {
-    pub fn new(metrics: metrics::HttpProfileRouteRetry) -> Self {
-        Self { metrics }
+    pub fn new(metrics: metrics::HttpProfileRouteRetry, max_buffered_bytes: usize) -> Self {
+        Self {
+            metrics,
+            max_buffered_bytes,
+        }
     }
 }
 
@@ -56,59 +79,98 @@ impl retry::NewPolicy<ProfileRoute> for NewRetryPolicy {
         let retries = route.route.retries().cloned()?;
 
         let metrics = self.metrics.get_handle(route.param());
+        let max_buffered_bytes = retries
+            .max_buffered_bytes()
+            .unwrap_or(self.max_buffered_bytes);
         Some(RetryPolicy {
             metrics,
-            budget: retries.budget().clone(),
+            max_buffered_bytes,
+            retries,
             response_classes: route.route.response_classes().clone(),
+            attempts: 1,
         })
     }
 }
 
 // === impl Retry ===
 
-impl<A, B, E> retry::Policy<http::Request<ReplayBody<A>>, http::Response<WithTrailers<B>>, E>
+impl<A, B> retry::Policy<http::Request<ReplayBody<A>>, http::Response<WithTrailers<B>>, Error>
     for RetryPolicy
 where
     A: HttpBody + Unpin,
     A::Error: Into<Error>,
     B: HttpBody + Unpin,
 {
-    type Future = future::Ready<Self>;
+    type Future = Pin<Box<dyn Future<Output = Self> + Send + 'static>>;
 
     fn retry(
         &self,
         req: &http::Request<ReplayBody<A>>,
-        result: Result<&http::Response<WithTrailers<B>>, &E>,
+        result: Result<&http::Response<WithTrailers<B>>, &Error>,
     ) -> Option<Self::Future> {
+        // did the body exceed the maximum length limit?
+        let exceeded_max_len = req.body().is_capped();
         let retryable = match result {
-            Err(_) => false,
+            // Only attempts that exceeded the per-try timeout are retried;
+            // other errors are not.
+            Err(error) => {
+                let timed_out = is_caused_by::<TryTimeoutError>(&**error);
+                let retryable = timed_out && !exceeded_max_len;
+                tracing::trace!(timed_out, exceeded_max_len, retryable);
+                if timed_out && exceeded_max_len {
+                    self.metrics.incr_body_too_large();
+                }
+                retryable
+            }
             Ok(rsp) => {
-                // is the request a failure?
+                // is the request a failure? A route's retryable gRPC status
+                // codes are retried in addition to classified failures.
                 let is_failure = classify::Request::from(self.response_classes.clone())
                     .classify(req)
                     .start(rsp)
                     .eos(rsp.body().trailers())
-                    .is_failure();
-                // did the body exceed the maximum length limit?
-                let exceeded_max_len = req.body().is_capped();
+                    .is_failure()
+                    || self.is_retryable_grpc_status(rsp);
                 let retryable = is_failure && !exceeded_max_len;
                 tracing::trace!(is_failure, exceeded_max_len, retryable);
+                if is_failure && exceeded_max_len {
+                    self.metrics.incr_body_too_large();
+                }
                 retryable
             }
         };
 
         if !retryable {
-            self.budget.deposit();
+            self.retries.budget().deposit();
             return None;
         }
 
-        let withdrew = self.budget.withdraw().is_ok();
+        if let Some(max) = self.retries.max_attempts() {
+            if self.attempts >= max.get() {
+                tracing::debug!(attempts = self.attempts, "Maximum attempts reached");
+                // The request will not be retried, so it counts towards the
+                // budget like any other request.
+                self.retries.budget().deposit();
+                return None;
+            }
+        }
+
+        let withdrew = self.retries.budget().withdraw().is_ok();
         self.metrics.incr_retryable(withdrew);
         if !withdrew {
             return None;
         }
 
-        Some(future::ready(self.clone()))
+        let mut policy = self.clone();
+        policy.attempts += 1;
+        let backoff = match self.retries.backoff() {
+            Some(backoff) => backoff,
+            None => return Some(Box::pin(future::ready(policy))),
+        };
+        // The first retry waits for the backoff's minimum delay.
+        let delay = backoff.delay((self.attempts - 1) as u32);
+        tracing::trace!(?delay, "Backing off");
+        Some(Box::pin(tokio::time::sleep(delay).map(move |()| policy)))
     }
 
     fn clone_request(
@@ -129,11 +191,33 @@ where
             clone.extensions_mut().insert(client_handle);
         }
 
+        // Retries of hedged requests must also avoid the original request's
+        // endpoint.
+        if let Some(distinct) = req.extensions().get::<DistinctEndpoint>().cloned() {
+            clone.extensions_mut().insert(distinct);
+        }
+
         Some(clone)
     }
 }
 
-impl<A, B, E> retry::PrepareRetry<http::Request<A>, http::Response<B>, E> for RetryPolicy
+impl RetryPolicy {
+    /// Determines whether a gRPC response has one of the route's retryable
+    /// status codes, if any are configured.
+    ///
+    /// The status may be sent in the response headers (for trailers-only
+    /// responses) or in the trailers.
+    fn is_retryable_grpc_status<B: HttpBody>(&self, rsp: &http::Response<WithTrailers<B>>) -> bool {
+        rsp.headers()
+            .get("grpc-status")
+            .or_else(|| rsp.body().trailers()?.get("grpc-status"))
+            .map_or(false, |status| {
+                self.retries.is_retryable_grpc_status(status)
+            })
+    }
+}
+
+impl<A, B> retry::PrepareRetry<http::Request<A>, http::Response<B>, Error> for RetryPolicy
 where
     A: HttpBody + Unpin,
     A::Error: Into<Error>,
@@ -145,7 +229,7 @@ where
     type RetryResponse = http::Response<WithTrailers<B>>;
     type ResponseFuture = future::Map<
         with_trailers::WithTrailersFuture<B>,
-        fn(http::Response<WithTrailers<B>>) -> Result<http::Response<WithTrailers<B>>, E>,
+        fn(http::Response<WithTrailers<B>>) -> Result<http::Response<WithTrailers<B>>, Error>,
     >;
 
     fn prepare_request(
@@ -153,7 +237,7 @@ where
         req: http::Request<A>,
     ) -> Either<Self::RetryRequest, http::Request<A>> {
         let (head, body) = req.into_parts();
-        let replay_body = match ReplayBody::try_new(body, MAX_BUFFERED_BYTES) {
+        let replay_body = match ReplayBody::try_new(body, self.max_buffered_bytes) {
             Ok(body) => body,
             Err(body) => {
                 tracing::debug!(
@@ -175,3 +259,129 @@ where
         WithTrailers::map_response(rsp).map(Ok)
     }
 }
+
+// === impl NewTryTimeout ===
+
+impl<N> NewTryTimeout<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<N> NewService<ProfileRoute> for NewTryTimeout<N>
+where
+    N: NewService<ProfileRoute>,
+{
+    type Service = MapErr<stack::Timeout<N::Service>, fn(Error) -> Error>;
+
+    fn new_service(&self, route: ProfileRoute) -> Self::Service {
+        let timeout = route.route.retries().and_then(|r| r.per_try_timeout());
+        let svc = match timeout {
+            Some(t) => stack::Timeout::new(self.inner.new_service(route), t),
+            None => stack::Timeout::passthru(self.inner.new_service(route)),
+        };
+        MapErr::new(svc, |error| {
+            if let Some(t) = error.downcast_ref::<stack::TimeoutError>() {
+                TryTimeoutError(t.duration()).into()
+            } else {
+                error
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_app_core::profiles::{
+        http::{ResponseClass, ResponseMatch, Retries, Route},
+        LogicalAddr,
+    };
+    use std::{num::NonZeroUsize, sync::Arc};
+
+    fn policy(retries: Retries) -> RetryPolicy {
+        // gRPC responses are always sent with a 200 status, so only 5XX
+        // responses are classified as failures.
+        let route = Route::new(
+            std::iter::empty(),
+            vec![ResponseClass::new(
+                true,
+                ResponseMatch::Status {
+                    min: http::StatusCode::INTERNAL_SERVER_ERROR,
+                    max: http::StatusCode::from_u16(599).unwrap(),
+                },
+            )],
+        );
+        let labels = metrics::ProfileRouteLabels::outbound(
+            LogicalAddr("foo.ns.svc.cluster.local:8080".parse().unwrap()),
+            &route,
+        );
+        RetryPolicy {
+            metrics: metrics::HttpProfileRouteRetry::default().get_handle(labels),
+            retries,
+            response_classes: route.response_classes().clone(),
+            max_buffered_bytes: 64 * 1024,
+            attempts: 1,
+        }
+    }
+
+    fn req() -> http::Request<ReplayBody<hyper::Body>> {
+        let body = ReplayBody::try_new(hyper::Body::empty(), 64 * 1024).unwrap();
+        http::Request::new(body)
+    }
+
+    async fn rsp(
+        status: http::StatusCode,
+        grpc_status: Option<&'static str>,
+    ) -> http::Response<WithTrailers<hyper::Body>> {
+        let mut rsp = http::Response::builder().status(status);
+        if let Some(grpc_status) = grpc_status {
+            rsp = rsp.header("grpc-status", grpc_status);
+        }
+        WithTrailers::map_response(rsp.body(hyper::Body::empty()).unwrap()).await
+    }
+
+    fn is_retried(policy: &RetryPolicy, rsp: &http::Response<WithTrailers<hyper::Body>>) -> bool {
+        retry::Policy::<_, _, Error>::retry(policy, &req(), Ok(rsp)).is_some()
+    }
+
+    #[tokio::test]
+    async fn retryable_grpc_codes_extend_response_classes() {
+        let budget = Arc::new(retry::Budget::new(Duration::from_secs(10), 10, 0.2));
+        let policy =
+            policy(Retries::new(budget).with_retryable_grpc_codes(vec![tonic::Code::Unavailable]));
+
+        // Retryable status codes are retried even though the response is not
+        // classified as a failure.
+        let rsp_ = rsp(http::StatusCode::OK, Some("14")).await;
+        assert!(is_retried(&policy, &rsp_));
+
+        // Other status codes are not retried unless they are classified as
+        // failures.
+        let rsp_ = rsp(http::StatusCode::OK, Some("13")).await;
+        assert!(!is_retried(&policy, &rsp_));
+        let rsp_ = rsp(http::StatusCode::OK, None).await;
+        assert!(!is_retried(&policy, &rsp_));
+        let rsp_ = rsp(http::StatusCode::SERVICE_UNAVAILABLE, Some("13")).await;
+        assert!(is_retried(&policy, &rsp_));
+        let rsp_ = rsp(http::StatusCode::SERVICE_UNAVAILABLE, None).await;
+        assert!(is_retried(&policy, &rsp_));
+    }
+
+    #[tokio::test]
+    async fn max_attempts_deposits_to_budget() {
+        // The budget has no reserve, so a retry may only be withdrawn after a
+        // request has been deposited.
+        let budget = Arc::new(retry::Budget::new(Duration::from_secs(10), 0, 1.0));
+        let policy =
+            policy(Retries::new(budget.clone()).with_max_attempts(NonZeroUsize::new(1).unwrap()));
+        assert!(budget.withdraw().is_err());
+
+        let rsp_ = rsp(http::StatusCode::SERVICE_UNAVAILABLE, None).await;
+        assert!(!is_retried(&policy, &rsp_));
+        assert!(
+            budget.withdraw().is_ok(),
+            "exhausted attempts must be deposited to the budget"
+        );
+    }
+}
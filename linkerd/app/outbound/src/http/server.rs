@@ -1,4 +1,4 @@
-use super::{IdentityRequired, ProxyConnectionClose};
+use super::{IdentityRequired, ProxyConnectionClose, TryTimeoutError};
 use crate::{http, trace_labels, Outbound};
 use linkerd_app_core::{
     config, errors, http_tracing,
@@ -107,6 +107,9 @@ impl errors::HttpRescue<Error> for ServerRescue {
         if let Some(cause) = errors::cause_ref::<http::ResponseTimeoutError>(&*error) {
             return Ok(errors::SyntheticHttpResponse::gateway_timeout(cause));
         }
+        if let Some(cause) = errors::cause_ref::<TryTimeoutError>(&*error) {
+            return Ok(errors::SyntheticHttpResponse::gateway_timeout(cause));
+        }
         if let Some(cause) = errors::cause_ref::<IdentityRequired>(&*error) {
             return Ok(errors::SyntheticHttpResponse::bad_gateway(cause));
         }
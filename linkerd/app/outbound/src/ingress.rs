@@ -7,7 +7,7 @@ use linkerd_app_core::{
         core::Resolve,
     },
     svc::{self, stack::Param},
-    tls,
+    tls, tls_rustls,
     transport::{OrigDstAddr, Remote, ServerAddr},
     AddrMatch, Error, Infallible, NameAddr,
 };
@@ -25,7 +25,7 @@ struct Http<T> {
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 enum Target {
-    Forward(OrigDstAddr),
+    Forward(OrigDstAddr, Option<tcp::originate_tls::ServerName>),
     Override(NameAddr),
 }
 
@@ -37,6 +37,10 @@ struct ProfileRequired;
 #[error("l5d-dst-override is not a valid host:port")]
 struct InvalidOverrideHeader;
 
+#[derive(Debug, Error)]
+#[error("ingress-mode TLS termination requires HTTP")]
+struct TerminatedNotHttp;
+
 const DST_OVERRIDE_HEADER: &str = "l5d-dst-override";
 
 type DetectIo<I> = io::PrefixedIo<I>;
@@ -49,6 +53,10 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
     /// This is only intended for Ingress configurations, where we assume all
     /// outbound traffic is HTTP and HTTP detection is **always** performed. If
     /// HTTP detection fails, we revert to using the provided `fallback` stack.
+    ///
+    /// If the ingress is configured with server certificates, connections
+    /// with an SNI that matches a certificate are terminated before HTTP
+    /// detection.
     //
     // Profile-based stacks are cached so that they can be reused across
     // multiple requests to the same logical destination (even if the
@@ -62,7 +70,7 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
         fallback: F,
     ) -> Outbound<svc::ArcNewTcp<T, I>>
     where
-        T: Param<OrigDstAddr> + Clone + Send + Sync + 'static,
+        T: Param<OrigDstAddr> + Param<Option<tls::ServerId>> + Clone + Send + Sync + 'static,
         I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + std::fmt::Debug + Send + Unpin + 'static,
         P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + Unpin + 'static,
         P::Error: Send,
@@ -82,9 +90,11 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
                 let detect_http = config.proxy.detect_http();
                 let Config {
                     allow_discovery,
+                    ingress_tls,
                     proxy:
                         ProxyConfig {
                             server: ServerConfig { h2_settings, .. },
+                            detect_protocol_timeout,
                             dispatch_timeout,
                             max_in_flight_requests,
                             buffer_capacity,
@@ -173,11 +183,12 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
                             Target::Override(target) => {
                                 Ok::<_, Infallible>(svc::Either::A(Http { target, version }))
                             }
-                            Target::Forward(OrigDstAddr(addr)) => {
+                            Target::Forward(OrigDstAddr(addr), server_name) => {
                                 Ok(svc::Either::B(http::Endpoint {
                                     addr: Remote(ServerAddr(addr)),
                                     metadata: Metadata::default(),
                                     logical_addr: None,
+                                    server_name,
                                     protocol: version,
                                     opaque_protocol: false,
                                     tls: tls::ConditionalClientTls::None(
@@ -205,12 +216,19 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
                     // are no real resources we need to share across connections. This
                     // allows us to avoid buffering requests to these endpoints.
                     .push(svc::NewRouter::layer(
-                        |http::Accept { orig_dst, protocol }| {
+                        |http::Accept {
+                             orig_dst, protocol, ..
+                         }| {
                             move |req: &http::Request<_>| {
                                 // Use either the override header or the original destination address.
+                                // Forwarded requests retain the name by which they address the
+                                // server, so that TLS may be originated to it.
                                 let target =
                                     match http::authority_from_header(req, DST_OVERRIDE_HEADER) {
-                                        None => Target::Forward(orig_dst),
+                                        None => Target::Forward(
+                                            orig_dst,
+                                            tcp::originate_tls::ServerName::from_request(req),
+                                        ),
                                         Some(a) => {
                                             let dst =
                                                 NameAddr::from_authority_with_default_port(&a, 80)
@@ -246,14 +264,33 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
                     )
                     .instrument(|a: &http::Accept| debug_span!("http", v = %a.protocol));
 
+                let new_http =
+                    new_http.push(http::NewServeHttp::layer(*h2_settings, rt.drain.clone()));
+
+                // Connections that are terminated by the ingress are expected to
+                // be HTTP. There is no fallback, since the application's original
+                // destination isn't expecting the decrypted stream.
+                let terminated = new_http
+                    .clone()
+                    .push_request_filter(
+                        |(http, t): (Option<http::Version>, T)| -> Result<_, TerminatedNotHttp> {
+                            let version = http.ok_or(TerminatedNotHttp)?;
+                            Ok(http::Accept::from((version, accept(&t))))
+                        },
+                    )
+                    .push_map_target(detect::allow_timeout)
+                    .push(detect::NewDetectService::layer(detect_http.clone()))
+                    .push(tls_rustls::NewTerminate::layer(*detect_protocol_timeout))
+                    .into_inner();
+
                 // HTTP detection is **always** performed. If detection fails, then we
                 // use the `fallback` stack to process the connection by its original
                 // destination address.
+                let ingress_tls = ingress_tls.clone();
                 new_http
-                    .push(http::NewServeHttp::layer(*h2_settings, rt.drain.clone()))
                     .push_switch(
                         |(http, t): (Option<http::Version>, T)| -> Result<_, Infallible> {
-                            let accept = tcp::Accept::from(t.param());
+                            let accept = accept(&t);
                             if let Some(version) = http {
                                 return Ok(svc::Either::A(http::Accept::from((version, accept))));
                             }
@@ -263,9 +300,34 @@ impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
                     )
                     .push_map_target(detect::allow_timeout)
                     .push(detect::NewDetectService::layer(detect_http))
+                    .push_switch(
+                        move |t: T| -> Result<_, Infallible> {
+                            if let Some(tls) = ingress_tls.as_ref() {
+                                if let Some(tls::ServerId(sni)) = t.param() {
+                                    if tls.has_cert(&sni) {
+                                        debug!(%sni, "Terminating TLS");
+                                        return Ok(svc::Either::B((tls.clone(), t)));
+                                    }
+                                }
+                            }
+                            Ok(svc::Either::A(t))
+                        },
+                        terminated,
+                    )
                     .push_on_service(svc::BoxService::layer())
                     .push(svc::ArcNewService::layer())
                     .check_new_service::<T, I>()
             })
     }
 }
+
+fn accept<T>(t: &T) -> tcp::Accept
+where
+    T: Param<OrigDstAddr> + Param<Option<tls::ServerId>>,
+{
+    tcp::Accept {
+        orig_dst: t.param(),
+        sni: t.param(),
+        protocol: (),
+    }
+}
@@ -11,6 +11,7 @@ pub mod http;
 mod ingress;
 pub mod logical;
 mod metrics;
+mod mirror;
 mod resolve;
 mod switch_logical;
 pub mod tcp;
@@ -31,9 +32,9 @@ use linkerd_app_core::{
     },
     serve,
     svc::{self, stack::Param},
-    tls,
+    tls, tls_rustls,
     transport::{self, addrs::*},
-    AddrMatch, Error, ProxyRuntime, Result,
+    AddrMatch, Error, IpMatch, ProxyRuntime, Result,
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -42,11 +43,13 @@ use std::{
     sync::Arc,
     time::Duration,
 };
-use tracing::{info, info_span};
+use tracing::{info, info_span, warn, Instrument};
 
 const EWMA_DEFAULT_RTT: Duration = Duration::from_millis(30);
 const EWMA_DECAY: Duration = Duration::from_secs(10);
 
+const INGRESS_TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub proxy: ProxyConfig,
@@ -56,10 +59,47 @@ pub struct Config {
     // not perform per-target-address discovery. Non-HTTP connections are
     // forwarded without discovery/routing/mTLS.
     pub ingress_mode: bool,
+
+    // If set, ingress-mode proxies terminate TLS for connections with an SNI
+    // that matches one of these certificates.
+    pub ingress_tls: Option<tls_rustls::ServerConfig>,
+
     pub inbound_ips: Arc<HashSet<IpAddr>>,
 
     // Whether the proxy may include informational headers on HTTP responses.
     pub emit_headers: bool,
+
+    // The maximum size of a request body that is buffered so that the request
+    // may be retried, unless a route configures its own limit. Requests with
+    // larger bodies are not retried.
+    pub retry_max_buffered_bytes: usize,
+
+    // The strategy used by balancers unless a destination's profile
+    // configures its own.
+    pub balancer_strategy: http::balance::Strategy,
+
+    // If set, HTTP balancers eject endpoints that fail consecutive requests.
+    pub http_outlier_detection: Option<http::balance::outlier::Config>,
+
+    // Connections to unmeshed endpoints in these networks are prefixed with a
+    // PROXY protocol header.
+    pub proxy_protocol_networks: IpMatch,
+
+    // The zone in which the proxy runs. When set, balancers prefer endpoints
+    // in the same zone.
+    pub zone: Option<http::balance::Zone>,
+
+    // Balancers spill over to endpoints in other zones when fewer than this
+    // percentage of the endpoints in the proxy's zone are healthy.
+    pub locality_min_healthy_percent: u8,
+
+    // Connections to these ports are peeked for a TLS ClientHello. When the
+    // application originates TLS, its SNI is used to discover the destination.
+    pub detect_sni_ports: Arc<HashSet<u16>>,
+
+    // If set, plaintext connections to named, unmeshed destinations are
+    // upgraded to TLS.
+    pub tls_origination: Option<tcp::originate_tls::Config>,
 }
 
 #[derive(Clone, Debug)]
@@ -78,9 +118,10 @@ struct Runtime {
     drain: drain::Watch,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct Accept<P> {
     pub orig_dst: OrigDstAddr,
+    pub sni: Option<tls::ServerId>,
     pub protocol: P,
 }
 
@@ -168,6 +209,18 @@ impl Outbound<()> {
     {
         if self.config.ingress_mode {
             info!("Outbound routing in ingress-mode");
+            if let Some(tls) = self.config.ingress_tls.clone() {
+                // Certificates are loaded before serving so that the first
+                // connections may be terminated. If they can't be loaded,
+                // loading is retried as the directory is watched.
+                if let Err(error) = tls.reload().instrument(info_span!("ingress_tls")).await {
+                    warn!(%error, "Failed to load ingress server certificates");
+                }
+                tokio::spawn(
+                    tls.watch(INGRESS_TLS_RELOAD_INTERVAL)
+                        .instrument(info_span!("ingress_tls")),
+                );
+            }
             let server = self.mk_ingress(profiles, resolve);
             let shutdown = self.runtime.drain.signaled();
             serve::serve(listen, server, shutdown).await;
@@ -243,6 +296,9 @@ impl Outbound<()> {
 
 impl<P> Param<transport::labels::Key> for Accept<P> {
     fn param(&self) -> transport::labels::Key {
+        // Application-originated SNI values are not used as labels, since any
+        // name may be sent. Connections that are routed by SNI are labeled
+        // with their discovered logical name by the endpoint stack.
         transport::labels::Key::outbound_server(self.orig_dst.into())
     }
 }
@@ -253,6 +309,12 @@ impl<P> Param<OrigDstAddr> for Accept<P> {
     }
 }
 
+impl<P> Param<Option<tls::ServerId>> for Accept<P> {
+    fn param(&self) -> Option<tls::ServerId> {
+        self.sni.clone()
+    }
+}
+
 fn stack_labels(proto: &'static str, name: &'static str) -> metrics::StackLabels {
     metrics::StackLabels::outbound(proto, name)
 }
@@ -113,6 +113,32 @@ impl<P> svc::Param<ConcreteAddr> for Concrete<P> {
     }
 }
 
+/// Used to configure consistent-hash balancing. The policy is read when the
+/// balancer is built, so changes only apply to new balancers.
+impl<P> svc::Param<Option<http::balance::HashKey>> for Concrete<P> {
+    fn param(&self) -> Option<http::balance::HashKey> {
+        self.logical.profile.hash_policy()
+    }
+}
+
+/// Used to configure cookie-based session affinity. Like the hash policy, the
+/// cookie is read when the balancer is built.
+impl<P> svc::Param<Option<http::balance::StickyCookie>> for Concrete<P> {
+    fn param(&self) -> Option<http::balance::StickyCookie> {
+        self.logical
+            .profile
+            .sticky_cookie()
+            .map(http::balance::StickyCookie)
+    }
+}
+
+/// Overrides the proxy's default balancer strategy for the destination.
+impl<P> svc::Param<Option<http::balance::Strategy>> for Concrete<P> {
+    fn param(&self) -> Option<http::balance::Strategy> {
+        self.logical.profile.balancer_strategy()
+    }
+}
+
 // === impl Outbound ===
 
 impl<C> Outbound<C> {
@@ -11,12 +11,23 @@
 pub(crate) mod error;
 
 pub use linkerd_app_core::metrics::*;
+use linkerd_app_core::proxy::http::balance::EjectionMetrics;
+
+metrics! {
+    outbound_http_balancer_endpoint_ejections_total: Counter {
+        "The total number of times that HTTP balancer endpoints were ejected by outlier detection."
+    },
+    outbound_http_balancer_endpoints_ejected: Gauge {
+        "The number of HTTP balancer endpoints that are currently ejected by outlier detection."
+    }
+}
 
 /// Holds outbound proxy metrics.
 #[derive(Clone, Debug)]
 pub struct Metrics {
     pub(crate) http_errors: error::Http,
     pub(crate) tcp_errors: error::Tcp,
+    pub(crate) http_ejections: EjectionMetrics,
 
     /// Holds metrics that are common to both inbound and outbound proxies. These metrics are
     /// reported separately
@@ -28,6 +39,7 @@ impl Metrics {
         Self {
             http_errors: error::Http::default(),
             tcp_errors: error::Tcp::default(),
+            http_ejections: EjectionMetrics::default(),
             proxy,
         }
     }
@@ -38,6 +50,12 @@ impl FmtMetrics for Metrics {
         self.http_errors.fmt_metrics(f)?;
         self.tcp_errors.fmt_metrics(f)?;
 
+        outbound_http_balancer_endpoint_ejections_total.fmt_help(f)?;
+        outbound_http_balancer_endpoint_ejections_total
+            .fmt_metric(f, self.http_ejections.ejections())?;
+        outbound_http_balancer_endpoints_ejected.fmt_help(f)?;
+        outbound_http_balancer_endpoints_ejected.fmt_metric(f, self.http_ejections.ejected())?;
+
         // XXX: Proxy metrics are reported elsewhere.
 
         Ok(())
@@ -2,7 +2,7 @@ mod http;
 mod tcp;
 
 pub(crate) use self::{http::Http, tcp::Tcp};
-use crate::http::IdentityRequired;
+use crate::http::{IdentityRequired, TryTimeoutError};
 use linkerd_app_core::{
     errors::FailFastError, metrics::FmtLabels, proxy::http::ResponseTimeoutError,
 };
@@ -28,7 +28,7 @@ impl ErrorKind {
             ErrorKind::IdentityRequired
         } else if err.is::<FailFastError>() {
             ErrorKind::FailFast
-        } else if err.is::<ResponseTimeoutError>() {
+        } else if err.is::<ResponseTimeoutError>() || err.is::<TryTimeoutError>() {
             ErrorKind::ResponseTimeout
         } else if let Some(e) = err.source() {
             Self::mk(e)
@@ -0,0 +1,136 @@
+use crate::{endpoint::Endpoint, http, stack_labels, Outbound};
+use linkerd_app_core::{
+    config::ProxyConfig,
+    profiles,
+    proxy::{
+        api_resolve::{ConcreteAddr, Metadata},
+        core::Resolve,
+    },
+    svc::{self, stack::Param},
+    transport::OrigDstAddr,
+    Addr, Error, NameAddr,
+};
+use std::{fmt, hash::Hash};
+use thiserror::Error;
+use tracing::{debug, debug_span};
+
+#[derive(Debug, Error)]
+#[error("mirror {0} could not be discovered")]
+struct MirrorNotDiscovered(NameAddr);
+
+// === impl Outbound ===
+
+impl Outbound<svc::ArcNewHttp<http::Endpoint>> {
+    /// Builds a stack that sends requests mirrored by the inbound proxy.
+    ///
+    /// Mirrors are discovered like the application's own outbound traffic: a
+    /// service profile is looked up for the mirror's address, so that requests
+    /// to a meshed mirror are balanced and secured with mTLS. Mirrors that
+    /// can't be discovered are only sent to when they are addressed by IP, in
+    /// which case they are forwarded without TLS.
+    pub fn push_http_mirror<T, P, R>(self, profiles: P, resolve: R) -> Outbound<svc::ArcNewHttp<T>>
+    where
+        T: Param<Addr> + Param<http::Version>,
+        T: Clone + Eq + Hash + fmt::Debug + Send + Sync + 'static,
+        P: profiles::GetProfile<profiles::LookupAddr> + Clone + Send + Sync + Unpin + 'static,
+        P::Error: Send,
+        P::Future: Send,
+        R: Clone + Send + Sync + 'static,
+        R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>,
+        R::Resolution: Send,
+        R::Future: Send + Unpin,
+    {
+        let no_tls_reason = self.no_tls_reason();
+        let endpoint = self.clone().into_stack();
+
+        self.push_http_logical(resolve)
+            .map_stack(|config, rt, logical| {
+                let allow = config.allow_discovery.clone();
+                let inbound_ips = config.inbound_ips.clone();
+                let ProxyConfig {
+                    buffer_capacity,
+                    cache_max_idle_age,
+                    dispatch_timeout,
+                    ..
+                } = config.proxy;
+
+                logical
+                    .push_switch(
+                        move |(profile, target): (Option<profiles::Receiver>, T)| -> Result<_, Error> {
+                            let version: http::Version = target.param();
+                            if let Some(profile) = profile {
+                                if let Some((addr, metadata)) = profile.endpoint() {
+                                    let endpoint = Endpoint::from_metadata(
+                                        addr,
+                                        metadata,
+                                        no_tls_reason,
+                                        profile.is_opaque_protocol(),
+                                        &*inbound_ips,
+                                    );
+                                    return Ok(svc::Either::B(http::Endpoint::from((
+                                        version, endpoint,
+                                    ))));
+                                }
+
+                                if let Some(logical_addr) = profile.logical_addr() {
+                                    return Ok(svc::Either::A(http::Logical {
+                                        profile,
+                                        logical_addr,
+                                        protocol: version,
+                                    }));
+                                }
+                            }
+
+                            match target.param() {
+                                Addr::Socket(addr) => {
+                                    let endpoint =
+                                        Endpoint::forward(OrigDstAddr(addr), no_tls_reason, false);
+                                    Ok(svc::Either::B(http::Endpoint::from((version, endpoint))))
+                                }
+                                Addr::Name(addr) => Err(MirrorNotDiscovered(addr).into()),
+                            }
+                        },
+                        endpoint.into_inner(),
+                    )
+                    .push(profiles::discover::layer(profiles, move |t: T| {
+                        let addr: Addr = t.param();
+                        let allowed = match addr {
+                            Addr::Name(ref addr) => allow.names().matches(addr.name()),
+                            Addr::Socket(addr) => allow.matches_ip(addr.ip()),
+                        };
+                        if allowed {
+                            return Ok(profiles::LookupAddr(addr));
+                        }
+                        debug!(%addr, "Mirror is not discoverable");
+                        Err(profiles::DiscoveryRejected::new(
+                            "not in discoverable domains or networks",
+                        ))
+                    }))
+                    .push_on_service(
+                        svc::layers()
+                            .push(
+                                rt.metrics
+                                    .proxy
+                                    .stack
+                                    .layer(stack_labels("http", "mirror")),
+                            )
+                            .push(svc::layer::mk(svc::SpawnReady::new))
+                            .push(svc::FailFast::layer("HTTP Mirror", dispatch_timeout))
+                            .push_spawn_buffer(buffer_capacity),
+                    )
+                    // Mirrors are cached so that discovery is shared across
+                    // requests.
+                    .push_cache(cache_max_idle_age)
+                    .instrument(|t: &T| {
+                        debug_span!("mirror", addr = %svc::Param::<Addr>::param(t))
+                    })
+                    .push_on_service(
+                        svc::layers()
+                            .push(http::Retain::layer())
+                            .push(http::BoxResponse::layer())
+                            .push(svc::BoxService::layer()),
+                    )
+                    .push(svc::ArcNewService::layer())
+            })
+    }
+}
@@ -1,17 +1,19 @@
 use crate::Outbound;
 use linkerd_app_core::{
-    io, svc,
+    io, svc, tls,
     transport::{metrics, OrigDstAddr},
     transport_header::SessionProtocol,
-    Error,
+    Error, Infallible,
 };
 
 pub mod connect;
 pub mod logical;
 pub mod opaque_transport;
+pub mod originate_tls;
+pub mod proxy_protocol;
 
 pub use self::connect::Connect;
-pub use linkerd_app_core::proxy::tcp::Forward;
+pub use linkerd_app_core::proxy::tcp::ForwardFromPeer;
 
 pub type Accept = crate::Accept<()>;
 pub type Logical = crate::logical::Logical<()>;
@@ -22,14 +24,19 @@ impl From<OrigDstAddr> for Accept {
     fn from(orig_dst: OrigDstAddr) -> Self {
         Self {
             orig_dst,
+            sni: None,
             protocol: (),
         }
     }
 }
 
 impl<P> From<(P, Accept)> for crate::Accept<P> {
-    fn from((protocol, Accept { orig_dst, .. }): (P, Accept)) -> Self {
-        Self { orig_dst, protocol }
+    fn from((protocol, Accept { orig_dst, sni, .. }): (P, Accept)) -> Self {
+        Self {
+            orig_dst,
+            sni,
+            protocol,
+        }
     }
 }
 
@@ -41,20 +48,53 @@ impl svc::Param<Option<SessionProtocol>> for Endpoint {
 
 impl<N> Outbound<N> {
     /// Wraps a TCP accept stack with tracing and metrics instrumentation.
+    ///
+    /// Connections to the configured ports are peeked (or, when the ClientHello
+    /// can't be peeked, buffered) for a TLS ClientHello so that the SNI of
+    /// application-originated TLS is known when the connection is discovered
+    /// and instrumented.
     pub fn push_tcp_instrument<T, I, G, NSvc>(self, mk_span: G) -> Outbound<svc::ArcNewTcp<T, I>>
     where
         T: svc::Param<OrigDstAddr> + Clone + Send + 'static,
         G: svc::GetSpan<T> + Clone + Send + Sync + 'static,
-        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + std::fmt::Debug + Send + Unpin + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::Peek + io::PeerAddr,
+        I: std::fmt::Debug + Send + Sync + Unpin + 'static,
         N: svc::NewService<Accept, Service = NSvc> + Clone + Send + Sync + 'static,
-        NSvc: svc::Service<metrics::SensorIo<I>, Response = (), Error = Error> + Send + 'static,
+        NSvc: svc::Service<metrics::SensorIo<tls::server::DetectIo<I>>, Response = (), Error = Error>
+            + Send
+            + 'static,
         NSvc::Future: Send,
     {
-        self.map_stack(|_, rt, inner| {
-            inner
-                .push(metrics::NewServer::layer(
-                    rt.metrics.proxy.transport.clone(),
-                ))
+        self.map_stack(|config, rt, inner| {
+            let detect_sni_ports = config.detect_sni_ports.clone();
+            let detect_timeout = tls::server::Timeout(config.proxy.detect_protocol_timeout);
+
+            let instrumented = inner.push(metrics::NewServer::layer(
+                rt.metrics.proxy.transport.clone(),
+            ));
+
+            instrumented
+                .clone()
+                .push_map_target(|(sni, accept): (Option<tls::ServerId>, Accept)| Accept {
+                    sni,
+                    ..accept
+                })
+                .push(tls::NewDetectSni::layer(detect_timeout))
+                .push_switch(
+                    move |accept: Accept| -> Result<_, Infallible> {
+                        let OrigDstAddr(addr) = accept.orig_dst;
+                        if detect_sni_ports.contains(&addr.port()) {
+                            tracing::debug!("Attempting SNI detection");
+                            return Ok(svc::Either::A(accept));
+                        }
+                        Ok(svc::Either::B(accept))
+                    },
+                    instrumented
+                        .push_on_service(svc::MapTargetLayer::new(
+                            |io: I| -> tls::server::DetectIo<I> { io::EitherIo::Left(io) },
+                        ))
+                        .into_inner(),
+                )
                 .push_request_filter(|t: T| Accept::try_from(t.param()))
                 .push(rt.metrics.tcp_errors.to_layer())
                 .instrument(mk_span)
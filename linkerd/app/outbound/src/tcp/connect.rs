@@ -1,21 +1,57 @@
-use super::opaque_transport::{self, OpaqueTransport};
+use super::{
+    opaque_transport::{self, OpaqueTransport},
+    originate_tls::{OriginateTls, ServerName},
+    proxy_protocol::ProxyProtocol,
+};
 use crate::{ConnectMeta, Outbound};
 use futures::future;
 use linkerd_app_core::{
     io,
+    profiles::LogicalAddr,
     proxy::http,
     svc, tls,
     transport::{self, ClientAddr, ConnectTcp, Local, Remote, ServerAddr},
     transport_header::SessionProtocol,
     Error,
 };
-use std::task::{Context, Poll};
+use std::{
+    net::SocketAddr,
+    task::{Context, Poll},
+};
 use tracing::debug_span;
 
 #[derive(Clone, Debug)]
 pub struct Connect {
     pub addr: Remote<ServerAddr>,
     pub tls: tls::ConditionalClientTls,
+    pub logical_addr: Option<LogicalAddr>,
+    /// The client on whose behalf the connection is established, if the
+    /// connection is not shared by several clients.
+    pub client_addr: Option<Remote<ClientAddr>>,
+    /// The name by which the application addressed the server, if it is not
+    /// known by a logical name.
+    pub server_name: Option<ServerName>,
+}
+
+/// A target that is connected on behalf of a single accepted client.
+#[derive(Clone, Debug)]
+pub struct ForClient<T> {
+    pub client: Remote<ClientAddr>,
+    pub target: T,
+}
+
+/// Builds a [`ConnectForClient`] for each target.
+#[derive(Clone, Debug)]
+pub struct NewConnectForClient<C> {
+    inner: C,
+}
+
+/// Connects to a target on behalf of the client whose address is provided as
+/// the request.
+#[derive(Clone, Debug)]
+pub struct ConnectForClient<C, T> {
+    inner: C,
+    target: T,
 }
 
 /// Prevents outbound connections on the loopback interface, unless the
@@ -50,6 +86,9 @@ impl<C> Outbound<C> {
             + svc::Param<Option<opaque_transport::PortOverride>>
             + svc::Param<Option<http::AuthorityOverride>>
             + svc::Param<Option<SessionProtocol>>
+            + svc::Param<Option<LogicalAddr>>
+            + svc::Param<Option<Remote<ClientAddr>>>
+            + svc::Param<Option<ServerName>>
             + svc::Param<transport::labels::Key>,
         C: svc::MakeConnection<Connect, Metadata = Local<ClientAddr>, Error = io::Error>,
         C: Clone + Send + 'static,
@@ -59,6 +98,12 @@ impl<C> Outbound<C> {
     {
         self.map_stack(|config, rt, connect| {
             connect
+                // Prefixes connections to unmeshed endpoints with a PROXY
+                // protocol header, if configured.
+                .push(ProxyProtocol::layer(config.proxy_protocol_networks.clone()))
+                // Upgrades connections to named, unmeshed destinations to TLS,
+                // if configured.
+                .push(OriginateTls::layer(config.tls_origination.clone()))
                 // Initiates mTLS if the target is configured with identity. The
                 // endpoint configures ALPN when there is an opaque transport hint OR
                 // when an authority override is present (indicating the target is a
@@ -87,15 +132,15 @@ impl<C> Outbound<C> {
     where
         T: Clone + Send + 'static,
         I: io::AsyncRead + io::AsyncWrite + io::PeerAddr + std::fmt::Debug + Send + Unpin + 'static,
-        C: svc::MakeConnection<T> + Clone + Send + Sync + 'static,
+        C: svc::MakeConnection<ForClient<T>> + Clone + Send + Sync + 'static,
         C::Connection: Send + Unpin,
         C::Metadata: Send + Unpin,
         C::Future: Send,
     {
         self.map_stack(|_, _, conn| {
             conn.push(svc::stack::WithoutConnectionMetadata::layer())
-                .push_make_thunk()
-                .push_on_service(super::Forward::layer())
+                .push(NewConnectForClient::layer())
+                .push_on_service(super::ForwardFromPeer::layer())
                 .instrument(|_: &_| debug_span!("tcp.forward"))
                 .push(svc::ArcNewService::layer())
                 .check_new_service::<T, I>()
@@ -163,6 +208,120 @@ impl svc::Param<tls::ConditionalClientTls> for Connect {
     }
 }
 
+// === impl ForClient ===
+
+impl<T: svc::Param<Remote<ServerAddr>>> svc::Param<Remote<ServerAddr>> for ForClient<T> {
+    #[inline]
+    fn param(&self) -> Remote<ServerAddr> {
+        self.target.param()
+    }
+}
+
+impl<T: svc::Param<tls::ConditionalClientTls>> svc::Param<tls::ConditionalClientTls>
+    for ForClient<T>
+{
+    #[inline]
+    fn param(&self) -> tls::ConditionalClientTls {
+        self.target.param()
+    }
+}
+
+impl<T: svc::Param<Option<opaque_transport::PortOverride>>>
+    svc::Param<Option<opaque_transport::PortOverride>> for ForClient<T>
+{
+    #[inline]
+    fn param(&self) -> Option<opaque_transport::PortOverride> {
+        self.target.param()
+    }
+}
+
+impl<T: svc::Param<Option<http::AuthorityOverride>>> svc::Param<Option<http::AuthorityOverride>>
+    for ForClient<T>
+{
+    #[inline]
+    fn param(&self) -> Option<http::AuthorityOverride> {
+        self.target.param()
+    }
+}
+
+impl<T: svc::Param<Option<SessionProtocol>>> svc::Param<Option<SessionProtocol>> for ForClient<T> {
+    #[inline]
+    fn param(&self) -> Option<SessionProtocol> {
+        self.target.param()
+    }
+}
+
+impl<T: svc::Param<Option<LogicalAddr>>> svc::Param<Option<LogicalAddr>> for ForClient<T> {
+    #[inline]
+    fn param(&self) -> Option<LogicalAddr> {
+        self.target.param()
+    }
+}
+
+impl<T: svc::Param<Option<ServerName>>> svc::Param<Option<ServerName>> for ForClient<T> {
+    #[inline]
+    fn param(&self) -> Option<ServerName> {
+        self.target.param()
+    }
+}
+
+impl<T: svc::Param<transport::labels::Key>> svc::Param<transport::labels::Key> for ForClient<T> {
+    #[inline]
+    fn param(&self) -> transport::labels::Key {
+        self.target.param()
+    }
+}
+
+impl<T> svc::Param<Option<Remote<ClientAddr>>> for ForClient<T> {
+    #[inline]
+    fn param(&self) -> Option<Remote<ClientAddr>> {
+        Some(self.client)
+    }
+}
+
+// === impl NewConnectForClient ===
+
+impl<C> NewConnectForClient<C> {
+    pub fn layer() -> impl svc::Layer<C, Service = Self> + Clone + Copy {
+        svc::layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<C: Clone, T> svc::NewService<T> for NewConnectForClient<C> {
+    type Service = ConnectForClient<C, T>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        ConnectForClient {
+            inner: self.inner.clone(),
+            target,
+        }
+    }
+}
+
+// === impl ConnectForClient ===
+
+impl<C, T> svc::Service<SocketAddr> for ConnectForClient<C, T>
+where
+    T: Clone,
+    C: svc::Service<ForClient<T>>,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = C::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), C::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, client: SocketAddr) -> C::Future {
+        self.inner.call(ForClient {
+            client: Remote(ClientAddr(client)),
+            target: self.target.clone(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,10 +337,13 @@ mod tests {
         let _trace = linkerd_tracing::test::trace_init();
 
         let addr = SocketAddr::new([192, 0, 2, 2].into(), 2222);
+        // The mocked I/O reports an unspecified peer address.
+        let client_addr = SocketAddr::new([0, 0, 0, 0].into(), 0);
         let (rt, _shutdown) = runtime();
         let stack = Outbound::new(default_config(), rt)
-            .with_stack(svc::mk(move |a: SocketAddr| {
-                assert_eq!(a, addr);
+            .with_stack(svc::mk(move |a: ForClient<SocketAddr>| {
+                assert_eq!(a.target, addr);
+                assert_eq!(a.client, Remote(ClientAddr(client_addr)));
                 let mut io = support::io();
                 io.write(b"hello").read(b"world");
                 future::ok::<_, support::io::Error>((
@@ -1,4 +1,4 @@
-use super::{Concrete, Endpoint, Logical};
+use super::{connect::ForClient, Concrete, Endpoint, Logical};
 use crate::{endpoint, resolve, Outbound};
 use linkerd_app_core::{
     config, drain, io, profiles,
@@ -24,12 +24,13 @@ impl<C> Outbound<C> {
         >,
     >
     where
-        C: svc::MakeConnection<Endpoint> + Clone + Send + 'static,
+        C: svc::MakeConnection<ForClient<Endpoint>> + Clone + Send + 'static,
         C::Connection: Send + Unpin,
         C::Metadata: Send + Unpin,
         C::Future: Send,
         C: Send + Sync + 'static,
-        I: io::AsyncRead + io::AsyncWrite + std::fmt::Debug + Send + Unpin + 'static,
+        I: io::AsyncRead + io::AsyncWrite + io::PeerAddr,
+        I: std::fmt::Debug + Send + Unpin + 'static,
         R: Resolve<ConcreteAddr, Endpoint = Metadata, Error = Error>
             + Clone
             + Send
@@ -62,7 +63,7 @@ impl<C> Outbound<C> {
 
             connect
                 .push(svc::stack::WithoutConnectionMetadata::layer())
-                .push_make_thunk()
+                .push(super::connect::NewConnectForClient::layer())
                 .instrument(|t: &Endpoint| {
                     debug_span!(
                         "endpoint",
@@ -70,10 +71,12 @@ impl<C> Outbound<C> {
                         server.id = t.tls.value().map(|tls| tracing::field::display(&tls.server_id)),
                     )
                 })
+                .push(tcp::balance::NewWeighted::layer())
                 .push(resolve::layer(resolve, config.proxy.cache_max_idle_age * 2))
                 .push_on_service(
                     svc::layers()
                         .push(tcp::balance::layer(
+                            config.balancer_strategy,
                             crate::EWMA_DEFAULT_RTT,
                             crate::EWMA_DECAY,
                         ))
@@ -83,7 +86,7 @@ impl<C> Outbound<C> {
                                 .stack
                                 .layer(crate::stack_labels("tcp", "balancer")),
                         )
-                        .push(tcp::Forward::layer())
+                        .push(tcp::ForwardFromPeer::layer())
                         .push(drain::Retain::layer(rt.drain.clone())),
                 )
                 .into_new_service()
@@ -154,8 +157,8 @@ mod tests {
         // Build the TCP logical stack with a mocked connector.
         let (rt, _shutdown) = runtime();
         let stack = Outbound::new(default_config(), rt)
-            .with_stack(svc::mk(move |ep: Endpoint| {
-                assert_eq!(*ep.addr, ep_addr);
+            .with_stack(svc::mk(move |ep: ForClient<Endpoint>| {
+                assert_eq!(*ep.target.addr, ep_addr);
                 let mut io = support::io();
                 io.write(b"hola").read(b"mundo");
                 let local = Local(ClientAddr(([0, 0, 0, 0], 4444).into()));
@@ -220,22 +223,24 @@ mod tests {
         // based on the address.
         let (rt, _shutdown) = runtime();
         let svc = Outbound::new(default_config(), rt)
-            .with_stack(svc::mk(move |ep: Endpoint| match ep.addr {
-                Remote(ServerAddr(addr)) if addr == ep0_addr => {
-                    tracing::debug!(%addr, "writing ep0");
-                    let mut io = support::io();
-                    io.write(b"who r u?").read(b"ep0");
-                    let local = Local(ClientAddr(([0, 0, 0, 0], 4444).into()));
-                    future::ok::<_, support::io::Error>((io.build(), local))
+            .with_stack(svc::mk(move |ep: ForClient<Endpoint>| {
+                match ep.target.addr {
+                    Remote(ServerAddr(addr)) if addr == ep0_addr => {
+                        tracing::debug!(%addr, "writing ep0");
+                        let mut io = support::io();
+                        io.write(b"who r u?").read(b"ep0");
+                        let local = Local(ClientAddr(([0, 0, 0, 0], 4444).into()));
+                        future::ok::<_, support::io::Error>((io.build(), local))
+                    }
+                    Remote(ServerAddr(addr)) if addr == ep1_addr => {
+                        tracing::debug!(%addr, "writing ep1");
+                        let mut io = support::io();
+                        io.write(b"who r u?").read(b"ep1");
+                        let local = Local(ClientAddr(([0, 0, 0, 0], 4444).into()));
+                        future::ok::<_, support::io::Error>((io.build(), local))
+                    }
+                    addr => unreachable!("unexpected endpoint: {}", addr),
                 }
-                Remote(ServerAddr(addr)) if addr == ep1_addr => {
-                    tracing::debug!(%addr, "writing ep1");
-                    let mut io = support::io();
-                    io.write(b"who r u?").read(b"ep1");
-                    let local = Local(ClientAddr(([0, 0, 0, 0], 4444).into()));
-                    future::ok::<_, support::io::Error>((io.build(), local))
-                }
-                addr => unreachable!("unexpected endpoint: {}", addr),
             }))
             .push_tcp_logical(resolve)
             .into_inner()
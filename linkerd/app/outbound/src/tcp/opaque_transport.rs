@@ -1,10 +1,14 @@
-use crate::{tcp::Connect, ConnectMeta};
+use crate::{
+    tcp::{originate_tls::ServerName, Connect},
+    ConnectMeta,
+};
 use futures::prelude::*;
 use linkerd_app_core::{
     dns,
+    profiles::LogicalAddr,
     proxy::http,
     svc, tls,
-    transport::{Remote, ServerAddr},
+    transport::{ClientAddr, Remote, ServerAddr},
     transport_header::{SessionProtocol, TransportHeader, PROTOCOL},
     Conditional, Error, Result,
 };
@@ -49,7 +53,10 @@ where
         + svc::Param<Remote<ServerAddr>>
         + svc::Param<Option<PortOverride>>
         + svc::Param<Option<http::AuthorityOverride>>
-        + svc::Param<Option<SessionProtocol>>,
+        + svc::Param<Option<SessionProtocol>>
+        + svc::Param<Option<LogicalAddr>>
+        + svc::Param<Option<Remote<ClientAddr>>>
+        + svc::Param<Option<ServerName>>,
     S: svc::MakeConnection<Connect, Metadata = ConnectMeta> + Send + 'static,
     S::Connection: Send + Unpin,
     S::Future: Send + 'static,
@@ -71,6 +78,9 @@ where
             let target = Connect {
                 addr: ep.param(),
                 tls,
+                logical_addr: ep.param(),
+                client_addr: ep.param(),
+                server_name: ep.param(),
             };
             return Box::pin(self.inner.connect(target).err_into::<Error>());
         }
@@ -113,6 +123,9 @@ where
         let connect = self.inner.connect(Connect {
             addr: Remote(ServerAddr((addr.ip(), connect_port).into())),
             tls,
+            logical_addr: ep.param(),
+            client_addr: ep.param(),
+            server_name: ep.param(),
         });
         Box::pin(async move {
             let (mut io, meta) = connect.await.map_err(Into::into)?;
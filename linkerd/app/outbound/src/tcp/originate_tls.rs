@@ -0,0 +1,210 @@
+use crate::tcp::Connect;
+use futures::prelude::*;
+use linkerd_app_core::{
+    dns, io,
+    profiles::LogicalAddr,
+    proxy::http,
+    svc::{self, ServiceExt},
+    tls_rustls,
+    transport::{ClientAddr, Local, Remote, ServerAddr},
+    NameMatch,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+use tracing::debug;
+
+/// Configures TLS origination to servers outside of the mesh.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Unmeshed destinations with logical names or server names in these
+    /// domains are sent TLS.
+    pub names: NameMatch,
+
+    /// The server port on which TLS is originated, regardless of the port on
+    /// which the application connected.
+    pub port: u16,
+
+    pub client: tls_rustls::ClientConfig,
+}
+
+/// The name by which the application addressed a server, e.g. the authority
+/// of an HTTP request.
+///
+/// Destinations outside of the mesh are not discovered, so they have no
+/// logical name.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ServerName(pub dns::Name);
+
+/// Upgrades plaintext connections to TLS for named, unmeshed destinations.
+///
+/// This allows the application to speak plaintext to external services so
+/// that the proxy can observe (and route) its traffic.
+#[derive(Clone, Debug)]
+pub struct OriginateTls<S> {
+    inner: S,
+    config: Option<Config>,
+}
+
+// === impl ServerName ===
+
+impl ServerName {
+    /// Returns the name in a request's authority or `Host` header, unless the
+    /// request addresses the server by IP.
+    pub(crate) fn from_request<B>(req: &http::Request<B>) -> Option<Self> {
+        let authority = req
+            .uri()
+            .authority()
+            .cloned()
+            .or_else(|| http::authority_from_header(req, http::header::HOST))?;
+        let name = dns::Name::from_str(authority.host()).ok()?;
+        Some(Self(name))
+    }
+}
+
+// === impl OriginateTls ===
+
+impl<S> OriginateTls<S> {
+    pub fn layer(config: Option<Config>) -> impl svc::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            config: config.clone(),
+        })
+    }
+
+    /// Returns a TLS connector and port if the target should be sent TLS.
+    fn originate(&self, target: &Connect) -> Option<(io::Result<tls_rustls::Connect>, u16)> {
+        let config = self.config.as_ref()?;
+        // Meshed endpoints are already sent mTLS.
+        if target.tls.is_some() {
+            return None;
+        }
+        let name = match (&target.logical_addr, &target.server_name) {
+            (Some(LogicalAddr(addr)), _) => addr.name(),
+            (None, Some(ServerName(name))) => name,
+            (None, None) => return None,
+        };
+        if !config.names.matches(name) {
+            return None;
+        }
+        debug!(server.name = %name, server.port = config.port, "Originating TLS");
+        Some((config.client.connect(name), config.port))
+    }
+}
+
+impl<S> svc::Service<Connect> for OriginateTls<S>
+where
+    S: svc::MakeConnection<Connect, Metadata = Local<ClientAddr>, Error = io::Error>,
+    S::Connection: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = (
+        io::EitherIo<S::Connection, tls_rustls::ClientIo<S::Connection>>,
+        Local<ClientAddr>,
+    );
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut target: Connect) -> Self::Future {
+        let (tls, port) = match self.originate(&target) {
+            None => {
+                return Box::pin(
+                    self.inner
+                        .connect(target)
+                        .map_ok(|(io, meta)| (io::EitherIo::Left(io), meta)),
+                )
+            }
+            Some((Err(error), _)) => return Box::pin(future::err(error)),
+            Some((Ok(tls), port)) => (tls, port),
+        };
+
+        let Remote(ServerAddr(addr)) = target.addr;
+        target.addr = Remote(ServerAddr((addr.ip(), port).into()));
+        let connect = self.inner.connect(target);
+        Box::pin(async move {
+            let (io, meta) = connect.await?;
+            let io = tls.oneshot(io).await?;
+            Ok((io::EitherIo::Right(io), meta))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use linkerd_app_core::{io::AsyncReadExt, tls};
+    use std::path::Path;
+
+    const SERVER: ([u8; 4], u16) = ([192, 0, 2, 10], 8080);
+
+    /// Connects to `SERVER` by the given name, expecting the connection to
+    /// target `port`. Returns whether TLS was originated.
+    async fn connect(
+        server_name: Option<&'static str>,
+        port: u16,
+        io: io::DuplexStream,
+    ) -> io::Result<bool> {
+        let mut io = Some(io);
+        let local = Local(ClientAddr(([192, 0, 2, 1], 40000).into()));
+        let roots =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tls/test-util/src/testdata/ca1.pem");
+        let originate_tls = OriginateTls {
+            inner: svc::mk(move |target: Connect| {
+                assert_eq!(target.addr, Remote(ServerAddr((SERVER.0, port).into())));
+                let io = io.take().expect("must only connect once");
+                future::ok::<_, io::Error>((io, local))
+            }),
+            config: Some(Config {
+                names: Some("example.com".parse::<dns::Suffix>().unwrap())
+                    .into_iter()
+                    .collect(),
+                port: 443,
+                client: tls_rustls::ClientConfig::load(Some(&roots), None).unwrap(),
+            }),
+        };
+        let target = Connect {
+            addr: Remote(ServerAddr(SERVER.into())),
+            tls: tls::ConditionalClientTls::None(tls::NoClientTls::NotProvidedByServiceDiscovery),
+            logical_addr: None,
+            client_addr: None,
+            server_name: server_name.map(|n| ServerName(n.parse().unwrap())),
+        };
+        let (io, _) = originate_tls.oneshot(target).await?;
+        Ok(matches!(io, io::EitherIo::Right(_)))
+    }
+
+    #[tokio::test]
+    async fn originates_tls_to_named_servers() {
+        let (client_io, mut server_io) = io::duplex(4096);
+        let connect = tokio::spawn(connect(Some("api.example.com"), 443, client_io));
+
+        // The connection begins with a TLS handshake record.
+        let mut record = [0u8; 1];
+        server_io.read_exact(&mut record).await.unwrap();
+        assert_eq!(record, [0x16]);
+
+        // The handshake fails when the server goes away.
+        drop(server_io);
+        assert!(connect.await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn forwards_other_servers_in_plaintext() {
+        for server_name in [None, Some("api.example.org")] {
+            let (client_io, _server_io) = io::duplex(4096);
+            let tls = connect(server_name, SERVER.1, client_io)
+                .await
+                .expect("connect must succeed");
+            assert!(!tls, "{:?} must not be sent TLS", server_name);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::tcp::Connect;
+use futures::prelude::*;
+use linkerd_app_core::{
+    io, svc, tls,
+    transport::{proxy_protocol::Header, ClientAddr, Local, Remote, ServerAddr},
+    IpMatch,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tracing::{debug, trace};
+
+/// Prefixes connections to unmeshed endpoints in the configured networks with
+/// a PROXY protocol header.
+///
+/// The header describes the accepted client on whose behalf the connection is
+/// established. Meshed endpoints are never sent a header, since the peer proxy
+/// identifies the client via mTLS, and neither are connections that are shared
+/// by several clients (e.g. pooled HTTP connections).
+#[derive(Clone, Debug)]
+pub struct ProxyProtocol<S> {
+    inner: S,
+    networks: IpMatch,
+}
+
+// === impl ProxyProtocol ===
+
+impl<S> ProxyProtocol<S> {
+    pub fn layer(networks: IpMatch) -> impl svc::Layer<S, Service = Self> + Clone {
+        svc::layer::mk(move |inner| Self {
+            inner,
+            networks: networks.clone(),
+        })
+    }
+}
+
+impl<S> svc::Service<Connect> for ProxyProtocol<S>
+where
+    S: svc::MakeConnection<Connect, Metadata = Local<ClientAddr>, Error = io::Error>,
+    S::Connection: Send + Unpin + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = (S::Connection, Local<ClientAddr>);
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: Connect) -> Self::Future {
+        let Remote(ServerAddr(server)) = target.addr;
+        let emit = matches!(target.tls, tls::ConditionalClientTls::None(_))
+            && self.networks.matches(server.ip());
+        if !emit {
+            return Box::pin(self.inner.connect(target));
+        }
+        let Remote(ClientAddr(client)) = match target.client_addr {
+            Some(client) => client,
+            None => {
+                trace!("Not writing a PROXY protocol header for a shared connection");
+                return Box::pin(self.inner.connect(target));
+            }
+        };
+        let connect = self.inner.connect(target);
+
+        Box::pin(async move {
+            let (mut io, local) = connect.await?;
+            let header = Header {
+                source: client,
+                destination: server,
+            };
+            trace!(?header, "Writing PROXY protocol header");
+            let sz = header.write(&mut io).await?;
+            debug!(sz, "Wrote PROXY protocol header");
+            Ok((io, local))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+    use linkerd_app_core::{svc::ServiceExt, IpNet};
+    use std::net::SocketAddr;
+
+    const SERVER: ([u8; 4], u16) = ([192, 0, 2, 10], 8080);
+
+    async fn connect(client_addr: Option<SocketAddr>, mock: support::io::Mock) -> io::Result<()> {
+        let mut mock = Some(mock);
+        let local = Local(ClientAddr(([192, 0, 2, 1], 40000).into()));
+        let proxy_protocol = ProxyProtocol {
+            inner: svc::mk(move |_: Connect| {
+                let mock = mock.take().expect("must only connect once");
+                future::ok::<_, io::Error>((mock, local))
+            }),
+            networks: IpMatch::new(Some("192.0.2.0/24".parse::<IpNet>().unwrap())),
+        };
+        let target = Connect {
+            addr: Remote(ServerAddr(SERVER.into())),
+            tls: tls::ConditionalClientTls::None(tls::NoClientTls::NotProvidedByServiceDiscovery),
+            logical_addr: None,
+            client_addr: client_addr.map(|a| Remote(ClientAddr(a))),
+            server_name: None,
+        };
+        proxy_protocol.oneshot(target).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn describes_accepted_client() {
+        let client = ([198, 51, 100, 7], 56324).into();
+        let header = Header {
+            source: client,
+            destination: SERVER.into(),
+        };
+        let mock = support::io().write(&header.encode_v2()).build();
+        connect(Some(client), mock)
+            .await
+            .expect("connect must succeed");
+    }
+
+    #[tokio::test]
+    async fn skips_shared_connections() {
+        // The mock fails if anything is written to it.
+        let mock = support::io().build();
+        connect(None, mock).await.expect("connect must succeed");
+    }
+}
@@ -15,6 +15,7 @@ use std::{str::FromStr, time::Duration};
 pub(crate) fn default_config() -> Config {
     Config {
         ingress_mode: false,
+        ingress_tls: None,
         emit_headers: true,
         allow_discovery: IpMatch::new(Some(IpNet::from_str("0.0.0.0/0").unwrap())).into(),
         proxy: config::ProxyConfig {
@@ -22,6 +23,8 @@ pub(crate) fn default_config() -> Config {
                 addr: ListenAddr(([0, 0, 0, 0], 0).into()),
                 keepalive: Keepalive(None),
                 h2_settings: h2::Settings::default(),
+                proxy_protocol: None,
+                unix_listen_path: None,
             },
             connect: config::ConnectConfig {
                 keepalive: Keepalive(None),
@@ -45,6 +48,14 @@ pub(crate) fn default_config() -> Config {
             detect_protocol_timeout: Duration::from_secs(3),
         },
         inbound_ips: Default::default(),
+        retry_max_buffered_bytes: 64 * 1024,
+        balancer_strategy: Default::default(),
+        http_outlier_detection: None,
+        proxy_protocol_networks: IpMatch::new(None),
+        zone: None,
+        locality_min_healthy_percent: 70,
+        detect_sni_ports: Default::default(),
+        tls_origination: None,
     }
 }
 
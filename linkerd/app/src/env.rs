@@ -2,10 +2,10 @@ use crate::core::{
     addr,
     config::*,
     control::{Config as ControlConfig, ControlAddr},
-    proxy::http::{h1, h2},
-    tls,
-    transport::{Keepalive, ListenAddr},
-    Addr, AddrMatch, Conditional, IpNet,
+    proxy::http::{self, h1, h2},
+    tls, tls_rustls,
+    transport::{proxy_protocol, Keepalive, ListenAddr, UnixListenPath, UnixSockets},
+    Addr, AddrMatch, Conditional, IpMatch, IpNet,
 };
 use crate::{dns, gateway, identity, inbound, oc_collector, outbound};
 use inbound::policy;
@@ -13,7 +13,7 @@ use std::{
     collections::{HashMap, HashSet},
     fs,
     net::{IpAddr, SocketAddr},
-    path::PathBuf,
+    path::{Path, PathBuf},
     str::FromStr,
     time::Duration,
 };
@@ -84,6 +84,12 @@ pub enum ParseError {
     InvalidTrustAnchors,
     #[error("not a valid port policy: {0}")]
     InvalidPortPolicy(String),
+    #[error("not a valid Unix socket mapping: {0}")]
+    InvalidUnixSocket(String),
+    #[error("not a valid ALPN protocol: {0}")]
+    InvalidAlpnProtocol(String),
+    #[error(transparent)]
+    InvalidBalancerStrategy(#[from] http::balance::InvalidStrategy),
 }
 
 // Environment variables to look at when loading the configuration
@@ -91,11 +97,33 @@ pub const ENV_OUTBOUND_LISTEN_ADDR: &str = "LINKERD2_PROXY_OUTBOUND_LISTEN_ADDR"
 pub const ENV_INBOUND_LISTEN_ADDR: &str = "LINKERD2_PROXY_INBOUND_LISTEN_ADDR";
 pub const ENV_CONTROL_LISTEN_ADDR: &str = "LINKERD2_PROXY_CONTROL_LISTEN_ADDR";
 pub const ENV_ADMIN_LISTEN_ADDR: &str = "LINKERD2_PROXY_ADMIN_LISTEN_ADDR";
+/// When set, the admin server listens on a Unix domain socket at this path
+/// instead of on a TCP port.
+pub const ENV_ADMIN_LISTEN_PATH: &str = "LINKERD2_PROXY_ADMIN_LISTEN_PATH";
+
+/// A comma-separated list of `port=path` mappings. Inbound connections that
+/// target a mapped port are forwarded to the application over the Unix domain
+/// socket at `path`.
+pub const ENV_INBOUND_UNIX_SOCKETS: &str = "LINKERD2_PROXY_INBOUND_UNIX_SOCKETS";
 
 pub const ENV_METRICS_RETAIN_IDLE: &str = "LINKERD2_PROXY_METRICS_RETAIN_IDLE";
 
 const ENV_INGRESS_MODE: &str = "LINKERD2_PROXY_INGRESS_MODE";
 
+/// A directory of server certificates with which ingress-mode proxies
+/// terminate TLS. Each subdirectory is named for a server and holds `tls.crt`
+/// and `tls.key` PEM files. A connection's SNI is only detected on
+/// `ENV_OUTBOUND_PORTS_DETECT_SNI`.
+///
+/// Certificates are loaded when the proxy starts and reloaded as they change.
+/// If the directory cannot be read, no connections are terminated until it
+/// can be.
+pub const ENV_INGRESS_TLS_CERTS_DIR: &str = "LINKERD2_PROXY_INGRESS_TLS_CERTS_DIR";
+
+/// A comma-separated list of the ALPN protocols that ingress-mode proxies
+/// negotiate when terminating TLS, in order of preference.
+pub const ENV_INGRESS_TLS_ALPN_PROTOCOLS: &str = "LINKERD2_PROXY_INGRESS_TLS_ALPN_PROTOCOLS";
+
 const ENV_INBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_INBOUND_DISPATCH_TIMEOUT";
 const ENV_OUTBOUND_DISPATCH_TIMEOUT: &str = "LINKERD2_PROXY_OUTBOUND_DISPATCH_TIMEOUT";
 
@@ -123,6 +151,80 @@ const ENV_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT: &str =
 pub const ENV_INBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_INBOUND_MAX_IN_FLIGHT";
 pub const ENV_OUTBOUND_MAX_IN_FLIGHT: &str = "LINKERD2_PROXY_OUTBOUND_MAX_IN_FLIGHT";
 
+pub const ENV_OUTBOUND_HTTP_RETRY_MAX_BUFFERED_BYTES: &str =
+    "LINKERD2_PROXY_OUTBOUND_HTTP_RETRY_MAX_BUFFERED_BYTES";
+
+pub const ENV_INBOUND_HTTP_MIRROR_MAX_BUFFERED_BYTES: &str =
+    "LINKERD2_PROXY_INBOUND_HTTP_MIRROR_MAX_BUFFERED_BYTES";
+pub const ENV_INBOUND_HTTP_MIRROR_MAX_IN_FLIGHT: &str =
+    "LINKERD2_PROXY_INBOUND_HTTP_MIRROR_MAX_IN_FLIGHT";
+
+/// The zone in which the proxy runs. Outbound balancers prefer endpoints with
+/// a matching `zone` label, except for destinations that are balanced by a
+/// sticky cookie or a hash policy, which pin requests to endpoints in any zone.
+pub const ENV_ZONE: &str = "LINKERD2_PROXY_ZONE";
+
+/// The minimum percentage of the endpoints in the proxy's zone that must be
+/// healthy (i.e., not ejected by outlier detection) for outbound balancers to
+/// prefer them. Otherwise, requests spill over to endpoints in other zones.
+pub const ENV_OUTBOUND_LOCALITY_MIN_HEALTHY_PERCENT: &str =
+    "LINKERD2_PROXY_OUTBOUND_LOCALITY_MIN_HEALTHY_PERCENT";
+
+/// The load balancing strategy used by outbound balancers, unless a
+/// destination's profile configures its own. One of `peak-ewma` (the
+/// default), `least-request`, or `round-robin`.
+pub const ENV_OUTBOUND_BALANCER_STRATEGY: &str = "LINKERD2_PROXY_OUTBOUND_BALANCER_STRATEGY";
+
+/// Connections from these networks must be prefixed with a PROXY protocol
+/// header that describes the original client and destination addresses. The
+/// header's client address is used as the connection's client address; the
+/// connection is still routed by its own original destination address.
+pub const ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS: &str =
+    "LINKERD2_PROXY_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS";
+pub const ENV_OUTBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS: &str =
+    "LINKERD2_PROXY_OUTBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS";
+pub const ENV_PROXY_PROTOCOL_HEADER_TIMEOUT: &str = "LINKERD2_PROXY_PROXY_PROTOCOL_HEADER_TIMEOUT";
+
+/// Outbound connections to endpoints in these networks are prefixed with a
+/// PROXY protocol header that describes the accepted client, unless the
+/// endpoint is meshed. Pooled HTTP connections are never prefixed.
+pub const ENV_OUTBOUND_PROXY_PROTOCOL_NETWORKS: &str =
+    "LINKERD2_PROXY_OUTBOUND_PROXY_PROTOCOL_NETWORKS";
+
+/// Outbound connections to these ports are peeked (or, when necessary,
+/// buffered) for a TLS ClientHello so that the SNI may be used to discover the
+/// destination. By default, SNI is not detected on any port.
+pub const ENV_OUTBOUND_PORTS_DETECT_SNI: &str = "LINKERD2_PROXY_OUTBOUND_PORTS_DETECT_SNI";
+
+/// Plaintext connections to unmeshed destinations with names in these domains
+/// are upgraded to TLS on `ENV_OUTBOUND_TLS_ORIGINATION_PORT`. Destinations are
+/// named by their logical address or, when they are not discovered, by the
+/// authority of each HTTP request.
+pub const ENV_OUTBOUND_TLS_ORIGINATION_SUFFIXES: &str =
+    "LINKERD2_PROXY_OUTBOUND_TLS_ORIGINATION_SUFFIXES";
+pub const ENV_OUTBOUND_TLS_ORIGINATION_PORT: &str = "LINKERD2_PROXY_OUTBOUND_TLS_ORIGINATION_PORT";
+/// A PEM file of roots used to verify servers. When unset, the system's trust
+/// roots are used.
+pub const ENV_OUTBOUND_TLS_ORIGINATION_TRUST_ROOTS: &str =
+    "LINKERD2_PROXY_OUTBOUND_TLS_ORIGINATION_TRUST_ROOTS";
+/// PEM files of a client certificate chain and key that are presented to
+/// servers that request client authentication.
+pub const ENV_OUTBOUND_TLS_ORIGINATION_CLIENT_CERT: &str =
+    "LINKERD2_PROXY_OUTBOUND_TLS_ORIGINATION_CLIENT_CERT";
+pub const ENV_OUTBOUND_TLS_ORIGINATION_CLIENT_KEY: &str =
+    "LINKERD2_PROXY_OUTBOUND_TLS_ORIGINATION_CLIENT_KEY";
+
+pub const ENV_OUTBOUND_OUTLIER_CONSECUTIVE_5XX: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_CONSECUTIVE_5XX";
+pub const ENV_OUTBOUND_OUTLIER_CONSECUTIVE_ERRORS: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_CONSECUTIVE_ERRORS";
+pub const ENV_OUTBOUND_OUTLIER_BASE_EJECTION_TIME: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_BASE_EJECTION_TIME";
+pub const ENV_OUTBOUND_OUTLIER_MAX_EJECTION_TIME: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_MAX_EJECTION_TIME";
+pub const ENV_OUTBOUND_OUTLIER_MAX_EJECTED_PERCENT: &str =
+    "LINKERD2_PROXY_OUTBOUND_OUTLIER_MAX_EJECTED_PERCENT";
+
 const ENV_OUTBOUND_DISABLE_INFORMATIONAL_HEADERS: &str =
     "LINKERD2_PROXY_OUTBOUND_DISABLE_INFORMATIONAL_HEADERS";
 
@@ -177,6 +279,16 @@ pub const ENV_POLICY_SVC_BASE: &str = "LINKERD2_PROXY_POLICY_SVC";
 pub const ENV_POLICY_WORKLOAD: &str = "LINKERD2_PROXY_POLICY_WORKLOAD";
 pub const ENV_POLICY_CLUSTER_NETWORKS: &str = "LINKERD2_PROXY_POLICY_CLUSTER_NETWORKS";
 
+/// Configures a YAML (or JSON) file that describes inbound server policies.
+///
+/// The file is only used when policy discovery is not configured (i.e. when
+/// `LINKERD2_PROXY_POLICY_SVC_ADDR` is not set), and it is reloaded whenever it
+/// changes. Ports that are not described by the file use the default policy.
+///
+/// This is currently the only way to configure a server in audit mode, since
+/// discovered policies are always enforced.
+pub const ENV_INBOUND_POLICY_FILE: &str = "LINKERD2_PROXY_INBOUND_POLICY_FILE";
+
 pub const ENV_INBOUND_IPS: &str = "LINKERD2_PROXY_INBOUND_IPS";
 
 pub const ENV_IDENTITY_DISABLED: &str = "LINKERD2_PROXY_IDENTITY_DISABLED";
@@ -187,6 +299,10 @@ pub const ENV_IDENTITY_TOKEN_FILE: &str = "LINKERD2_PROXY_IDENTITY_TOKEN_FILE";
 pub const ENV_IDENTITY_MIN_REFRESH: &str = "LINKERD2_PROXY_IDENTITY_MIN_REFRESH";
 pub const ENV_IDENTITY_MAX_REFRESH: &str = "LINKERD2_PROXY_IDENTITY_MAX_REFRESH";
 
+/// A comma-separated list of trust domains in which clients may be identified
+/// by a SPIFFE ID. When unset, clients are only identified by their DNS SANs.
+pub const ENV_IDENTITY_SPIFFE_TRUST_DOMAINS: &str = "LINKERD2_PROXY_IDENTITY_SPIFFE_TRUST_DOMAINS";
+
 pub const ENV_IDENTITY_SVC_BASE: &str = "LINKERD2_PROXY_IDENTITY_SVC";
 
 pub const ENV_DESTINATION_SVC_BASE: &str = "LINKERD2_PROXY_DESTINATION_SVC";
@@ -228,15 +344,19 @@ const DEFAULT_ADMIN_LISTEN_ADDR: &str = "127.0.0.1:4191";
 const DEFAULT_METRICS_RETAIN_IDLE: Duration = Duration::from_secs(10 * 60);
 const DEFAULT_INBOUND_DISPATCH_TIMEOUT: Duration = Duration::from_secs(1);
 const DEFAULT_INBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_INBOUND_POLICY_FILE_RELOAD_INTERVAL: Duration = Duration::from_secs(5);
 const DEFAULT_INBOUND_CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
 const DEFAULT_INBOUND_CONNECT_BACKOFF: ExponentialBackoff =
     ExponentialBackoff::new_unchecked(Duration::from_millis(100), Duration::from_millis(500), 0.1);
 const DEFAULT_OUTBOUND_DISPATCH_TIMEOUT: Duration = Duration::from_secs(3);
 const DEFAULT_OUTBOUND_DETECT_TIMEOUT: Duration = Duration::from_secs(10);
 const DEFAULT_OUTBOUND_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_OUTBOUND_PORTS_DETECT_SNI: &str = "";
+const DEFAULT_OUTBOUND_TLS_ORIGINATION_PORT: u16 = 443;
 const DEFAULT_OUTBOUND_CONNECT_BACKOFF: ExponentialBackoff =
     ExponentialBackoff::new_unchecked(Duration::from_millis(100), Duration::from_millis(500), 0.1);
 const DEFAULT_RESOLV_CONF: &str = "/etc/resolv.conf";
+const DEFAULT_INGRESS_TLS_ALPN_PROTOCOLS: &str = "h2,http/1.1";
 
 const DEFAULT_INITIAL_STREAM_WINDOW_SIZE: u32 = 65_535; // Protocol default
 const DEFAULT_INITIAL_CONNECTION_WINDOW_SIZE: u32 = 1048576; // 1MB ~ 16 streams at capacity
@@ -271,6 +391,29 @@ const DEFAULT_OUTBOUND_MAX_IDLE_CONNS_PER_ENDPOINT: usize = usize::MAX;
 const DEFAULT_INBOUND_MAX_IN_FLIGHT: usize = 100_000;
 const DEFAULT_OUTBOUND_MAX_IN_FLIGHT: usize = 100_000;
 
+// Request bodies up to this size are buffered so that requests may be retried.
+const DEFAULT_OUTBOUND_HTTP_RETRY_MAX_BUFFERED_BYTES: usize = 64 * 1024;
+
+// Request bodies up to this size are buffered so that requests may be mirrored.
+// Larger requests are not mirrored.
+const DEFAULT_INBOUND_HTTP_MIRROR_MAX_BUFFERED_BYTES: usize = 64 * 1024;
+
+// Limits the number of mirrored requests that may be in flight at once. Requests
+// are not mirrored while this limit is reached.
+const DEFAULT_INBOUND_HTTP_MIRROR_MAX_IN_FLIGHT: usize = 100;
+
+const DEFAULT_PROXY_PROTOCOL_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Ejected endpoints are ejected for longer each time they are ejected, up to
+// the max ejection time. At most half of a balancer's endpoints are ejected.
+const DEFAULT_OUTBOUND_OUTLIER_BASE_EJECTION_TIME: Duration = Duration::from_secs(30);
+const DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTION_TIME: Duration = Duration::from_secs(300);
+const DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTED_PERCENT: u8 = 50;
+
+// Requests spill over to other zones once more than 30% of the local
+// endpoints are ejected.
+const DEFAULT_OUTBOUND_LOCALITY_MIN_HEALTHY_PERCENT: u8 = 70;
+
 // This value should be large enough to admit requests without exerting
 // backpressure so that requests implicitly buffer in the executor; but it
 // should be small enough that callers can't force the proxy to consume an
@@ -297,6 +440,8 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let outbound_listener_addr = parse(strings, ENV_OUTBOUND_LISTEN_ADDR, parse_socket_addr);
     let inbound_listener_addr = parse(strings, ENV_INBOUND_LISTEN_ADDR, parse_socket_addr);
     let admin_listener_addr = parse(strings, ENV_ADMIN_LISTEN_ADDR, parse_socket_addr);
+    let admin_listener_path = strings.get(ENV_ADMIN_LISTEN_PATH);
+    let inbound_unix_sockets = parse(strings, ENV_INBOUND_UNIX_SOCKETS, parse_unix_sockets);
 
     let inbound_detect_timeout = parse(strings, ENV_INBOUND_DETECT_TIMEOUT, parse_duration);
     let inbound_dispatch_timeout = parse(strings, ENV_INBOUND_DISPATCH_TIMEOUT, parse_duration);
@@ -340,6 +485,41 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let inbound_max_in_flight = parse(strings, ENV_INBOUND_MAX_IN_FLIGHT, parse_number);
     let outbound_max_in_flight = parse(strings, ENV_OUTBOUND_MAX_IN_FLIGHT, parse_number);
+    let outbound_retry_max_buffered_bytes = parse(
+        strings,
+        ENV_OUTBOUND_HTTP_RETRY_MAX_BUFFERED_BYTES,
+        parse_number,
+    );
+    let inbound_mirror_max_buffered_bytes = parse(
+        strings,
+        ENV_INBOUND_HTTP_MIRROR_MAX_BUFFERED_BYTES,
+        parse_number,
+    );
+    let inbound_mirror_max_in_flight =
+        parse(strings, ENV_INBOUND_HTTP_MIRROR_MAX_IN_FLIGHT, parse_number);
+    let outbound_balancer_strategy = parse(
+        strings,
+        ENV_OUTBOUND_BALANCER_STRATEGY,
+        parse_balancer_strategy,
+    );
+    let outbound_outlier_detection = parse_outlier_detection(strings);
+    let outbound_tls_origination = parse_tls_origination(strings);
+    let inbound_proxy_protocol =
+        parse_proxy_protocol(strings, ENV_INBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS);
+    let outbound_proxy_protocol =
+        parse_proxy_protocol(strings, ENV_OUTBOUND_PROXY_PROTOCOL_TRUSTED_NETWORKS);
+    let outbound_proxy_protocol_networks = parse(
+        strings,
+        ENV_OUTBOUND_PROXY_PROTOCOL_NETWORKS,
+        parse_networks,
+    );
+    let outbound_detect_sni_ports = parse(strings, ENV_OUTBOUND_PORTS_DETECT_SNI, parse_port_set);
+    let zone = strings.get(ENV_ZONE);
+    let locality_min_healthy_percent = parse(
+        strings,
+        ENV_OUTBOUND_LOCALITY_MIN_HEALTHY_PERCENT,
+        parse_number,
+    );
 
     let metrics_retain_idle = parse(strings, ENV_METRICS_RETAIN_IDLE, parse_duration);
 
@@ -351,6 +531,11 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
     let dns_max_ttl = parse(strings, ENV_DNS_MAX_TTL, parse_duration);
 
     let identity_config = parse_identity_config(strings);
+    let identity_spiffe_trust_domains = parse(
+        strings,
+        ENV_IDENTITY_SPIFFE_TRUST_DOMAINS,
+        parse_spiffe_trust_domains,
+    );
 
     let hostname = strings.get(ENV_HOSTNAME);
 
@@ -411,6 +596,20 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
     let outbound = {
         let ingress_mode = parse(strings, ENV_INGRESS_MODE, parse_bool)?.unwrap_or(false);
+        let ingress_tls = match strings.get(ENV_INGRESS_TLS_CERTS_DIR)? {
+            Some(dir) => {
+                let alpn_protocols = parse(
+                    strings,
+                    ENV_INGRESS_TLS_ALPN_PROTOCOLS,
+                    parse_alpn_protocols,
+                )?
+                .unwrap_or_else(|| {
+                    parse_alpn_protocols(DEFAULT_INGRESS_TLS_ALPN_PROTOCOLS).unwrap()
+                });
+                Some(tls_rustls::ServerConfig::new(dir.into(), alpn_protocols))
+            }
+            None => None,
+        };
 
         // Instances can opt out of receiving informational headers by setting this configuration.
         // These headers are also omitted by default if ingress-mode is enabled.
@@ -430,6 +629,8 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             addr,
             keepalive,
             h2_settings,
+            proxy_protocol: outbound_proxy_protocol?,
+            unix_listen_path: None,
         };
         let cache_max_idle_age =
             outbound_cache_max_idle_age?.unwrap_or(DEFAULT_OUTBOUND_ROUTER_MAX_IDLE_AGE);
@@ -458,6 +659,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
 
         outbound::Config {
             ingress_mode,
+            ingress_tls,
             emit_headers: !disable_headers,
             allow_discovery: AddrMatch::new(dst_profile_suffixes.clone(), dst_profile_networks),
             proxy: ProxyConfig {
@@ -471,6 +673,23 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 detect_protocol_timeout,
             },
             inbound_ips: inbound_ips.clone(),
+            retry_max_buffered_bytes: outbound_retry_max_buffered_bytes?
+                .unwrap_or(DEFAULT_OUTBOUND_HTTP_RETRY_MAX_BUFFERED_BYTES),
+            balancer_strategy: outbound_balancer_strategy?.unwrap_or_default(),
+            http_outlier_detection: outbound_outlier_detection?,
+            proxy_protocol_networks: IpMatch::new(
+                outbound_proxy_protocol_networks?.into_iter().flatten(),
+            ),
+            zone: zone?
+                .filter(|z| !z.is_empty())
+                .map(|z| http::balance::Zone::from(z.as_str())),
+            locality_min_healthy_percent: locality_min_healthy_percent?
+                .unwrap_or(DEFAULT_OUTBOUND_LOCALITY_MIN_HEALTHY_PERCENT),
+            tls_origination: outbound_tls_origination?,
+            detect_sni_ports: std::sync::Arc::new(
+                outbound_detect_sni_ports?
+                    .unwrap_or_else(|| parse_port_set(DEFAULT_OUTBOUND_PORTS_DETECT_SNI).unwrap()),
+            ),
         }
     };
 
@@ -491,6 +710,8 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             addr,
             keepalive,
             h2_settings,
+            proxy_protocol: inbound_proxy_protocol?,
+            unix_listen_path: None,
         };
         let cache_max_idle_age =
             inbound_cache_max_idle_age?.unwrap_or(DEFAULT_INBOUND_ROUTER_MAX_IDLE_AGE);
@@ -544,8 +765,17 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 policy::defaults::all_unauthenticated(detect_protocol_timeout).into()
             });
 
-            match parse_control_addr(strings, ENV_POLICY_SVC_BASE)? {
-                Some(addr) => {
+            let policy_addr = parse_control_addr(strings, ENV_POLICY_SVC_BASE)?;
+            let policy_file = strings.get(ENV_INBOUND_POLICY_FILE)?;
+            match (policy_addr, policy_file) {
+                (Some(addr), policy_file) => {
+                    if policy_file.is_some() {
+                        warn!(
+                            "{} is ignored when {}_ADDR is set",
+                            ENV_INBOUND_POLICY_FILE, ENV_POLICY_SVC_BASE
+                        );
+                    }
+
                     // If the inbound is proxy is configured to discover policies, then load the set
                     // of all known inbound ports to be discovered during initialization.
                     let mut ports = match parse(strings, ENV_INBOUND_PORTS, parse_port_set)? {
@@ -598,7 +828,16 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                     }
                 }
 
-                None => {
+                // If the inbound proxy is configured with a policy file, then
+                // policies are read from it as it changes.
+                (None, Some(path)) => inbound::policy::Config::File {
+                    path: path.into(),
+                    default,
+                    cache_max_idle_age,
+                    reload_interval: DEFAULT_INBOUND_POLICY_FILE_RELOAD_INTERVAL,
+                },
+
+                (None, None) => {
                     let default_allow = match default.clone() {
                         policy::DefaultPolicy::Allow(a) => a,
                         policy::DefaultPolicy::Deny => {
@@ -704,6 +943,11 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             profile_idle_timeout: dst_profile_idle_timeout?
                 .unwrap_or(DEFAULT_DESTINATION_PROFILE_IDLE_TIMEOUT),
             allowed_ips: inbound_ips.into(),
+            unix_sockets: UnixSockets::new(inbound_unix_sockets?.unwrap_or_default()),
+            mirror_max_buffered_bytes: inbound_mirror_max_buffered_bytes?
+                .unwrap_or(DEFAULT_INBOUND_HTTP_MIRROR_MAX_BUFFERED_BYTES),
+            mirror_max_in_flight: inbound_mirror_max_in_flight?
+                .unwrap_or(DEFAULT_INBOUND_HTTP_MIRROR_MAX_IN_FLIGHT),
         }
     };
 
@@ -730,6 +974,10 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
             addr: ListenAddr(admin_listener_addr),
             keepalive: inbound.proxy.server.keepalive,
             h2_settings,
+            proxy_protocol: None,
+            unix_listen_path: admin_listener_path?
+                .filter(|p| !p.is_empty())
+                .map(|p| UnixListenPath(p.into())),
         },
     };
 
@@ -776,6 +1024,8 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 addr: ListenAddr(addr),
                 keepalive: inbound.proxy.server.keepalive,
                 h2_settings,
+                proxy_protocol: None,
+                unix_listen_path: None,
             },
         })
         .unwrap_or(super::tap::Config::Disabled);
@@ -796,6 +1046,7 @@ pub fn parse_config<S: Strings>(strings: &S) -> Result<super::Config, EnvError>
                 buffer_capacity: 1,
             },
             documents,
+            spiffe_trust_domains: identity_spiffe_trust_domains?.unwrap_or_default(),
         }
     };
 
@@ -882,7 +1133,7 @@ fn parse_tap_config(
     if let Some(id) = tap_identity {
         return Ok(Some((
             addr,
-            vec![id].into_iter().map(tls::ClientId).collect(),
+            vec![id].into_iter().map(tls::ClientId::from).collect(),
         )));
     }
     Ok(None)
@@ -942,6 +1193,26 @@ fn parse_addr(s: &str) -> Result<Addr, ParseError> {
     })
 }
 
+fn parse_balancer_strategy(s: &str) -> Result<http::balance::Strategy, ParseError> {
+    s.parse().map_err(Into::into)
+}
+
+fn parse_unix_sockets(s: &str) -> Result<HashMap<u16, PathBuf>, ParseError> {
+    let mut sockets = HashMap::new();
+    for mapping in s.split(',') {
+        let mapping = mapping.trim();
+        if mapping.is_empty() {
+            continue;
+        }
+        let (port, path) = mapping.split_once('=').ok_or_else(|| {
+            error!(%mapping, "Unix socket mappings must be of the form `port=path`");
+            ParseError::InvalidUnixSocket(mapping.to_string())
+        })?;
+        sockets.insert(parse_number(port.trim())?, PathBuf::from(path.trim()));
+    }
+    Ok(sockets)
+}
+
 fn parse_port_set(s: &str) -> Result<HashSet<u16>, ParseError> {
     let mut set = HashSet::new();
     if !s.is_empty() {
@@ -1023,6 +1294,34 @@ fn parse_dns_suffix(s: &str) -> Result<dns::Suffix, ParseError> {
     dns::Suffix::from_str(s).map_err(|_| ParseError::NotADomainSuffix)
 }
 
+fn parse_spiffe_trust_domains(list: &str) -> Result<Vec<String>, ParseError> {
+    let mut domains = Vec::new();
+    for item in list.split(',') {
+        let item = item.trim();
+        if !item.is_empty() {
+            // Trust domains are DNS-like names.
+            parse_identity(item)?;
+            domains.push(item.to_string());
+        }
+    }
+    Ok(domains)
+}
+
+fn parse_alpn_protocols(list: &str) -> Result<Vec<Vec<u8>>, ParseError> {
+    let mut protocols = Vec::new();
+    for item in list.split(',') {
+        let item = item.trim();
+        if !item.is_empty() {
+            // Protocol identifiers are length-prefixed by a single byte.
+            if item.len() > u8::MAX as usize {
+                return Err(ParseError::InvalidAlpnProtocol(item.to_string()));
+            }
+            protocols.push(item.as_bytes().to_vec());
+        }
+    }
+    Ok(protocols)
+}
+
 fn parse_networks(list: &str) -> Result<HashSet<IpNet>, ParseError> {
     let mut nets = HashSet::new();
     for input in list.split(',') {
@@ -1087,6 +1386,121 @@ pub fn parse_backoff<S: Strings>(
     }
 }
 
+fn parse_proxy_protocol<S: Strings>(
+    strings: &S,
+    networks_env: &str,
+) -> Result<Option<proxy_protocol::Config>, EnvError> {
+    let networks = parse(strings, networks_env, parse_networks)?.unwrap_or_default();
+    if networks.is_empty() {
+        return Ok(None);
+    }
+    let header_timeout = parse(strings, ENV_PROXY_PROTOCOL_HEADER_TIMEOUT, parse_duration)?
+        .unwrap_or(DEFAULT_PROXY_PROTOCOL_HEADER_TIMEOUT);
+    Ok(Some(proxy_protocol::Config {
+        trusted_networks: networks.into_iter().collect(),
+        header_timeout,
+    }))
+}
+
+fn parse_tls_origination<S: Strings>(
+    strings: &S,
+) -> Result<Option<outbound::tcp::originate_tls::Config>, EnvError> {
+    let suffixes = parse(
+        strings,
+        ENV_OUTBOUND_TLS_ORIGINATION_SUFFIXES,
+        parse_dns_suffixes,
+    );
+    let port = parse(strings, ENV_OUTBOUND_TLS_ORIGINATION_PORT, parse_number);
+    let roots = strings.get(ENV_OUTBOUND_TLS_ORIGINATION_TRUST_ROOTS);
+    let cert = strings.get(ENV_OUTBOUND_TLS_ORIGINATION_CLIENT_CERT);
+    let key = strings.get(ENV_OUTBOUND_TLS_ORIGINATION_CLIENT_KEY);
+
+    let suffixes = suffixes?.unwrap_or_default();
+    if suffixes.is_empty() {
+        return Ok(None);
+    }
+
+    let identity = match (cert?, key?) {
+        (None, None) => None,
+        (Some(cert), Some(key)) => {
+            let identity =
+                tls_rustls::Identity::load(Path::new(&cert), Path::new(&key)).map_err(|error| {
+                    error!(%error, "Failed to load TLS origination client identity");
+                    EnvError::InvalidEnvVar
+                })?;
+            Some(identity)
+        }
+        _ => {
+            error!(
+                "{} and {} must be set together",
+                ENV_OUTBOUND_TLS_ORIGINATION_CLIENT_CERT, ENV_OUTBOUND_TLS_ORIGINATION_CLIENT_KEY
+            );
+            return Err(EnvError::InvalidEnvVar);
+        }
+    };
+    let client = tls_rustls::ClientConfig::load(roots?.as_deref().map(Path::new), identity)
+        .map_err(|error| {
+            error!(%error, "Failed to load TLS origination trust roots");
+            EnvError::InvalidEnvVar
+        })?;
+
+    Ok(Some(outbound::tcp::originate_tls::Config {
+        names: suffixes.into_iter().collect(),
+        port: port?.unwrap_or(DEFAULT_OUTBOUND_TLS_ORIGINATION_PORT),
+        client,
+    }))
+}
+
+/// Outlier detection is only enabled when at least one of the consecutive
+/// failure thresholds is configured.
+fn parse_outlier_detection<S: Strings>(
+    strings: &S,
+) -> Result<Option<http::balance::outlier::Config>, EnvError> {
+    let consecutive_5xx = parse(strings, ENV_OUTBOUND_OUTLIER_CONSECUTIVE_5XX, parse_number);
+    let consecutive_errors = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_CONSECUTIVE_ERRORS,
+        parse_number,
+    );
+    let base = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_BASE_EJECTION_TIME,
+        parse_duration,
+    );
+    let max = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_MAX_EJECTION_TIME,
+        parse_duration,
+    );
+    let max_ejected_percent = parse(
+        strings,
+        ENV_OUTBOUND_OUTLIER_MAX_EJECTED_PERCENT,
+        parse_number,
+    );
+
+    let (consecutive_5xx, consecutive_errors) = (consecutive_5xx?, consecutive_errors?);
+    if consecutive_5xx.is_none() && consecutive_errors.is_none() {
+        return Ok(None);
+    }
+
+    let base = base?.unwrap_or(DEFAULT_OUTBOUND_OUTLIER_BASE_EJECTION_TIME);
+    let max = max?.unwrap_or(DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTION_TIME);
+    let max_ejected_percent =
+        max_ejected_percent?.unwrap_or(DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTED_PERCENT);
+    http::balance::outlier::Config::try_new(
+        consecutive_5xx,
+        consecutive_errors,
+        base,
+        max,
+        max_ejected_percent,
+    )
+    .map(Some)
+    .map_err(|error| {
+        error!(message="Invalid outlier ejection times", %error, ?base, ?max);
+        EnvError::InvalidEnvVar
+    })
+}
+
 pub fn parse_control_addr<S: Strings>(
     strings: &S,
     base: &str,
@@ -1408,4 +1822,30 @@ mod tests {
         assert!(parse_ip_set("10.4.0.3,foobar,192.168.0.69").is_err());
         assert!(parse_ip_set("10.0.1.1/24").is_err());
     }
+
+    #[test]
+    fn alpn_protocols() {
+        assert_eq!(parse_alpn_protocols(""), Ok(vec![]));
+        assert_eq!(
+            parse_alpn_protocols(" h2, http/1.1,"),
+            Ok(vec![b"h2".to_vec(), b"http/1.1".to_vec()])
+        );
+        assert!(parse_alpn_protocols(&"a".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn unix_sockets() {
+        assert_eq!(parse_unix_sockets(""), Ok(HashMap::new()));
+        assert_eq!(
+            parse_unix_sockets("8080=/var/run/app.sock, 9090=/tmp/admin.sock"),
+            Ok(vec![
+                (8080, PathBuf::from("/var/run/app.sock")),
+                (9090, PathBuf::from("/tmp/admin.sock")),
+            ]
+            .into_iter()
+            .collect())
+        );
+        assert!(parse_unix_sockets("/var/run/app.sock").is_err());
+        assert!(parse_unix_sockets("http=/var/run/app.sock").is_err());
+    }
 }
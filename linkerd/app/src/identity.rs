@@ -21,6 +21,10 @@ pub struct Config {
     pub control: control::Config,
     pub certify: certify::Config,
     pub documents: Documents,
+
+    /// Trust domains in which clients may be identified by a SPIFFE ID (i.e.
+    /// a URI SAN). Clients are otherwise identified by their DNS SANs.
+    pub spiffe_trust_domains: Vec<String>,
 }
 
 #[derive(Clone)]
@@ -61,6 +65,7 @@ impl Config {
             &self.documents.key_pkcs8,
             &self.documents.csr_der,
         )?;
+        let receiver = receiver.with_spiffe_trust_domains(self.spiffe_trust_domains);
 
         let certify = Certify::from(self.certify);
         let metrics = certify.metrics();
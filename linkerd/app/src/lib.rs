@@ -20,7 +20,7 @@ use linkerd_app_core::{
     metrics::FmtMetrics,
     svc::Param,
     telemetry,
-    transport::{listen::Bind, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr},
+    transport::{listen::Bind, ClientAddr, Local, OrigDstAddr, Remote, ServerAddr, UnixListenPath},
     Error, ProxyRuntime,
 };
 use linkerd_app_gateway as gateway;
@@ -67,6 +67,7 @@ pub struct Config {
 
 pub struct App {
     admin: admin::Task,
+    admin_listen_path: Option<UnixListenPath>,
     drain: drain::Signal,
     dst: ControlAddr,
     identity: identity::Identity,
@@ -167,6 +168,7 @@ impl Config {
             info_span!("policy").in_scope(|| inbound.build_policies(dns, metrics))
         };
 
+        let admin_listen_path = admin.server.unix_listen_path.clone();
         let admin = {
             let identity = identity.receiver().server();
             let metrics = inbound.metrics();
@@ -189,6 +191,18 @@ impl Config {
             })?
         };
 
+        // Requests mirrored by inbound route policies are sent through the
+        // outbound proxy, so that mirrors are discovered and secured like any
+        // other meshed traffic.
+        let inbound = inbound.with_mirrors(
+            outbound
+                .to_tcp_connect()
+                .push_tcp_endpoint()
+                .push_http_endpoint()
+                .push_http_mirror(dst.profiles.clone(), dst.resolve.clone())
+                .into_inner(),
+        );
+
         let dst_addr = dst.addr.clone();
         let gateway_stack = gateway::stack(
             gateway,
@@ -240,6 +254,7 @@ impl Config {
 
         Ok(App {
             admin,
+            admin_listen_path,
             dst: dst_addr,
             drain: drain_tx,
             identity,
@@ -272,6 +287,12 @@ impl App {
         self.admin.listen_addr
     }
 
+    /// The Unix domain socket on which the admin server listens, if any. When
+    /// set, the admin server cannot be reached at `admin_addr`.
+    pub fn admin_listen_path(&self) -> Option<&UnixListenPath> {
+        self.admin_listen_path.as_ref()
+    }
+
     pub fn inbound_addr(&self) -> Local<ServerAddr> {
         self.inbound_addr
     }
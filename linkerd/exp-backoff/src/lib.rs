@@ -5,6 +5,7 @@ use futures::Stream;
 use pin_project::pin_project;
 use rand::{rngs::SmallRng, thread_rng, SeedableRng};
 use std::future::Future;
+use std::hash::{Hash, Hasher};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use thiserror::Error;
@@ -78,6 +79,16 @@ impl ExponentialBackoff {
         }
     }
 
+    /// Returns a jittered delay to wait before the `iterations`-th retry of
+    /// an operation (starting at zero).
+    ///
+    /// This may be used when an operation is not driven by a stream, e.g.
+    /// when a retry policy must compute a single delay.
+    pub fn delay(&self, iterations: u32) -> time::Duration {
+        let base = self.base(iterations);
+        base + self.jitter(base, &mut thread_rng())
+    }
+
     fn base(&self, iterations: u32) -> time::Duration {
         debug_assert!(
             self.min <= self.max,
@@ -113,6 +124,21 @@ impl ExponentialBackoff {
     }
 }
 
+impl PartialEq for ExponentialBackoff {
+    fn eq(&self, other: &Self) -> bool {
+        self.min == other.min && self.max == other.max && self.jitter == other.jitter
+    }
+}
+
+/// Only the backoff's bounds are hashed, since its jitter is a float; backoffs
+/// that are equal always have equal bounds.
+impl Hash for ExponentialBackoff {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.min.hash(state);
+        self.max.hash(state);
+    }
+}
+
 impl Stream for ExponentialBackoffStream {
     type Item = ();
 
@@ -170,6 +196,17 @@ mod tests {
             TestResult::from_bool(min <= delay && delay <= max)
         }
 
+        fn backoff_delay(min_ms: u64, max_ms: u64, jitter: f64, iterations: u32) -> TestResult {
+            let min = time::Duration::from_millis(min_ms);
+            let max = time::Duration::from_millis(max_ms);
+            let backoff = match ExponentialBackoff::try_new(min, max, jitter) {
+                Err(_) => return TestResult::discard(),
+                Ok(backoff) => backoff,
+            };
+            let delay = backoff.delay(iterations);
+            TestResult::from_bool(backoff.base(iterations) <= delay && delay <= max)
+        }
+
         fn backoff_jitter(base_ms: u64, max_ms: u64, jitter: f64) -> TestResult {
             let base = time::Duration::from_millis(base_ms);
             let max = time::Duration::from_millis(max_ms);
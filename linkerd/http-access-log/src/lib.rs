@@ -28,7 +28,7 @@ pub struct NewAccessLog<N> {
 pub struct AccessLogContext<S> {
     inner: S,
     client_addr: SocketAddr,
-    client_id: Option<identity::Name>,
+    client_id: Option<identity::Id>,
 }
 
 struct ResponseFutureInner {
@@ -76,7 +76,7 @@ where
         let tls: tls::ConditionalServerTls = target.param();
         let client_id = tls
             .value()
-            .and_then(|tls| tls.client_id().map(|tls::ClientId(name)| name.clone()));
+            .and_then(|tls| tls.client_id().map(|tls::ClientId(id)| id.clone()));
         let inner = self.inner.new_service(target);
         AccessLogContext {
             inner,
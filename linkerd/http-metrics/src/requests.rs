@@ -4,9 +4,10 @@ mod service;
 pub use self::service::{NewHttpMetrics, ResponseBody};
 use super::Report;
 use linkerd_http_classify::ClassifyResponse;
-use linkerd_metrics::{latency, Counter, FmtMetrics, Histogram, LastUpdate, NewMetrics};
+use linkerd_metrics::{latency, Bucket, Counter, FmtMetrics, Histogram, LastUpdate, NewMetrics};
 use linkerd_stack::{self as svc, layer};
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use parking_lot::Mutex;
+use std::{collections::HashMap, fmt::Debug, hash::Hash, sync::Arc};
 use tokio::time::{Duration, Instant};
 
 type Registry<T, C> = super::Registry<T, Metrics<C>>;
@@ -41,6 +42,12 @@ pub struct ClassMetrics {
     total: Counter,
 }
 
+/// Reads a target's response latencies, e.g. to derive a delay from them.
+#[derive(Debug)]
+pub struct Latencies<C>(Arc<Mutex<Metrics<C>>>)
+where
+    C: Hash + Eq;
+
 // === impl Requests ===
 
 impl<T: Hash + Eq, C: Hash + Eq> Default for Requests<T, C> {
@@ -67,6 +74,11 @@ impl<T: Hash + Eq, C: Hash + Eq> Requests<T, C> {
         let reg = self.0.clone();
         NewMetrics::layer(reg)
     }
+
+    pub fn latencies(&self, target: T) -> Latencies<C> {
+        let mut reg = self.0.lock();
+        Latencies(reg.entry(target).or_default().clone())
+    }
 }
 
 impl<T: Hash + Eq, C: Hash + Eq> Clone for Requests<T, C> {
@@ -75,6 +87,55 @@ impl<T: Hash + Eq, C: Hash + Eq> Clone for Requests<T, C> {
     }
 }
 
+// === impl Latencies ===
+
+impl<C: Hash + Eq> Latencies<C> {
+    /// Estimates the `q`-quantile (on `[0, 1]`) of the target's response
+    /// latencies over all status codes.
+    ///
+    /// The estimate is the upper bound of the histogram bucket that contains
+    /// the quantile. `None` is returned if fewer than `min_count` responses
+    /// have been recorded or if the quantile falls in the unbounded bucket.
+    pub fn quantile(&self, q: f64, min_count: u64) -> Option<Duration> {
+        let metrics = self.0.lock();
+
+        // All histograms share the same bounds, so their buckets may be summed.
+        let mut buckets = Vec::<(Bucket, u64)>::new();
+        for status in metrics.by_status.values() {
+            for (i, (bucket, count)) in (&status.latency).into_iter().enumerate() {
+                match buckets.get_mut(i) {
+                    Some((_, total)) => *total += u64::from(count),
+                    None => buckets.push((*bucket, count.into())),
+                }
+            }
+        }
+
+        let total = buckets.iter().map(|(_, c)| c).sum::<u64>();
+        if total == 0 || total < min_count {
+            return None;
+        }
+
+        let rank = (q.clamp(0.0, 1.0) * total as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, count) in buckets {
+            seen += count;
+            if seen >= rank.max(1) {
+                return match bucket {
+                    Bucket::Le(ms) => Some(Duration::from_millis(ms as u64)),
+                    Bucket::Inf => None,
+                };
+            }
+        }
+        None
+    }
+}
+
+impl<C: Hash + Eq> Clone for Latencies<C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
 // === impl Metrics ===
 
 impl<C: Hash + Eq> Default for Metrics<C> {
@@ -107,6 +168,38 @@ where
 
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn latency_quantile() {
+        use super::*;
+
+        let requests = Requests::<usize, ()>::default();
+        let latencies = requests.latencies(0);
+        assert_eq!(latencies.quantile(0.5, 0), None);
+
+        {
+            let mut metrics = latencies.0.lock();
+            let ok = metrics
+                .by_status
+                .entry(Some(http::StatusCode::OK))
+                .or_default();
+            for _ in 0..8 {
+                ok.latency.add(Duration::from_millis(3));
+            }
+            let err = metrics
+                .by_status
+                .entry(Some(http::StatusCode::INTERNAL_SERVER_ERROR))
+                .or_default();
+            err.latency.add(Duration::from_millis(45));
+            err.latency.add(Duration::from_secs(100));
+        }
+
+        assert_eq!(latencies.quantile(0.5, 0), Some(Duration::from_millis(3)));
+        assert_eq!(latencies.quantile(0.8, 0), Some(Duration::from_millis(3)));
+        assert_eq!(latencies.quantile(0.9, 0), Some(Duration::from_millis(50)));
+        assert_eq!(latencies.quantile(0.99, 0), None);
+        assert_eq!(latencies.quantile(0.5, 11), None);
+    }
+
     #[test]
     fn expiry() {
         use linkerd_metrics::FmtLabels;
@@ -18,10 +18,13 @@ pub struct Metrics {
     last_update: Instant,
     retryable: Counter,
     no_budget: Counter,
+    body_too_large: Counter,
 }
 
 struct NoBudgetLabel;
 
+struct BodyTooLargeLabel;
+
 // === impl Retries ===
 
 impl<T: Hash + Eq> Default for Retries<T> {
@@ -58,6 +61,16 @@ impl Handle {
             m.no_budget.incr();
         }
     }
+
+    /// Records a failed response whose request could not be retried because
+    /// its body exceeded the retry buffer.
+    ///
+    /// Such responses are not counted as retryable.
+    pub fn incr_body_too_large(&self) {
+        let mut m = self.0.lock();
+        m.last_update = Instant::now();
+        m.body_too_large.incr();
+    }
 }
 
 // === impl Metrics ===
@@ -68,6 +81,7 @@ impl Default for Metrics {
             last_update: Instant::now(),
             retryable: Counter::default(),
             no_budget: Counter::default(),
+            body_too_large: Counter::default(),
         }
     }
 }
@@ -115,6 +129,8 @@ where
             m.retryable.fmt_metric_labeled(f, &metric.name, tgt)?;
             m.no_budget
                 .fmt_metric_labeled(f, &metric.name, (tgt, NoBudgetLabel))?;
+            m.body_too_large
+                .fmt_metric_labeled(f, &metric.name, (tgt, BodyTooLargeLabel))?;
         }
 
         registry.retain_since(Instant::now() - self.retain_idle);
@@ -128,3 +144,28 @@ impl FmtLabels for NoBudgetLabel {
         write!(f, "skipped=\"no_budget\"")
     }
 }
+
+impl FmtLabels for BodyTooLargeLabel {
+    fn fmt_labels(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "skipped=\"body_too_large\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_too_large_is_not_retryable() {
+        let retries = Retries::<usize>::default();
+        let handle = retries.get_handle(0);
+        handle.incr_retryable(true);
+        handle.incr_retryable(false);
+        handle.incr_body_too_large();
+
+        let m = handle.0.lock();
+        assert_eq!(m.retryable.value(), 2.0);
+        assert_eq!(m.no_budget.value(), 1.0);
+        assert_eq!(m.body_too_large.value(), 1.0);
+    }
+}
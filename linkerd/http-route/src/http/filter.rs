@@ -1,15 +1,79 @@
+pub mod inject_delay;
 pub mod inject_failure;
+pub mod mirror;
 pub mod modify_header;
 pub mod redirect;
+pub mod rewrite_url;
 
 pub use self::{
+    inject_delay::{Delay, InjectDelay},
     inject_failure::{Distribution, FailureResponse, InjectFailure},
+    mirror::RequestMirror,
     modify_header::ModifyHeader,
     redirect::{InvalidRedirect, RedirectRequest, Redirection},
+    rewrite_url::{InvalidRewrite, InvalidRewriteHost, RewriteHost, RewriteUrl},
 };
+use crate::http::RouteMatch;
+use http::uri::{InvalidUri, PathAndQuery, Uri};
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum ModifyPath {
     ReplaceFullPath(String),
     ReplacePrefixMatch(String),
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidModifyPath {
+    #[error("paths may only replace the path prefix when a path prefix match applied")]
+    ReplacePrefix,
+
+    #[error("path modification produced an invalid path: {0}")]
+    Path(#[from] InvalidUri),
+}
+
+// === impl ModifyPath ===
+
+impl ModifyPath {
+    /// Returns the original URI's path and query, modified as configured.
+    //
+    // XXX This function probably does more allocation that is strictly needed.
+    // We may want to optimize it as it settles.
+    pub fn apply(
+        &self,
+        orig_uri: &Uri,
+        rm: &RouteMatch,
+    ) -> Result<PathAndQuery, InvalidModifyPath> {
+        use crate::http::r#match::PathMatch;
+
+        match self {
+            // If a full path is specified (potentially including a query), use
+            // it.
+            Self::ReplaceFullPath(p) => p.clone().try_into().map_err(Into::into),
+
+            // If a prefix rewrite is specified, use the original query
+            // parameters.
+            //
+            // XXX #fragments are not included in the rewritten path; but
+            // fragments are generally not transmitted to servers.
+            Self::ReplacePrefixMatch(new_pfx) => match rm.route.path() {
+                PathMatch::Prefix(pfx_len) if *pfx_len <= orig_uri.path().len() => {
+                    let mut new_path = new_pfx.to_string();
+                    let (_, rest) = orig_uri.path().split_at(*pfx_len);
+                    if !rest.is_empty() && !rest.starts_with('/') {
+                        new_path.push('/');
+                    }
+                    new_path.push_str(rest);
+                    if let Some(q) = orig_uri.query() {
+                        new_path.push('?');
+                        new_path.push_str(q);
+                    }
+                    new_path.try_into().map_err(Into::into)
+                }
+
+                // If the matched rule was not a prefix match, the filter is
+                // invalid. This should cause us to fail requests with a 5XX.
+                _ => Err(InvalidModifyPath::ReplacePrefix),
+            },
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::Distribution;
+use rand::Rng;
+use std::time::Duration;
+
+/// A filter that delays requests at a predictable rate.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct InjectDelay {
+    pub delay: Delay,
+    pub distribution: Distribution,
+}
+
+/// The amount of time that a request is delayed.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Delay {
+    /// Every delayed request is delayed by the same duration.
+    Fixed(Duration),
+
+    /// Each delayed request is delayed by a duration selected uniformly from
+    /// `min..=max`.
+    Uniform { min: Duration, max: Duration },
+}
+
+// === impl InjectDelay ===
+
+impl InjectDelay {
+    /// Returns the duration for which the current request should be delayed,
+    /// if at all.
+    pub fn apply(&self) -> Option<Duration> {
+        use rand::distributions::Distribution;
+
+        let mut rng = rand::thread_rng();
+        if self.distribution.sample(&mut rng) {
+            return Some(self.delay.sample(&mut rng));
+        }
+
+        None
+    }
+}
+
+// === impl Delay ===
+
+impl Delay {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Duration {
+        match *self {
+            Self::Fixed(delay) => delay,
+            Self::Uniform { min, max } if min < max => rng.gen_range(min..=max),
+            Self::Uniform { min, .. } => min,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed() {
+        let delay = InjectDelay {
+            delay: Delay::Fixed(Duration::from_millis(100)),
+            distribution: Distribution::default(),
+        };
+        assert_eq!(delay.apply(), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn uniform() {
+        let (min, max) = (Duration::from_millis(100), Duration::from_millis(200));
+        let delay = InjectDelay {
+            delay: Delay::Uniform { min, max },
+            distribution: Distribution::default(),
+        };
+        for _ in 0..100 {
+            let d = delay.apply().expect("request must be delayed");
+            assert!((min..=max).contains(&d), "unexpected delay: {:?}", d);
+        }
+
+        // An empty range always delays by the minimum.
+        let delay = InjectDelay {
+            delay: Delay::Uniform { min: max, max: min },
+            distribution: Distribution::default(),
+        };
+        assert_eq!(delay.apply(), Some(max));
+    }
+
+    #[test]
+    fn never() {
+        let delay = InjectDelay {
+            delay: Delay::Fixed(Duration::from_millis(100)),
+            distribution: Distribution::from_ratio(0, 1).unwrap(),
+        };
+        assert_eq!(delay.apply(), None);
+    }
+}
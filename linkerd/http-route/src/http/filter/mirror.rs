@@ -0,0 +1,21 @@
+use super::Distribution;
+
+/// A filter that sends a copy of a portion of requests to another authority.
+///
+/// Responses to mirrored requests are discarded.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct RequestMirror {
+    pub authority: http::uri::Authority,
+    pub distribution: Distribution,
+}
+
+// === impl RequestMirror ===
+
+impl RequestMirror {
+    /// Returns true if the current request should be mirrored.
+    pub fn apply(&self) -> bool {
+        use rand::distributions::Distribution;
+
+        self.distribution.sample(&mut rand::thread_rng())
+    }
+}
@@ -1,4 +1,4 @@
-use super::ModifyPath;
+use super::{InvalidModifyPath, ModifyPath};
 use crate::http::RouteMatch;
 use http::{
     uri::{Authority, InvalidUri, PathAndQuery, Scheme, Uri},
@@ -144,15 +144,11 @@ impl RedirectRequest {
         Some(port)
     }
 
-    // XXX This function probably does more allocation that is strictly needed.
-    // We may want to optimize it as it settles.
     fn path_and_query(
         &self,
         orig_uri: &http::Uri,
         rm: &RouteMatch,
     ) -> Result<PathAndQuery, InvalidRedirect> {
-        use crate::http::r#match::PathMatch;
-
         match &self.path {
             // If the redirect does not specify a path, use the original path/query.
             None => Ok(orig_uri
@@ -160,35 +156,16 @@ impl RedirectRequest {
                 .expect("URI must have a path")
                 .clone()),
 
-            // If the redirect specifies a full path (potentially including a
-            // query), use it.
-            Some(ModifyPath::ReplaceFullPath(p)) => p.clone().try_into().map_err(Into::into),
-
-            // If the redirect specifies a prefix rewrite, using the original
-            // query parameters.
-            //
-            // XXX #fragments are not included in the rewritten location; but
-            // fragments are generally not transmitted to servers.
-            Some(ModifyPath::ReplacePrefixMatch(new_pfx)) => match rm.route.path() {
-                PathMatch::Prefix(pfx_len) if *pfx_len <= orig_uri.path().len() => {
-                    let mut new_path = new_pfx.to_string();
-                    let (_, rest) = orig_uri.path().split_at(*pfx_len);
-                    if !rest.is_empty() && !rest.starts_with('/') {
-                        new_path.push('/');
-                    }
-                    new_path.push_str(rest);
-                    if let Some(q) = orig_uri.query() {
-                        new_path.push('?');
-                        new_path.push_str(q);
-                    }
-                    new_path.try_into().map_err(Into::into)
-                }
+            Some(path) => path.apply(orig_uri, rm).map_err(Into::into),
+        }
+    }
+}
 
-                // If the matched rule was not a prefix match, the redirect
-                // filter is invalid. This should cause us to fail requests with
-                // a 5XX.
-                _ => Err(InvalidRedirect::ReplacePrefix),
-            },
+impl From<InvalidModifyPath> for InvalidRedirect {
+    fn from(err: InvalidModifyPath) -> Self {
+        match err {
+            InvalidModifyPath::ReplacePrefix => Self::ReplacePrefix,
+            InvalidModifyPath::Path(e) => Self::Authority(e),
         }
     }
 }
@@ -0,0 +1,189 @@
+use super::{InvalidModifyPath, ModifyPath};
+use crate::http::RouteMatch;
+use http::uri::{Authority, InvalidUri, Scheme, Uri};
+use std::{fmt, str::FromStr};
+
+/// A filter that rewrites a request's URL before it is forwarded.
+///
+/// Unlike `RedirectRequest`, the client is not informed of the new location.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct RewriteUrl {
+    /// Replaces the request's hostname, retaining its port.
+    pub host: Option<RewriteHost>,
+    pub path: Option<ModifyPath>,
+}
+
+/// A hostname that replaces a request's hostname.
+///
+/// Ports are rejected, since the request's port is retained.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct RewriteHost(Authority);
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidRewrite {
+    #[error("{0}")]
+    Path(#[from] InvalidModifyPath),
+
+    #[error("rewrite produced an invalid authority: {0}")]
+    Authority(#[from] InvalidUri),
+
+    #[error("rewrite produced an invalid URI: {0}")]
+    Uri(#[from] http::uri::InvalidUriParts),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidRewriteHost {
+    #[error("invalid hostname: {0}")]
+    Authority(#[from] InvalidUri),
+
+    #[error("hostname must not include a port: {0}")]
+    Port(Authority),
+
+    #[error("hostname must not include userinfo")]
+    UserInfo,
+}
+
+// === impl RewriteUrl ===
+
+impl RewriteUrl {
+    /// Returns the rewritten URI, if it differs from the original URI.
+    pub fn apply(&self, orig_uri: &Uri, rm: &RouteMatch) -> Result<Option<Uri>, InvalidRewrite> {
+        let mut parts = orig_uri.clone().into_parts();
+
+        if let Some(RewriteHost(host)) = &self.host {
+            let authority = match orig_uri.port_u16() {
+                Some(port) => format!("{}:{}", host, port).try_into()?,
+                None => host.clone(),
+            };
+            parts.authority = Some(authority);
+            // An origin-form URI cannot hold an authority, so the rewritten
+            // URI is made absolute.
+            if parts.scheme.is_none() {
+                parts.scheme = Some(Scheme::HTTP);
+            }
+        }
+
+        if let Some(path) = &self.path {
+            parts.path_and_query = Some(path.apply(orig_uri, rm)?);
+        }
+
+        let uri = Uri::from_parts(parts)?;
+        if &uri == orig_uri {
+            return Ok(None);
+        }
+        Ok(Some(uri))
+    }
+}
+
+// === impl RewriteHost ===
+
+impl FromStr for RewriteHost {
+    type Err = InvalidRewriteHost;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let authority = s.parse::<Authority>()?;
+        if authority.port().is_some() {
+            return Err(InvalidRewriteHost::Port(authority));
+        }
+        if authority.as_str().contains('@') {
+            return Err(InvalidRewriteHost::UserInfo);
+        }
+        Ok(Self(authority))
+    }
+}
+
+impl fmt::Display for RewriteHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{find, r#match::MatchPath, MatchRequest, Route, Rule};
+
+    fn rewrite(rw: RewriteUrl, uri: &str) -> Result<Option<Uri>, InvalidRewrite> {
+        let rts = vec![Route {
+            hosts: vec![],
+            rules: vec![Rule {
+                matches: vec![MatchRequest {
+                    path: Some(MatchPath::Prefix("/foo".to_string())),
+                    ..MatchRequest::default()
+                }],
+                policy: rw,
+            }],
+        }];
+        let req = http::Request::builder()
+            .uri(uri)
+            .body(())
+            .expect("request must be valid");
+        let (rm, rw) = find(&rts, &req).expect("request must match");
+        rw.apply(req.uri(), &rm)
+    }
+
+    #[test]
+    fn rewrites_prefix() {
+        let rw = RewriteUrl {
+            path: Some(ModifyPath::ReplacePrefixMatch("/api/foo".to_string())),
+            ..RewriteUrl::default()
+        };
+        assert_eq!(
+            rewrite(rw, "http://example.com/foo/bar?baz=qux").unwrap(),
+            Some("http://example.com/api/foo/bar?baz=qux".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn rewrites_full_path() {
+        let rw = RewriteUrl {
+            path: Some(ModifyPath::ReplaceFullPath("/bar".to_string())),
+            ..RewriteUrl::default()
+        };
+        assert_eq!(
+            rewrite(rw, "http://example.com/foo/bar").unwrap(),
+            Some("http://example.com/bar".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn rewrites_host() {
+        let rw = RewriteUrl {
+            host: Some("example.org".parse().unwrap()),
+            ..RewriteUrl::default()
+        };
+        assert_eq!(
+            rewrite(rw.clone(), "http://example.com:8080/foo").unwrap(),
+            Some("http://example.org:8080/foo".parse().unwrap())
+        );
+        assert_eq!(
+            rewrite(rw.clone(), "http://example.com/foo").unwrap(),
+            Some("http://example.org/foo".parse().unwrap())
+        );
+        assert_eq!(rewrite(rw, "http://example.org/foo").unwrap(), None);
+    }
+
+    #[test]
+    fn rewrites_host_of_origin_form_uri() {
+        let rw = RewriteUrl {
+            host: Some("example.org".parse().unwrap()),
+            ..RewriteUrl::default()
+        };
+        assert_eq!(
+            rewrite(rw, "/foo").unwrap(),
+            Some("http://example.org/foo".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_host_with_port() {
+        assert!(matches!(
+            "example.org:8080".parse::<RewriteHost>(),
+            Err(InvalidRewriteHost::Port(_))
+        ));
+        assert!(matches!(
+            "user@example.org".parse::<RewriteHost>(),
+            Err(InvalidRewriteHost::UserInfo)
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+use crate::Name;
+use linkerd_dns_name::InvalidName;
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// A peer's identity, as presented in its certificate.
+///
+/// Mesh identities are DNS-like names, while workloads that are issued
+/// certificates by a SPIFFE implementation (e.g. SPIRE) are identified by a
+/// URI SAN. When a certificate includes both and the SPIFFE ID is in a trust
+/// domain that the proxy is configured to accept, its URI SAN identifies the
+/// peer and its DNS SANs are ignored.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub enum Id {
+    Dns(Name),
+    Spiffe(SpiffeId),
+}
+
+/// A SPIFFE ID of the form `spiffe://<trust-domain>/<path>`.
+///
+/// See <https://github.com/spiffe/spiffe/blob/main/standards/SPIFFE-ID.md>.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct SpiffeId {
+    id: Arc<str>,
+    path_start: usize,
+}
+
+const SPIFFE_SCHEME: &str = "spiffe://";
+
+// === impl Id ===
+
+impl Id {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Dns(name) => name.as_str(),
+            Self::Spiffe(id) => id.as_str(),
+        }
+    }
+
+    /// Returns the identity's DNS name, if it has one.
+    pub fn dns_name(&self) -> Option<&Name> {
+        match self {
+            Self::Dns(name) => Some(name),
+            Self::Spiffe(_) => None,
+        }
+    }
+}
+
+impl From<Name> for Id {
+    fn from(name: Name) -> Self {
+        Self::Dns(name)
+    }
+}
+
+impl From<SpiffeId> for Id {
+    fn from(id: SpiffeId) -> Self {
+        Self::Spiffe(id)
+    }
+}
+
+impl FromStr for Id {
+    type Err = InvalidName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with(SPIFFE_SCHEME) {
+            return s.parse().map(Self::Spiffe);
+        }
+        s.parse().map(Self::Dns)
+    }
+}
+
+impl fmt::Debug for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns(name) => fmt::Debug::fmt(name, f),
+            Self::Spiffe(id) => fmt::Debug::fmt(id, f),
+        }
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// === impl SpiffeId ===
+
+impl SpiffeId {
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the ID's trust domain, e.g. `example.org`.
+    pub fn trust_domain(&self) -> &str {
+        &self.id[SPIFFE_SCHEME.len()..self.path_start]
+    }
+
+    /// Returns the ID's path, e.g. `/ns/default/sa/web`, which may be empty.
+    pub fn path(&self) -> &str {
+        &self.id[self.path_start..]
+    }
+
+    /// Returns true if this ID is in the given trust domain and its path is
+    /// `prefix` or is nested under it.
+    ///
+    /// Prefixes are matched on path-segment boundaries, so that `/ns/web`
+    /// matches `/ns/web/sa/foo` but not `/ns/webhooks`.
+    pub fn has_path_prefix(&self, trust_domain: &str, prefix: &str) -> bool {
+        if self.trust_domain() != trust_domain {
+            return false;
+        }
+        let prefix = prefix.trim_end_matches('/');
+        match self.path().strip_prefix(prefix) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+impl FromStr for SpiffeId {
+    type Err = InvalidName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s.strip_prefix(SPIFFE_SCHEME).ok_or(InvalidName)?;
+        let (trust_domain, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+
+        // Trust domains are limited to lowercase letters, digits, dots,
+        // dashes, and underscores (so they may not include a port or
+        // userinfo).
+        if trust_domain.is_empty()
+            || !trust_domain.bytes().all(|b| {
+                b.is_ascii_lowercase() || b.is_ascii_digit() || matches!(b, b'.' | b'-' | b'_')
+            })
+        {
+            return Err(InvalidName);
+        }
+
+        // Paths are a sequence of non-empty segments, each of which is limited
+        // to letters, digits, dots, dashes, and underscores. Relative segments
+        // are not permitted.
+        if !path.is_empty() {
+            for segment in path[1..].split('/') {
+                if segment.is_empty()
+                    || segment == "."
+                    || segment == ".."
+                    || !segment
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_'))
+                {
+                    return Err(InvalidName);
+                }
+            }
+        }
+
+        Ok(Self {
+            id: s.into(),
+            path_start: SPIFFE_SCHEME.len() + trust_domain.len(),
+        })
+    }
+}
+
+impl fmt::Debug for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.id, f)
+    }
+}
+
+impl fmt::Display for SpiffeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ids() {
+        let id = "web.ns.serviceaccount.identity.linkerd.cluster.local"
+            .parse::<Id>()
+            .unwrap();
+        assert!(id.dns_name().is_some());
+        assert_eq!(
+            id.as_str(),
+            "web.ns.serviceaccount.identity.linkerd.cluster.local"
+        );
+
+        let id = "spiffe://example.org/ns/default/sa/web"
+            .parse::<Id>()
+            .unwrap();
+        assert!(id.dns_name().is_none());
+        assert_eq!(id.as_str(), "spiffe://example.org/ns/default/sa/web");
+        match id {
+            Id::Spiffe(id) => {
+                assert_eq!(id.trust_domain(), "example.org");
+                assert_eq!(id.path(), "/ns/default/sa/web");
+            }
+            Id::Dns(_) => panic!("expected a SPIFFE ID"),
+        }
+
+        let id = "spiffe://example.org".parse::<SpiffeId>().unwrap();
+        assert_eq!(id.trust_domain(), "example.org");
+        assert_eq!(id.path(), "");
+    }
+
+    #[test]
+    fn rejects_invalid_spiffe_ids() {
+        for id in &[
+            "spiffe://",
+            "spiffe:///path",
+            "spiffe://Example.org/path",
+            "spiffe://example.org:8080/path",
+            "spiffe://user@example.org/path",
+            "spiffe://example.org/",
+            "spiffe://example.org/path/",
+            "spiffe://example.org//path",
+            "spiffe://example.org/../path",
+            "spiffe://example.org/path?query",
+            "spiffe://example.org/path#fragment",
+            "https://example.org/path",
+        ] {
+            assert!(id.parse::<SpiffeId>().is_err(), "{} must be invalid", id);
+        }
+    }
+
+    #[test]
+    fn matches_path_prefix() {
+        let id = "spiffe://example.org/ns/web/sa/foo"
+            .parse::<SpiffeId>()
+            .unwrap();
+        assert!(id.has_path_prefix("example.org", ""));
+        assert!(id.has_path_prefix("example.org", "/"));
+        assert!(id.has_path_prefix("example.org", "/ns/web"));
+        assert!(id.has_path_prefix("example.org", "/ns/web/"));
+        assert!(id.has_path_prefix("example.org", "/ns/web/sa/foo"));
+        assert!(!id.has_path_prefix("example.org", "/ns/we"));
+        assert!(!id.has_path_prefix("example.org", "/ns/web/sa/foo/bar"));
+        assert!(!id.has_path_prefix("example.com", "/ns/web"));
+    }
+}
@@ -45,6 +45,16 @@ impl Peek for tokio::net::TcpStream {
     }
 }
 
+// Unix domain sockets are only used for local, plaintext connections, so they
+// are not peeked.
+#[cfg(unix)]
+#[async_trait::async_trait]
+impl Peek for tokio::net::UnixStream {
+    async fn peek(&self, _: &mut [u8]) -> Result<usize> {
+        Ok(0)
+    }
+}
+
 #[async_trait::async_trait]
 impl Peek for tokio::io::DuplexStream {
     async fn peek(&self, _: &mut [u8]) -> Result<usize> {
@@ -71,6 +81,15 @@ impl PeerAddr for tokio_test::io::Mock {
     }
 }
 
+// Unix domain socket peers don't have an IP address, but they are necessarily
+// local to the proxy.
+#[cfg(unix)]
+impl PeerAddr for tokio::net::UnixStream {
+    fn peer_addr(&self) -> Result<SocketAddr> {
+        Ok((std::net::Ipv4Addr::LOCALHOST, 0).into())
+    }
+}
+
 impl PeerAddr for tokio::io::DuplexStream {
     fn peer_addr(&self) -> Result<SocketAddr> {
         Ok(([0, 0, 0, 0], 0).into())
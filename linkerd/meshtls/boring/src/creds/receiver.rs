@@ -1,16 +1,35 @@
 use super::CredsRx;
 use crate::{NewClient, Server};
 use linkerd_identity::Name;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Receiver {
     name: Name,
     rx: CredsRx,
+    spiffe_trust_domains: Arc<[String]>,
 }
 
 impl Receiver {
     pub(crate) fn new(name: Name, rx: CredsRx) -> Self {
-        Self { name, rx }
+        Self {
+            name,
+            rx,
+            spiffe_trust_domains: Arc::new([]),
+        }
+    }
+
+    /// Configures the trust domains in which clients may be identified by a
+    /// SPIFFE ID (i.e. a URI SAN).
+    ///
+    /// Clients that present SPIFFE IDs in other trust domains are identified
+    /// by their DNS SANs.
+    pub fn with_spiffe_trust_domains(
+        mut self,
+        trust_domains: impl IntoIterator<Item = String>,
+    ) -> Self {
+        self.spiffe_trust_domains = trust_domains.into_iter().collect();
+        self
     }
 
     /// Returns the local identity.
@@ -25,7 +44,11 @@ impl Receiver {
 
     /// Returns a `Server` that can be used to terminate TLS on server connections.
     pub fn server(&self) -> Server {
-        Server::new(self.name.clone(), self.rx.clone())
+        Server::new(
+            self.name.clone(),
+            self.rx.clone(),
+            self.spiffe_trust_domains.clone(),
+        )
     }
 }
 
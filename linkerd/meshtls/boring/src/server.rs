@@ -1,5 +1,5 @@
 use crate::creds::CredsRx;
-use linkerd_identity::Name;
+use linkerd_identity::{Name, SpiffeId};
 use linkerd_io as io;
 use linkerd_stack::{Param, Service};
 use linkerd_tls::{ClientId, LocalId, NegotiatedProtocol, ServerTls};
@@ -11,6 +11,7 @@ pub struct Server {
     name: Name,
     rx: CredsRx,
     alpn: Option<Arc<[Vec<u8>]>>,
+    spiffe_trust_domains: Arc<[String]>,
 }
 
 pub type TerminateFuture<I> =
@@ -22,11 +23,12 @@ pub struct ServerIo<I>(tokio_boring::SslStream<I>);
 // === impl Server ===
 
 impl Server {
-    pub(crate) fn new(name: Name, rx: CredsRx) -> Self {
+    pub(crate) fn new(name: Name, rx: CredsRx, spiffe_trust_domains: Arc<[String]>) -> Self {
         Self {
             name,
             rx,
             alpn: None,
+            spiffe_trust_domains,
         }
     }
 
@@ -66,6 +68,7 @@ where
             .rx
             .borrow()
             .acceptor(self.alpn.as_deref().unwrap_or(&[]));
+        let spiffe_trust_domains = self.spiffe_trust_domains.clone();
         Box::pin(async move {
             let acc = acceptor.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
             let io = tokio_boring::accept(&acc, io)
@@ -78,7 +81,7 @@ where
                     None => io::Error::new(io::ErrorKind::Other, "unexpected TLS handshake error"),
                 })?;
 
-            let client_id = io.client_identity();
+            let client_id = io.client_identity(&spiffe_trust_domains);
             let negotiated_protocol = io.negotiated_protocol();
 
             debug!(
@@ -109,7 +112,9 @@ impl<I> ServerIo<I> {
             .map(|p| NegotiatedProtocol(p.to_vec()))
     }
 
-    fn client_identity(&self) -> Option<ClientId> {
+    /// Identifies the client by a SPIFFE ID in one of the given trust domains,
+    /// falling back to its first DNS SAN.
+    fn client_identity(&self, spiffe_trust_domains: &[String]) -> Option<ClientId> {
         let cert = self.0.ssl().peer_certificate().or_else(|| {
             debug!("Connection missing peer certificate");
             None
@@ -118,8 +123,24 @@ impl<I> ServerIo<I> {
             debug!("Peer certificate missing SANs");
             None
         })?;
-        sans.into_iter()
-            .filter_map(|san| san.dnsname()?.parse().ok())
+
+        // Workloads with SPIFFE identities are identified by a URI SAN, so it
+        // takes precedence over any DNS SANs.
+        let spiffe_id = sans
+            .iter()
+            .filter_map(|san| san.uri()?.parse::<SpiffeId>().ok())
+            .find(|id| {
+                spiffe_trust_domains
+                    .iter()
+                    .any(|td| td == id.trust_domain())
+            });
+        if let Some(id) = spiffe_id {
+            return Some(ClientId(id.into()));
+        }
+
+        sans.iter()
+            .filter_map(|san| san.dnsname()?.parse::<Name>().ok())
+            .map(ClientId::from)
             .next()
             .or_else(|| {
                 debug!("Peer certificate missing DNS SANs");
@@ -10,6 +10,7 @@ pub struct Receiver {
     name: Name,
     client_rx: watch::Receiver<Arc<rustls::ClientConfig>>,
     server_rx: watch::Receiver<Arc<rustls::ServerConfig>>,
+    spiffe_trust_domains: Arc<[String]>,
 }
 
 // === impl Receiver ===
@@ -24,9 +25,23 @@ impl Receiver {
             name,
             client_rx,
             server_rx,
+            spiffe_trust_domains: Arc::new([]),
         }
     }
 
+    /// Configures the trust domains in which clients may be identified by a
+    /// SPIFFE ID (i.e. a URI SAN).
+    ///
+    /// Clients that present SPIFFE IDs in other trust domains are identified
+    /// by their DNS SANs.
+    pub fn with_spiffe_trust_domains(
+        mut self,
+        trust_domains: impl IntoIterator<Item = String>,
+    ) -> Self {
+        self.spiffe_trust_domains = trust_domains.into_iter().collect();
+        self
+    }
+
     /// Returns the local identity.
     pub fn name(&self) -> &Name {
         &self.name
@@ -39,7 +54,11 @@ impl Receiver {
 
     /// Returns a `Server` that can be used to terminate TLS on server connections.
     pub fn server(&self) -> Server {
-        Server::new(self.name.clone(), self.server_rx.clone())
+        Server::new(
+            self.name.clone(),
+            self.server_rx.clone(),
+            self.spiffe_trust_domains.clone(),
+        )
     }
 }
 
@@ -84,11 +103,7 @@ mod tests {
         let init_config = Arc::new(empty_server_config());
         let (server_tx, server_rx) = watch::channel(init_config.clone());
         let (_, client_rx) = watch::channel(Arc::new(empty_client_config()));
-        let receiver = Receiver {
-            name: "example".parse().unwrap(),
-            server_rx,
-            client_rx,
-        };
+        let receiver = Receiver::new("example".parse().unwrap(), client_rx, server_rx);
 
         let server = receiver.server();
 
@@ -108,11 +123,7 @@ mod tests {
         let init_config = Arc::new(empty_server_config());
         let (server_tx, server_rx) = watch::channel(init_config.clone());
         let (_, client_rx) = watch::channel(Arc::new(empty_client_config()));
-        let receiver = Receiver {
-            name: "example".parse().unwrap(),
-            server_rx,
-            client_rx,
-        };
+        let receiver = Receiver::new("example".parse().unwrap(), client_rx, server_rx);
 
         let server = receiver
             .server()
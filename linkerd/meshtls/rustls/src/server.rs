@@ -1,9 +1,14 @@
-use futures::prelude::*;
-use linkerd_identity::{LocalId, Name};
+use linkerd_identity::{LocalId, Name, SpiffeId};
 use linkerd_io as io;
 use linkerd_stack::{Param, Service};
 use linkerd_tls::{ClientId, NegotiatedProtocol, NegotiatedProtocolRef, ServerTls};
-use std::{convert::TryFrom, pin::Pin, sync::Arc, task::Context};
+use std::{
+    convert::TryFrom,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
 use thiserror::Error;
 use tokio::sync::watch;
 use tokio_rustls::rustls::{Certificate, ServerConfig};
@@ -14,12 +19,14 @@ use tracing::debug;
 pub struct Server {
     name: Name,
     rx: watch::Receiver<Arc<ServerConfig>>,
+    spiffe_trust_domains: Arc<[String]>,
 }
 
-pub type TerminateFuture<I> = futures::future::MapOk<
-    tokio_rustls::Accept<I>,
-    fn(tokio_rustls::server::TlsStream<I>) -> (ServerTls, ServerIo<I>),
->;
+/// Completes a TLS handshake and identifies the client.
+pub struct TerminateFuture<I> {
+    accept: tokio_rustls::Accept<I>,
+    spiffe_trust_domains: Arc<[String]>,
+}
 
 #[derive(Debug)]
 pub struct ServerIo<I>(tokio_rustls::server::TlsStream<I>);
@@ -29,8 +36,16 @@ pub struct ServerIo<I>(tokio_rustls::server::TlsStream<I>);
 pub struct LostStore(());
 
 impl Server {
-    pub(crate) fn new(name: Name, rx: watch::Receiver<Arc<ServerConfig>>) -> Self {
-        Self { name, rx }
+    pub(crate) fn new(
+        name: Name,
+        rx: watch::Receiver<Arc<ServerConfig>>,
+        spiffe_trust_domains: Arc<[String]>,
+    ) -> Self {
+        Self {
+            name,
+            rx,
+            spiffe_trust_domains,
+        }
     }
 
     #[cfg(test)]
@@ -78,7 +93,7 @@ impl Server {
             }
         });
 
-        Ok(Self::new(self.name, rx))
+        Ok(Self::new(self.name, rx, self.spiffe_trust_domains))
     }
 }
 
@@ -103,39 +118,63 @@ where
 
     #[inline]
     fn call(&mut self, io: I) -> Self::Future {
-        tokio_rustls::TlsAcceptor::from((*self.rx.borrow()).clone())
-            .accept(io)
-            .map_ok(|io| {
-                // Determine the peer's identity, if it exist.
-                let client_id = client_identity(&io);
-
-                let negotiated_protocol = io
-                    .get_ref()
-                    .1
-                    .alpn_protocol()
-                    .map(|b| NegotiatedProtocol(b.into()));
-
-                debug!(client.id = ?client_id, alpn = ?negotiated_protocol, "Accepted TLS connection");
-                let tls = ServerTls::Established {
-                    client_id,
-                    negotiated_protocol,
-                };
-                (tls, ServerIo(io))
-            })
+        TerminateFuture {
+            accept: tokio_rustls::TlsAcceptor::from((*self.rx.borrow()).clone()).accept(io),
+            spiffe_trust_domains: self.spiffe_trust_domains.clone(),
+        }
     }
 }
 
-fn client_identity<I>(tls: &tokio_rustls::server::TlsStream<I>) -> Option<ClientId> {
+// === impl TerminateFuture ===
+
+impl<I> Future for TerminateFuture<I>
+where
+    I: io::AsyncRead + io::AsyncWrite + Unpin,
+{
+    type Output = io::Result<(ServerTls, ServerIo<I>)>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let io = futures::ready!(Pin::new(&mut self.accept).poll(cx))?;
+
+        // Determine the peer's identity, if it exist.
+        let client_id = client_identity(&io, &self.spiffe_trust_domains);
+
+        let negotiated_protocol = io
+            .get_ref()
+            .1
+            .alpn_protocol()
+            .map(|b| NegotiatedProtocol(b.into()));
+
+        debug!(client.id = ?client_id, alpn = ?negotiated_protocol, "Accepted TLS connection");
+        let tls = ServerTls::Established {
+            client_id,
+            negotiated_protocol,
+        };
+        Poll::Ready(Ok((tls, ServerIo(io))))
+    }
+}
+
+fn client_identity<I>(
+    tls: &tokio_rustls::server::TlsStream<I>,
+    spiffe_trust_domains: &[String],
+) -> Option<ClientId> {
     let (_io, session) = tls.get_ref();
     let certs = session.peer_certificates()?;
     let c = certs.first().map(Certificate::as_ref)?;
+
+    // URI SANs are only read when SPIFFE trust domains are configured, so
+    // that certificates are otherwise only parsed by webpki.
+    if !spiffe_trust_domains.is_empty() {
+        return x509_client_identity(c, spiffe_trust_domains);
+    }
+
     let end_cert = webpki::EndEntityCert::try_from(c).ok()?;
     let dns_names = end_cert.dns_names().ok()?;
 
     match dns_names.first()? {
         webpki::GeneralDnsNameRef::DnsName(n) => {
             let s: &str = (*n).into();
-            s.parse().ok().map(ClientId)
+            s.parse::<Name>().ok().map(ClientId::from)
         }
         webpki::GeneralDnsNameRef::Wildcard(_) => {
             // Wildcards can perhaps be handled in a future path...
@@ -144,6 +183,39 @@ fn client_identity<I>(tls: &tokio_rustls::server::TlsStream<I>) -> Option<Client
     }
 }
 
+/// Identifies a client by a SPIFFE ID in one of the given trust domains,
+/// falling back to its first DNS SAN.
+///
+/// webpki only exposes a certificate's DNS names, so the certificate is parsed
+/// by x509-parser instead.
+fn x509_client_identity(cert: &[u8], spiffe_trust_domains: &[String]) -> Option<ClientId> {
+    use x509_parser::extensions::GeneralName;
+
+    let (_, cert) = x509_parser::parse_x509_certificate(cert).ok()?;
+    let sans = cert.subject_alternative_name().ok()??;
+    let sans = &sans.value.general_names;
+
+    // Workloads with SPIFFE identities are identified by a URI SAN, so it
+    // takes precedence over any DNS SANs.
+    let spiffe_id = sans.iter().find_map(|san| match san {
+        GeneralName::URI(uri) => uri.parse::<SpiffeId>().ok().filter(|id| {
+            spiffe_trust_domains
+                .iter()
+                .any(|td| td == id.trust_domain())
+        }),
+        _ => None,
+    });
+    if let Some(id) = spiffe_id {
+        return Some(ClientId(id.into()));
+    }
+
+    let dns_name = sans.iter().find_map(|san| match san {
+        GeneralName::DNSName(name) => Some(*name),
+        _ => None,
+    })?;
+    dns_name.parse::<Name>().ok().map(ClientId::from)
+}
+
 // === impl ServerIo ===
 
 impl<I: io::AsyncRead + io::AsyncWrite + Unpin> io::AsyncRead for ServerIo<I> {
@@ -205,3 +277,36 @@ impl<I: io::PeerAddr> io::PeerAddr for ServerIo<I> {
         self.0.get_ref().0.peer_addr()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use linkerd_tls_test_util::{FOO_NS1, FOO_NS1_SPIFFE};
+
+    #[test]
+    fn spiffe_id_in_trust_domain() {
+        let id = x509_client_identity(FOO_NS1_SPIFFE.crt, &["cluster.local".to_string()]);
+        assert_eq!(
+            id.map(|ClientId(id)| id.to_string()),
+            Some("spiffe://cluster.local/ns/ns1/sa/foo".to_string())
+        );
+    }
+
+    #[test]
+    fn spiffe_id_in_other_trust_domain_falls_back_to_dns() {
+        let id = x509_client_identity(FOO_NS1_SPIFFE.crt, &["example.org".to_string()]);
+        assert_eq!(
+            id.map(|ClientId(id)| id.to_string()),
+            Some(FOO_NS1_SPIFFE.name.to_string())
+        );
+    }
+
+    #[test]
+    fn dns_name_without_spiffe_id() {
+        let id = x509_client_identity(FOO_NS1.crt, &["cluster.local".to_string()]);
+        assert_eq!(
+            id.map(|ClientId(id)| id.to_string()),
+            Some(FOO_NS1.name.to_string())
+        );
+    }
+}
@@ -103,6 +103,27 @@ impl Receiver {
         }
     }
 
+    /// Configures the trust domains in which clients may be identified by a
+    /// SPIFFE ID (i.e. a URI SAN) rather than their DNS SANs.
+    pub fn with_spiffe_trust_domains(
+        self,
+        trust_domains: impl IntoIterator<Item = String>,
+    ) -> Self {
+        match self {
+            #[cfg(feature = "boring")]
+            Self::Boring(receiver) => {
+                Self::Boring(receiver.with_spiffe_trust_domains(trust_domains))
+            }
+
+            #[cfg(feature = "rustls")]
+            Self::Rustls(receiver) => {
+                Self::Rustls(receiver.with_spiffe_trust_domains(trust_domains))
+            }
+            #[cfg(not(feature = "__has_any_tls_impls"))]
+            _ => crate::no_tls!(trust_domains),
+        }
+    }
+
     pub fn new_client(&self) -> NewClient {
         match self {
             #[cfg(feature = "boring")]
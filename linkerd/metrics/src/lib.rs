@@ -23,7 +23,7 @@ pub use self::summary::Summary;
 pub use self::{
     counter::Counter,
     gauge::Gauge,
-    histogram::Histogram,
+    histogram::{Bucket, Histogram},
     prom::{FmtLabels, FmtMetric, FmtMetrics, Metric},
     scopes::Scopes,
     serve::Serve,
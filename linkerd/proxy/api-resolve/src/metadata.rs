@@ -22,6 +22,9 @@ pub struct Metadata {
 
     /// Used to override the the authority if needed
     authority_override: Option<Authority>,
+
+    /// The endpoint's weight relative to other endpoints in the same service.
+    weight: u32,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -43,11 +46,15 @@ impl Default for Metadata {
             authority_override: None,
             opaque_transport_port: None,
             protocol_hint: ProtocolHint::Unknown,
+            weight: Self::DEFAULT_WEIGHT,
         }
     }
 }
 
 impl Metadata {
+    /// The weight of endpoints that are not explicitly weighted.
+    pub const DEFAULT_WEIGHT: u32 = 10_000;
+
     pub fn new(
         labels: impl IntoIterator<Item = (String, String)>,
         protocol_hint: ProtocolHint,
@@ -61,9 +68,14 @@ impl Metadata {
             opaque_transport_port,
             identity,
             authority_override,
+            weight: Self::DEFAULT_WEIGHT,
         }
     }
 
+    pub fn with_weight(self, weight: u32) -> Self {
+        Self { weight, ..self }
+    }
+
     /// Returns the endpoint's labels from the destination service, if it has them.
     pub fn labels(&self) -> Labels {
         self.labels.clone()
@@ -85,6 +97,10 @@ impl Metadata {
         self.authority_override.as_ref()
     }
 
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
     pub fn clear_upgrade(&mut self) {
         self.protocol_hint = ProtocolHint::Unknown;
         self.opaque_transport_port = None;
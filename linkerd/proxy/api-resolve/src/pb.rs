@@ -48,7 +48,8 @@ pub fn to_addr_meta(
         opaque_transport_port,
         tls_id,
         authority_override,
-    );
+    )
+    .with_weight(pb.weight);
     Some((addr, meta))
 }
 
@@ -0,0 +1,185 @@
+//! A load metric that counts each endpoint's outstanding requests.
+//!
+//! Unlike PeakEWMA, this metric does not depend on request latency, so it is
+//! not skewed by workloads in which some requests are expected to be slow.
+
+use crate::Weight;
+use futures::{ready, Stream, TryFuture};
+use linkerd_stack::Param;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    load::{Load, TrackCompletion},
+};
+
+/// Wraps a discovery stream's endpoints with [`LeastRequest`] load metrics.
+#[pin_project]
+#[derive(Debug)]
+pub struct LeastRequestDiscover<D, C> {
+    #[pin]
+    discover: D,
+    completion: C,
+}
+
+/// Tracks the number of outstanding requests to an endpoint.
+///
+/// The endpoint's load is its number of outstanding requests divided by its
+/// weight, so that heavier endpoints are assigned proportionally more
+/// requests.
+#[derive(Debug)]
+pub struct LeastRequest<S, C> {
+    inner: S,
+    weight: Weight,
+    pending: Arc<()>,
+    completion: C,
+}
+
+/// Counts a request as outstanding until it is dropped.
+#[derive(Debug)]
+pub struct Handle(Arc<()>);
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F, C> {
+    #[pin]
+    inner: F,
+    handle: Option<Handle>,
+    completion: C,
+}
+
+// === impl LeastRequestDiscover ===
+
+impl<D, C> LeastRequestDiscover<D, C> {
+    pub fn new(discover: D, completion: C) -> Self {
+        Self {
+            discover,
+            completion,
+        }
+    }
+}
+
+impl<D, C> Stream for LeastRequestDiscover<D, C>
+where
+    D: Discover,
+    D::Service: Param<Weight>,
+    C: Clone,
+{
+    type Item = Result<Change<D::Key, LeastRequest<D::Service, C>>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let change = match ready!(this.discover.poll_discover(cx)) {
+            None => return Poll::Ready(None),
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            Some(Ok(Change::Remove(key))) => Change::Remove(key),
+            Some(Ok(Change::Insert(key, inner))) => Change::Insert(
+                key,
+                LeastRequest {
+                    weight: inner.param(),
+                    inner,
+                    pending: Arc::new(()),
+                    completion: this.completion.clone(),
+                },
+            ),
+        };
+        Poll::Ready(Some(Ok(change)))
+    }
+}
+
+// === impl LeastRequest ===
+
+impl<S, C> Load for LeastRequest<S, C> {
+    type Metric = f64;
+
+    fn load(&self) -> Self::Metric {
+        // The service holds one reference; every other reference is held by
+        // an outstanding request.
+        let pending = Arc::strong_count(&self.pending) - 1;
+        pending as f64 / self.weight.as_f64()
+    }
+}
+
+impl<S, C, Req> tower::Service<Req> for LeastRequest<S, C>
+where
+    S: tower::Service<Req>,
+    C: TrackCompletion<Handle, S::Response> + Clone,
+{
+    type Response = C::Output;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, C>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        ResponseFuture {
+            handle: Some(Handle(self.pending.clone())),
+            inner: self.inner.call(req),
+            completion: self.completion.clone(),
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, C> Future for ResponseFuture<F, C>
+where
+    F: TryFuture,
+    C: TrackCompletion<Handle, F::Ok>,
+{
+    type Output = Result<C::Output, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let rsp = ready!(this.inner.try_poll(cx))?;
+        let handle = this.handle.take().expect("polled after completion");
+        Poll::Ready(Ok(this.completion.track_completion(handle, rsp)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use tower::load::CompleteOnResponse;
+    use tower::Service;
+
+    #[test]
+    fn load_is_relative_to_weight() {
+        let mut light = LeastRequest {
+            inner: linkerd_stack::service_fn(|()| future::ok::<_, ()>(())),
+            weight: Weight(1),
+            pending: Arc::new(()),
+            completion: CompleteOnResponse::default(),
+        };
+        let mut heavy = LeastRequest {
+            inner: linkerd_stack::service_fn(|()| future::ok::<_, ()>(())),
+            weight: Weight(4),
+            pending: Arc::new(()),
+            completion: CompleteOnResponse::default(),
+        };
+        assert_eq!(light.load(), 0.0);
+
+        let rsp = light.call(());
+        let _h0 = heavy.call(());
+        let _h1 = heavy.call(());
+        assert_eq!(light.load(), 1.0);
+        assert_eq!(heavy.load(), 0.5);
+
+        drop(rsp);
+        assert_eq!(light.load(), 0.0);
+
+        // A zero weight is treated as the smallest weight.
+        light.weight = Weight(0);
+        let _rsp = light.call(());
+        assert_eq!(light.load(), 1.0);
+    }
+}
@@ -0,0 +1,169 @@
+#![deny(rust_2018_idioms, clippy::disallowed_methods, clippy::disallowed_types)]
+#![forbid(unsafe_code)]
+
+use linkerd_stack::{layer, NewService, Param};
+use std::{
+    str::FromStr,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+
+pub mod least_request;
+pub mod round_robin;
+
+pub use self::{
+    least_request::{LeastRequest, LeastRequestDiscover},
+    round_robin::RoundRobin,
+};
+
+/// Describes how a balancer chooses an endpoint for each request.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Chooses the less loaded of two random endpoints, where load is an
+    /// exponentially-weighted moving average of each endpoint's latency.
+    ///
+    /// Endpoint weights are ignored.
+    #[default]
+    PeakEwma,
+
+    /// Chooses the endpoint with the fewest outstanding requests (relative to
+    /// its weight) of two random endpoints.
+    LeastRequest,
+
+    /// Cycles through endpoints in proportion to their weights.
+    RoundRobin,
+}
+
+/// Describes how a request's hash key is determined when a destination is
+/// balanced by consistent hashing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashKey {
+    /// Hashes the value of a request header.
+    Header(http::header::HeaderName),
+    /// Hashes the value of a request cookie.
+    Cookie(String),
+    /// Hashes the client's IP address.
+    ///
+    /// The client is the peer of the proxy's server-side connection. For an
+    /// outbound sidecar, that is the local application itself, so all of the
+    /// application's requests hash to the same endpoint. This policy is only
+    /// useful for ingress-mode proxies, which receive connections from many
+    /// clients.
+    ClientIp,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[error("invalid balancer strategy: {0:?}; expected one of peak-ewma, least-request, round-robin")]
+pub struct InvalidStrategy(String);
+
+/// An endpoint's relative weight, as indicated by service discovery.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Weight(pub u32);
+
+/// Builds endpoint services that are annotated with their [`Weight`].
+#[derive(Clone, Debug)]
+pub struct NewWeighted<N> {
+    inner: N,
+}
+
+/// An endpoint service annotated with its [`Weight`].
+#[derive(Clone, Debug)]
+pub struct Weighted<S> {
+    inner: S,
+    weight: Weight,
+}
+
+// === impl Strategy ===
+
+impl FromStr for Strategy {
+    type Err = InvalidStrategy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "peak-ewma" => Ok(Self::PeakEwma),
+            "least-request" => Ok(Self::LeastRequest),
+            "round-robin" => Ok(Self::RoundRobin),
+            s => Err(InvalidStrategy(s.to_string())),
+        }
+    }
+}
+
+// === impl Weight ===
+
+impl Weight {
+    /// Returns the weight as a divisor, treating a zero weight as the
+    /// smallest non-zero weight so that endpoints are never starved.
+    fn as_f64(self) -> f64 {
+        f64::from(self.0.max(1))
+    }
+}
+
+// === impl NewWeighted ===
+
+impl<N> NewWeighted<N> {
+    pub fn layer() -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(|inner| Self { inner })
+    }
+}
+
+impl<T, N> NewService<T> for NewWeighted<N>
+where
+    T: Param<Weight>,
+    N: NewService<T>,
+{
+    type Service = Weighted<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let weight = target.param();
+        Weighted {
+            inner: self.inner.new_service(target),
+            weight,
+        }
+    }
+}
+
+// === impl Weighted ===
+
+impl<S> Param<Weight> for Weighted<S> {
+    fn param(&self) -> Weight {
+        self.weight
+    }
+}
+
+impl<Req, S> tower::Service<Req> for Weighted<S>
+where
+    S: tower::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_strategies() {
+        assert_eq!("peak-ewma".parse::<Strategy>().unwrap(), Strategy::PeakEwma);
+        assert_eq!(
+            "least-request".parse::<Strategy>().unwrap(),
+            Strategy::LeastRequest
+        );
+        assert_eq!(
+            "round-robin".parse::<Strategy>().unwrap(),
+            Strategy::RoundRobin
+        );
+        assert!("random".parse::<Strategy>().is_err());
+    }
+}
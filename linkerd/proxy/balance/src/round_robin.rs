@@ -0,0 +1,211 @@
+//! Smooth weighted round-robin balancing.
+//!
+//! Each time an endpoint is chosen, every ready endpoint's current weight is
+//! increased by its configured weight; the endpoint with the greatest current
+//! weight is chosen and its current weight is reduced by the total weight of
+//! all ready endpoints. This interleaves endpoints in proportion to their
+//! weights rather than sending bursts of requests to heavier endpoints.
+
+use crate::Weight;
+use futures::TryFutureExt;
+use linkerd_error::Error;
+use linkerd_stack::Param;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    ready_cache::{error::Failed, ReadyCache},
+};
+use tracing::{debug, trace};
+
+/// Balances requests over endpoints in proportion to their weights.
+pub struct RoundRobin<D: Discover, Req> {
+    discover: D,
+    services: ReadyCache<D::Key, D::Service, Req>,
+    weights: HashMap<D::Key, Current>,
+    _req: PhantomData<fn(Req)>,
+}
+
+#[derive(Debug)]
+struct Current {
+    weight: i64,
+    current: i64,
+}
+
+// === impl RoundRobin ===
+
+impl<D: Discover, Req> RoundRobin<D, Req> {
+    pub fn new(discover: D) -> Self {
+        Self {
+            discover,
+            services: ReadyCache::default(),
+            weights: HashMap::new(),
+            _req: PhantomData,
+        }
+    }
+}
+
+impl<D, Req> RoundRobin<D, Req>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<Req> + Param<Weight>,
+    <D::Service as tower::Service<Req>>::Error: Into<Error>,
+{
+    fn update_endpoints(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        loop {
+            match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Pending | Poll::Ready(None) => return Ok(()),
+                Poll::Ready(Some(Err(e))) => return Err(e.into()),
+                Poll::Ready(Some(Ok(Change::Insert(key, svc)))) => {
+                    trace!("Inserting endpoint");
+                    let weight = i64::from(svc.param().0.max(1));
+                    self.weights
+                        .insert(key.clone(), Current { weight, current: 0 });
+                    self.services.push(key, svc);
+                }
+                Poll::Ready(Some(Ok(Change::Remove(key)))) => {
+                    trace!("Removing endpoint");
+                    self.weights.remove(&key);
+                    self.services.evict(&key);
+                }
+            }
+        }
+    }
+
+    /// Chooses the ready endpoint with the greatest current weight.
+    fn choose(&mut self) -> Option<usize> {
+        let mut total = 0;
+        let mut chosen: Option<(usize, i64)> = None;
+        for index in 0..self.services.ready_len() {
+            let (key, _) = self.services.get_ready_index(index)?;
+            let endpoint = self.weights.get_mut(key)?;
+            endpoint.current += endpoint.weight;
+            total += endpoint.weight;
+            if chosen.map_or(true, |(_, c)| endpoint.current > c) {
+                chosen = Some((index, endpoint.current));
+            }
+        }
+
+        let (index, _) = chosen?;
+        let (key, _) = self.services.get_ready_index(index)?;
+        self.weights.get_mut(key)?.current -= total;
+        Some(index)
+    }
+}
+
+impl<D, Req> tower::Service<Req> for RoundRobin<D, Req>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<Req> + Param<Weight>,
+    <D::Service as tower::Service<Req>>::Error: Into<Error>,
+{
+    type Response = <D::Service as tower::Service<Req>>::Response;
+    type Error = Error;
+    type Future = futures::future::ErrInto<<D::Service as tower::Service<Req>>::Future, Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.update_endpoints(cx)?;
+
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => break,
+                Poll::Ready(Err(Failed(key, error))) => {
+                    // Failed services are dropped from the cache until they
+                    // are rediscovered.
+                    debug!(%error, "Endpoint failed");
+                    self.weights.remove(&key);
+                }
+            }
+        }
+
+        // An endpoint is only chosen when a request is dispatched, since
+        // choosing updates the endpoints' current weights. Ready endpoints
+        // remain ready until they are called.
+        if self.services.ready_len() == 0 {
+            trace!("No ready endpoints");
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let index = self.choose().expect("called before ready");
+        self.services.call_ready_index(index, req).err_into()
+    }
+}
+
+impl<D: Discover, Req> std::fmt::Debug for RoundRobin<D, Req> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RoundRobin")
+            .field("endpoints", &self.weights.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{future, stream};
+    use std::convert::Infallible;
+    use tower::Service;
+
+    #[derive(Debug)]
+    struct Svc(usize, Weight);
+
+    impl Param<Weight> for Svc {
+        fn param(&self) -> Weight {
+            self.1
+        }
+    }
+
+    impl tower::Service<()> for Svc {
+        type Response = usize;
+        type Error = Infallible;
+        type Future = future::Ready<Result<usize, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::ok(self.0)
+        }
+    }
+
+    #[test]
+    fn distributes_requests_by_weight() {
+        let discover = stream::iter(vec![
+            Ok::<_, Infallible>(Change::Insert(0, Svc(0, Weight(1)))),
+            Ok(Change::Insert(1, Svc(1, Weight(2)))),
+            Ok(Change::Insert(2, Svc(2, Weight(3)))),
+        ]);
+        let mut rr = RoundRobin::new(discover);
+
+        let mut task = tokio_test::task::spawn(());
+        let mut counts = [0; 3];
+        let mut order = Vec::new();
+        for _ in 0..12 {
+            // Polling for readiness more than once does not affect which
+            // endpoint is chosen.
+            tokio_test::assert_ready_ok!(task.enter(|cx, _| rr.poll_ready(cx)));
+            tokio_test::assert_ready_ok!(task.enter(|cx, _| rr.poll_ready(cx)));
+            let i = tokio_test::block_on(rr.call(())).unwrap();
+            counts[i] += 1;
+            order.push(i);
+        }
+        assert_eq!(counts, [2, 4, 6]);
+        // The heaviest endpoint is not chosen more than twice in a row.
+        assert!(order
+            .windows(3)
+            .all(|w| !(w[0] == 2 && w[1] == 2 && w[2] == 2)));
+    }
+}
@@ -1,38 +1,216 @@
-use crate::Error;
+use crate::{BoxBody, BoxResponse, Error};
+use futures::ready;
 use hyper::body::HttpBody;
 pub use hyper_balance::{PendingUntilFirstData, PendingUntilFirstDataBody};
+pub use linkerd_proxy_balance::{InvalidStrategy, NewWeighted, Strategy, Weight};
+use linkerd_proxy_balance::{LeastRequestDiscover, RoundRobin};
+use linkerd_stack::{layer, BoxService, Param};
+use pin_project::pin_project;
 use rand::thread_rng;
-use std::{hash::Hash, marker::PhantomData, time::Duration};
+use std::{
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
 use tower::discover::Discover;
 pub use tower::{
     balance::p2c::Balance,
     load::{Load, PeakEwmaDiscover},
 };
 
+pub mod distinct;
+pub mod hash;
+pub mod locality;
+pub mod outlier;
+pub mod sticky;
+
+pub use self::{
+    distinct::{DistinctDiscover, DistinctEndpoint, SameEndpoint},
+    hash::{HashBalance, HashKey},
+    locality::{LocalHealth, Locality, LocalityDiscover, NewLocality, PreferLocal, Zone},
+    outlier::{EjectionMetrics, OutlierDiscover},
+    sticky::{StickyBalance, StickyCookie},
+};
+
 /// Configures a stack to resolve `T` typed targets to balance requests over
 /// `M`-typed endpoint stacks.
 #[derive(Debug)]
 pub struct Layer<A, B> {
     decay: Duration,
     default_rtt: Duration,
+    outlier: Option<outlier::Config>,
+    ejection_metrics: EjectionMetrics,
     _marker: PhantomData<fn(A) -> B>,
 }
 
+/// Configures a stack to balance requests over endpoints in the proxy's zone,
+/// spilling over to endpoints in other zones when fewer than
+/// `min_healthy_percent` of the local endpoints are healthy or when no local
+/// endpoint is ready.
+///
+/// Endpoint services must be annotated with their [`Locality`] (i.e. by
+/// [`NewLocality`]).
+#[derive(Debug)]
+pub struct PreferLocalLayer<A, B> {
+    layer: Layer<A, B>,
+    min_healthy_percent: u8,
+}
+
+/// Builds a balancer for each target's endpoints.
+///
+/// Targets that have a [`StickyCookie`] pin each client's requests to an
+/// endpoint with an affinity cookie. Targets that have a [`HashKey`] are
+/// balanced by consistent hashing so that requests with the same key are sent
+/// to the same endpoint. All other targets are balanced with the target's
+/// [`Strategy`] (or the default strategy), preferring local endpoints.
+///
+/// A sticky cookie takes precedence over a hash key, which takes precedence
+/// over the strategy. All balancers dispatch hedged requests to a different
+/// endpoint than the request they hedge (see [`DistinctEndpoint`]). Sticky and hash balancers choose among all of a target's
+/// endpoints, regardless of their locality, so that a client remains pinned to
+/// the same endpoint as local endpoints come and go.
+#[derive(Debug)]
+pub struct MakeBalance<M, A, B> {
+    make: M,
+    layer: PreferLocalLayer<A, B>,
+    strategy: Strategy,
+}
+
+#[pin_project]
+pub struct MakeBalanceFuture<F, A, B> {
+    #[pin]
+    inner: F,
+    sticky: Option<StickyCookie>,
+    key: Option<HashKey>,
+    strategy: Strategy,
+    layer: PreferLocalLayer<A, B>,
+}
+
+pub type BoxBalance<A> = BoxService<http::Request<A>, http::Response<BoxBody>, Error>;
+
+type PeakEwmaBalance<D, A> =
+    Balance<DistinctDiscover<PeakEwmaDiscover<OutlierDiscover<D>, PendingUntilFirstData>>, A>;
+
+type LocalityBalance<D, A> = PeakEwmaBalance<LocalityDiscover<D>, A>;
+
+type HashBalancer<D, A> =
+    HashBalance<DistinctDiscover<PeakEwmaDiscover<OutlierDiscover<D>, PendingUntilFirstData>>, A>;
+
+type StickyBalancer<D, A> =
+    StickyBalance<DistinctDiscover<PeakEwmaDiscover<OutlierDiscover<D>, PendingUntilFirstData>>, A>;
+
 // === impl Layer ===
 
 pub fn layer<A, B>(default_rtt: Duration, decay: Duration) -> Layer<A, B> {
     Layer {
         decay,
         default_rtt,
+        outlier: None,
+        ejection_metrics: EjectionMetrics::default(),
         _marker: PhantomData,
     }
 }
 
+impl<A, B> Layer<A, B> {
+    /// Ejects endpoints that fail consecutive requests from the balancer.
+    pub fn with_outlier_detection(
+        self,
+        config: Option<outlier::Config>,
+        metrics: EjectionMetrics,
+    ) -> Self {
+        Self {
+            outlier: config,
+            ejection_metrics: metrics,
+            ..self
+        }
+    }
+
+    /// Balances requests over local endpoints while at least
+    /// `min_healthy_percent` of them are healthy.
+    pub fn prefer_local(self, min_healthy_percent: u8) -> PreferLocalLayer<A, B> {
+        PreferLocalLayer {
+            layer: self,
+            min_healthy_percent: min_healthy_percent.min(100),
+        }
+    }
+
+    fn outliers<D>(&self, discover: D) -> OutlierDiscover<D> {
+        OutlierDiscover::new(discover, self.outlier, self.ejection_metrics.clone())
+    }
+
+    fn hash<D>(&self, discover: D, key: HashKey) -> HashBalancer<D, http::Request<A>>
+    where
+        D: Discover,
+    {
+        let discover = self.outliers(discover);
+        let instrument = PendingUntilFirstData::default();
+        let loaded = PeakEwmaDiscover::new(discover, self.default_rtt, self.decay, instrument);
+        HashBalance::new(DistinctDiscover::new(loaded), key)
+    }
+
+    fn sticky<D>(&self, discover: D, cookie: StickyCookie) -> StickyBalancer<D, http::Request<A>>
+    where
+        D: Discover,
+    {
+        let discover = self.outliers(discover);
+        let instrument = PendingUntilFirstData::default();
+        let loaded = PeakEwmaDiscover::new(discover, self.default_rtt, self.decay, instrument);
+        StickyBalance::new(DistinctDiscover::new(loaded), cookie)
+    }
+
+    fn least_request<D>(
+        &self,
+        discover: OutlierDiscover<D>,
+    ) -> Balance<
+        DistinctDiscover<LeastRequestDiscover<OutlierDiscover<D>, PendingUntilFirstData>>,
+        http::Request<A>,
+    >
+    where
+        A: HttpBody,
+        B: HttpBody,
+        D: Discover,
+        D::Key: Hash,
+        D::Service: tower::Service<http::Request<A>, Response = http::Response<B>> + Param<Weight>,
+        <D::Service as tower::Service<http::Request<A>>>::Error: Into<Error>,
+    {
+        let loaded = LeastRequestDiscover::new(discover, PendingUntilFirstData::default());
+        Balance::from_rng(DistinctDiscover::new(loaded), &mut thread_rng())
+            .expect("RNG must be valid")
+    }
+
+    fn round_robin<D: Discover>(
+        &self,
+        discover: OutlierDiscover<D>,
+    ) -> RoundRobin<DistinctDiscover<OutlierDiscover<D>>, http::Request<A>> {
+        RoundRobin::new(DistinctDiscover::new(discover))
+    }
+
+    fn balance<D>(&self, discover: OutlierDiscover<D>) -> PeakEwmaBalance<D, http::Request<A>>
+    where
+        A: HttpBody,
+        B: HttpBody,
+        D: Discover,
+        D::Key: Hash,
+        D::Service: tower::Service<http::Request<A>, Response = http::Response<B>>,
+        <D::Service as tower::Service<http::Request<A>>>::Error: Into<Error>,
+    {
+        let instrument = PendingUntilFirstData::default();
+        let loaded = PeakEwmaDiscover::new(discover, self.default_rtt, self.decay, instrument);
+        Balance::from_rng(DistinctDiscover::new(loaded), &mut thread_rng())
+            .expect("RNG must be valid")
+    }
+}
+
 impl<A, B> Clone for Layer<A, B> {
     fn clone(&self) -> Self {
         Self {
             decay: self.decay,
             default_rtt: self.default_rtt,
+            outlier: self.outlier,
+            ejection_metrics: self.ejection_metrics.clone(),
             _marker: PhantomData,
         }
     }
@@ -46,14 +224,173 @@ where
     D::Key: Hash,
     S: tower::Service<http::Request<A>, Response = http::Response<B>>,
     S::Error: Into<Error>,
-    Balance<PeakEwmaDiscover<D, PendingUntilFirstData>, http::Request<A>>:
-        tower::Service<http::Request<A>>,
+    PeakEwmaBalance<D, http::Request<A>>: tower::Service<http::Request<A>>,
 {
-    type Service = Balance<PeakEwmaDiscover<D, PendingUntilFirstData>, http::Request<A>>;
+    type Service = PeakEwmaBalance<D, http::Request<A>>;
 
     fn layer(&self, discover: D) -> Self::Service {
-        let instrument = PendingUntilFirstData::default();
-        let loaded = PeakEwmaDiscover::new(discover, self.default_rtt, self.decay, instrument);
-        Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid")
+        self.balance(self.outliers(discover))
+    }
+}
+
+// === impl PreferLocalLayer ===
+
+impl<A, B> PreferLocalLayer<A, B> {
+    /// Partitions endpoints by locality and builds a balancer for each
+    /// partition.
+    fn partition<D, S>(
+        &self,
+        discover: D,
+        balance: impl Fn(&Layer<A, B>, OutlierDiscover<LocalityDiscover<D>>) -> S,
+    ) -> PreferLocal<S>
+    where
+        D: Discover,
+    {
+        // Outlier detection limits the proportion of ejected endpoints in
+        // each zone independently.
+        let (local, remote) = locality::partition(discover);
+        let endpoints = local.endpoints();
+        let local = self.layer.outliers(local);
+        let health = LocalHealth::new(endpoints, local.ejected());
+        PreferLocal::new(
+            balance(&self.layer, local),
+            balance(&self.layer, self.layer.outliers(remote)),
+            health,
+            self.min_healthy_percent,
+        )
     }
 }
+
+impl<A, B> Clone for PreferLocalLayer<A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer.clone(),
+            min_healthy_percent: self.min_healthy_percent,
+        }
+    }
+}
+
+impl<D, S, A, B> tower::layer::Layer<D> for PreferLocalLayer<A, B>
+where
+    A: HttpBody,
+    B: HttpBody,
+    D: Discover<Service = S>,
+    D::Key: Hash + Clone,
+    S: tower::Service<http::Request<A>, Response = http::Response<B>> + Param<Locality>,
+    S::Error: Into<Error>,
+    LocalityBalance<D, http::Request<A>>: tower::Service<http::Request<A>>,
+{
+    type Service = PreferLocal<LocalityBalance<D, http::Request<A>>>;
+
+    fn layer(&self, discover: D) -> Self::Service {
+        self.partition(discover, Layer::balance)
+    }
+}
+
+// === impl MakeBalance ===
+
+impl<M: Clone, A, B> Clone for MakeBalance<M, A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            make: self.make.clone(),
+            layer: self.layer.clone(),
+            strategy: self.strategy,
+        }
+    }
+}
+
+impl<M, A, B> MakeBalance<M, A, B> {
+    /// Balances requests with `strategy` unless a target configures its own.
+    pub fn layer(
+        balance: PreferLocalLayer<A, B>,
+        strategy: Strategy,
+    ) -> impl layer::Layer<M, Service = Self> + Clone {
+        layer::mk(move |make| Self {
+            make,
+            layer: balance.clone(),
+            strategy,
+        })
+    }
+}
+
+impl<T, M, A, B> tower::Service<T> for MakeBalance<M, A, B>
+where
+    T: Param<Option<StickyCookie>> + Param<Option<HashKey>> + Param<Option<Strategy>>,
+    M: tower::Service<T>,
+    MakeBalanceFuture<M::Future, A, B>: Future<Output = Result<BoxBalance<A>, M::Error>>,
+{
+    type Response = BoxBalance<A>;
+    type Error = M::Error;
+    type Future = MakeBalanceFuture<M::Future, A, B>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.make.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let sticky = Param::<Option<StickyCookie>>::param(&target);
+        let key = Param::<Option<HashKey>>::param(&target);
+        if sticky.is_some() && key.is_some() {
+            tracing::warn!("Target configures both a sticky cookie and a hash policy; ignoring the hash policy");
+        }
+        let strategy = Param::<Option<Strategy>>::param(&target).unwrap_or(self.strategy);
+        MakeBalanceFuture {
+            sticky,
+            key,
+            strategy,
+            inner: self.make.call(target),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<F, D, E, S, A, B> Future for MakeBalanceFuture<F, A, B>
+where
+    F: Future<Output = Result<D, E>>,
+    D: Discover<Service = S> + Unpin + Send + 'static,
+    D::Key: Hash + Clone + Send + Sync + 'static,
+    D::Error: Into<Error>,
+    S: tower::Service<http::Request<A>, Response = http::Response<B>>,
+    S: Param<Locality> + Param<Weight> + Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+    A: HttpBody + Send + 'static,
+    B: HttpBody + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    type Output = Result<BoxBalance<A>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let discover = ready!(this.inner.poll(cx))?;
+        let layer = &this.layer.layer;
+        if let Some(cookie) = this.sticky.take() {
+            return Poll::Ready(Ok(boxed(layer.sticky(discover, cookie))));
+        }
+        let balance = match (this.key.take(), *this.strategy) {
+            (Some(key), _) => boxed(layer.hash(discover, key)),
+            (None, Strategy::PeakEwma) => boxed(tower::layer::Layer::layer(&*this.layer, discover)),
+            (None, Strategy::LeastRequest) => {
+                boxed(this.layer.partition(discover, Layer::least_request))
+            }
+            (None, Strategy::RoundRobin) => {
+                boxed(this.layer.partition(discover, Layer::round_robin))
+            }
+        };
+        Poll::Ready(Ok(balance))
+    }
+}
+
+fn boxed<S, A, B>(balance: S) -> BoxBalance<A>
+where
+    S: tower::Service<http::Request<A>, Response = http::Response<B>, Error = Error>,
+    S: Send + 'static,
+    S::Future: Send + 'static,
+    B: HttpBody + Send + 'static,
+    B::Data: Send + 'static,
+    B::Error: Into<Error>,
+{
+    BoxService::new(tower::layer::Layer::layer(&BoxResponse::layer(), balance))
+}
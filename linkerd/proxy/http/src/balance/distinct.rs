@@ -0,0 +1,215 @@
+//! Dispatches hedged requests to a different endpoint than the request that
+//! they hedge.
+//!
+//! A request and its hedges share a [`DistinctEndpoint`] request extension.
+//! The endpoint to which the original request is dispatched is recorded, and a
+//! hedged request that a balancer dispatches to the same endpoint fails with
+//! [`SameEndpoint`] without being sent, so that it may be dispatched again.
+//!
+//! Endpoints are wrapped outside of their load and outlier-detection
+//! middlewares, so rejected requests affect neither.
+
+use futures::{future, Stream, TryFutureExt};
+use linkerd_error::Error;
+use linkerd_proxy_balance::Weight;
+use linkerd_stack::Param;
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    hash::Hash,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    load::Load,
+};
+
+/// A request extension that identifies the endpoint to which a request was
+/// dispatched, so that its hedges may be dispatched elsewhere.
+#[derive(Clone, Debug)]
+pub struct DistinctEndpoint {
+    endpoint: Arc<Mutex<Option<u64>>>,
+    hedge: bool,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("hedged request was dispatched to the endpoint of the request it hedges")]
+pub struct SameEndpoint(());
+
+/// Wraps a balancer's endpoints so that hedged requests are not sent to the
+/// endpoint of the request they hedge.
+#[pin_project]
+#[derive(Debug)]
+pub struct DistinctDiscover<D> {
+    #[pin]
+    discover: D,
+}
+
+/// An endpoint service that rejects hedged requests if it served the request
+/// they hedge.
+#[derive(Debug)]
+pub struct Distinct<S> {
+    inner: S,
+    id: u64,
+}
+
+// === impl DistinctEndpoint ===
+
+impl DistinctEndpoint {
+    /// Returns the extensions for an original request and for its hedges,
+    /// respectively.
+    pub fn pair() -> (Self, Self) {
+        let endpoint = Arc::new(Mutex::new(None));
+        let original = Self {
+            endpoint: endpoint.clone(),
+            hedge: false,
+        };
+        let hedge = Self {
+            endpoint,
+            hedge: true,
+        };
+        (original, hedge)
+    }
+
+    /// Indicates whether a request may be sent to the endpoint identified by
+    /// `id`.
+    ///
+    /// The original request's first endpoint is recorded. Hedged requests may
+    /// be sent to any other endpoint.
+    fn dispatch(&self, id: u64) -> bool {
+        let mut endpoint = self.endpoint.lock();
+        if self.hedge {
+            return *endpoint != Some(id);
+        }
+        endpoint.get_or_insert(id);
+        true
+    }
+}
+
+// === impl DistinctDiscover ===
+
+impl<D> DistinctDiscover<D> {
+    pub fn new(discover: D) -> Self {
+        Self { discover }
+    }
+}
+
+impl<D> Stream for DistinctDiscover<D>
+where
+    D: Discover,
+    D::Key: Hash,
+{
+    type Item = Result<Change<D::Key, Distinct<D::Service>>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let change = match futures::ready!(self.project().discover.poll_discover(cx)) {
+            None => return Poll::Ready(None),
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            Some(Ok(Change::Remove(key))) => Change::Remove(key),
+            Some(Ok(Change::Insert(key, inner))) => {
+                let id = super::hash::hash(&key);
+                Change::Insert(key, Distinct { inner, id })
+            }
+        };
+        Poll::Ready(Some(Ok(change)))
+    }
+}
+
+// === impl Distinct ===
+
+impl<S: Load> Load for Distinct<S> {
+    type Metric = S::Metric;
+
+    #[inline]
+    fn load(&self) -> Self::Metric {
+        self.inner.load()
+    }
+}
+
+impl<S: Param<Weight>> Param<Weight> for Distinct<S> {
+    fn param(&self) -> Weight {
+        self.inner.param()
+    }
+}
+
+impl<S, B> tower::Service<http::Request<B>> for Distinct<S>
+where
+    S: tower::Service<http::Request<B>>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = future::Either<
+        future::ErrInto<S::Future, Error>,
+        future::Ready<Result<S::Response, Error>>,
+    >;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if let Some(distinct) = req.extensions().get::<DistinctEndpoint>() {
+            if !distinct.dispatch(self.id) {
+                tracing::debug!("Hedged request chose the original request's endpoint");
+                return future::Either::Right(future::err(SameEndpoint(()).into()));
+            }
+        }
+        future::Either::Left(self.inner.call(req).err_into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower::Service;
+
+    #[derive(Debug)]
+    struct Svc;
+
+    impl tower::Service<http::Request<()>> for Svc {
+        type Response = ();
+        type Error = Error;
+        type Future = future::Ready<Result<(), Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: http::Request<()>) -> Self::Future {
+            future::ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn hedges_avoid_original_endpoint() {
+        let mut a = Distinct { inner: Svc, id: 1 };
+        let mut b = Distinct { inner: Svc, id: 2 };
+        let (original, hedge) = DistinctEndpoint::pair();
+        let req = |ext: &DistinctEndpoint| {
+            let mut req = http::Request::new(());
+            req.extensions_mut().insert(ext.clone());
+            req
+        };
+
+        a.call(req(&original)).await.expect("original is sent");
+        // A retry of the original request may be sent to another endpoint
+        // without changing the recorded endpoint.
+        b.call(req(&original)).await.expect("original is sent");
+
+        let error = a
+            .call(req(&hedge))
+            .await
+            .expect_err("hedge must not be sent to the original endpoint");
+        assert!(error.is::<SameEndpoint>());
+        b.call(req(&hedge)).await.expect("hedge is sent");
+
+        // Requests that are not hedged are unaffected.
+        a.call(http::Request::new(()))
+            .await
+            .expect("request is sent");
+    }
+}
@@ -0,0 +1,246 @@
+//! Consistent-hash load balancing.
+//!
+//! Each endpoint is placed at many points on a hash ring. Requests are hashed
+//! by a key (e.g. a header value) and are dispatched to the first ready
+//! endpoint at or after the request's position on the ring. When endpoints
+//! are added or removed, only the requests that hashed to the affected
+//! portions of the ring are moved to other endpoints.
+
+use crate::ClientHandle;
+use futures::{future, TryFutureExt};
+use linkerd_error::Error;
+pub use linkerd_proxy_balance::HashKey;
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    ready_cache::{error::Failed, ReadyCache},
+};
+use tracing::{debug, trace};
+
+/// The number of points on the ring at which each endpoint is placed.
+const POINTS_PER_ENDPOINT: u64 = 100;
+
+/// Balances requests over endpoints by consistent hashing.
+///
+/// Requests that have no hash key (e.g. because the header is not set) are
+/// dispatched to a random endpoint.
+pub struct HashBalance<D: Discover, Req> {
+    discover: D,
+    key: HashKey,
+    endpoints: HashSet<D::Key>,
+    ring: Vec<(u64, D::Key)>,
+    services: ReadyCache<D::Key, D::Service, Req>,
+    _req: PhantomData<fn(Req)>,
+}
+
+// === impl HashBalance ===
+
+impl<D: Discover, Req> HashBalance<D, Req> {
+    pub fn new(discover: D, key: HashKey) -> Self {
+        Self {
+            discover,
+            key,
+            endpoints: HashSet::new(),
+            ring: Vec::new(),
+            services: ReadyCache::default(),
+            _req: PhantomData,
+        }
+    }
+}
+
+impl<D, B> HashBalance<D, http::Request<B>>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<http::Request<B>>,
+    <D::Service as tower::Service<http::Request<B>>>::Error: Into<Error>,
+{
+    /// Processes endpoint updates, returning true if the set of endpoints
+    /// changed.
+    fn update_endpoints(&mut self, cx: &mut Context<'_>) -> Result<bool, Error> {
+        let mut changed = false;
+        loop {
+            match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Pending | Poll::Ready(None) => return Ok(changed),
+                Poll::Ready(Some(Err(e))) => return Err(e.into()),
+                Poll::Ready(Some(Ok(Change::Insert(key, svc)))) => {
+                    trace!("Inserting endpoint");
+                    changed |= self.endpoints.insert(key.clone());
+                    self.services.push(key, svc);
+                }
+                Poll::Ready(Some(Ok(Change::Remove(key)))) => {
+                    trace!("Removing endpoint");
+                    changed |= self.endpoints.remove(&key);
+                    self.services.evict(&key);
+                }
+            }
+        }
+    }
+}
+
+impl<D, B> tower::Service<http::Request<B>> for HashBalance<D, http::Request<B>>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<http::Request<B>>,
+    <D::Service as tower::Service<http::Request<B>>>::Error: Into<Error>,
+{
+    type Response = <D::Service as tower::Service<http::Request<B>>>::Response;
+    type Error = Error;
+    type Future = future::ErrInto<<D::Service as tower::Service<http::Request<B>>>::Future, Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.update_endpoints(cx)? {
+            self.ring = ring(self.endpoints.iter());
+            debug!(endpoints = self.endpoints.len(), "Updated hash ring");
+        }
+
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => break,
+                Poll::Ready(Err(Failed(key, error))) => {
+                    // Failed services are dropped from the cache. They are
+                    // removed from the ring until they are rediscovered.
+                    debug!(%error, "Endpoint failed");
+                    self.endpoints.remove(&key);
+                    self.ring = ring(self.endpoints.iter());
+                }
+            }
+        }
+
+        if self.services.ready_len() == 0 {
+            trace!("No ready endpoints");
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let hash = hash_key(&self.key, &req).unwrap_or_else(rand::random);
+        let key = lookup(&self.ring, hash, |key| {
+            self.services.get_ready(key).is_some()
+        })
+        .cloned()
+        .expect("called before ready");
+        self.services.call_ready(&key, req).err_into()
+    }
+}
+
+impl<D: Discover, Req> std::fmt::Debug for HashBalance<D, Req> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HashBalance")
+            .field("key", &self.key)
+            .field("endpoints", &self.endpoints.len())
+            .finish()
+    }
+}
+
+pub(super) fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    // The default hasher is keyed identically in all proxies, so that every
+    // proxy builds the same ring for a given set of endpoints.
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns the value of the named request cookie, if it is set.
+/// Determines a request's hash by its [`HashKey`], if the request has one.
+fn hash_key<B>(key: &HashKey, req: &http::Request<B>) -> Option<u64> {
+    match key {
+        HashKey::Header(name) => req.headers().get(name).map(|v| hash(v.as_bytes())),
+        HashKey::Cookie(name) => cookie(req, name).map(hash),
+        HashKey::ClientIp => req
+            .extensions()
+            .get::<ClientHandle>()
+            .map(|client| hash(&client.addr.ip())),
+    }
+}
+
+pub(super) fn cookie<'r, B>(req: &'r http::Request<B>, name: &str) -> Option<&'r str> {
+    req.headers()
+        .get_all(http::header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|c| c.trim().split_once('='))
+        .find(|(n, _)| *n == name)
+        .map(|(_, v)| v)
+}
+
+/// Builds a ring that places each endpoint at several points.
+fn ring<'k, K: Hash + Clone + 'k>(endpoints: impl Iterator<Item = &'k K>) -> Vec<(u64, K)> {
+    let mut ring = endpoints
+        .flat_map(|key| (0..POINTS_PER_ENDPOINT).map(move |i| (hash(&(key, i)), key.clone())))
+        .collect::<Vec<_>>();
+    ring.sort_unstable_by_key(|(point, _)| *point);
+    ring
+}
+
+/// Finds the first endpoint at or after `hash` on the ring (wrapping around)
+/// that satisfies `is_ready`.
+fn lookup<K>(ring: &[(u64, K)], hash: u64, mut is_ready: impl FnMut(&K) -> bool) -> Option<&K> {
+    let start = ring.partition_point(|(point, _)| *point < hash);
+    ring[start..]
+        .iter()
+        .chain(ring[..start].iter())
+        .map(|(_, key)| key)
+        .find(|key| is_ready(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_is_mostly_stable() {
+        let mut endpoints = (0..10u16).collect::<Vec<_>>();
+        let before = ring(endpoints.iter());
+        endpoints.push(10);
+        let after = ring(endpoints.iter());
+
+        let hashes = (0..10_000u64).map(|i| hash(&i)).collect::<Vec<_>>();
+        let moved = hashes
+            .iter()
+            .filter(|h| lookup(&before, **h, |_| true) != lookup(&after, **h, |_| true))
+            .count();
+        // Roughly 1/11th of keys are expected to move to the new endpoint.
+        assert!(moved < 2_000, "{} keys moved", moved);
+        for h in &hashes {
+            let key = lookup(&after, *h, |_| true).unwrap();
+            if lookup(&before, *h, |_| true) != Some(key) {
+                assert_eq!(*key, 10, "keys may only move to the new endpoint");
+            }
+        }
+
+        // Keys for an endpoint that is not ready are sent to the next
+        // endpoint on the ring.
+        let h = hashes[0];
+        let key = *lookup(&after, h, |_| true).unwrap();
+        let next = *lookup(&after, h, |k| *k != key).unwrap();
+        assert_ne!(key, next);
+    }
+
+    #[test]
+    fn hashes_request_keys() {
+        let req = http::Request::builder()
+            .header("x-user", "alice")
+            .header("cookie", "a=b; session=abc")
+            .body(())
+            .unwrap();
+
+        let header = HashKey::Header(http::header::HeaderName::from_static("x-user"));
+        assert_eq!(hash_key(&header, &req), Some(hash(b"alice".as_slice())));
+        let cookie = HashKey::Cookie("session".into());
+        assert_eq!(hash_key(&cookie, &req), Some(hash("abc")));
+        assert_eq!(hash_key(&HashKey::Cookie("missing".into()), &req), None);
+        assert_eq!(hash_key(&HashKey::ClientIp, &req), None);
+    }
+}
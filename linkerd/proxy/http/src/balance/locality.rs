@@ -0,0 +1,527 @@
+//! Locality-aware load balancing.
+//!
+//! Endpoints are partitioned by whether they are in the same zone as the
+//! proxy. Requests are balanced over local endpoints while enough of them are
+//! healthy, and spill over to endpoints in other zones when too many local
+//! endpoints have been ejected by outlier detection or when no local endpoint
+//! is ready (e.g. because there are none).
+
+use super::outlier::Ejected;
+use futures::Stream;
+use linkerd_proxy_balance::Weight;
+use linkerd_stack::{layer, NewService, Param};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    hash::Hash,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+};
+use tower::discover::{Change, Discover};
+
+/// A zone (e.g. a cloud provider's availability zone) in which a proxy or an
+/// endpoint runs.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Zone(Arc<str>);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Locality {
+    /// The endpoint is in the proxy's zone, or either zone is unknown.
+    Local,
+    /// The endpoint is known to be in another zone.
+    Remote,
+}
+
+/// Builds endpoint services that are annotated with their [`Locality`].
+#[derive(Clone, Debug)]
+pub struct NewLocality<N> {
+    inner: N,
+    zone: Option<Zone>,
+}
+
+/// An endpoint service annotated with its [`Locality`].
+#[derive(Clone, Debug)]
+pub struct Localized<S> {
+    inner: S,
+    locality: Locality,
+}
+
+/// One side of a discovery stream that has been partitioned by locality.
+pub struct LocalityDiscover<D: Discover> {
+    shared: Arc<Mutex<Shared<D>>>,
+    locality: Locality,
+}
+
+/// Tracks the proportion of the endpoints in the proxy's zone that have not
+/// been ejected.
+#[derive(Clone, Debug)]
+pub struct LocalHealth {
+    endpoints: Arc<AtomicUsize>,
+    ejected: Ejected,
+}
+
+/// Dispatches requests to the local balancer when it is ready and to the
+/// remote balancer otherwise.
+///
+/// When fewer than `min_healthy_percent` of the local endpoints are healthy,
+/// the remaining local endpoints may not have the capacity to serve all
+/// requests, so the remote balancer is preferred instead while it is ready.
+#[derive(Debug)]
+pub struct PreferLocal<S> {
+    local: S,
+    remote: S,
+    health: LocalHealth,
+    min_healthy_percent: u8,
+    use_local: bool,
+}
+
+struct Shared<D: Discover> {
+    discover: Pin<Box<D>>,
+    localities: HashMap<D::Key, Locality>,
+    local: Queue<D::Key, D::Service>,
+    remote: Queue<D::Key, D::Service>,
+    done: bool,
+}
+
+struct Queue<K, S> {
+    changes: VecDeque<Change<K, S>>,
+    waker: Option<Waker>,
+    endpoints: Arc<AtomicUsize>,
+}
+
+/// Splits a discovery stream into streams of local and remote endpoints.
+pub fn partition<D>(discover: D) -> (LocalityDiscover<D>, LocalityDiscover<D>)
+where
+    D: Discover,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        discover: Box::pin(discover),
+        localities: HashMap::new(),
+        local: Queue::default(),
+        remote: Queue::default(),
+        done: false,
+    }));
+    let local = LocalityDiscover {
+        shared: shared.clone(),
+        locality: Locality::Local,
+    };
+    let remote = LocalityDiscover {
+        shared,
+        locality: Locality::Remote,
+    };
+    (local, remote)
+}
+
+// === impl Zone ===
+
+impl Zone {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Zone {
+    fn from(zone: &str) -> Self {
+        Self(zone.into())
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// === impl NewLocality ===
+
+impl<N> NewLocality<N> {
+    /// Endpoints are local if they are in `zone`. When the proxy's zone is
+    /// not known, all endpoints are local.
+    pub fn layer(zone: Option<Zone>) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self {
+            inner,
+            zone: zone.clone(),
+        })
+    }
+}
+
+impl<T, N> NewService<T> for NewLocality<N>
+where
+    T: Param<Option<Zone>>,
+    N: NewService<T>,
+{
+    type Service = Localized<N::Service>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        let locality = match (self.zone.as_ref(), target.param()) {
+            (Some(local), Some(zone)) if *local != zone => Locality::Remote,
+            _ => Locality::Local,
+        };
+        Localized {
+            inner: self.inner.new_service(target),
+            locality,
+        }
+    }
+}
+
+// === impl Localized ===
+
+impl<S> Param<Locality> for Localized<S> {
+    fn param(&self) -> Locality {
+        self.locality
+    }
+}
+
+impl<S: Param<Weight>> Param<Weight> for Localized<S> {
+    fn param(&self) -> Weight {
+        self.inner.param()
+    }
+}
+
+impl<Req, S> tower::Service<Req> for Localized<S>
+where
+    S: tower::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[inline]
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// === impl LocalityDiscover ===
+
+impl<D: Discover> LocalityDiscover<D> {
+    /// Returns a handle on the number of endpoints in this partition.
+    pub(crate) fn endpoints(&self) -> Arc<AtomicUsize> {
+        self.shared.lock().queue(self.locality).endpoints.clone()
+    }
+}
+
+impl<D> Stream for LocalityDiscover<D>
+where
+    D: Discover,
+    D::Key: Hash + Clone,
+    D::Service: Param<Locality>,
+{
+    type Item = Result<Change<D::Key, D::Service>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock();
+        loop {
+            if let Some(change) = shared.queue(self.locality).changes.pop_front() {
+                return Poll::Ready(Some(Ok(change)));
+            }
+            if shared.done {
+                return Poll::Ready(None);
+            }
+
+            let change = match shared.discover.as_mut().poll_discover(cx) {
+                Poll::Pending => {
+                    shared.queue(self.locality).waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                Poll::Ready(None) => {
+                    shared.done = true;
+                    shared.wake_all();
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(Some(Ok(change))) => change,
+            };
+
+            match change {
+                Change::Insert(key, svc) => {
+                    let locality = svc.param();
+                    // If the endpoint moved to a different zone, it must be
+                    // removed from its prior partition.
+                    match shared.localities.insert(key.clone(), locality) {
+                        Some(prior) if prior != locality => {
+                            shared.push(prior, Change::Remove(key.clone()));
+                            shared
+                                .queue(prior)
+                                .endpoints
+                                .fetch_sub(1, Ordering::Release);
+                            shared
+                                .queue(locality)
+                                .endpoints
+                                .fetch_add(1, Ordering::Release);
+                        }
+                        Some(_) => {}
+                        None => {
+                            shared
+                                .queue(locality)
+                                .endpoints
+                                .fetch_add(1, Ordering::Release);
+                        }
+                    }
+                    shared.push(locality, Change::Insert(key, svc));
+                }
+                Change::Remove(key) => {
+                    if let Some(locality) = shared.localities.remove(&key) {
+                        shared
+                            .queue(locality)
+                            .endpoints
+                            .fetch_sub(1, Ordering::Release);
+                        shared.push(locality, Change::Remove(key));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<D: Discover> fmt::Debug for LocalityDiscover<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalityDiscover")
+            .field("locality", &self.locality)
+            .finish()
+    }
+}
+
+// === impl Shared ===
+
+impl<D: Discover> Shared<D> {
+    fn queue(&mut self, locality: Locality) -> &mut Queue<D::Key, D::Service> {
+        match locality {
+            Locality::Local => &mut self.local,
+            Locality::Remote => &mut self.remote,
+        }
+    }
+
+    fn push(&mut self, locality: Locality, change: Change<D::Key, D::Service>) {
+        let queue = self.queue(locality);
+        queue.changes.push_back(change);
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for waker in [self.local.waker.take(), self.remote.waker.take()]
+            .into_iter()
+            .flatten()
+        {
+            waker.wake();
+        }
+    }
+}
+
+impl<K, S> Default for Queue<K, S> {
+    fn default() -> Self {
+        Self {
+            changes: VecDeque::new(),
+            waker: None,
+            endpoints: Default::default(),
+        }
+    }
+}
+
+// === impl LocalHealth ===
+
+impl LocalHealth {
+    pub(crate) fn new(endpoints: Arc<AtomicUsize>, ejected: Ejected) -> Self {
+        Self { endpoints, ejected }
+    }
+
+    /// Indicates whether at least `min_percent` of the local endpoints are
+    /// healthy. This is never the case when there are no local endpoints.
+    fn is_healthy(&self, min_percent: u8) -> bool {
+        let endpoints = self.endpoints.load(Ordering::Acquire);
+        let healthy = endpoints.saturating_sub(self.ejected.get());
+        endpoints > 0 && healthy * 100 >= endpoints * min_percent as usize
+    }
+}
+
+// === impl PreferLocal ===
+
+impl<S> PreferLocal<S> {
+    pub fn new(local: S, remote: S, health: LocalHealth, min_healthy_percent: u8) -> Self {
+        Self {
+            local,
+            remote,
+            health,
+            min_healthy_percent,
+            use_local: true,
+        }
+    }
+}
+
+impl<Req, S> tower::Service<Req> for PreferLocal<S>
+where
+    S: tower::Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if !self.health.is_healthy(self.min_healthy_percent) {
+            // The local balancer is still driven so that its ejected endpoints
+            // are restored once their ejection times elapse.
+            let local = self.local.poll_ready(cx);
+            if let Poll::Ready(Err(e)) = local {
+                return Poll::Ready(Err(e));
+            }
+            if let Poll::Ready(res) = self.remote.poll_ready(cx) {
+                tracing::debug!(
+                    "Too few local endpoints are healthy; using endpoints in other zones"
+                );
+                self.use_local = false;
+                return Poll::Ready(res);
+            }
+            if local.is_ready() {
+                self.use_local = true;
+                return Poll::Ready(Ok(()));
+            }
+            return Poll::Pending;
+        }
+
+        if let Poll::Ready(res) = self.local.poll_ready(cx) {
+            self.use_local = true;
+            return Poll::Ready(res);
+        }
+
+        // The remote balancer is only driven when no local endpoints are
+        // ready. Both balancers register the task's waker, so the local
+        // balancer is preferred again as soon as one of its endpoints becomes
+        // ready.
+        let res = futures::ready!(self.remote.poll_ready(cx));
+        tracing::debug!("No local endpoints are ready; using endpoints in other zones");
+        self.use_local = false;
+        Poll::Ready(res)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        if self.use_local {
+            self.local.call(req)
+        } else {
+            self.remote.call(req)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{stream, FutureExt, StreamExt};
+    use std::convert::Infallible;
+    use tower::Service;
+
+    /// A service that is always ready and responds with its name.
+    struct Named(&'static str);
+
+    impl Service<()> for Named {
+        type Response = &'static str;
+        type Error = Infallible;
+        type Future = futures::future::Ready<Result<&'static str, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, (): ()) -> Self::Future {
+            futures::future::ok(self.0)
+        }
+    }
+
+    fn svc(locality: Locality) -> Localized<()> {
+        Localized {
+            inner: (),
+            locality,
+        }
+    }
+
+    #[tokio::test]
+    async fn partitions_endpoints_by_locality() {
+        let changes = stream::iter(vec![
+            Ok::<_, Infallible>(Change::Insert(1, svc(Locality::Local))),
+            Ok(Change::Insert(2, svc(Locality::Remote))),
+            Ok(Change::Insert(3, svc(Locality::Local))),
+            // Endpoint 3 moves to another zone.
+            Ok(Change::Insert(3, svc(Locality::Remote))),
+            Ok(Change::Remove(1)),
+            Ok(Change::Remove(2)),
+        ]);
+        let (local, remote) = partition(changes);
+
+        let local = local
+            .map(|c| match c.unwrap() {
+                Change::Insert(k, _) => (k, true),
+                Change::Remove(k) => (k, false),
+            })
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(local, vec![(1, true), (3, true), (3, false), (1, false)]);
+
+        let remote = remote
+            .map(|c| match c.unwrap() {
+                Change::Insert(k, _) => (k, true),
+                Change::Remove(k) => (k, false),
+            })
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(remote, vec![(2, true), (3, true), (2, false)]);
+    }
+
+    #[test]
+    fn endpoints_without_zones_are_local() {
+        let new = |zone: Option<&str>| NewLocality {
+            inner: |_: Option<Zone>| (),
+            zone: zone.map(Zone::from),
+        };
+        let locality = |zone: Option<&str>, endpoint: Option<&str>| {
+            new(zone).new_service(endpoint.map(Zone::from)).param()
+        };
+        assert_eq!(locality(Some("a"), Some("a")), Locality::Local);
+        assert_eq!(locality(Some("a"), Some("b")), Locality::Remote);
+        assert_eq!(locality(Some("a"), None), Locality::Local);
+        assert_eq!(locality(None, Some("b")), Locality::Local);
+    }
+
+    #[tokio::test]
+    async fn spills_over_when_too_few_local_endpoints_are_healthy() {
+        let endpoints = Arc::new(AtomicUsize::new(4));
+        let ejected = Ejected::for_test(0);
+        let mut svc = PreferLocal::new(
+            Named("local"),
+            Named("remote"),
+            LocalHealth::new(endpoints.clone(), ejected.clone()),
+            70,
+        );
+        let mut call = || {
+            futures::future::poll_fn(|cx| svc.poll_ready(cx))
+                .now_or_never()
+                .expect("service must be ready")
+                .unwrap();
+            svc.call(()).now_or_never().unwrap().unwrap()
+        };
+        assert_eq!(call(), "local");
+
+        // 3 of 4 local endpoints are healthy.
+        ejected.set_for_test(1);
+        assert_eq!(call(), "local");
+
+        // Only 2 of 4 local endpoints are healthy.
+        ejected.set_for_test(2);
+        assert_eq!(call(), "remote");
+
+        // Without local endpoints, the remote endpoints are used.
+        ejected.set_for_test(0);
+        endpoints.store(0, Ordering::Release);
+        assert_eq!(call(), "remote");
+    }
+}
@@ -0,0 +1,468 @@
+//! Passive outlier detection for balanced endpoints.
+//!
+//! Each endpoint's responses are observed as they complete. When an endpoint
+//! fails too many consecutive requests, it is ejected from the balancer for a
+//! period of time that grows exponentially each time the endpoint is ejected.
+//! While ejected, the endpoint's service is not ready, so the balancer does
+//! not select it.
+
+use futures::{ready, Stream, TryFuture};
+use linkerd_exp_backoff::{ExponentialBackoff, InvalidBackoff};
+use linkerd_metrics::{Counter, Gauge};
+use linkerd_proxy_balance::Weight;
+use linkerd_stack::Param;
+use parking_lot::Mutex;
+use pin_project::pin_project;
+use std::{
+    future::Future,
+    num::NonZeroU32,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::time::{self, Duration, Instant};
+use tower::discover::{Change, Discover};
+use tracing::{debug, info};
+
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    /// Ejects an endpoint after this many consecutive 5XX responses or
+    /// errors.
+    pub consecutive_5xx: Option<NonZeroU32>,
+
+    /// Ejects an endpoint after this many consecutive errors (e.g. connection
+    /// failures), without an intervening response.
+    pub consecutive_errors: Option<NonZeroU32>,
+
+    /// Determines how long an endpoint is ejected. The ejection time doubles
+    /// (up to the backoff's maximum) each time an endpoint is ejected.
+    pub ejection_backoff: ExponentialBackoff,
+
+    /// The maximum percentage of a balancer's endpoints that may be ejected
+    /// at once. An endpoint that would exceed this limit is not ejected.
+    pub max_ejected_percent: u8,
+}
+
+/// Records endpoint ejections across all balancers.
+#[derive(Clone, Debug, Default)]
+pub struct EjectionMetrics {
+    ejections: Arc<Counter>,
+    ejected: Arc<Gauge>,
+}
+
+/// Wraps a balancer's endpoints with outlier detection.
+#[pin_project]
+#[derive(Debug)]
+pub struct OutlierDiscover<D> {
+    #[pin]
+    discover: D,
+    config: Option<Config>,
+    pool: Arc<Mutex<Pool>>,
+    metrics: EjectionMetrics,
+}
+
+/// Counts the endpoints of an [`OutlierDiscover`] that are currently ejected.
+#[derive(Clone, Debug)]
+pub struct Ejected(Arc<Mutex<Pool>>);
+
+/// An endpoint service that is not ready while the endpoint is ejected.
+#[derive(Debug)]
+pub struct Outlier<S> {
+    inner: S,
+    endpoint: Option<Arc<Endpoint>>,
+    sleep: Option<Pin<Box<time::Sleep>>>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    endpoint: Option<Arc<Endpoint>>,
+}
+
+/// Tracks the number of endpoints in a balancer that are ejected.
+#[derive(Debug, Default)]
+struct Pool {
+    endpoints: usize,
+    ejected: usize,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    config: Config,
+    pool: Arc<Mutex<Pool>>,
+    metrics: EjectionMetrics,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    consecutive_5xx: u32,
+    consecutive_errors: u32,
+    /// The number of times the endpoint has been ejected without having
+    /// recovered.
+    ejections: u32,
+    ejected_until: Option<Instant>,
+    restored_at: Option<Instant>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Outcome {
+    Success,
+    Status5xx,
+    Error,
+}
+
+// === impl Config ===
+
+impl Config {
+    pub fn try_new(
+        consecutive_5xx: Option<NonZeroU32>,
+        consecutive_errors: Option<NonZeroU32>,
+        base_ejection_time: Duration,
+        max_ejection_time: Duration,
+        max_ejected_percent: u8,
+    ) -> Result<Self, InvalidBackoff> {
+        let ejection_backoff =
+            ExponentialBackoff::try_new(base_ejection_time, max_ejection_time, 0.0)?;
+        Ok(Self {
+            consecutive_5xx,
+            consecutive_errors,
+            ejection_backoff,
+            max_ejected_percent: max_ejected_percent.min(100),
+        })
+    }
+}
+
+// === impl EjectionMetrics ===
+
+impl EjectionMetrics {
+    /// The total number of times that endpoints have been ejected.
+    pub fn ejections(&self) -> &Counter {
+        &self.ejections
+    }
+
+    /// The number of endpoints that are currently ejected.
+    pub fn ejected(&self) -> &Gauge {
+        &self.ejected
+    }
+}
+
+// === impl OutlierDiscover ===
+
+impl<D> OutlierDiscover<D> {
+    pub fn new(discover: D, config: Option<Config>, metrics: EjectionMetrics) -> Self {
+        Self {
+            discover,
+            config,
+            pool: Default::default(),
+            metrics,
+        }
+    }
+
+    pub fn ejected(&self) -> Ejected {
+        Ejected(self.pool.clone())
+    }
+}
+
+impl<D: Discover> Stream for OutlierDiscover<D> {
+    type Item = Result<Change<D::Key, Outlier<D::Service>>, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let change = match ready!(this.discover.poll_discover(cx)) {
+            None => return Poll::Ready(None),
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            Some(Ok(Change::Remove(key))) => Change::Remove(key),
+            Some(Ok(Change::Insert(key, inner))) => {
+                let endpoint = this.config.map(|config| {
+                    this.pool.lock().endpoints += 1;
+                    Arc::new(Endpoint {
+                        config,
+                        pool: this.pool.clone(),
+                        metrics: this.metrics.clone(),
+                        state: Default::default(),
+                    })
+                });
+                Change::Insert(
+                    key,
+                    Outlier {
+                        inner,
+                        endpoint,
+                        sleep: None,
+                    },
+                )
+            }
+        };
+        Poll::Ready(Some(Ok(change)))
+    }
+}
+
+// === impl Ejected ===
+
+impl Ejected {
+    pub fn get(&self) -> usize {
+        self.0.lock().ejected
+    }
+
+    #[cfg(test)]
+    pub(crate) fn for_test(ejected: usize) -> Self {
+        Self(Arc::new(Mutex::new(Pool {
+            endpoints: ejected,
+            ejected,
+        })))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_for_test(&self, ejected: usize) {
+        self.0.lock().ejected = ejected;
+    }
+}
+
+// === impl Outlier ===
+
+impl<S: Param<Weight>> Param<Weight> for Outlier<S> {
+    fn param(&self) -> Weight {
+        self.inner.param()
+    }
+}
+
+impl<S, Req, B> tower::Service<Req> for Outlier<S>
+where
+    S: tower::Service<Req, Response = http::Response<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Some(endpoint) = self.endpoint.as_ref() {
+            if let Some(until) = endpoint.ejected_until() {
+                let sleep = self
+                    .sleep
+                    .get_or_insert_with(|| Box::pin(time::sleep_until(until)));
+                if sleep.deadline() != until {
+                    sleep.as_mut().reset(until);
+                }
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+                endpoint.restore();
+            }
+        }
+
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        ResponseFuture {
+            inner: self.inner.call(req),
+            endpoint: self.endpoint.clone(),
+        }
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+{
+    type Output = Result<http::Response<B>, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.try_poll(cx));
+        if let Some(endpoint) = this.endpoint.take() {
+            let outcome = match res {
+                Ok(ref rsp) if rsp.status().is_server_error() => Outcome::Status5xx,
+                Ok(_) => Outcome::Success,
+                Err(_) => Outcome::Error,
+            };
+            endpoint.record(outcome);
+        }
+        Poll::Ready(res)
+    }
+}
+
+// === impl Endpoint ===
+
+impl Endpoint {
+    /// Returns the time at which the endpoint will be restored, if it is
+    /// ejected.
+    fn ejected_until(&self) -> Option<Instant> {
+        self.state.lock().ejected_until
+    }
+
+    /// Restores an ejected endpoint once its ejection time has elapsed.
+    fn restore(&self) {
+        let mut state = self.state.lock();
+        if state.ejected_until.take().is_none() {
+            return;
+        }
+        state.restored_at = Some(Instant::now());
+        self.pool.lock().ejected -= 1;
+        self.metrics.ejected.decr();
+        debug!("Endpoint restored");
+    }
+
+    fn record(&self, outcome: Outcome) {
+        let mut state = self.state.lock();
+        if state.ejected_until.is_some() {
+            // Responses for requests that were dispatched before the endpoint
+            // was ejected do not affect it further.
+            return;
+        }
+
+        match outcome {
+            Outcome::Success => {
+                state.consecutive_5xx = 0;
+                state.consecutive_errors = 0;
+                // Once an endpoint has been healthy for the maximum ejection
+                // time (i.e. the backoff's delay after arbitrarily many
+                // ejections), its ejection time is reset.
+                if let Some(restored_at) = state.restored_at {
+                    let healthy = Instant::now().saturating_duration_since(restored_at);
+                    if healthy >= self.config.ejection_backoff.delay(u32::MAX) {
+                        state.ejections = 0;
+                        state.restored_at = None;
+                    }
+                }
+                return;
+            }
+            Outcome::Status5xx => {
+                state.consecutive_5xx += 1;
+                state.consecutive_errors = 0;
+            }
+            Outcome::Error => {
+                state.consecutive_5xx += 1;
+                state.consecutive_errors += 1;
+            }
+        }
+
+        let exceeds = |n: u32, max: Option<NonZeroU32>| max.map_or(false, |max| n >= max.get());
+        if !exceeds(state.consecutive_5xx, self.config.consecutive_5xx)
+            && !exceeds(state.consecutive_errors, self.config.consecutive_errors)
+        {
+            return;
+        }
+
+        {
+            let mut pool = self.pool.lock();
+            let max_ejected = pool.endpoints * self.config.max_ejected_percent as usize / 100;
+            if pool.ejected >= max_ejected {
+                debug!(
+                    ejected = pool.ejected,
+                    endpoints = pool.endpoints,
+                    "Too many endpoints are ejected; not ejecting endpoint"
+                );
+                return;
+            }
+            pool.ejected += 1;
+        }
+
+        let ejection = self.config.ejection_backoff.delay(state.ejections);
+        info!(
+            consecutive_5xx = state.consecutive_5xx,
+            consecutive_errors = state.consecutive_errors,
+            ?ejection,
+            "Ejecting endpoint"
+        );
+        state.ejections = state.ejections.saturating_add(1);
+        state.consecutive_5xx = 0;
+        state.consecutive_errors = 0;
+        state.ejected_until = Some(Instant::now() + ejection);
+        state.restored_at = None;
+        self.metrics.ejections.incr();
+        self.metrics.ejected.incr();
+    }
+}
+
+impl Drop for Endpoint {
+    fn drop(&mut self) {
+        let mut pool = self.pool.lock();
+        pool.endpoints -= 1;
+        if self.state.get_mut().ejected_until.is_some() {
+            pool.ejected -= 1;
+            self.metrics.ejected.decr();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(config: Config, n: usize) -> (Arc<Mutex<Pool>>, Vec<Endpoint>) {
+        let pool = Arc::new(Mutex::new(Pool {
+            endpoints: n,
+            ejected: 0,
+        }));
+        let endpoints = (0..n)
+            .map(|_| Endpoint {
+                config,
+                pool: pool.clone(),
+                metrics: EjectionMetrics::default(),
+                state: Default::default(),
+            })
+            .collect();
+        (pool, endpoints)
+    }
+
+    fn config() -> Config {
+        Config::try_new(
+            NonZeroU32::new(3),
+            NonZeroU32::new(2),
+            Duration::from_secs(10),
+            Duration::from_secs(60),
+            50,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ejects_after_consecutive_failures() {
+        let (pool, eps) = endpoints(config(), 2);
+        let ep = &eps[0];
+
+        ep.record(Outcome::Status5xx);
+        ep.record(Outcome::Status5xx);
+        ep.record(Outcome::Success);
+        ep.record(Outcome::Status5xx);
+        ep.record(Outcome::Status5xx);
+        assert!(ep.ejected_until().is_none());
+        ep.record(Outcome::Status5xx);
+        let until = ep.ejected_until().expect("endpoint must be ejected");
+        assert_eq!(until, Instant::now() + Duration::from_secs(10));
+        assert_eq!(pool.lock().ejected, 1);
+        assert_eq!(ep.metrics.ejections().value(), 1.0);
+
+        // Ejection times double each time the endpoint is ejected.
+        time::sleep(Duration::from_secs(10)).await;
+        ep.restore();
+        assert_eq!(pool.lock().ejected, 0);
+        ep.record(Outcome::Error);
+        ep.record(Outcome::Error);
+        let until = ep.ejected_until().expect("endpoint must be ejected");
+        assert_eq!(until, Instant::now() + Duration::from_secs(20));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn limits_ejected_endpoints() {
+        let (pool, eps) = endpoints(config(), 3);
+        for ep in eps.iter() {
+            ep.record(Outcome::Error);
+            ep.record(Outcome::Error);
+        }
+        // Only 50% of the three endpoints may be ejected.
+        assert_eq!(pool.lock().ejected, 1);
+        assert_eq!(
+            eps.iter().filter(|ep| ep.ejected_until().is_some()).count(),
+            1
+        );
+
+        drop(eps);
+        assert_eq!(pool.lock().endpoints, 0);
+        assert_eq!(pool.lock().ejected, 0);
+    }
+}
@@ -0,0 +1,286 @@
+//! Cookie-based session affinity.
+//!
+//! Responses are annotated with an opaque cookie that identifies the endpoint
+//! that served the request. Subsequent requests that carry the cookie are
+//! dispatched to the same endpoint, as long as it remains in discovery and is
+//! ready. All other requests are balanced by choosing the less loaded of two
+//! random endpoints.
+
+use super::hash::{cookie, hash};
+use futures::{ready, TryFuture};
+use http::header::{HeaderValue, SET_COOKIE};
+use linkerd_error::Error;
+use pin_project::pin_project;
+use rand::{seq::index, thread_rng};
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{
+    discover::{Change, Discover},
+    load::Load,
+    ready_cache::{error::Failed, ReadyCache},
+};
+use tracing::{debug, trace};
+
+/// The name of the cookie that pins a client's requests to an endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StickyCookie(pub String);
+
+/// Balances requests over endpoints, dispatching requests that carry an
+/// affinity cookie to the endpoint that set it.
+pub struct StickyBalance<D: Discover, Req> {
+    discover: D,
+    cookie: StickyCookie,
+    endpoints: HashMap<String, D::Key>,
+    services: ReadyCache<D::Key, D::Service, Req>,
+    _req: PhantomData<fn(Req)>,
+}
+
+#[pin_project]
+#[derive(Debug)]
+pub struct ResponseFuture<F> {
+    #[pin]
+    inner: F,
+    set_cookie: Option<HeaderValue>,
+}
+
+// === impl StickyBalance ===
+
+impl<D: Discover, Req> StickyBalance<D, Req> {
+    pub fn new(discover: D, cookie: StickyCookie) -> Self {
+        Self {
+            discover,
+            cookie,
+            endpoints: HashMap::new(),
+            services: ReadyCache::default(),
+            _req: PhantomData,
+        }
+    }
+}
+
+impl<D, B> StickyBalance<D, http::Request<B>>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<http::Request<B>> + Load,
+    <D::Service as Load>::Metric: PartialOrd,
+    <D::Service as tower::Service<http::Request<B>>>::Error: Into<Error>,
+{
+    fn update_endpoints(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        loop {
+            match Pin::new(&mut self.discover).poll_discover(cx) {
+                Poll::Pending | Poll::Ready(None) => return Ok(()),
+                Poll::Ready(Some(Err(e))) => return Err(e.into()),
+                Poll::Ready(Some(Ok(Change::Insert(key, svc)))) => {
+                    trace!("Inserting endpoint");
+                    self.endpoints.insert(token(&key), key.clone());
+                    self.services.push(key, svc);
+                }
+                Poll::Ready(Some(Ok(Change::Remove(key)))) => {
+                    trace!("Removing endpoint");
+                    self.endpoints.remove(&token(&key));
+                    self.services.evict(&key);
+                }
+            }
+        }
+    }
+
+    /// Chooses the less loaded of two random ready endpoints.
+    fn p2c_ready_index(&self) -> usize {
+        match self.services.ready_len() {
+            0 => panic!("called before ready"),
+            1 => 0,
+            len => {
+                let sample = index::sample(&mut thread_rng(), len, 2);
+                let (a, b) = (sample.index(0), sample.index(1));
+                if self.load(a) <= self.load(b) {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+    }
+
+    fn load(&self, index: usize) -> Option<<D::Service as Load>::Metric> {
+        self.services
+            .get_ready_index(index)
+            .map(|(_, svc)| svc.load())
+    }
+}
+
+impl<D, B> tower::Service<http::Request<B>> for StickyBalance<D, http::Request<B>>
+where
+    D: Discover + Unpin,
+    D::Key: Hash + Clone,
+    D::Error: Into<Error>,
+    D::Service: tower::Service<http::Request<B>> + Load,
+    <D::Service as Load>::Metric: PartialOrd,
+    <D::Service as tower::Service<http::Request<B>>>::Error: Into<Error>,
+{
+    type Response = <D::Service as tower::Service<http::Request<B>>>::Response;
+    type Error = Error;
+    type Future = ResponseFuture<<D::Service as tower::Service<http::Request<B>>>::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.update_endpoints(cx)?;
+
+        loop {
+            match self.services.poll_pending(cx) {
+                Poll::Ready(Ok(())) | Poll::Pending => break,
+                Poll::Ready(Err(Failed(key, error))) => {
+                    // Failed services are dropped from the cache, so sessions
+                    // pinned to them are rebalanced until the endpoint is
+                    // rediscovered.
+                    debug!(%error, "Endpoint failed");
+                    self.endpoints.remove(&token(&key));
+                }
+            }
+        }
+
+        if self.services.ready_len() == 0 {
+            trace!("No ready endpoints");
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let pinned = cookie(&req, &self.cookie.0)
+            .and_then(|token| self.endpoints.get(token))
+            .filter(|key| self.services.get_ready(*key).is_some())
+            .cloned();
+        if let Some(key) = pinned {
+            trace!("Dispatching to pinned endpoint");
+            return ResponseFuture {
+                inner: self.services.call_ready(&key, req),
+                set_cookie: None,
+            };
+        }
+
+        let index = self.p2c_ready_index();
+        let (key, _) = self
+            .services
+            .get_ready_index(index)
+            .expect("index must be ready");
+        let set_cookie = HeaderValue::from_str(&format!(
+            "{}={}; Path=/; HttpOnly",
+            self.cookie.0,
+            token(key)
+        ))
+        .ok();
+        ResponseFuture {
+            inner: self.services.call_ready_index(index, req),
+            set_cookie,
+        }
+    }
+}
+
+impl<D: Discover, Req> std::fmt::Debug for StickyBalance<D, Req> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StickyBalance")
+            .field("cookie", &self.cookie)
+            .field("endpoints", &self.endpoints.len())
+            .finish()
+    }
+}
+
+// === impl ResponseFuture ===
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: TryFuture<Ok = http::Response<B>>,
+    F::Error: Into<Error>,
+{
+    type Output = Result<http::Response<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut rsp = ready!(this.inner.try_poll(cx)).map_err(Into::into)?;
+        if let Some(set_cookie) = this.set_cookie.take() {
+            rsp.headers_mut().append(SET_COOKIE, set_cookie);
+        }
+        Poll::Ready(Ok(rsp))
+    }
+}
+
+/// Returns an opaque token that identifies an endpoint.
+///
+/// Tokens are derived from the endpoint's key so that every proxy issues the
+/// same token for a given endpoint.
+fn token<K: Hash>(key: &K) -> String {
+    format!("{:016x}", hash(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{future, stream};
+    use std::convert::Infallible;
+    use tower::Service;
+
+    #[derive(Debug)]
+    struct Svc(usize);
+
+    impl Load for Svc {
+        type Metric = usize;
+
+        fn load(&self) -> usize {
+            0
+        }
+    }
+
+    impl tower::Service<http::Request<()>> for Svc {
+        type Response = http::Response<usize>;
+        type Error = Infallible;
+        type Future = future::Ready<Result<http::Response<usize>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: http::Request<()>) -> Self::Future {
+            future::ok(http::Response::new(self.0))
+        }
+    }
+
+    #[test]
+    fn pins_requests_with_cookies() {
+        let discover = stream::iter((0..3).map(|i| Ok::<_, Infallible>(Change::Insert(i, Svc(i)))));
+        let mut balance = StickyBalance::new(discover, StickyCookie("l5d-sticky".into()));
+        let mut task = tokio_test::task::spawn(());
+        let mut send = |cookie: Option<&str>| {
+            tokio_test::assert_ready_ok!(task.enter(|cx, _| balance.poll_ready(cx)));
+            let mut req = http::Request::builder();
+            if let Some(cookie) = cookie {
+                req = req.header(http::header::COOKIE, cookie);
+            }
+            tokio_test::block_on(balance.call(req.body(()).unwrap())).unwrap()
+        };
+
+        let rsp = send(None);
+        let endpoint = *rsp.body();
+        let set_cookie = rsp.headers()[SET_COOKIE].to_str().unwrap().to_string();
+        assert_eq!(
+            set_cookie,
+            format!("l5d-sticky={}; Path=/; HttpOnly", token(&endpoint))
+        );
+
+        let cookie = set_cookie.split(';').next().unwrap().to_string();
+        for _ in 0..10 {
+            let rsp = send(Some(&format!("a=b; {}", cookie)));
+            assert_eq!(*rsp.body(), endpoint);
+            assert!(rsp.headers().get(SET_COOKIE).is_none());
+        }
+
+        // Unknown tokens are rebalanced and issued a new cookie.
+        let rsp = send(Some("l5d-sticky=unknown"));
+        assert!(rsp.headers().get(SET_COOKIE).is_some());
+    }
+}
@@ -1,5 +1,7 @@
 use linkerd_error::Error;
-use linkerd_stack::layer;
+pub use linkerd_proxy_balance::{InvalidStrategy, NewWeighted, Strategy, Weight};
+use linkerd_proxy_balance::{LeastRequestDiscover, RoundRobin};
+use linkerd_stack::{layer, Either, Param};
 use rand::thread_rng;
 use std::{hash::Hash, time::Duration};
 pub use tower::{
@@ -8,21 +10,39 @@ pub use tower::{
 };
 use tower::{discover::Discover, load::CompleteOnResponse};
 
-/// Produces a PeakEWMA balancer that uses connect latency (and pending
-/// connections) as its load metric.
+pub type Balancer<D, T> = Either<
+    Balance<PeakEwmaDiscover<D, CompleteOnResponse>, T>,
+    Either<Balance<LeastRequestDiscover<D, CompleteOnResponse>, T>, RoundRobin<D, T>>,
+>;
+
+/// Produces a balancer that uses the given strategy.
+///
+/// The PeakEWMA strategy uses connect latency (and pending connections) as its
+/// load metric. The least-request strategy uses pending connections, relative
+/// to each endpoint's weight.
 pub fn layer<T, D>(
+    strategy: Strategy,
     default_rtt: Duration,
     decay: Duration,
-) -> impl tower::layer::Layer<D, Service = Balance<PeakEwmaDiscover<D, CompleteOnResponse>, T>> + Clone
+) -> impl tower::layer::Layer<D, Service = Balancer<D, T>> + Clone
 where
     D: Discover,
     D::Key: Hash,
-    D::Service: tower::Service<T>,
+    D::Service: tower::Service<T> + Param<Weight>,
     <D::Service as tower::Service<T>>::Error: Into<Error>,
 {
-    layer::mk(move |discover| {
-        let loaded =
-            PeakEwmaDiscover::new(discover, default_rtt, decay, CompleteOnResponse::default());
-        Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid")
+    layer::mk(move |discover| match strategy {
+        Strategy::PeakEwma => {
+            let loaded =
+                PeakEwmaDiscover::new(discover, default_rtt, decay, CompleteOnResponse::default());
+            Either::A(Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid"))
+        }
+        Strategy::LeastRequest => {
+            let loaded = LeastRequestDiscover::new(discover, CompleteOnResponse::default());
+            Either::B(Either::A(
+                Balance::from_rng(loaded, &mut thread_rng()).expect("RNG must be valid"),
+            ))
+        }
+        Strategy::RoundRobin => Either::B(Either::B(RoundRobin::new(discover))),
     })
 }
@@ -1,9 +1,11 @@
 use futures::prelude::*;
 use linkerd_duplex::Duplex;
 use linkerd_error::{Error, Result};
+use linkerd_io::PeerAddr;
 use linkerd_stack::layer;
 use std::{
     future::Future,
+    net::SocketAddr,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -15,6 +17,14 @@ pub struct Forward<C> {
     connect: C,
 }
 
+/// Like [`Forward`], but connects with the accepted connection's peer address
+/// so that the connection may describe its client (e.g. in a PROXY protocol
+/// header).
+#[derive(Clone, Debug)]
+pub struct ForwardFromPeer<C> {
+    connect: C,
+}
+
 impl<C> Forward<C> {
     fn new(connect: C) -> Self {
         Self { connect }
@@ -51,3 +61,46 @@ where
         )
     }
 }
+
+// === impl ForwardFromPeer ===
+
+impl<C> ForwardFromPeer<C> {
+    fn new(connect: C) -> Self {
+        Self { connect }
+    }
+
+    pub fn layer() -> impl layer::Layer<C, Service = Self> + Clone + Copy {
+        layer::mk(Self::new)
+    }
+}
+
+impl<C, I> Service<I> for ForwardFromPeer<C>
+where
+    I: AsyncRead + AsyncWrite + PeerAddr + Send + Unpin + 'static,
+    C: tower::Service<SocketAddr> + Send + 'static,
+    C::Error: Into<Error>,
+    C::Future: Send + 'static,
+    C::Response: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>;
+
+    #[inline]
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), self::Error>> {
+        self.connect.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, src_io: I) -> Self::Future {
+        let client = match src_io.peer_addr() {
+            Ok(client) => client,
+            Err(e) => return Box::pin(future::err(e.into())),
+        };
+        Box::pin(
+            self.connect
+                .call(client)
+                .err_into::<Error>()
+                .and_then(|dst_io| Duplex::new(src_io, dst_io).err_into::<Error>()),
+        )
+    }
+}
@@ -1,12 +1,19 @@
 use crate::{ClientAddr, Keepalive, Local, Remote, ServerAddr};
 use linkerd_io as io;
 use linkerd_stack::{Param, Service};
+#[cfg(unix)]
+use std::net::Ipv4Addr;
 use std::{
+    collections::HashMap,
     future::Future,
+    path::PathBuf,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tracing::debug;
 
 #[derive(Copy, Clone, Debug)]
@@ -14,6 +21,22 @@ pub struct ConnectTcp {
     keepalive: Keepalive,
 }
 
+/// Maps local ports to the Unix domain sockets on which the application
+/// listens in place of those ports.
+#[derive(Clone, Debug, Default)]
+pub struct UnixSockets(Arc<HashMap<u16, PathBuf>>);
+
+/// Connects to a Unix domain socket when the target port is mapped to one;
+/// otherwise, connects over TCP.
+///
+/// Unix domain sockets are only supported on Unix platforms; elsewhere,
+/// connecting to a mapped port fails.
+#[derive(Clone, Debug)]
+pub struct ConnectUnixOrTcp {
+    tcp: ConnectTcp,
+    sockets: UnixSockets,
+}
+
 impl ConnectTcp {
     pub fn new(keepalive: Keepalive) -> Self {
         Self { keepalive }
@@ -47,3 +70,200 @@ impl<T: Param<Remote<ServerAddr>>> Service<T> for ConnectTcp {
         })
     }
 }
+
+// === impl UnixSockets ===
+
+impl UnixSockets {
+    pub fn new(sockets: impl IntoIterator<Item = (u16, PathBuf)>) -> Self {
+        Self(Arc::new(sockets.into_iter().collect()))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, port: u16) -> Option<&PathBuf> {
+        self.0.get(&port)
+    }
+}
+
+// === impl ConnectUnixOrTcp ===
+
+impl ConnectUnixOrTcp {
+    pub fn new(tcp: ConnectTcp, sockets: UnixSockets) -> Self {
+        Self { tcp, sockets }
+    }
+}
+
+#[cfg(unix)]
+impl<T: Param<Remote<ServerAddr>>> Service<T> for ConnectUnixOrTcp {
+    type Response = (
+        io::EitherIo<io::ScopedIo<TcpStream>, io::ScopedIo<UnixStream>>,
+        Local<ClientAddr>,
+    );
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send + Sync + 'static>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, t: T) -> Self::Future {
+        let Remote(ServerAddr(addr)) = t.param();
+        let path = match self.sockets.get(addr.port()) {
+            Some(path) => path.clone(),
+            None => {
+                let connect = self.tcp.call(t);
+                return Box::pin(async move {
+                    let (io, local) = connect.await?;
+                    Ok((io::EitherIo::Left(io), local))
+                });
+            }
+        };
+
+        debug!(server.addr = %addr, path = %path.display(), "Connecting to Unix socket");
+        Box::pin(async move {
+            let io = UnixStream::connect(&path).await?;
+            debug!(path = %path.display(), "Connected");
+            // Unix socket clients are unnamed, but they are necessarily local.
+            let local = Local(ClientAddr((Ipv4Addr::LOCALHOST, 0).into()));
+            Ok((io::EitherIo::Right(io::ScopedIo::client(io)), local))
+        })
+    }
+}
+
+#[cfg(not(unix))]
+impl<T: Param<Remote<ServerAddr>>> Service<T> for ConnectUnixOrTcp {
+    type Response = (io::ScopedIo<TcpStream>, Local<ClientAddr>);
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send + Sync + 'static>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, t: T) -> Self::Future {
+        let Remote(ServerAddr(addr)) = t.param();
+        if let Some(path) = self.sockets.get(addr.port()) {
+            let error = io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "cannot connect to {}: Unix domain sockets are not supported on this platform",
+                    path.display()
+                ),
+            );
+            return Box::pin(futures::future::err(error));
+        }
+        self.tcp.call(t)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{listen::Bind, BindUnixOrTcp, ListenAddr, UnixListenPath};
+    use futures::prelude::*;
+    use io::{AsyncReadExt, AsyncWriteExt};
+
+    struct Listen {
+        addr: ListenAddr,
+        path: Option<UnixListenPath>,
+    }
+
+    impl Param<ListenAddr> for Listen {
+        fn param(&self) -> ListenAddr {
+            self.addr
+        }
+    }
+
+    impl Param<Keepalive> for Listen {
+        fn param(&self) -> Keepalive {
+            Keepalive(None)
+        }
+    }
+
+    impl Param<Option<UnixListenPath>> for Listen {
+        fn param(&self) -> Option<UnixListenPath> {
+            self.path.clone()
+        }
+    }
+
+    /// Accepts a single connection and echoes what is read from it.
+    fn echo<S, A, I>(mut incoming: S) -> tokio::task::JoinHandle<()>
+    where
+        S: Stream<Item = linkerd_error::Result<(A, I)>> + Send + Unpin + 'static,
+        A: Send + 'static,
+        I: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+    {
+        tokio::spawn(async move {
+            let (_, mut io) = incoming
+                .next()
+                .await
+                .expect("listener must accept")
+                .expect("connection must be accepted");
+            let mut buf = [0u8; 5];
+            io.read_exact(&mut buf).await.expect("must read");
+            io.write_all(&buf).await.expect("must write");
+        })
+    }
+
+    async fn roundtrip<I: io::AsyncRead + io::AsyncWrite + Unpin>(mut io: I) {
+        io.write_all(b"hello").await.expect("must write");
+        let mut buf = [0u8; 5];
+        io.read_exact(&mut buf).await.expect("must read");
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn connects_to_mapped_unix_sockets() {
+        let path = std::env::temp_dir().join(format!(
+            "linkerd-proxy-transport-{}.sock",
+            std::process::id()
+        ));
+        let listen = Listen {
+            addr: ListenAddr(([127, 0, 0, 1], 8080).into()),
+            path: Some(UnixListenPath(path.clone())),
+        };
+        let (server, incoming) = BindUnixOrTcp::default().bind(&listen).expect("must bind");
+        assert_eq!(server, Local(ServerAddr(([127, 0, 0, 1], 8080).into())));
+        let accept = echo(incoming);
+
+        let mut connect = ConnectUnixOrTcp::new(
+            ConnectTcp::new(Keepalive(None)),
+            UnixSockets::new(Some((8080, path.clone()))),
+        );
+        let target = Remote(ServerAddr(([10, 0, 0, 1], 8080).into()));
+        let (io, _) = connect.call(target).await.expect("must connect");
+        assert!(matches!(io, io::EitherIo::Right(_)), "must use the socket");
+        roundtrip(io).await;
+
+        accept.await.expect("echo must complete");
+        std::fs::remove_file(&path).expect("must remove socket");
+    }
+
+    #[tokio::test]
+    async fn connects_to_unmapped_ports_over_tcp() {
+        let listen = Listen {
+            addr: ListenAddr(([127, 0, 0, 1], 0).into()),
+            path: None,
+        };
+        let (Local(ServerAddr(addr)), incoming) =
+            BindUnixOrTcp::default().bind(&listen).expect("must bind");
+        let accept = echo(incoming);
+
+        let mut connect = ConnectUnixOrTcp::new(
+            ConnectTcp::new(Keepalive(None)),
+            UnixSockets::new(Some((addr.port().wrapping_add(1), "/nonexistent".into()))),
+        );
+        let (io, _) = connect
+            .call(Remote(ServerAddr(addr)))
+            .await
+            .expect("must connect");
+        assert!(matches!(io, io::EitherIo::Left(_)), "must use TCP");
+        roundtrip(io).await;
+
+        accept.await.expect("echo must complete");
+    }
+}
@@ -3,10 +3,18 @@ use futures::prelude::*;
 use linkerd_error::Result;
 use linkerd_io as io;
 use linkerd_stack::Param;
-use std::{fmt, pin::Pin};
+#[cfg(unix)]
+use std::net::Ipv4Addr;
+use std::{fmt, path::PathBuf, pin::Pin};
 use thiserror::Error;
 use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixStream;
 use tokio_stream::wrappers::TcpListenerStream;
+#[cfg(unix)]
+use tokio_stream::wrappers::UnixListenerStream;
+#[cfg(unix)]
+use tracing::debug;
 
 /// Binds a listener, producing a stream of incoming connections.
 ///
@@ -33,6 +41,19 @@ pub type Bound<I> = (Local<ServerAddr>, I);
 #[derive(Copy, Clone, Debug, Default)]
 pub struct BindTcp(());
 
+/// A filesystem path on which to listen for connections over a Unix domain
+/// socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnixListenPath(pub PathBuf);
+
+/// Binds a Unix domain socket when the params include a path; otherwise,
+/// binds a TCP socket.
+///
+/// Unix domain sockets are only supported on Unix platforms; elsewhere, binding
+/// a path fails.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BindUnixOrTcp(BindTcp);
+
 #[derive(Clone, Debug)]
 pub struct Addrs {
     pub server: Local<ServerAddr>,
@@ -89,6 +110,76 @@ where
     }
 }
 
+// === impl BindUnixOrTcp ===
+
+#[cfg(unix)]
+impl<T> Bind<T> for BindUnixOrTcp
+where
+    T: Param<ListenAddr> + Param<Keepalive> + Param<Option<UnixListenPath>>,
+{
+    type Addrs = Addrs;
+    type Incoming = Pin<Box<dyn Stream<Item = Result<(Self::Addrs, Self::Io)>> + Send + Sync>>;
+    type Io = io::EitherIo<TcpStream, UnixStream>;
+
+    fn bind(self, params: &T) -> Result<Bound<Self::Incoming>> {
+        let path = match params.param() {
+            Some(UnixListenPath(path)) => path,
+            None => {
+                let (server, accept) = self.0.bind(params)?;
+                let accept = accept.map_ok(|(addrs, tcp)| (addrs, io::EitherIo::Left(tcp)));
+                return Ok((server, Box::pin(accept)));
+            }
+        };
+
+        // Replace a socket that was left behind by a previous process.
+        if let Ok(meta) = std::fs::symlink_metadata(&path) {
+            use std::os::unix::fs::FileTypeExt;
+            if meta.file_type().is_socket() {
+                debug!(path = %path.display(), "Removing stale socket");
+                std::fs::remove_file(&path)?;
+            }
+        }
+        let listen = tokio::net::UnixListener::bind(&path)?;
+
+        // Unix sockets don't have IP addresses, but they are necessarily
+        // local. The configured port is retained so that the server can be
+        // identified (e.g. by policy).
+        let ListenAddr(addr) = params.param();
+        let server = Local(ServerAddr((Ipv4Addr::LOCALHOST, addr.port()).into()));
+        let client = Remote(ClientAddr((Ipv4Addr::LOCALHOST, 0).into()));
+        let accept = UnixListenerStream::new(listen).map(move |res| {
+            let uds = res.map_err(AcceptError)?;
+            Ok((Addrs { server, client }, io::EitherIo::Right(uds)))
+        });
+
+        Ok((server, Box::pin(accept)))
+    }
+}
+
+#[cfg(not(unix))]
+impl<T> Bind<T> for BindUnixOrTcp
+where
+    T: Param<ListenAddr> + Param<Keepalive> + Param<Option<UnixListenPath>>,
+{
+    type Addrs = Addrs;
+    type Incoming = <BindTcp as Bind<T>>::Incoming;
+    type Io = TcpStream;
+
+    fn bind(self, params: &T) -> Result<Bound<Self::Incoming>> {
+        if let Some(UnixListenPath(path)) = params.param() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "cannot listen on {}: Unix domain sockets are not supported on this platform",
+                    path.display()
+                ),
+            )
+            .into());
+        }
+        self.0.bind(params)
+    }
+}
+
 // === impl Addrs ===
 
 impl Param<Remote<ClientAddr>> for Addrs {
@@ -0,0 +1,524 @@
+//! HAProxy PROXY protocol (v1 and v2) support.
+//!
+//! When the proxy is deployed behind an L4 load balancer, the load balancer
+//! may prefix each connection with a header that describes the original
+//! client and destination addresses. Headers are only read from connections
+//! that originate from trusted networks, since any client could otherwise
+//! spoof its address.
+//!
+//! A header's source replaces the connection's client address (i.e. its
+//! `Remote<ClientAddr>` and its I/O's peer address). The header's destination
+//! is exposed separately, as a [`ProxiedDstAddr`], since the connection is
+//! still routed by its own original destination address.
+//!
+//! See <https://www.haproxy.org/download/2.6/doc/proxy-protocol.txt>.
+
+use crate::{
+    addrs::*,
+    listen::{Bind, Bound},
+};
+use futures::prelude::*;
+use ipnet::IpNet;
+use linkerd_error::Result;
+use linkerd_io::{self as io, AsyncReadExt, AsyncWriteExt};
+use linkerd_stack::Param;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+    task::Context,
+    time::Duration,
+};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tracing::{debug, trace};
+
+/// The maximum length of a v1 header, including the `PROXY ` prefix and the
+/// trailing CRLF.
+const V1_MAX_LEN: usize = 107;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The maximum number of accepted connections whose headers may be read
+/// concurrently.
+const MAX_PENDING_HEADERS: usize = 1_000;
+
+/// Configures a listener to read PROXY protocol headers.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Networks from which headers are read. Connections from other addresses
+    /// are accepted without reading a header.
+    pub trusted_networks: Arc<[IpNet]>,
+
+    /// The amount of time to wait for a trusted connection's header.
+    pub header_timeout: Duration,
+}
+
+/// The original client and destination addresses of a proxied connection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+}
+
+/// Reads PROXY protocol headers from connections accepted by the inner
+/// listener, when the listener is configured to do so.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BindWithProxyProtocol<B> {
+    inner: B,
+}
+
+#[derive(Clone, Debug)]
+pub struct Addrs<A> {
+    pub inner: A,
+    pub header: Option<Header>,
+}
+
+/// The destination address described by a connection's PROXY protocol header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProxiedDstAddr(pub SocketAddr);
+
+/// An accepted connection whose peer address is the source described by its
+/// PROXY protocol header, if it has one.
+#[derive(Debug)]
+pub struct ProxiedIo {
+    io: TcpStream,
+    source: Option<SocketAddr>,
+}
+
+#[derive(Debug, Error)]
+#[error("invalid PROXY protocol header: {0}")]
+pub struct InvalidHeader(&'static str);
+
+#[derive(Debug, Error)]
+#[error("failed to read PROXY protocol header: {0}")]
+struct HeaderError(#[source] io::Error);
+
+#[derive(Debug, Error)]
+#[error("PROXY protocol header not received after {0:?}")]
+struct HeaderTimeout(Duration);
+
+// === impl Config ===
+
+impl Config {
+    fn is_trusted(&self, addr: IpAddr) -> bool {
+        self.trusted_networks.iter().any(|net| net.contains(&addr))
+    }
+}
+
+// === impl Header ===
+
+impl Header {
+    /// Reads a v1 or v2 header from the start of a connection.
+    ///
+    /// Returns `None` if the header does not describe a proxied connection
+    /// (i.e. a v1 `UNKNOWN` or v2 `LOCAL` header), in which case the
+    /// connection's own addresses should be used.
+    pub async fn read<I: io::AsyncRead + Unpin>(io: &mut I) -> io::Result<Option<Self>> {
+        let mut prefix = [0u8; 6];
+        io.read_exact(&mut prefix).await?;
+
+        if prefix == V1_PREFIX {
+            return Self::read_v1(io).await;
+        }
+
+        if prefix == V2_SIGNATURE[..6] {
+            let mut rest = [0u8; 10];
+            io.read_exact(&mut rest).await?;
+            if rest[..6] != V2_SIGNATURE[6..] {
+                return Err(invalid("bad signature"));
+            }
+            let len = u16::from_be_bytes([rest[8], rest[9]]);
+            let mut body = vec![0u8; len as usize];
+            io.read_exact(&mut body).await?;
+            return Self::decode_v2(rest[6], rest[7], &body);
+        }
+
+        Err(invalid("missing header"))
+    }
+
+    async fn read_v1<I: io::AsyncRead + Unpin>(io: &mut I) -> io::Result<Option<Self>> {
+        // The header is short and is only read from trusted peers, so it is
+        // read a byte at a time to avoid consuming any of the stream's data.
+        let mut line = Vec::with_capacity(V1_MAX_LEN - V1_PREFIX.len());
+        while !line.ends_with(b"\r\n") {
+            if line.len() == V1_MAX_LEN - V1_PREFIX.len() {
+                return Err(invalid("header too long"));
+            }
+            line.push(io.read_u8().await?);
+        }
+        line.truncate(line.len() - 2);
+        let line = std::str::from_utf8(&line).map_err(|_| invalid("not UTF-8"))?;
+        Self::decode_v1(line)
+    }
+
+    fn decode_v1(line: &str) -> io::Result<Option<Self>> {
+        let mut parts = line.split(' ');
+        let ip = |s: Option<&str>| -> io::Result<IpAddr> {
+            s.and_then(|s| s.parse().ok())
+                .ok_or_else(|| invalid("bad address"))
+        };
+        let port = |s: Option<&str>| -> io::Result<u16> {
+            s.and_then(|s| s.parse().ok())
+                .ok_or_else(|| invalid("bad port"))
+        };
+        match parts.next() {
+            Some("TCP4") | Some("TCP6") => {}
+            Some("UNKNOWN") => return Ok(None),
+            _ => return Err(invalid("unsupported protocol")),
+        }
+        let (src_ip, dst_ip) = (ip(parts.next())?, ip(parts.next())?);
+        let (src_port, dst_port) = (port(parts.next())?, port(parts.next())?);
+        if parts.next().is_some() {
+            return Err(invalid("trailing data"));
+        }
+        Ok(Some(Self {
+            source: SocketAddr::new(src_ip, src_port),
+            destination: SocketAddr::new(dst_ip, dst_port),
+        }))
+    }
+
+    fn decode_v2(version_command: u8, family: u8, body: &[u8]) -> io::Result<Option<Self>> {
+        if version_command >> 4 != 2 {
+            return Err(invalid("unsupported version"));
+        }
+        match version_command & 0xf {
+            // LOCAL connections are health checks, etc, from the balancer itself.
+            0 => return Ok(None),
+            1 => {}
+            _ => return Err(invalid("unsupported command")),
+        }
+
+        // Any TLVs that follow the addresses are ignored.
+        let port = |b: &[u8]| u16::from_be_bytes([b[0], b[1]]);
+        match family {
+            // TCP over IPv4
+            0x11 if body.len() >= 12 => {
+                let src: [u8; 4] = body[0..4].try_into().expect("slice must be 4 bytes");
+                let dst: [u8; 4] = body[4..8].try_into().expect("slice must be 4 bytes");
+                Ok(Some(Self {
+                    source: SocketAddr::new(Ipv4Addr::from(src).into(), port(&body[8..10])),
+                    destination: SocketAddr::new(Ipv4Addr::from(dst).into(), port(&body[10..12])),
+                }))
+            }
+            // TCP over IPv6
+            0x21 if body.len() >= 36 => {
+                let src: [u8; 16] = body[0..16].try_into().expect("slice must be 16 bytes");
+                let dst: [u8; 16] = body[16..32].try_into().expect("slice must be 16 bytes");
+                Ok(Some(Self {
+                    source: SocketAddr::new(Ipv6Addr::from(src).into(), port(&body[32..34])),
+                    destination: SocketAddr::new(Ipv6Addr::from(dst).into(), port(&body[34..36])),
+                }))
+            }
+            0x11 | 0x21 => Err(invalid("truncated addresses")),
+            // Other address families (e.g. UNIX sockets) don't describe a
+            // TCP client, so the connection's own addresses are used.
+            _ => Ok(None),
+        }
+    }
+
+    /// Encodes the header in the v2 (binary) format.
+    pub fn encode_v2(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(16 + 36);
+        buf.extend_from_slice(&V2_SIGNATURE);
+        // Version 2, PROXY command.
+        buf.push(0x21);
+        match (self.source.ip(), self.destination.ip()) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                buf.push(0x11);
+                buf.extend_from_slice(&12u16.to_be_bytes());
+                buf.extend_from_slice(&src.octets());
+                buf.extend_from_slice(&dst.octets());
+            }
+            (src, dst) => {
+                let v6 = |ip: IpAddr| match ip {
+                    IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+                    IpAddr::V6(ip) => ip,
+                };
+                buf.push(0x21);
+                buf.extend_from_slice(&36u16.to_be_bytes());
+                buf.extend_from_slice(&v6(src).octets());
+                buf.extend_from_slice(&v6(dst).octets());
+            }
+        }
+        buf.extend_from_slice(&self.source.port().to_be_bytes());
+        buf.extend_from_slice(&self.destination.port().to_be_bytes());
+        buf
+    }
+
+    /// Writes the header to the start of a connection in the v2 format.
+    pub async fn write<I: io::AsyncWrite + Unpin>(&self, io: &mut I) -> io::Result<usize> {
+        let buf = self.encode_v2();
+        io.write_all(&buf).await?;
+        Ok(buf.len())
+    }
+}
+
+fn invalid(reason: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, InvalidHeader(reason))
+}
+
+// === impl BindWithProxyProtocol ===
+
+impl<B> From<B> for BindWithProxyProtocol<B> {
+    fn from(inner: B) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T, B> Bind<T> for BindWithProxyProtocol<B>
+where
+    T: Param<Option<Config>>,
+    B: Bind<T, Io = TcpStream> + 'static,
+    B::Addrs: Param<Remote<ClientAddr>>,
+{
+    type Addrs = Addrs<B::Addrs>;
+    type Io = ProxiedIo;
+    type Incoming =
+        Pin<Box<dyn Stream<Item = Result<(Self::Addrs, ProxiedIo)>> + Send + Sync + 'static>>;
+
+    fn bind(self, params: &T) -> Result<Bound<Self::Incoming>> {
+        let (addr, incoming) = self.inner.bind(params)?;
+
+        let config = match params.param() {
+            Some(config) => config,
+            None => {
+                let incoming = incoming.map_ok(|(inner, tcp)| {
+                    (
+                        Addrs {
+                            inner,
+                            header: None,
+                        },
+                        ProxiedIo::new(tcp, None),
+                    )
+                });
+                return Ok((addr, Box::pin(incoming)));
+            }
+        };
+
+        // Headers are read concurrently so that a slow peer does not prevent
+        // other connections from being accepted.
+        let incoming = incoming
+            .map(move |res| {
+                let config = config.clone();
+                async move {
+                    let (inner, mut tcp) = res?;
+                    let Remote(ClientAddr(client)) = inner.param();
+                    if !config.is_trusted(client.ip()) {
+                        trace!(%client, "Not reading PROXY protocol header from untrusted peer");
+                        return Ok::<_, linkerd_error::Error>((
+                            Addrs {
+                                inner,
+                                header: None,
+                            },
+                            ProxiedIo::new(tcp, None),
+                        ));
+                    }
+
+                    let header =
+                        tokio::time::timeout(config.header_timeout, Header::read(&mut tcp))
+                            .await
+                            .map_err(|_| HeaderTimeout(config.header_timeout))?
+                            .map_err(HeaderError)?;
+                    debug!(?header, "Read PROXY protocol header");
+                    let io = ProxiedIo::new(tcp, header.map(|h| h.source));
+                    Ok((Addrs { inner, header }, io))
+                }
+            })
+            .buffer_unordered(MAX_PENDING_HEADERS);
+
+        Ok((addr, Box::pin(incoming)))
+    }
+}
+
+// === impl Addrs ===
+
+impl<A> Param<Remote<ClientAddr>> for Addrs<A>
+where
+    A: Param<Remote<ClientAddr>>,
+{
+    fn param(&self) -> Remote<ClientAddr> {
+        match self.header {
+            Some(Header { source, .. }) => Remote(ClientAddr(source)),
+            None => self.inner.param(),
+        }
+    }
+}
+
+impl<A> Param<Local<ServerAddr>> for Addrs<A>
+where
+    A: Param<Local<ServerAddr>>,
+{
+    fn param(&self) -> Local<ServerAddr> {
+        self.inner.param()
+    }
+}
+
+impl<A> Param<OrigDstAddr> for Addrs<A>
+where
+    A: Param<OrigDstAddr>,
+{
+    fn param(&self) -> OrigDstAddr {
+        self.inner.param()
+    }
+}
+
+impl<A> Param<Option<ProxiedDstAddr>> for Addrs<A> {
+    fn param(&self) -> Option<ProxiedDstAddr> {
+        self.header
+            .map(|Header { destination, .. }| ProxiedDstAddr(destination))
+    }
+}
+
+// === impl ProxiedIo ===
+
+impl ProxiedIo {
+    fn new(io: TcpStream, source: Option<SocketAddr>) -> Self {
+        Self { io, source }
+    }
+}
+
+#[async_trait::async_trait]
+impl io::Peek for ProxiedIo {
+    async fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.io.peek(buf).await
+    }
+}
+
+impl io::PeerAddr for ProxiedIo {
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self.source {
+            Some(source) => Ok(source),
+            None => self.io.peer_addr(),
+        }
+    }
+}
+
+impl io::AsyncRead for ProxiedIo {
+    #[inline]
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl io::AsyncWrite for ProxiedIo {
+    #[inline]
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> io::Poll<usize> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(mut buf: &[u8]) -> io::Result<Option<Header>> {
+        Header::read(&mut buf).await
+    }
+
+    #[tokio::test]
+    async fn reads_v1_headers() {
+        let header = read(b"PROXY TCP4 192.0.2.1 198.51.100.2 56324 443\r\nGET /")
+            .await
+            .unwrap();
+        assert_eq!(
+            header,
+            Some(Header {
+                source: ([192, 0, 2, 1], 56324).into(),
+                destination: ([198, 51, 100, 2], 443).into(),
+            })
+        );
+
+        assert_eq!(read(b"PROXY UNKNOWN\r\n").await.unwrap(), None);
+        assert!(read(b"PROXY TCP4 192.0.2.1 198.51.100.2 56324\r\n")
+            .await
+            .is_err());
+        assert!(read(b"GET / HTTP/1.1\r\n").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn v2_headers_roundtrip() {
+        for header in [
+            Header {
+                source: ([192, 0, 2, 1], 56324).into(),
+                destination: ([198, 51, 100, 2], 443).into(),
+            },
+            Header {
+                source: "[2001:db8::1]:56324".parse().unwrap(),
+                destination: "[2001:db8::2]:443".parse().unwrap(),
+            },
+        ] {
+            let mut buf = header.encode_v2();
+            buf.extend_from_slice(b"GET /");
+            let mut io = &buf[..];
+            assert_eq!(Header::read(&mut io).await.unwrap(), Some(header));
+            assert_eq!(io, b"GET /", "data after the header must not be consumed");
+        }
+    }
+
+    #[test]
+    fn addrs_keep_the_original_destination() {
+        let orig_dst = OrigDstAddr(([10, 0, 0, 2], 8080).into());
+        let header = Header {
+            source: ([192, 0, 2, 1], 56324).into(),
+            destination: ([198, 51, 100, 2], 443).into(),
+        };
+        let addrs = Addrs {
+            inner: crate::orig_dst::Addrs {
+                inner: crate::listen::Addrs {
+                    server: Local(ServerAddr(([10, 0, 0, 2], 4140).into())),
+                    client: Remote(ClientAddr(([10, 0, 0, 1], 40000).into())),
+                },
+                orig_dst,
+            },
+            header: Some(header),
+        };
+
+        assert_eq!(Param::<OrigDstAddr>::param(&addrs), orig_dst);
+        assert_eq!(
+            Param::<Option<ProxiedDstAddr>>::param(&addrs),
+            Some(ProxiedDstAddr(header.destination))
+        );
+        assert_eq!(
+            Param::<Remote<ClientAddr>>::param(&addrs),
+            Remote(ClientAddr(header.source))
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_v2_local_headers() {
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+        assert_eq!(read(&buf).await.unwrap(), None);
+    }
+}
@@ -1,9 +1,14 @@
 use super::Meta;
+use linkerd_identity::SpiffeId;
 use std::{collections::BTreeSet, sync::Arc};
 
+mod jwt;
 mod network;
 
-pub use self::network::Network;
+pub use self::{
+    jwt::{ClaimMatch, InvalidJwt, Jwks, Jwt},
+    network::Network,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Authorization {
@@ -19,7 +24,21 @@ pub enum Authentication {
     TlsAuthenticated {
         identities: BTreeSet<String>,
         suffixes: Vec<Suffix>,
+
+        /// Matches clients with SPIFFE IDs (i.e. URI SANs) whose paths are
+        /// nested under a prefix.
+        ///
+        /// A client certificate with a SPIFFE ID is identified only by it, so
+        /// such clients are never matched by their DNS SANs.
+        ///
+        /// The policy API has no field for prefixes, so they are discovered as
+        /// identities that end with [`SPIFFE_PREFIX_WILDCARD`].
+        spiffe_prefixes: Vec<SpiffePrefix>,
     },
+
+    /// Requests must present a bearer token that is validated against a JWKS.
+    /// This only applies to HTTP routes.
+    Jwt(Jwt),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -27,6 +46,24 @@ pub struct Suffix {
     ends_with: String,
 }
 
+/// Matches SPIFFE IDs in `trust_domain` whose paths are `path` or are nested
+/// under it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpiffePrefix {
+    pub trust_domain: String,
+    pub path: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid SPIFFE ID prefix: {0:?}")]
+pub struct InvalidSpiffePrefix(String);
+
+/// Marks a discovered identity like `spiffe://example.org/ns/web/*` as a
+/// [`SpiffePrefix`] rather than an exact identity.
+///
+/// TODO Update the API to describe SPIFFE ID prefixes explicitly.
+pub const SPIFFE_PREFIX_WILDCARD: &str = "/*";
+
 // === impl Suffix ===
 
 impl From<Vec<String>> for Suffix {
@@ -45,6 +82,32 @@ impl Suffix {
     pub fn contains(&self, name: &str) -> bool {
         name.ends_with(&self.ends_with)
     }
+
+    /// Returns true if this suffix matches all names.
+    #[inline]
+    pub fn is_any(&self) -> bool {
+        self.ends_with.is_empty()
+    }
+}
+
+// === impl SpiffePrefix ===
+
+impl std::str::FromStr for SpiffePrefix {
+    type Err = InvalidSpiffePrefix;
+
+    /// Parses a prefix like `spiffe://example.org/ns/web`.
+    ///
+    /// Prefixes must be valid SPIFFE IDs, so empty, relative, and trailing
+    /// path segments are rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = s
+            .parse::<SpiffeId>()
+            .map_err(|_| InvalidSpiffePrefix(s.to_string()))?;
+        Ok(Self {
+            trust_domain: id.trust_domain().to_string(),
+            path: id.path().to_string(),
+        })
+    }
 }
 
 #[cfg(feature = "proto")]
@@ -69,6 +132,9 @@ pub mod proto {
 
         #[error("invalid label: {0}")]
         Meta(#[from] InvalidMeta),
+
+        #[error("{0}")]
+        SpiffePrefix(#[from] InvalidSpiffePrefix),
     }
 
     pub(crate) fn mk_authorizations(
@@ -121,11 +187,23 @@ pub mod proto {
                                 Authentication::TlsUnauthenticated
                             }
                             api::authn::permit_mesh_tls::Clients::Identities(ids) => {
-                                let identities = ids
-                                    .identities
-                                    .into_iter()
-                                    .map(|api::Identity { name }| name)
-                                    .collect();
+                                // The policy API does not describe SPIFFE ID
+                                // prefixes, so identities like
+                                // `spiffe://example.org/ns/web/*` are
+                                // interpreted as prefixes.
+                                let mut identities = BTreeSet::new();
+                                let mut spiffe_prefixes = Vec::new();
+                                for api::Identity { name } in ids.identities {
+                                    match name
+                                        .strip_suffix(SPIFFE_PREFIX_WILDCARD)
+                                        .filter(|p| p.starts_with("spiffe://"))
+                                    {
+                                        Some(prefix) => spiffe_prefixes.push(prefix.parse()?),
+                                        None => {
+                                            identities.insert(name);
+                                        }
+                                    }
+                                }
                                 let suffixes = ids
                                     .suffixes
                                     .into_iter()
@@ -134,6 +212,7 @@ pub mod proto {
                                 Authentication::TlsAuthenticated {
                                     identities,
                                     suffixes,
+                                    spiffe_prefixes,
                                 }
                             }
                         }
@@ -158,3 +237,33 @@ pub mod proto {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_spiffe_prefixes() {
+        let p = "spiffe://example.org/ns/web"
+            .parse::<SpiffePrefix>()
+            .unwrap();
+        assert_eq!(p.trust_domain, "example.org");
+        assert_eq!(p.path, "/ns/web");
+
+        let p = "spiffe://example.org".parse::<SpiffePrefix>().unwrap();
+        assert_eq!(p.trust_domain, "example.org");
+        assert_eq!(p.path, "");
+
+        for p in &[
+            "example.org/ns/web",
+            "spiffe://",
+            "spiffe://example.org/",
+            "spiffe://example.org/ns/web/",
+            "spiffe://example.org//web",
+            "spiffe://example.org/ns/./web",
+            "spiffe://example.org/ns/..",
+        ] {
+            assert!(p.parse::<SpiffePrefix>().is_err(), "{} must be invalid", p);
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use jsonwebtoken::{
+    jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use parking_lot::Mutex;
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tokio::{
+    sync::watch,
+    time::{self, Duration},
+};
+
+/// Authenticates HTTP requests that present a bearer JWT signed by a key in a
+/// locally-configured JWKS.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Jwt {
+    pub jwks: Jwks,
+
+    /// If non-empty, the token's `iss` claim must be one of these values.
+    pub issuers: BTreeSet<String>,
+
+    /// If non-empty, the token's `aud` claim must include one of these values.
+    pub audiences: BTreeSet<String>,
+
+    /// Additional claims that the token must include.
+    pub claims: Vec<ClaimMatch>,
+}
+
+/// Requires that a token's claim has one of the given values.
+///
+/// When the claim is an array, any of its elements may match.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ClaimMatch {
+    pub name: String,
+    pub values: BTreeSet<String>,
+}
+
+/// A JSON Web Key Set that is read from a file.
+///
+/// The file is checked for changes every `RELOAD_INTERVAL` by a background
+/// task, so that keys may be rotated without a policy update. If the file
+/// cannot be read or parsed, the previously-loaded keys continue to be used.
+#[derive(Clone, Debug)]
+pub struct Jwks {
+    path: Arc<Path>,
+    keys: watch::Receiver<Arc<Vec<Jwk>>>,
+}
+
+type KeysTx = Arc<watch::Sender<Arc<Vec<Jwk>>>>;
+
+/// Key sets that are being loaded in the background, by path, so that all
+/// policies that reference a file share a single reload task.
+static LOADERS: Mutex<Option<HashMap<Arc<Path>, KeysTx>>> = parking_lot::const_mutex(None);
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidJwt {
+    #[error("missing bearer token")]
+    MissingToken,
+
+    #[error("no key found for token")]
+    UnknownKey,
+
+    #[error("token algorithm {0:?} does not match its key")]
+    Algorithm(Algorithm),
+
+    #[error("invalid token: {0}")]
+    Token(#[from] jsonwebtoken::errors::Error),
+
+    #[error("token does not include a permitted {0:?} claim")]
+    Claim(String),
+}
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+// === impl Jwt ===
+
+impl Jwt {
+    /// Validates the request's bearer token, returning an error if it is not
+    /// signed by a known key or does not satisfy the required claims.
+    pub fn authenticate(&self, headers: &http::HeaderMap) -> Result<(), InvalidJwt> {
+        let token = bearer_token(headers).ok_or(InvalidJwt::MissingToken)?;
+        let header = jsonwebtoken::decode_header(token)?;
+
+        // If the token names a key, only that key is used; otherwise, each key
+        // in the set is tried.
+        let mut claims = Err(InvalidJwt::UnknownKey);
+        for jwk in self.jwks.keys().iter() {
+            let matches_kid = match (&header.kid, &jwk.common.key_id) {
+                (Some(kid), Some(id)) => kid == id,
+                (Some(_), None) => false,
+                (None, _) => true,
+            };
+            if !matches_kid {
+                continue;
+            }
+
+            // The algorithm is determined by the key and never by the token,
+            // so that a token cannot select a weaker algorithm than the key's.
+            let alg = match key_algorithm(jwk) {
+                Some(alg) if alg == header.alg => alg,
+                _ => {
+                    claims = Err(InvalidJwt::Algorithm(header.alg));
+                    continue;
+                }
+            };
+            let key = match DecodingKey::from_jwk(jwk) {
+                Ok(key) => key,
+                Err(_) => continue,
+            };
+
+            let mut validation = Validation::new(alg);
+            if !self.issuers.is_empty() {
+                validation.set_issuer(&self.issuers.iter().collect::<Vec<_>>());
+            }
+            if !self.audiences.is_empty() {
+                validation.set_audience(&self.audiences.iter().collect::<Vec<_>>());
+            }
+            match jsonwebtoken::decode::<Map<String, Value>>(token, &key, &validation) {
+                Ok(data) => {
+                    claims = Ok(data.claims);
+                    break;
+                }
+                Err(e) => claims = Err(e.into()),
+            }
+        }
+        let claims = claims?;
+
+        for ClaimMatch { name, values } in &self.claims {
+            if !claim_matches(claims.get(name), values) {
+                return Err(InvalidJwt::Claim(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the algorithm that a key may be used with.
+///
+/// Keys that don't specify an algorithm are used with the conventional
+/// algorithm for their type.
+fn key_algorithm(jwk: &Jwk) -> Option<Algorithm> {
+    if let Some(alg) = jwk.common.algorithm {
+        return Some(alg);
+    }
+    match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => Some(Algorithm::RS256),
+        AlgorithmParameters::EllipticCurve(ec) => match ec.curve {
+            EllipticCurve::P256 => Some(Algorithm::ES256),
+            EllipticCurve::P384 => Some(Algorithm::ES384),
+            _ => None,
+        },
+        AlgorithmParameters::OctetKeyPair(okp) => match okp.curve {
+            EllipticCurve::Ed25519 => Some(Algorithm::EdDSA),
+            _ => None,
+        },
+        AlgorithmParameters::OctetKey(_) => Some(Algorithm::HS256),
+    }
+}
+
+fn bearer_token(headers: &http::HeaderMap) -> Option<&str> {
+    let auth = headers.get(http::header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = auth.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let token = token.trim();
+    if token.is_empty() {
+        return None;
+    }
+    Some(token)
+}
+
+fn claim_matches(claim: Option<&Value>, values: &BTreeSet<String>) -> bool {
+    match claim {
+        Some(Value::String(s)) => values.contains(s),
+        Some(Value::Array(claims)) => claims.iter().any(|c| claim_matches(Some(c), values)),
+        Some(v @ Value::Bool(_)) | Some(v @ Value::Number(_)) => values.contains(&v.to_string()),
+        Some(Value::Null) | Some(Value::Object(_)) | None => false,
+    }
+}
+
+// === impl Jwks ===
+
+impl Jwks {
+    /// Loads the key set at `path`.
+    ///
+    /// When called within a Tokio runtime, the key set is loaded by a
+    /// background task that reloads it as it changes. This task is shared by
+    /// all `Jwks` with the same path and completes once they have all been
+    /// dropped. Until the file has been loaded, no tokens are valid.
+    ///
+    /// Outside of a runtime, the file is loaded once, immediately.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path: Arc<Path> = path.into().into();
+        let rt = match tokio::runtime::Handle::try_current() {
+            Ok(rt) => rt,
+            Err(_) => {
+                let (_, keys) = load(&path, None).unwrap_or_default();
+                let (_, rx) = watch::channel(Arc::new(keys));
+                return Self { path, keys: rx };
+            }
+        };
+
+        let mut loaders = LOADERS.lock();
+        let loaders = loaders.get_or_insert_with(HashMap::new);
+        if let Some(tx) = loaders.get(&path) {
+            let keys = tx.subscribe();
+            return Self { path, keys };
+        }
+
+        let (tx, rx) = watch::channel(Arc::new(Vec::new()));
+        let tx = Arc::new(tx);
+        loaders.insert(path.clone(), tx.clone());
+        rt.spawn(reload(path.clone(), tx));
+        Self { path, keys: rx }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the most recently loaded key set.
+    fn keys(&self) -> Arc<Vec<Jwk>> {
+        self.keys.borrow().clone()
+    }
+}
+
+/// Loads the key set and reloads it whenever the file's modification time
+/// changes.
+///
+/// File I/O is performed on the blocking thread pool.
+async fn reload(path: Arc<Path>, tx: KeysTx) {
+    let mut modified = None;
+    let mut interval = time::interval(RELOAD_INTERVAL);
+    interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    loop {
+        // The first tick completes immediately.
+        interval.tick().await;
+        {
+            // New key sets are only subscribed while the lock is held, so the
+            // task cannot complete while another `Jwks` starts using it.
+            let mut loaders = LOADERS.lock();
+            if tx.receiver_count() == 0 {
+                tracing::trace!(path = %path.display(), "JWKS dropped");
+                if let Some(loaders) = loaders.as_mut() {
+                    loaders.remove(&path);
+                }
+                return;
+            }
+        }
+
+        let p = path.clone();
+        match tokio::task::spawn_blocking(move || load(&p, modified)).await {
+            Ok(Some((m, keys))) => {
+                modified = m;
+                let _ = tx.send(Arc::new(keys));
+            }
+            Ok(None) => {}
+            Err(error) => {
+                tracing::warn!(path = %path.display(), %error, "JWKS reload task failed");
+            }
+        }
+    }
+}
+
+/// Loads the key set if its modification time differs from `prior`.
+///
+/// Returns `None` if the file is unchanged or cannot be loaded.
+fn load(path: &Path, prior: Option<SystemTime>) -> Option<(Option<SystemTime>, Vec<Jwk>)> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    if modified.is_some() && modified == prior {
+        tracing::trace!(path = %path.display(), "JWKS unchanged");
+        return None;
+    }
+
+    let read = || -> Result<JwkSet, Box<dyn std::error::Error + Send + Sync>> {
+        let buf = std::fs::read(path)?;
+        Ok(serde_json::from_slice(&buf)?)
+    };
+    match read() {
+        Ok(JwkSet { keys }) => {
+            tracing::debug!(path = %path.display(), keys = keys.len(), "Loaded JWKS");
+            Some((modified, keys))
+        }
+        Err(error) => {
+            tracing::warn!(path = %path.display(), %error, "Failed to load JWKS");
+            None
+        }
+    }
+}
+
+impl PartialEq for Jwks {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for Jwks {}
+
+impl Hash for Jwks {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bearer_token() {
+        let mut headers = http::HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(
+            http::header::AUTHORIZATION,
+            "Basic Zm9vOmJhcg==".parse().unwrap(),
+        );
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(http::header::AUTHORIZATION, "Bearer  ".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(http::header::AUTHORIZATION, "bearer a.b.c".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("a.b.c"));
+    }
+
+    #[test]
+    fn matches_claims() {
+        let values = ["admin", "true", "42"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<BTreeSet<_>>();
+
+        assert!(claim_matches(Some(&Value::from("admin")), &values));
+        assert!(!claim_matches(Some(&Value::from("user")), &values));
+        assert!(claim_matches(Some(&Value::from(true)), &values));
+        assert!(claim_matches(Some(&Value::from(42)), &values));
+        assert!(claim_matches(
+            Some(&Value::from(vec!["user", "admin"])),
+            &values
+        ));
+        assert!(!claim_matches(Some(&Value::from(vec!["user"])), &values));
+        assert!(!claim_matches(Some(&Value::Null), &values));
+        assert!(!claim_matches(None, &values));
+    }
+
+    #[test]
+    fn missing_jwks() {
+        let jwt = Jwt {
+            jwks: Jwks::new("/dev/null/jwks.json"),
+            issuers: Default::default(),
+            audiences: Default::default(),
+            claims: vec![],
+        };
+        let mut headers = http::HeaderMap::new();
+        assert!(matches!(
+            jwt.authenticate(&headers),
+            Err(InvalidJwt::MissingToken)
+        ));
+
+        // An unsigned token with an unknown key is rejected.
+        headers.insert(
+            http::header::AUTHORIZATION,
+            "Bearer eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCIsImtpZCI6InRlc3QifQ.e30.c2ln"
+                .parse()
+                .unwrap(),
+        );
+        assert!(matches!(
+            jwt.authenticate(&headers),
+            Err(InvalidJwt::UnknownKey)
+        ));
+    }
+
+    const TEST_KEY: &str = r#"{"kty": "oct", "kid": "test", "alg": "HS256", "k": "c2VjcmV0"}"#;
+
+    fn jwks_file(jwk: &str) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), format!("{{\"keys\": [{}]}}", jwk)).unwrap();
+        file
+    }
+
+    fn jwt_with_key(jwk: &str) -> Jwt {
+        let file = jwks_file(jwk);
+        let jwks = Jwks::new(file.path());
+        Jwt {
+            jwks,
+            issuers: Some("issuer".to_string()).into_iter().collect(),
+            audiences: Default::default(),
+            claims: vec![ClaimMatch {
+                name: "role".to_string(),
+                values: Some("admin".to_string()).into_iter().collect(),
+            }],
+        }
+    }
+
+    fn bearer(alg: Algorithm, claims: Value) -> http::HeaderMap {
+        let header = jsonwebtoken::Header {
+            kid: Some("test".to_string()),
+            ..jsonwebtoken::Header::new(alg)
+        };
+        let key = jsonwebtoken::EncodingKey::from_secret(b"secret");
+        let token = jsonwebtoken::encode(&header, &claims, &key).unwrap();
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            http::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn validates_signed_token() {
+        // The key is the base64url-encoding of "secret".
+        let jwt = jwt_with_key(TEST_KEY);
+
+        let claims = serde_json::json!({
+            "iss": "issuer",
+            "exp": 4_000_000_000u64,
+            "role": "admin",
+        });
+        jwt.authenticate(&bearer(Algorithm::HS256, claims))
+            .expect("token must be valid");
+
+        let claims = serde_json::json!({
+            "iss": "issuer",
+            "exp": 4_000_000_000u64,
+            "role": "user",
+        });
+        assert!(matches!(
+            jwt.authenticate(&bearer(Algorithm::HS256, claims)),
+            Err(InvalidJwt::Claim(_))
+        ));
+
+        let claims = serde_json::json!({
+            "iss": "other",
+            "exp": 4_000_000_000u64,
+            "role": "admin",
+        });
+        assert!(matches!(
+            jwt.authenticate(&bearer(Algorithm::HS256, claims)),
+            Err(InvalidJwt::Token(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_mismatched_algorithm() {
+        let jwt = jwt_with_key(TEST_KEY);
+        let claims = serde_json::json!({
+            "iss": "issuer",
+            "exp": 4_000_000_000u64,
+            "role": "admin",
+        });
+        assert!(matches!(
+            jwt.authenticate(&bearer(Algorithm::HS384, claims)),
+            Err(InvalidJwt::Algorithm(Algorithm::HS384))
+        ));
+    }
+    #[tokio::test(flavor = "current_thread")]
+    async fn shares_loaders() {
+        let file = jwks_file(TEST_KEY);
+        let a = Jwks::new(file.path());
+        let b = Jwks::new(file.path());
+        assert!(a.keys().is_empty(), "keys must be loaded in the background");
+
+        let mut keys = a.keys.clone();
+        keys.changed().await.expect("keys must be loaded");
+        assert_eq!(a.keys().len(), 1);
+        assert_eq!(b.keys().len(), 1);
+
+        let loaders = LOADERS.lock();
+        let tx = loaders
+            .as_ref()
+            .and_then(|l| l.get(file.path()))
+            .expect("key set must be loading");
+        assert_eq!(tx.receiver_count(), 3, "loader must be shared");
+    }
+}
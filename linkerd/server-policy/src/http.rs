@@ -7,12 +7,35 @@ pub type Rule = http::Rule<Policy>;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Filter {
+    InjectDelay(filter::InjectDelay),
     InjectFailure(filter::InjectFailure),
     Redirect(filter::RedirectRequest),
+    RequestMirror(filter::RequestMirror),
+    RewriteUrl(filter::RewriteUrl),
     RequestHeaders(filter::ModifyHeader),
+    ResponseHeaders(std::sync::Arc<filter::ModifyHeader>),
+    RateLimit(crate::RateLimit),
     InternalError(&'static str),
 }
 
+impl crate::rate_limit::RateLimitFilter for Filter {
+    #[inline]
+    fn rate_limit(&self) -> Option<&crate::RateLimit> {
+        match self {
+            Self::RateLimit(rl) => Some(rl),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn rate_limit_mut(&mut self) -> Option<&mut crate::RateLimit> {
+        match self {
+            Self::RateLimit(rl) => Some(rl),
+            _ => None,
+        }
+    }
+}
+
 #[inline]
 pub fn find<'r, B>(
     routes: &'r [Route],
@@ -30,6 +53,7 @@ pub fn default(authorizations: std::sync::Arc<[crate::Authorization]>) -> Route
                 meta: crate::Meta::new_default("default"),
                 authorizations,
                 filters: vec![],
+                timeouts: Default::default(),
             },
         }],
     }
@@ -138,6 +162,10 @@ pub mod proto {
                 authorizations,
                 filters,
                 meta,
+                // TODO Update the API to include route timeouts. Until then,
+                // timeouts may only be configured by a policy file and
+                // discovered routes never time out.
+                timeouts: Default::default(),
             }
         };
 
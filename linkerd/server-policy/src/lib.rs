@@ -7,10 +7,12 @@ pub mod authz;
 pub mod grpc;
 pub mod http;
 pub mod meta;
+pub mod rate_limit;
 
 pub use self::{
     authz::{Authentication, Authorization},
     meta::Meta,
+    rate_limit::{RateLimit, RateLimitKey},
 };
 pub use linkerd_http_route as route;
 
@@ -18,6 +20,14 @@ pub use linkerd_http_route as route;
 pub struct ServerPolicy {
     pub protocol: Protocol,
     pub meta: Arc<Meta>,
+
+    /// When true, authorization decisions are recorded but not enforced:
+    /// requests and connections that would have been denied are logged,
+    /// counted as audit denials (rather than as allowed), and allowed.
+    ///
+    /// The policy API cannot yet express this, so only policies read from a
+    /// file may be audited; discovered policies are always enforced.
+    pub audit: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -39,9 +49,40 @@ pub struct RoutePolicy<T> {
     pub meta: Arc<Meta>,
     pub authorizations: Arc<[Authorization]>,
     pub filters: Vec<T>,
+    pub timeouts: RouteTimeouts,
+}
+
+/// Bounds the amount of time spent processing requests on a route.
+///
+/// The policy API does not yet describe route timeouts, so these may only be
+/// configured by a policy file.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RouteTimeouts {
+    /// The maximum amount of time to wait for a response's headers after a
+    /// request has been dispatched.
+    pub request: Option<time::Duration>,
+
+    /// The maximum amount of time a response stream may remain idle between
+    /// body frames.
+    pub idle: Option<time::Duration>,
 }
 
 impl ServerPolicy {
+    /// Carries rate limit buckets over from a prior version of this server's
+    /// policy so that unchanged limits aren't reset by policy updates.
+    pub fn inherit_rate_limits(&mut self, prior: &Self) {
+        if let (Some(http), Some(prior)) = (
+            self.protocol.http_routes_mut(),
+            prior.protocol.http_routes(),
+        ) {
+            *http = rate_limit::inherit_routes(http, prior);
+        }
+        if let (Protocol::Grpc(grpc), Protocol::Grpc(prior)) = (&mut self.protocol, &prior.protocol)
+        {
+            *grpc = rate_limit::inherit_routes(grpc, prior);
+        }
+    }
+
     pub fn invalid(timeout: time::Duration) -> Self {
         let meta = Arc::new(Meta::Default {
             name: "invalid".into(),
@@ -60,11 +101,31 @@ impl ServerPolicy {
                             filters: vec![http::Filter::InternalError(
                                 "invalid server configuration",
                             )],
+                            timeouts: RouteTimeouts::default(),
                         },
                     }],
                 }]),
                 tcp_authorizations: Arc::new([]),
             },
+            audit: false,
+        }
+    }
+}
+
+// === impl Protocol ===
+
+impl Protocol {
+    fn http_routes(&self) -> Option<&Arc<[http::Route]>> {
+        match self {
+            Self::Detect { http, .. } | Self::Http1(http) | Self::Http2(http) => Some(http),
+            _ => None,
+        }
+    }
+
+    fn http_routes_mut(&mut self) -> Option<&mut Arc<[http::Route]>> {
+        match self {
+            Self::Detect { http, .. } | Self::Http1(http) | Self::Http2(http) => Some(http),
+            _ => None,
         }
     }
 }
@@ -184,7 +245,14 @@ pub mod proto {
             // avoid label inference.
             let meta = Meta::try_new_with_default(labels, "policy.linkerd.io", "server")?;
 
-            Ok(ServerPolicy { protocol, meta })
+            // TODO Update the API to indicate whether a server's
+            // authorizations should only be audited. Until then, discovered
+            // policies are always enforced.
+            Ok(ServerPolicy {
+                protocol,
+                meta,
+                audit: false,
+            })
         }
     }
 }
@@ -0,0 +1,358 @@
+use crate::RoutePolicy;
+use linkerd_http_route::{Route, Rule};
+use parking_lot::Mutex;
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hash, Hasher},
+    net::IpAddr,
+    num::NonZeroU32,
+    sync::Arc,
+};
+use tokio::time::Instant;
+
+/// A filter that limits the rate of requests on a route with a token bucket.
+///
+/// Each bucket holds up to `burst` tokens and is refilled at `per_second`
+/// tokens per second. A request consumes a single token and is rejected when
+/// its bucket is empty.
+///
+/// Buckets are owned by the filter. When a policy update replaces the route's
+/// filters, [`RateLimit::inherit`] carries the buckets over to an unchanged
+/// limit.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    pub per_second: NonZeroU32,
+    pub burst: NonZeroU32,
+    pub key: RateLimitKey,
+    buckets: Arc<Buckets>,
+}
+
+/// Determines how requests are grouped into buckets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// All requests on the route share a single bucket.
+    Route,
+
+    /// Each authenticated client identity has its own bucket. Clients without
+    /// an identity are limited by their IP address.
+    ClientIdentity,
+
+    /// Each client IP address has its own bucket.
+    ClientIp,
+}
+
+/// Filters that may hold a [`RateLimit`].
+pub(crate) trait RateLimitFilter {
+    fn rate_limit(&self) -> Option<&RateLimit>;
+
+    fn rate_limit_mut(&mut self) -> Option<&mut RateLimit>;
+}
+
+/// Buckets are spread over independently locked shards so that clients
+/// contend only with the clients that hash to the same shard.
+#[derive(Debug, Default)]
+struct Buckets {
+    hasher: RandomState,
+    shards: [Mutex<Shard>; SHARDS],
+}
+
+/// An approximate LRU: buckets are inserted into the active generation and,
+/// when it fills up, the active generation replaces the inactive one, evicting
+/// every bucket that has not been used since the last rotation. Buckets found
+/// in the inactive generation are promoted into the active one.
+///
+/// Every operation is O(1), so clients that cycle through keys can't force
+/// an expensive eviction scan.
+#[derive(Debug, Default)]
+struct Shard {
+    active: HashMap<BucketKey, Bucket>,
+    inactive: HashMap<BucketKey, Bucket>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum BucketKey {
+    Route,
+    Identity(String),
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// The maximum number of per-client buckets retained.
+const MAX_BUCKETS: usize = 10_000;
+
+const SHARDS: usize = 16;
+
+/// The number of buckets in each generation of a shard.
+const SHARD_GENERATION_CAPACITY: usize = MAX_BUCKETS / SHARDS / 2;
+
+// === impl RateLimit ===
+
+impl RateLimit {
+    pub fn new(per_second: NonZeroU32, burst: NonZeroU32, key: RateLimitKey) -> Self {
+        Self {
+            per_second,
+            burst,
+            key,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Shares the buckets of a prior version of this limit, so that a policy
+    /// update doesn't reset clients' limits. Returns false, leaving this
+    /// limit's buckets in place, if the limits are configured differently.
+    pub fn inherit(&mut self, prior: &Self) -> bool {
+        if self != prior {
+            return false;
+        }
+        self.buckets = prior.buckets.clone();
+        true
+    }
+
+    /// Attempts to acquire a token for a request from the given client,
+    /// returning false if the request should be rejected.
+    pub fn check(&self, client_id: Option<&str>, client_ip: IpAddr) -> bool {
+        let key = match (self.key, client_id) {
+            (RateLimitKey::Route, _) => BucketKey::Route,
+            (RateLimitKey::ClientIdentity, Some(id)) => BucketKey::Identity(id.to_string()),
+            (RateLimitKey::ClientIdentity, None) | (RateLimitKey::ClientIp, _) => {
+                BucketKey::Ip(client_ip)
+            }
+        };
+
+        let now = Instant::now();
+        let burst = f64::from(self.burst.get());
+        let mut shard = self.buckets.shard(&key).lock();
+        let (bucket, evicted) = shard.get_or_insert(key, || Bucket {
+            tokens: burst,
+            updated_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * f64::from(self.per_second.get())).min(burst);
+        bucket.updated_at = now;
+
+        let permitted = bucket.tokens >= 1.0;
+        if permitted {
+            bucket.tokens -= 1.0;
+        }
+
+        // Release the lock before dropping any evicted buckets.
+        drop(shard);
+        drop(evicted);
+        permitted
+    }
+}
+
+/// Carries rate limit state from a prior version of a server's routes into
+/// updated routes.
+///
+/// Rules are identified by their route's hostnames and by their matches, and
+/// each rate limit inherits the buckets of an identically configured limit on
+/// the prior rule.
+pub(crate) fn inherit_routes<M, F>(
+    routes: &Arc<[Route<M, RoutePolicy<F>>]>,
+    prior: &[Route<M, RoutePolicy<F>>],
+) -> Arc<[Route<M, RoutePolicy<F>>]>
+where
+    M: Clone + PartialEq,
+    F: Clone + RateLimitFilter,
+{
+    if !routes
+        .iter()
+        .flat_map(|r| &r.rules)
+        .flat_map(|r| &r.policy.filters)
+        .any(|f| f.rate_limit().is_some())
+    {
+        return routes.clone();
+    }
+
+    routes
+        .iter()
+        .cloned()
+        .map(|mut route| {
+            let prior = match prior.iter().find(|p| p.hosts == route.hosts) {
+                Some(prior) => prior,
+                None => return route,
+            };
+            for rule in route.rules.iter_mut() {
+                if let Some(prior) = prior.rules.iter().find(|p| p.matches == rule.matches) {
+                    inherit_rule(rule, prior);
+                }
+            }
+            route
+        })
+        .collect()
+}
+
+fn inherit_rule<M, F: RateLimitFilter>(
+    rule: &mut Rule<M, RoutePolicy<F>>,
+    prior: &Rule<M, RoutePolicy<F>>,
+) {
+    for limit in rule
+        .policy
+        .filters
+        .iter_mut()
+        .filter_map(RateLimitFilter::rate_limit_mut)
+    {
+        let _ = prior
+            .policy
+            .filters
+            .iter()
+            .filter_map(RateLimitFilter::rate_limit)
+            .any(|p| limit.inherit(p));
+    }
+}
+
+// === impl Buckets ===
+
+impl Buckets {
+    fn shard(&self, key: &BucketKey) -> &Mutex<Shard> {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| {
+                let s = s.lock();
+                s.active.len() + s.inactive.len()
+            })
+            .sum()
+    }
+}
+
+// === impl Shard ===
+
+impl Shard {
+    /// Returns the bucket for the given key, along with any buckets evicted to
+    /// make room for it.
+    fn get_or_insert(
+        &mut self,
+        key: BucketKey,
+        mk: impl FnOnce() -> Bucket,
+    ) -> (&mut Bucket, Option<HashMap<BucketKey, Bucket>>) {
+        if self.active.contains_key(&key) {
+            return (self.active.get_mut(&key).expect("bucket must exist"), None);
+        }
+
+        let bucket = self.inactive.remove(&key).unwrap_or_else(mk);
+        let evicted = if self.active.len() >= SHARD_GENERATION_CAPACITY {
+            let active = std::mem::take(&mut self.active);
+            Some(std::mem::replace(&mut self.inactive, active))
+        } else {
+            None
+        };
+        (self.active.entry(key).or_insert(bucket), evicted)
+    }
+}
+
+impl PartialEq for RateLimit {
+    fn eq(&self, other: &Self) -> bool {
+        self.per_second == other.per_second && self.burst == other.burst && self.key == other.key
+    }
+}
+
+impl Eq for RateLimit {}
+
+impl Hash for RateLimit {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.per_second.hash(state);
+        self.burst.hash(state);
+        self.key.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::Duration;
+
+    fn limit(per_second: u32, burst: u32, key: RateLimitKey) -> RateLimit {
+        RateLimit::new(
+            NonZeroU32::new(per_second).unwrap(),
+            NonZeroU32::new(burst).unwrap(),
+            key,
+        )
+    }
+
+    const IP0: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 168, 1, 1));
+    const IP1: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 168, 1, 2));
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn refills_over_time() {
+        let rl = limit(2, 2, RateLimitKey::Route);
+        assert!(rl.check(None, IP0));
+        assert!(rl.check(None, IP1));
+        assert!(!rl.check(None, IP0), "bucket must be exhausted");
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert!(rl.check(None, IP0));
+        assert!(!rl.check(None, IP0), "bucket must be exhausted");
+
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(rl.check(None, IP0));
+        assert!(rl.check(None, IP0));
+        assert!(!rl.check(None, IP0), "refill must not exceed the burst");
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn keyed_by_client_ip() {
+        let rl = limit(1, 1, RateLimitKey::ClientIp);
+        let foo = "foo.ns.serviceaccount.identity.linkerd.cluster.local";
+        let bar = "bar.ns.serviceaccount.identity.linkerd.cluster.local";
+        assert!(rl.check(Some(foo), IP0));
+        assert!(!rl.check(Some(bar), IP0));
+        assert!(rl.check(None, IP1));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn keyed_by_client_identity() {
+        let rl = limit(1, 1, RateLimitKey::ClientIdentity);
+        let foo = "foo.ns.serviceaccount.identity.linkerd.cluster.local";
+        let bar = "bar.ns.serviceaccount.identity.linkerd.cluster.local";
+        assert!(rl.check(Some(foo), IP0));
+        assert!(!rl.check(Some(foo), IP1));
+        assert!(rl.check(Some(bar), IP0));
+
+        // Unauthenticated clients fall back to their IP address.
+        assert!(rl.check(None, IP0));
+        assert!(!rl.check(None, IP0));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn bounds_active_buckets() {
+        let rl = limit(1, 1, RateLimitKey::ClientIp);
+        let mut last = IP0;
+        for i in 0..(MAX_BUCKETS as u32 * 2) {
+            last = IpAddr::V4(std::net::Ipv4Addr::from(i));
+            assert!(rl.check(None, last));
+        }
+        assert!(rl.buckets.len() <= MAX_BUCKETS);
+
+        // The most recently used buckets are retained.
+        assert!(!rl.check(None, last));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn inherits_unchanged_limits() {
+        let prior = limit(1, 1, RateLimitKey::ClientIp);
+        assert!(prior.check(None, IP0));
+
+        let mut rl = limit(1, 1, RateLimitKey::ClientIp);
+        assert!(rl.inherit(&prior));
+        assert!(!rl.check(None, IP0), "bucket must be shared");
+
+        let mut rl = limit(2, 1, RateLimitKey::ClientIp);
+        assert!(!rl.inherit(&prior));
+        assert!(rl.check(None, IP0), "bucket must not be shared");
+    }
+}
@@ -1,10 +1,12 @@
 mod proxy;
 mod service;
 
+use linkerd_exp_backoff::ExponentialBackoff;
 use regex::Regex;
 use std::{
     fmt,
     hash::{Hash, Hasher},
+    num::NonZeroUsize,
     ops::Deref,
     sync::Arc,
     time::Duration,
@@ -28,6 +30,23 @@ pub enum RequestMatch {
     Not(Box<RequestMatch>),
     Path(Box<Regex>),
     Method(http::Method),
+    Header(http::header::HeaderName, ValueMatch),
+    QueryParam(String, ValueMatch),
+}
+
+/// Matches the value of a request header or query parameter.
+///
+/// A request matches if any of the header's (or query parameter's) values
+/// match.
+#[derive(Clone, Debug)]
+pub enum ValueMatch {
+    /// Matches if the header or query parameter is present, regardless of its
+    /// value.
+    Present,
+    Exact(String),
+
+    /// Matches values against a regex. The regex must match the entire value.
+    Regex(Box<Regex>),
 }
 
 #[derive(Clone, Debug)]
@@ -50,11 +69,40 @@ pub enum ResponseMatch {
     },
 }
 
+/// Configures how a route's failed requests are retried.
+///
+/// Retries are always limited by the budget. By default, requests are retried
+/// immediately, without a limit on the number of attempts.
 #[derive(Clone, Debug)]
 pub struct Retries {
     budget: Arc<Budget>,
+    backoff: Option<ExponentialBackoff>,
+    per_try_timeout: Option<Duration>,
+    max_attempts: Option<NonZeroUsize>,
+    retryable_grpc_codes: Option<Arc<[tonic::Code]>>,
+    max_buffered_bytes: Option<usize>,
+    hedge: Option<Hedge>,
 }
 
+/// Configures hedged requests for idempotent routes.
+///
+/// If a request has not received a response within the route's latency at the
+/// given percentile, a second request is issued to a different endpoint and
+/// whichever response is received first is used. Hedged requests are governed
+/// by the route's retry budget.
+///
+/// Only requests with idempotent methods (`GET`, `HEAD`, and `OPTIONS`) are
+/// hedged, unless the route is marked as idempotent.
+#[derive(Clone, Debug)]
+pub struct Hedge {
+    percentile: f64,
+    idempotent_route: bool,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("hedge percentile must be greater than 0 and at most 100: {0}")]
+pub struct InvalidHedge(f64);
+
 #[derive(Clone, Default)]
 struct Labels(Arc<std::collections::BTreeMap<String, String>>);
 
@@ -103,8 +151,8 @@ impl Route {
         self.timeout
     }
 
-    pub fn set_retries(&mut self, budget: Arc<Budget>) {
-        self.retries = Some(Retries { budget });
+    pub fn set_retries(&mut self, retries: impl Into<Retries>) {
+        self.retries = Some(retries.into());
     }
 
     pub fn set_timeout(&mut self, timeout: Duration) {
@@ -119,6 +167,17 @@ impl RequestMatch {
         match self {
             RequestMatch::Method(ref method) => req.method() == *method,
             RequestMatch::Path(ref re) => re.is_match(req.uri().path()),
+            RequestMatch::Header(ref name, ref value) => req
+                .headers()
+                .get_all(name)
+                .iter()
+                .any(|v| value.is_match_bytes(v.as_bytes())),
+            RequestMatch::QueryParam(ref name, ref value) => {
+                req.uri().query().map_or(false, |qs| {
+                    url::form_urlencoded::parse(qs.as_bytes())
+                        .any(|(n, v)| n == name.as_str() && value.is_match(&v))
+                })
+            }
             RequestMatch::Not(ref m) => !m.is_match(req),
             RequestMatch::All(ref ms) => ms.iter().all(|m| m.is_match(req)),
             RequestMatch::Any(ref ms) => ms.iter().any(|m| m.is_match(req)),
@@ -126,6 +185,45 @@ impl RequestMatch {
     }
 }
 
+// === impl ValueMatch ===
+
+impl ValueMatch {
+    /// Builds a regex match that is anchored to the start and end of a value.
+    pub fn regex(regex: &str) -> Result<Self, regex::Error> {
+        anchored_regex(regex).map(|re| Self::Regex(Box::new(re)))
+    }
+
+    pub(crate) fn is_match(&self, value: &str) -> bool {
+        match self {
+            Self::Present => true,
+            Self::Exact(ref v) => v == value,
+            Self::Regex(ref re) => re.is_match(value),
+        }
+    }
+
+    pub(crate) fn is_match_bytes(&self, value: &[u8]) -> bool {
+        match self {
+            Self::Present => true,
+            Self::Exact(ref v) => v.as_bytes() == value,
+            Self::Regex(ref re) => std::str::from_utf8(value).map_or(false, |v| re.is_match(v)),
+        }
+    }
+}
+
+/// Compiles a regex so that it must match an entire input, adding anchors if
+/// they are not already present.
+pub(crate) fn anchored_regex(regex: &str) -> Result<Regex, regex::Error> {
+    let regex = regex.trim();
+    match (regex.starts_with('^'), regex.ends_with('$')) {
+        (true, true) => Regex::new(regex),
+        (hd_anchor, tl_anchor) => {
+            let hd = if hd_anchor { "" } else { "^" };
+            let tl = if tl_anchor { "" } else { "$" };
+            Regex::new(&format!("{}{}{}", hd, regex, tl))
+        }
+    }
+}
+
 // === impl ResponseClass ===
 
 impl ResponseClass {
@@ -190,14 +288,121 @@ impl ResponseMatch {
 // === impl Retries ===
 
 impl Retries {
+    pub fn new(budget: Arc<Budget>) -> Self {
+        Self {
+            budget,
+            backoff: None,
+            per_try_timeout: None,
+            max_attempts: None,
+            retryable_grpc_codes: None,
+            max_buffered_bytes: None,
+            hedge: None,
+        }
+    }
+
+    /// Waits for a jittered, exponentially-increasing delay before each retry.
+    pub fn with_backoff(self, backoff: ExponentialBackoff) -> Self {
+        Self {
+            backoff: Some(backoff),
+            ..self
+        }
+    }
+
+    /// Bounds the time spent on each attempt, independently of the route's
+    /// overall timeout. An attempt that times out may be retried.
+    pub fn with_per_try_timeout(self, timeout: Duration) -> Self {
+        Self {
+            per_try_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Limits the total number of attempts, including the original request.
+    pub fn with_max_attempts(self, max: NonZeroUsize) -> Self {
+        Self {
+            max_attempts: Some(max),
+            ..self
+        }
+    }
+
+    /// Also retries gRPC responses whose status is one of `codes`, in addition
+    /// to responses that the route's response classes consider failures.
+    pub fn with_retryable_grpc_codes(self, codes: impl IntoIterator<Item = tonic::Code>) -> Self {
+        Self {
+            retryable_grpc_codes: Some(codes.into_iter().collect()),
+            ..self
+        }
+    }
+
+    /// Overrides the proxy's default limit on the size of a request body that
+    /// may be buffered so that the request can be retried.
+    pub fn with_max_buffered_bytes(self, max: usize) -> Self {
+        Self {
+            max_buffered_bytes: Some(max),
+            ..self
+        }
+    }
+
+    pub fn with_hedge(self, hedge: Hedge) -> Self {
+        Self {
+            hedge: Some(hedge),
+            ..self
+        }
+    }
+
     pub fn budget(&self) -> &Arc<Budget> {
         &self.budget
     }
+
+    pub fn backoff(&self) -> Option<&ExponentialBackoff> {
+        self.backoff.as_ref()
+    }
+
+    pub fn per_try_timeout(&self) -> Option<Duration> {
+        self.per_try_timeout
+    }
+
+    pub fn max_attempts(&self) -> Option<NonZeroUsize> {
+        self.max_attempts
+    }
+
+    pub fn max_buffered_bytes(&self) -> Option<usize> {
+        self.max_buffered_bytes
+    }
+
+    pub fn hedge(&self) -> Option<&Hedge> {
+        self.hedge.as_ref()
+    }
+
+    /// Determines whether a gRPC response with the given `grpc-status` may be
+    /// retried.
+    ///
+    /// This is in addition to the response's classification: a response is
+    /// retryable if it is classified as a failure *or* if its status is one of
+    /// the route's retryable codes.
+    pub fn is_retryable_grpc_status(&self, status: &http::HeaderValue) -> bool {
+        let code = tonic::Code::from_bytes(status.as_bytes());
+        self.retryable_grpc_codes
+            .as_ref()
+            .map_or(false, |codes| codes.contains(&code))
+    }
+}
+
+impl From<Arc<Budget>> for Retries {
+    fn from(budget: Arc<Budget>) -> Self {
+        Self::new(budget)
+    }
 }
 
 impl PartialEq for Retries {
     fn eq(&self, other: &Self) -> bool {
         Arc::ptr_eq(&self.budget, &other.budget)
+            && self.backoff == other.backoff
+            && self.per_try_timeout == other.per_try_timeout
+            && self.max_attempts == other.max_attempts
+            && self.max_buffered_bytes == other.max_buffered_bytes
+            && self.hedge == other.hedge
+            && self.retryable_grpc_codes == other.retryable_grpc_codes
     }
 }
 
@@ -206,6 +411,64 @@ impl Eq for Retries {}
 impl Hash for Retries {
     fn hash<H: Hasher>(&self, state: &mut H) {
         state.write_usize(Arc::as_ref(&self.budget) as *const _ as usize);
+        self.backoff.hash(state);
+        self.per_try_timeout.hash(state);
+        self.max_attempts.hash(state);
+        self.max_buffered_bytes.hash(state);
+        self.hedge.hash(state);
+        self.retryable_grpc_codes.hash(state);
+    }
+}
+
+// === impl Hedge ===
+
+impl Hedge {
+    pub fn new(percentile: f64) -> Result<Self, InvalidHedge> {
+        if percentile.is_nan() || percentile <= 0.0 || percentile > 100.0 {
+            return Err(InvalidHedge(percentile));
+        }
+        Ok(Self {
+            percentile,
+            idempotent_route: false,
+        })
+    }
+
+    /// Marks all of the route's requests as idempotent, so that requests are
+    /// hedged regardless of their method.
+    pub fn with_idempotent_route(self) -> Self {
+        Self {
+            idempotent_route: true,
+            ..self
+        }
+    }
+
+    /// The latency percentile, on `(0, 100]`, after which a request is hedged.
+    pub fn percentile(&self) -> f64 {
+        self.percentile
+    }
+
+    /// Indicates whether a request with the given method may be hedged.
+    pub fn is_hedgeable(&self, method: &http::Method) -> bool {
+        self.idempotent_route
+            || matches!(
+                *method,
+                http::Method::GET | http::Method::HEAD | http::Method::OPTIONS
+            )
+    }
+}
+
+impl PartialEq for Hedge {
+    fn eq(&self, other: &Self) -> bool {
+        self.percentile == other.percentile && self.idempotent_route == other.idempotent_route
+    }
+}
+
+/// The percentile is validated to be a positive, non-NaN number, so hashing
+/// its bits is consistent with comparing it.
+impl Hash for Hedge {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.percentile.to_bits().hash(state);
+        self.idempotent_route.hash(state);
     }
 }
 
@@ -230,3 +493,152 @@ impl fmt::Debug for Labels {
         self.0.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn req(uri: &str, headers: &[(&'static str, &'static str)]) -> http::Request<()> {
+        let mut req = http::Request::builder().uri(uri);
+        for (n, v) in headers {
+            req = req.header(*n, *v);
+        }
+        req.body(()).unwrap()
+    }
+
+    #[test]
+    fn header_match() {
+        let name = http::header::HeaderName::from_static("x-api-version");
+        let exact = RequestMatch::Header(name.clone(), ValueMatch::Exact("2".into()));
+        assert!(exact.is_match(&req("/", &[("x-api-version", "2")])));
+        assert!(exact.is_match(&req("/", &[("x-api-version", "1"), ("x-api-version", "2")])));
+        assert!(!exact.is_match(&req("/", &[("x-api-version", "20")])));
+        assert!(!exact.is_match(&req("/", &[])));
+
+        let present = RequestMatch::Header(name.clone(), ValueMatch::Present);
+        assert!(present.is_match(&req("/", &[("x-api-version", "")])));
+        assert!(!present.is_match(&req("/", &[("x-other", "2")])));
+
+        let regex = RequestMatch::Header(name, ValueMatch::regex("2|3").unwrap());
+        assert!(regex.is_match(&req("/", &[("x-api-version", "3")])));
+        assert!(!regex.is_match(&req("/", &[("x-api-version", "23")])));
+    }
+
+    #[test]
+    fn query_param_match() {
+        let exact =
+            RequestMatch::QueryParam("operationName".into(), ValueMatch::Exact("GetUser".into()));
+        assert!(exact.is_match(&req("/graphql?operationName=GetUser", &[])));
+        assert!(exact.is_match(&req("/graphql?a=b&operationName=GetUser", &[])));
+        assert!(!exact.is_match(&req("/graphql?operationName=GetUsers", &[])));
+        assert!(!exact.is_match(&req("/graphql", &[])));
+
+        let regex =
+            RequestMatch::QueryParam("operationName".into(), ValueMatch::regex("Get.*").unwrap());
+        assert!(regex.is_match(&req("/graphql?operationName=Get%20User", &[])));
+        assert!(!regex.is_match(&req("/graphql?operationName=ListUsers", &[])));
+
+        let present = RequestMatch::QueryParam("debug".into(), ValueMatch::Present);
+        assert!(present.is_match(&req("/?debug", &[])));
+        assert!(!present.is_match(&req("/?nodebug=1", &[])));
+    }
+
+    #[test]
+    fn route_for_request_uses_first_match() {
+        let v2 = Route::new(
+            Some(("version".to_string(), "2".to_string())).into_iter(),
+            vec![],
+        );
+        let fallback = Route::default();
+        let routes = vec![
+            (
+                RequestMatch::All(vec![
+                    RequestMatch::Path(Box::new(anchored_regex("/api/.*").unwrap())),
+                    RequestMatch::Header(
+                        http::header::HeaderName::from_static("x-api-version"),
+                        ValueMatch::Exact("2".into()),
+                    ),
+                ]),
+                v2.clone(),
+            ),
+            (
+                RequestMatch::Path(Box::new(anchored_regex("/api/.*").unwrap())),
+                fallback.clone(),
+            ),
+        ];
+
+        let route = route_for_request(&routes, &req("/api/users", &[("x-api-version", "2")]));
+        assert_eq!(route, Some(&v2));
+        let route = route_for_request(&routes, &req("/api/users", &[]));
+        assert_eq!(route, Some(&fallback));
+        assert_eq!(route_for_request(&routes, &req("/users", &[])), None);
+    }
+
+    #[test]
+    fn retryable_grpc_status() {
+        let budget = Arc::new(Budget::new(Duration::from_secs(10), 10, 0.2));
+        let retries = Retries::new(budget.clone());
+        assert!(!retries.is_retryable_grpc_status(&http::HeaderValue::from_static("14")));
+
+        let retries = Retries::new(budget)
+            .with_retryable_grpc_codes(vec![tonic::Code::Unavailable, tonic::Code::Internal]);
+        assert!(retries.is_retryable_grpc_status(&http::HeaderValue::from_static("14")));
+        assert!(retries.is_retryable_grpc_status(&http::HeaderValue::from_static("13")));
+        assert!(!retries.is_retryable_grpc_status(&http::HeaderValue::from_static("0")));
+        assert!(!retries.is_retryable_grpc_status(&http::HeaderValue::from_static("5")));
+    }
+
+    #[test]
+    fn retries_compare_grpc_codes_by_value() {
+        let budget = Arc::new(Budget::new(Duration::from_secs(10), 10, 0.2));
+        let a =
+            Retries::new(budget.clone()).with_retryable_grpc_codes(vec![tonic::Code::Unavailable]);
+        let b =
+            Retries::new(budget.clone()).with_retryable_grpc_codes(vec![tonic::Code::Unavailable]);
+        let c = Retries::new(budget.clone()).with_retryable_grpc_codes(vec![tonic::Code::Internal]);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, Retries::new(budget.clone()));
+    }
+
+    #[test]
+    fn retries_hash_all_compared_fields() {
+        use std::collections::hash_map::DefaultHasher;
+
+        fn hash(retries: &Retries) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            retries.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let budget = Arc::new(Budget::new(Duration::from_secs(10), 10, 0.2));
+        let backoff =
+            ExponentialBackoff::try_new(Duration::from_millis(10), Duration::from_secs(1), 0.1)
+                .unwrap();
+        let hedge = Hedge::new(90.0).unwrap();
+        let mk = || {
+            Retries::new(budget.clone())
+                .with_backoff(backoff)
+                .with_hedge(hedge.clone())
+                .with_retryable_grpc_codes(vec![tonic::Code::Unavailable])
+        };
+        assert_eq!(mk(), mk());
+        assert_eq!(hash(&mk()), hash(&mk()));
+
+        let others = [
+            Retries::new(budget.clone())
+                .with_hedge(hedge.clone())
+                .with_retryable_grpc_codes(vec![tonic::Code::Unavailable]),
+            Retries::new(budget.clone())
+                .with_backoff(backoff)
+                .with_retryable_grpc_codes(vec![tonic::Code::Unavailable]),
+            Retries::new(budget.clone())
+                .with_backoff(backoff)
+                .with_hedge(hedge.clone()),
+        ];
+        for other in &others {
+            assert_ne!(&mk(), other);
+            assert_ne!(hash(&mk()), hash(other));
+        }
+    }
+}
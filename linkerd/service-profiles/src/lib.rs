@@ -25,6 +25,7 @@ mod proto;
 pub mod split;
 
 pub use self::client::Client;
+pub use linkerd_proxy_balance::HashKey;
 
 #[derive(Clone, Debug)]
 pub struct Receiver {
@@ -41,8 +42,12 @@ pub struct Profile {
     pub addr: Option<LogicalAddr>,
     pub http_routes: Vec<(self::http::RequestMatch, self::http::Route)>,
     pub targets: Vec<Target>,
+    pub split_rules: Vec<self::split::SplitRule>,
     pub opaque_protocol: bool,
     pub endpoint: Option<(SocketAddr, Metadata)>,
+    pub hash_policy: Option<HashKey>,
+    pub balancer_strategy: Option<linkerd_proxy_balance::Strategy>,
+    pub sticky_cookie: Option<String>,
 }
 
 /// A profile lookup target.
@@ -142,9 +147,28 @@ impl Receiver {
         self.inner.borrow().endpoint.clone()
     }
 
+    /// Configures the destination's balancers to route requests by consistent
+    /// hashing, so that requests with the same key are sent to the same
+    /// endpoint.
+    pub fn hash_policy(&self) -> Option<HashKey> {
+        self.inner.borrow().hash_policy.clone()
+    }
+
+    pub fn balancer_strategy(&self) -> Option<linkerd_proxy_balance::Strategy> {
+        self.inner.borrow().balancer_strategy
+    }
+
+    pub fn sticky_cookie(&self) -> Option<String> {
+        self.inner.borrow().sticky_cookie.clone()
+    }
+
     fn targets(&self) -> Vec<Target> {
         self.inner.borrow().targets.clone()
     }
+
+    fn split_rules(&self) -> Vec<self::split::SplitRule> {
+        self.inner.borrow().split_rules.clone()
+    }
 }
 
 // === impl ReceiverStream ===
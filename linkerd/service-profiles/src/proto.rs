@@ -3,7 +3,6 @@ use linkerd2_proxy_api::destination as api;
 use linkerd_addr::NameAddr;
 use linkerd_dns_name::Name;
 use linkerd_proxy_api_resolve::pb as resolve;
-use regex::Regex;
 use std::{str::FromStr, sync::Arc, time::Duration};
 use tower::retry::budget::Budget;
 use tracing::warn;
@@ -29,8 +28,17 @@ pub(super) fn convert_profile(proto: api::DestinationProfile, port: u16) -> Prof
         addr: name.map(move |n| LogicalAddr(NameAddr::from((n, port)))),
         http_routes,
         targets,
+        // TODO The destination API does not yet describe split rules, so
+        // discovered profiles never have any.
+        split_rules: vec![],
         opaque_protocol: proto.opaque_protocol,
         endpoint,
+        // TODO The destination API does not yet describe load balancing
+        // policies, so discovered profiles always use the default strategy
+        // without hashing or sticky cookies.
+        hash_policy: None,
+        balancer_strategy: None,
+        sticky_cookie: None,
     }
 }
 
@@ -74,6 +82,9 @@ fn set_route_retry(route: &mut http::Route, retry_budget: Option<&Arc<Budget>>)
         }
     };
 
+    // TODO The destination API does not yet describe retry backoffs,
+    // per-try timeouts, attempt limits, retryable gRPC status codes, buffering
+    // limits, or hedging, so discovered routes always use the defaults.
     route.set_retries(budget);
 }
 
@@ -88,6 +99,10 @@ fn set_route_timeout(route: &mut http::Route, timeout: Result<Duration, Duration
     }
 }
 
+// TODO The destination API does not yet describe header or query parameter
+// matches; when it does, they should be converted to `RequestMatch::Header` and
+// `RequestMatch::QueryParam` here. Until then, discovered routes only match
+// on paths and methods.
 fn convert_req_match(orig: api::RequestMatch) -> Option<http::RequestMatch> {
     let m = match orig.r#match? {
         api::request_match::Match::All(ms) => {
@@ -103,16 +118,7 @@ fn convert_req_match(orig: api::RequestMatch) -> Option<http::RequestMatch> {
             http::RequestMatch::Not(Box::new(m))
         }
         api::request_match::Match::Path(api::PathMatch { regex }) => {
-            let regex = regex.trim();
-            let re = match (regex.starts_with('^'), regex.ends_with('$')) {
-                (true, true) => Regex::new(regex).ok()?,
-                (hd_anchor, tl_anchor) => {
-                    let hd = if hd_anchor { "" } else { "^" };
-                    let tl = if tl_anchor { "" } else { "$" };
-                    let re = format!("{}{}{}", hd, regex, tl);
-                    Regex::new(&re).ok()?
-                }
-            };
+            let re = http::anchored_regex(&regex).ok()?;
             http::RequestMatch::Path(Box::new(re))
         }
         api::request_match::Match::Method(mm) => {
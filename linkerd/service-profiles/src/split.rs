@@ -1,9 +1,10 @@
-use crate::{LogicalAddr, Profile, Receiver, ReceiverStream, Target};
+use crate::{http::ValueMatch, HashKey, LogicalAddr, Profile, Receiver, ReceiverStream, Target};
 use futures::{prelude::*, ready};
 use indexmap::IndexSet;
 use linkerd_addr::NameAddr;
 use linkerd_error::Error;
 use linkerd_proxy_api_resolve::ConcreteAddr;
+use linkerd_proxy_balance::Strategy;
 use linkerd_stack::{layer, NewService, Param};
 use rand::distributions::{Distribution, WeightedIndex};
 use rand::{rngs::SmallRng, thread_rng, SeedableRng};
@@ -15,18 +16,51 @@ use std::{
 use tower::ready_cache::ReadyCache;
 use tracing::{debug, trace};
 
+/// Builds a split that distributes requests over a profile's targets by
+/// weight.
+///
+/// Split rules are ignored, since they can only be applied to HTTP requests.
 pub fn layer<N, S, Req>() -> impl layer::Layer<N, Service = NewSplit<N, S, Req>> + Clone {
-    // This RNG doesn't need to be cryptographically secure. Small and fast is
-    // preferable.
     layer::mk(move |inner| NewSplit {
         inner,
+        is_match: None,
         _service: PhantomData,
     })
 }
 
+/// Builds a split that dispatches HTTP requests that match one of the
+/// profile's split rules to the rule's target, falling back to distributing
+/// requests over the profile's targets by weight.
+pub fn http_layer<N, S, B>(
+) -> impl layer::Layer<N, Service = NewSplit<N, S, http::Request<B>>> + Clone {
+    layer::mk(move |inner| NewSplit {
+        inner,
+        is_match: Some(SplitMatch::is_match_request::<B>),
+        _service: PhantomData,
+    })
+}
+
+/// Deterministically routes requests that match `match_` to `addr`,
+/// regardless of the profile's target weights.
+///
+/// This may be used, for instance, to send requests with an `x-canary: true`
+/// header to a canary backend.
+#[derive(Clone, Debug)]
+pub struct SplitRule {
+    pub match_: SplitMatch,
+    pub addr: NameAddr,
+}
+
+#[derive(Clone, Debug)]
+pub enum SplitMatch {
+    Header(http::header::HeaderName, ValueMatch),
+    Cookie(String, ValueMatch),
+}
+
 #[derive(Debug)]
 pub struct NewSplit<N, S, Req> {
     inner: N,
+    is_match: Option<fn(&SplitMatch, &Req) -> bool>,
     _service: PhantomData<fn(Req) -> S>,
 }
 
@@ -35,9 +69,26 @@ pub struct Split<T, N, S, Req> {
     rx: ReceiverStream,
     target: T,
     new_service: N,
+    is_match: Option<fn(&SplitMatch, &Req) -> bool>,
+    rules: Vec<SplitRule>,
     distribution: WeightedIndex<u32>,
+    /// The number of weighted targets, which are the first entries in
+    /// `addrs`. Addresses that are only referenced by rules follow.
+    weighted: usize,
     addrs: IndexSet<NameAddr>,
     services: ReadyCache<NameAddr, S, Req>,
+    balancer: Balancer,
+}
+
+/// The profile's configuration of its targets' balancers.
+///
+/// Balancers read this configuration only when they are built, so all targets
+/// are rebuilt when it changes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Balancer {
+    hash_policy: Option<HashKey>,
+    sticky_cookie: Option<String>,
+    strategy: Option<Strategy>,
 }
 
 // === impl NewSplit ===
@@ -46,6 +97,7 @@ impl<N: Clone, S, Req> Clone for NewSplit<N, S, Req> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            is_match: self.is_match,
             _service: self._service,
         }
     }
@@ -67,7 +119,12 @@ where
             let LogicalAddr(addr) = target.param();
             targets.push(Target { addr, weight: 1 })
         }
-        trace!(?targets, "Building split service");
+        let rules = if self.is_match.is_some() {
+            rx.split_rules()
+        } else {
+            vec![]
+        };
+        trace!(?targets, ?rules, "Building split service");
 
         let mut addrs = IndexSet::with_capacity(targets.len());
         let mut weights = Vec::with_capacity(targets.len());
@@ -81,14 +138,33 @@ where
             addrs.insert(addr);
             weights.push(weight);
         }
+        let weighted = addrs.len();
+        for SplitRule { addr, .. } in rules.iter() {
+            if addrs.insert(addr.clone()) {
+                services.push(
+                    addr.clone(),
+                    new_service.new_service((ConcreteAddr(addr.clone()), target.clone())),
+                );
+            }
+        }
 
         Split {
+            balancer: Balancer {
+                hash_policy: rx.hash_policy(),
+                sticky_cookie: rx.sticky_cookie(),
+                strategy: rx.balancer_strategy(),
+            },
             rx: rx.into(),
             target,
             new_service,
+            is_match: self.is_match,
+            rules,
             services,
             addrs,
+            weighted,
             distribution: WeightedIndex::new(weights).unwrap(),
+            // This RNG doesn't need to be cryptographically secure. Small and
+            // fast is preferable.
             rng: SmallRng::from_rng(&mut thread_rng()).expect("RNG must initialize"),
         }
     }
@@ -117,13 +193,41 @@ where
         }
 
         // Every time the profile updates, rebuild the distribution, reusing
-        // services that existed in the prior state.
-        if let Some(Profile { mut targets, .. }) = update {
+        // services that existed in the prior state unless the balancer
+        // configuration changed.
+        if let Some(Profile {
+            mut targets,
+            split_rules,
+            hash_policy,
+            balancer_strategy,
+            sticky_cookie,
+            ..
+        }) = update
+        {
+            let balancer = Balancer {
+                hash_policy,
+                sticky_cookie,
+                strategy: balancer_strategy,
+            };
+            let rebuild = balancer != self.balancer;
+            if rebuild {
+                debug!(
+                    ?balancer,
+                    "Balancer configuration changed; rebuilding targets"
+                );
+                self.balancer = balancer;
+            }
+
             if targets.is_empty() {
                 let LogicalAddr(addr) = self.target.param();
                 targets.push(Target { addr, weight: 1 })
             }
-            debug!(?targets, "Updating");
+            self.rules = if self.is_match.is_some() {
+                split_rules
+            } else {
+                vec![]
+            };
+            debug!(?targets, rules = ?self.rules, "Updating");
 
             // Replace the old set of addresses with an empty set. The
             // prior set is used to determine whether a new service
@@ -136,7 +240,7 @@ where
             // Create an updated distribution and set of services.
             for Target { weight, addr } in targets.into_iter() {
                 // Reuse the prior services whenever possible.
-                if !prior_addrs.remove(&addr) {
+                if !prior_addrs.remove(&addr) || rebuild {
                     debug!(%addr, "Creating target");
                     let svc = self
                         .new_service
@@ -150,6 +254,22 @@ where
             }
 
             self.distribution = WeightedIndex::new(weights).unwrap();
+            self.weighted = self.addrs.len();
+
+            // Rules may refer to addresses that are not weighted targets.
+            for SplitRule { addr, .. } in self.rules.iter() {
+                if self.addrs.contains(addr) {
+                    continue;
+                }
+                if !prior_addrs.remove(addr) || rebuild {
+                    debug!(%addr, "Creating rule target");
+                    let svc = self
+                        .new_service
+                        .new_service((ConcreteAddr(addr.clone()), self.target.clone()));
+                    self.services.push(addr.clone(), svc);
+                }
+                self.addrs.insert(addr.clone());
+            }
 
             // Remove all prior services that did not exist in the new
             // set of targets.
@@ -164,7 +284,18 @@ where
     }
 
     fn call(&mut self, req: Req) -> Self::Future {
-        let idx = if self.addrs.len() == 1 {
+        // Requests that match a rule are dispatched to the rule's target
+        // without consulting the distribution.
+        if let Some(is_match) = self.is_match {
+            if let Some(SplitRule { addr, .. }) =
+                self.rules.iter().find(|r| is_match(&r.match_, &req))
+            {
+                trace!(?addr, "Dispatching by rule");
+                return Box::pin(self.services.call_ready(addr, req).err_into::<Error>());
+            }
+        }
+
+        let idx = if self.weighted == 1 {
             0
         } else {
             self.distribution.sample(&mut self.rng)
@@ -174,3 +305,125 @@ where
         Box::pin(self.services.call_ready(addr, req).err_into::<Error>())
     }
 }
+
+// === impl SplitMatch ===
+
+impl SplitMatch {
+    fn is_match_request<B>(&self, req: &http::Request<B>) -> bool {
+        self.is_match(req.headers())
+    }
+
+    fn is_match(&self, headers: &http::HeaderMap) -> bool {
+        match self {
+            Self::Header(ref name, ref value) => headers
+                .get_all(name)
+                .iter()
+                .any(|v| value.is_match_bytes(v.as_bytes())),
+            Self::Cookie(ref name, ref value) => headers
+                .get_all(http::header::COOKIE)
+                .iter()
+                .filter_map(|v| v.to_str().ok())
+                .flat_map(|v| v.split(';'))
+                .filter_map(|c| c.trim().split_once('='))
+                .any(|(n, v)| n == name && value.is_match(v)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(hs: &[(&'static str, &'static str)]) -> http::HeaderMap {
+        let mut headers = http::HeaderMap::new();
+        for (name, value) in hs {
+            headers.append(*name, http::HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[derive(Clone)]
+    struct Logical(Receiver);
+
+    impl Param<LogicalAddr> for Logical {
+        fn param(&self) -> LogicalAddr {
+            LogicalAddr("foo.ns.svc.cluster.local:8080".parse().unwrap())
+        }
+    }
+
+    impl Param<Receiver> for Logical {
+        fn param(&self) -> Receiver {
+            self.0.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn rebuilds_targets_when_balancer_changes() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+        use tower::Service;
+
+        let (tx, rx) = tokio::sync::watch::channel(Profile::default());
+        let built = Arc::new(AtomicUsize::new(0));
+        let new_split = NewSplit {
+            inner: {
+                let built = built.clone();
+                move |_: (ConcreteAddr, Logical)| {
+                    built.fetch_add(1, Ordering::SeqCst);
+                    tower::service_fn(|()| future::ok::<_, Error>(()))
+                }
+            },
+            is_match: None,
+            _service: PhantomData,
+        };
+        let mut split = new_split.new_service(Logical(rx.into()));
+        assert_eq!(built.load(Ordering::SeqCst), 1);
+
+        // Updates that do not change the balancer reuse the target's service.
+        tx.send(Profile::default()).unwrap();
+        future::poll_fn(|cx| split.poll_ready(cx)).await.unwrap();
+        assert_eq!(built.load(Ordering::SeqCst), 1);
+
+        tx.send(Profile {
+            hash_policy: Some(HashKey::ClientIp),
+            ..Profile::default()
+        })
+        .unwrap();
+        future::poll_fn(|cx| split.poll_ready(cx)).await.unwrap();
+        assert_eq!(built.load(Ordering::SeqCst), 2);
+
+        tx.send(Profile {
+            hash_policy: Some(HashKey::ClientIp),
+            balancer_strategy: Some(Strategy::RoundRobin),
+            ..Profile::default()
+        })
+        .unwrap();
+        future::poll_fn(|cx| split.poll_ready(cx)).await.unwrap();
+        assert_eq!(built.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn header_match() {
+        let m = SplitMatch::Header(
+            http::header::HeaderName::from_static("x-canary"),
+            ValueMatch::Exact("true".into()),
+        );
+        assert!(m.is_match(&headers(&[("x-canary", "true")])));
+        assert!(m.is_match(&headers(&[("x-canary", "false"), ("x-canary", "true")])));
+        assert!(!m.is_match(&headers(&[("x-canary", "false")])));
+        assert!(!m.is_match(&headers(&[])));
+    }
+
+    #[test]
+    fn cookie_match() {
+        let m = SplitMatch::Cookie("canary".into(), ValueMatch::regex("yes|true").unwrap());
+        assert!(m.is_match(&headers(&[("cookie", "canary=true")])));
+        assert!(m.is_match(&headers(&[("cookie", "session=abc; canary=yes")])));
+        assert!(m.is_match(&headers(&[("cookie", "a=b"), ("cookie", "canary=yes")])));
+        assert!(!m.is_match(&headers(&[("cookie", "canary=no; x=true")])));
+        assert!(!m.is_match(&headers(&[("cookie", "notcanary=true")])));
+        assert!(!m.is_match(&headers(&[("x-canary", "true")])));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::creds::{self, Identity, InvalidCredentials};
+use futures::prelude::*;
+use linkerd_dns_name as dns;
+use linkerd_io as io;
+use linkerd_stack::Service;
+use std::{convert::TryFrom, path::Path, pin::Pin, sync::Arc, task::Context};
+use tokio_rustls::rustls;
+
+/// Configures clients that originate TLS to servers outside of the mesh.
+#[derive(Clone)]
+pub struct ClientConfig(Arc<rustls::ClientConfig>);
+
+/// A `Service` that initiates client-side TLS connections.
+#[derive(Clone)]
+pub struct Connect {
+    server_name: rustls::ServerName,
+    config: Arc<rustls::ClientConfig>,
+}
+
+pub type ConnectFuture<I> = futures::future::MapOk<
+    tokio_rustls::Connect<I>,
+    fn(tokio_rustls::client::TlsStream<I>) -> ClientIo<I>,
+>;
+
+#[derive(Debug)]
+pub struct ClientIo<I>(tokio_rustls::client::TlsStream<I>);
+
+// === impl ClientConfig ===
+
+impl ClientConfig {
+    /// Builds a configuration that verifies servers against the trust roots in
+    /// `roots` or, if no path is provided, the system's trust roots.
+    ///
+    /// If an identity is provided, it is presented to servers that request
+    /// client authentication.
+    pub fn load(
+        roots: Option<&Path>,
+        identity: Option<Identity>,
+    ) -> Result<Self, InvalidCredentials> {
+        let builder = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(creds::load_roots(roots)?);
+        let config = match identity {
+            None => builder.with_no_client_auth(),
+            Some(Identity { path, certs, key }) => builder
+                .with_single_cert(certs, key)
+                .map_err(|e| InvalidCredentials::new(&path, e))?,
+        };
+        Ok(Self(Arc::new(config)))
+    }
+
+    /// Returns a `Service` that originates TLS to the named server.
+    pub fn connect(&self, name: &dns::Name) -> io::Result<Connect> {
+        let server_name = rustls::ServerName::try_from(name.without_trailing_dot())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(Connect {
+            server_name,
+            config: self.0.clone(),
+        })
+    }
+}
+
+impl std::fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientConfig").finish()
+    }
+}
+
+// === impl Connect ===
+
+impl<I> Service<I> for Connect
+where
+    I: io::AsyncRead + io::AsyncWrite + Send + Unpin,
+{
+    type Response = ClientIo<I>;
+    type Error = io::Error;
+    type Future = ConnectFuture<I>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> io::Poll<()> {
+        io::Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        tokio_rustls::TlsConnector::from(self.config.clone())
+            .connect(self.server_name.clone(), io)
+            .map_ok(ClientIo)
+    }
+}
+
+impl std::fmt::Debug for Connect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connect")
+            .field("server_name", &self.server_name)
+            .finish()
+    }
+}
+
+// === impl ClientIo ===
+
+impl<I: io::AsyncRead + io::AsyncWrite + Unpin> io::AsyncRead for ClientIo<I> {
+    #[inline]
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl<I: io::AsyncRead + io::AsyncWrite + Unpin> io::AsyncWrite for ClientIo<I> {
+    #[inline]
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> io::Poll<usize> {
+        Pin::new(&mut self.0).poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.0.is_write_vectored()
+    }
+}
+
+impl<I: io::PeerAddr> io::PeerAddr for ClientIo<I> {
+    #[inline]
+    fn peer_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.0.get_ref().0.peer_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata;
+
+    #[test]
+    fn connects_to_named_servers() {
+        let config = ClientConfig::load(Some(&testdata("ca1.pem")), None).expect("must load");
+        // Names may be fully qualified.
+        for name in ["api.example.com", "api.example.com."] {
+            let connect = config
+                .connect(&name.parse().unwrap())
+                .expect("name must be valid");
+            assert_eq!(
+                connect.server_name,
+                rustls::ServerName::try_from("api.example.com").unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn presents_client_identity() {
+        let config = ClientConfig::load(Some(&testdata("ca1.pem")), None).expect("must load");
+        assert!(!config.0.client_auth_cert_resolver.has_certs());
+
+        let id = Identity::load(&testdata("ca1.pem"), &testdata("ca1-key.pem")).unwrap();
+        let config = ClientConfig::load(Some(&testdata("ca1.pem")), Some(id)).expect("must load");
+        assert!(config.0.client_auth_cert_resolver.has_certs());
+    }
+
+    #[test]
+    fn rejects_missing_roots() {
+        assert!(ClientConfig::load(Some(&testdata("missing.pem")), None).is_err());
+    }
+}
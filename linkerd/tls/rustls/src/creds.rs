@@ -0,0 +1,158 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tokio_rustls::rustls;
+use tracing::{debug, warn};
+
+/// Well-known locations of the system's trust roots.
+const SYSTEM_ROOTS: &[&str] = &[
+    // Debian, Ubuntu, Alpine
+    "/etc/ssl/certs/ca-certificates.crt",
+    // Fedora, RHEL
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    // macOS, OpenBSD
+    "/etc/ssl/cert.pem",
+];
+
+/// A certificate chain and its private key.
+#[derive(Clone)]
+pub struct Identity {
+    pub(crate) path: PathBuf,
+    pub(crate) certs: Vec<rustls::Certificate>,
+    pub(crate) key: rustls::PrivateKey,
+}
+
+#[derive(Debug, Error)]
+#[error("invalid credentials in {}: {reason}", .path.display())]
+pub struct InvalidCredentials {
+    path: PathBuf,
+    reason: String,
+}
+
+// === impl Identity ===
+
+impl Identity {
+    /// Loads a certificate chain and its private key from PEM files.
+    ///
+    /// The chain must start with the end-entity certificate. The key may be
+    /// encoded as PKCS#8, PKCS#1 (RSA), or SEC1 (EC).
+    pub fn load(certs: &Path, key: &Path) -> Result<Self, InvalidCredentials> {
+        let certs = read_certs(certs)?
+            .into_iter()
+            .map(rustls::Certificate)
+            .collect();
+        Ok(Self {
+            path: key.to_path_buf(),
+            certs,
+            key: read_key(key)?,
+        })
+    }
+}
+
+impl std::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Identity")
+            .field("path", &self.path)
+            .field("certs", &self.certs.len())
+            .finish()
+    }
+}
+
+// === impl InvalidCredentials ===
+
+impl InvalidCredentials {
+    pub(crate) fn new(path: &Path, reason: impl ToString) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Loads trust roots from a PEM file or, if no path is provided, from the
+/// system's trust store.
+pub(crate) fn load_roots(path: Option<&Path>) -> Result<rustls::RootCertStore, InvalidCredentials> {
+    let path = match path {
+        Some(path) => path,
+        None => SYSTEM_ROOTS
+            .iter()
+            .map(Path::new)
+            .find(|p| p.is_file())
+            .ok_or_else(|| {
+                InvalidCredentials::new(Path::new(SYSTEM_ROOTS[0]), "no system trust roots found")
+            })?,
+    };
+    debug!(path = %path.display(), "Loading trust roots");
+
+    let mut roots = rustls::RootCertStore::empty();
+    let (added, skipped) = roots.add_parsable_certificates(&read_certs(path)?);
+    if skipped != 0 {
+        warn!(path = %path.display(), "Skipped {} invalid trust roots", skipped);
+    }
+    if added == 0 {
+        return Err(InvalidCredentials::new(path, "no valid trust roots"));
+    }
+    Ok(roots)
+}
+
+fn read_certs(path: &Path) -> Result<Vec<Vec<u8>>, InvalidCredentials> {
+    let pem = fs::read(path).map_err(|e| InvalidCredentials::new(path, e))?;
+    let certs =
+        rustls_pemfile::certs(&mut &pem[..]).map_err(|e| InvalidCredentials::new(path, e))?;
+    if certs.is_empty() {
+        return Err(InvalidCredentials::new(path, "no certificates"));
+    }
+    Ok(certs)
+}
+
+fn read_key(path: &Path) -> Result<rustls::PrivateKey, InvalidCredentials> {
+    use rustls_pemfile::Item;
+
+    let pem = fs::read(path).map_err(|e| InvalidCredentials::new(path, e))?;
+    let mut pem = &pem[..];
+    loop {
+        match rustls_pemfile::read_one(&mut pem).map_err(|e| InvalidCredentials::new(path, e))? {
+            Some(Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key)) => {
+                return Ok(rustls::PrivateKey(key))
+            }
+            Some(_) => {}
+            None => return Err(InvalidCredentials::new(path, "no private key")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata;
+
+    #[test]
+    fn loads_identity() {
+        let id = Identity::load(&testdata("ca1.pem"), &testdata("ca1-key.pem"))
+            .expect("identity must load");
+        assert_eq!(id.certs.len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_identity() {
+        let missing = testdata("missing.pem");
+        let error = Identity::load(&missing, &testdata("ca1-key.pem")).unwrap_err();
+        assert_eq!(error.path, missing);
+
+        // Key files don't contain certificates, and certificate files don't
+        // contain keys.
+        let error = Identity::load(&testdata("ca1-key.pem"), &testdata("ca1-key.pem")).unwrap_err();
+        assert_eq!(error.reason, "no certificates");
+        let error = Identity::load(&testdata("ca1.pem"), &testdata("ca1.pem")).unwrap_err();
+        assert_eq!(error.reason, "no private key");
+    }
+
+    #[test]
+    fn loads_roots() {
+        let roots = load_roots(Some(&testdata("ca1.pem"))).expect("roots must load");
+        assert_eq!(roots.len(), 1);
+        assert!(load_roots(Some(&testdata("ca1-key.pem"))).is_err());
+    }
+}
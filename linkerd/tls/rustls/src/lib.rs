@@ -0,0 +1,26 @@
+#![deny(rust_2018_idioms, clippy::disallowed_methods, clippy::disallowed_types)]
+#![forbid(unsafe_code)]
+
+//! TLS for connections with peers outside of the mesh.
+//!
+//! Unlike `linkerd-meshtls`, credentials are not issued to the proxy by the
+//! identity controller. Instead, they are loaded from PEM files so that the
+//! proxy can originate TLS to (and terminate TLS from) arbitrary hosts.
+
+mod client;
+mod creds;
+mod server;
+
+pub use self::{
+    client::{ClientConfig, ClientIo, Connect, ConnectFuture},
+    creds::{Identity, InvalidCredentials},
+    server::{NewTerminate, ServerConfig, ServerIo, Terminate},
+};
+
+/// Returns the path of a PEM file shared with `linkerd-tls-test-util`.
+#[cfg(test)]
+fn testdata(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../test-util/src/testdata")
+        .join(name)
+}
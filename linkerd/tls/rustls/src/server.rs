@@ -0,0 +1,469 @@
+use crate::creds::{Identity, InvalidCredentials};
+use futures::prelude::*;
+use linkerd_dns_name as dns;
+use linkerd_error::Error;
+use linkerd_io as io;
+use linkerd_stack::{layer, NewService, Service, ServiceExt};
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+use tokio::{task, time};
+use tokio_rustls::rustls::{
+    self,
+    server::{ClientHello, ResolvesServerCert},
+    sign::{self, CertifiedKey},
+};
+use tracing::{debug, trace, warn};
+
+/// The file name of each server's certificate chain.
+const CERT_FILE: &str = "tls.crt";
+
+/// The file name of each server's private key.
+const KEY_FILE: &str = "tls.key";
+
+/// Configures servers that terminate TLS with certificates loaded from disk.
+///
+/// Certificates are read from a directory that contains a subdirectory for
+/// each server name, holding `tls.crt` and `tls.key` PEM files (i.e., the
+/// layout of a mounted Kubernetes TLS secret). A certificate is selected by
+/// the client's SNI.
+///
+/// No certificates are loaded until the config is [reloaded](Self::reload).
+#[derive(Clone)]
+pub struct ServerConfig {
+    config: Arc<rustls::ServerConfig>,
+    certs: Arc<Certs>,
+}
+
+/// Terminates TLS with a target's `ServerConfig` before building the inner
+/// service.
+#[derive(Clone, Debug)]
+pub struct NewTerminate<N> {
+    inner: N,
+    timeout: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct Terminate<T, N> {
+    target: T,
+    config: ServerConfig,
+    timeout: Duration,
+    inner: N,
+}
+
+#[derive(Debug)]
+pub struct ServerIo<I>(tokio_rustls::server::TlsStream<I>);
+
+/// Resolves server certificates by name.
+struct Certs {
+    dir: PathBuf,
+    state: RwLock<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Certificates keyed by their lowercased server names.
+    keys: HashMap<dns::Name, Arc<CertifiedKey>>,
+    modified: Vec<(PathBuf, SystemTime)>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("TLS handshake timed out after {0:?}")]
+struct HandshakeTimeout(Duration);
+
+// === impl ServerConfig ===
+
+impl ServerConfig {
+    /// Configures servers with the certificates in `dir`, negotiating the
+    /// given ALPN protocols in order of preference.
+    pub fn new(dir: PathBuf, alpn_protocols: Vec<Vec<u8>>) -> Self {
+        let certs = Arc::new(Certs {
+            dir,
+            state: RwLock::new(State::default()),
+        });
+
+        let mut config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(certs.clone());
+        config.alpn_protocols = alpn_protocols;
+
+        Self {
+            config: Arc::new(config),
+            certs,
+        }
+    }
+
+    /// Indicates whether a certificate has been loaded for the named server.
+    pub fn has_cert(&self, name: &dns::Name) -> bool {
+        match name.as_str().to_ascii_lowercase().parse::<dns::Name>() {
+            Ok(name) => self.certs.state.read().keys.contains_key(&name),
+            Err(_) => false,
+        }
+    }
+
+    /// Loads any certificates that have changed since they were last loaded.
+    ///
+    /// Servers whose certificates cannot be loaded are skipped (or continue to
+    /// use their prior certificates), but it is an error if the directory
+    /// cannot be read. Certificates are read on a blocking thread.
+    pub async fn reload(&self) -> Result<(), Error> {
+        let certs = self.certs.clone();
+        task::spawn_blocking(move || certs.reload()).await??;
+        Ok(())
+    }
+
+    /// Reloads certificates whenever their files change.
+    ///
+    /// The directory is checked for changes at the given interval. If a
+    /// server's updated certificate cannot be loaded, its prior certificate
+    /// continues to be used.
+    pub async fn watch(self, interval: Duration) {
+        let mut interval = time::interval_at(time::Instant::now() + interval, interval);
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.reload().await {
+                warn!(%error, "Failed to reload server certificates");
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("dir", &self.certs.dir)
+            .finish()
+    }
+}
+
+// === impl Certs ===
+
+impl Certs {
+    fn reload(&self) -> Result<(), InvalidCredentials> {
+        let servers = self.servers()?;
+
+        let mut modified = servers
+            .iter()
+            .flat_map(|(_, dir)| [dir.join(CERT_FILE), dir.join(KEY_FILE)])
+            .filter_map(|path| {
+                // Follows symlinks, so that atomic updates to mounted secrets
+                // are observed.
+                let mtime = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((path, mtime))
+            })
+            .collect::<Vec<_>>();
+        modified.sort();
+        if self.state.read().modified == modified {
+            trace!("Server certificates unchanged");
+            return Ok(());
+        }
+
+        let mut keys = HashMap::with_capacity(servers.len());
+        for (name, dir) in servers {
+            match load_key(&dir) {
+                Ok(key) => {
+                    debug!(server.name = %name, "Loaded server certificate");
+                    keys.insert(name, key);
+                }
+                Err(error) => {
+                    warn!(server.name = %name, %error, "Failed to load server certificate");
+                    if let Some(key) = self.state.read().keys.get(&name) {
+                        keys.insert(name, key.clone());
+                    }
+                }
+            }
+        }
+        *self.state.write() = State { keys, modified };
+        Ok(())
+    }
+
+    /// Returns the certificate for the server named by a client's SNI.
+    fn get(&self, sni: &str) -> Option<Arc<CertifiedKey>> {
+        let name = sni.to_ascii_lowercase().parse::<dns::Name>().ok()?;
+        let key = self.state.read().keys.get(&name).cloned();
+        if key.is_none() {
+            debug!(server.name = %name, "No certificate for server");
+        }
+        key
+    }
+
+    /// Lists the server names that have certificate directories.
+    fn servers(&self) -> Result<Vec<(dns::Name, PathBuf)>, InvalidCredentials> {
+        let entries = fs::read_dir(&self.dir).map_err(|e| InvalidCredentials::new(&self.dir, e))?;
+        let mut servers = Vec::new();
+        for entry in entries {
+            let path = entry
+                .map_err(|e| InvalidCredentials::new(&self.dir, e))?
+                .path();
+            if !path.is_dir() {
+                continue;
+            }
+            // Directories that aren't DNS names (like the `..data` directory
+            // of a mounted secret) are ignored.
+            match path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.to_ascii_lowercase().parse::<dns::Name>().ok())
+            {
+                Some(name) => servers.push((name, path)),
+                None => trace!(path = %path.display(), "Ignoring directory"),
+            }
+        }
+        Ok(servers)
+    }
+}
+
+impl ResolvesServerCert for Certs {
+    fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.get(hello.server_name()?)
+    }
+}
+
+fn load_key(dir: &Path) -> Result<Arc<CertifiedKey>, InvalidCredentials> {
+    let Identity { path, certs, key } = Identity::load(&dir.join(CERT_FILE), &dir.join(KEY_FILE))?;
+    let key = sign::any_supported_type(&key).map_err(|e| InvalidCredentials::new(&path, e))?;
+    Ok(Arc::new(CertifiedKey::new(certs, key)))
+}
+
+// === impl NewTerminate ===
+
+impl<N> NewTerminate<N> {
+    pub fn layer(timeout: Duration) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self { inner, timeout })
+    }
+}
+
+impl<T, N: Clone> NewService<(ServerConfig, T)> for NewTerminate<N> {
+    type Service = Terminate<T, N>;
+
+    fn new_service(&self, (config, target): (ServerConfig, T)) -> Self::Service {
+        Terminate {
+            target,
+            config,
+            timeout: self.timeout,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<I, T, N, NSvc> Service<I> for Terminate<T, N>
+where
+    I: io::AsyncRead + io::AsyncWrite + Send + Unpin + 'static,
+    T: Clone + Send + 'static,
+    N: NewService<T, Service = NSvc> + Clone + Send + 'static,
+    NSvc: Service<ServerIo<I>, Response = ()> + Send + 'static,
+    NSvc::Error: Into<Error>,
+    NSvc::Future: Send,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let target = self.target.clone();
+        let inner = self.inner.clone();
+        let timeout = self.timeout;
+        let accept = tokio_rustls::TlsAcceptor::from(self.config.config.clone()).accept(io);
+        Box::pin(async move {
+            let io = time::timeout(timeout, accept)
+                .await
+                .map_err(|_| HandshakeTimeout(timeout))??;
+            debug!(
+                server.name = ?io.get_ref().1.sni_hostname(),
+                alpn = ?io.get_ref().1.alpn_protocol().map(String::from_utf8_lossy),
+                "Terminated TLS",
+            );
+
+            let svc = inner.new_service(target);
+            svc.oneshot(ServerIo(io)).err_into::<Error>().await
+        })
+    }
+}
+
+// === impl ServerIo ===
+
+impl<I: io::AsyncRead + io::AsyncWrite + Unpin> io::AsyncRead for ServerIo<I> {
+    #[inline]
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut io::ReadBuf<'_>,
+    ) -> io::Poll<()> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl<I: io::AsyncRead + io::AsyncWrite + Unpin> io::AsyncWrite for ServerIo<I> {
+    #[inline]
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> io::Poll<()> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+
+    #[inline]
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> io::Poll<usize> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> io::Poll<usize> {
+        Pin::new(&mut self.0).poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn is_write_vectored(&self) -> bool {
+        self.0.is_write_vectored()
+    }
+}
+
+impl<I: io::PeerAddr> io::PeerAddr for ServerIo<I> {
+    #[inline]
+    fn peer_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.0.get_ref().0.peer_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdata;
+
+    /// A directory of server certificates that is removed when dropped.
+    struct CertsDir(PathBuf);
+
+    impl CertsDir {
+        fn new(test: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "linkerd-tls-rustls-{}-{}",
+                test,
+                std::process::id()
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, cert: &str, key: &str) {
+            let dir = self.0.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::copy(testdata(cert), dir.join(CERT_FILE)).unwrap();
+            fs::copy(testdata(key), dir.join(KEY_FILE)).unwrap();
+        }
+
+        /// Sets the modification time of a server's certificate files, so
+        /// that changes are observed regardless of the filesystem's mtime
+        /// resolution.
+        fn set_modified(&self, name: &str, unix_secs: i64) {
+            let mtime = filetime::FileTime::from_unix_time(unix_secs, 0);
+            for file in [CERT_FILE, KEY_FILE] {
+                filetime::set_file_mtime(self.0.join(name).join(file), mtime).unwrap();
+            }
+        }
+    }
+
+    impl Drop for CertsDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn cert(name: &str) -> rustls::Certificate {
+        Identity::load(&testdata(name), &testdata("ca1-key.pem"))
+            .unwrap()
+            .certs
+            .remove(0)
+    }
+
+    fn load(dir: &CertsDir) -> ServerConfig {
+        let config = ServerConfig::new(dir.0.clone(), vec![]);
+        config.certs.reload().expect("certificates must load");
+        config
+    }
+
+    fn served(config: &ServerConfig, sni: &str) -> Option<rustls::Certificate> {
+        let key = config.certs.get(sni)?;
+        Some(key.cert[0].clone())
+    }
+
+    #[test]
+    fn selects_certificates_by_server_name() {
+        let dir = CertsDir::new("select");
+        dir.write("foo.example.com", "ca1.pem", "ca1-key.pem");
+        dir.write("bar.example.com", "ca2.pem", "ca2-key.pem");
+        // Directories that aren't named for servers are ignored.
+        dir.write("..data", "ca1.pem", "ca1-key.pem");
+
+        let config = load(&dir);
+        assert!(config.has_cert(&"foo.example.com".parse().unwrap()));
+        assert!(config.has_cert(&"FOO.example.com".parse().unwrap()));
+        assert!(!config.has_cert(&"baz.example.com".parse().unwrap()));
+        assert_eq!(served(&config, "foo.example.com"), Some(cert("ca1.pem")));
+        assert_eq!(served(&config, "FOO.example.com"), Some(cert("ca1.pem")));
+        assert_eq!(served(&config, "bar.example.com"), Some(cert("ca2.pem")));
+        assert_eq!(served(&config, "baz.example.com"), None);
+        assert_eq!(served(&config, "..data"), None);
+    }
+
+    #[test]
+    fn reloads_modified_certificates() {
+        let dir = CertsDir::new("reload");
+        dir.write("foo.example.com", "ca1.pem", "ca1-key.pem");
+        dir.set_modified("foo.example.com", 1);
+        let config = load(&dir);
+        let key = config.certs.get("foo.example.com").unwrap();
+
+        // Unmodified certificates are not reloaded.
+        config.certs.reload().unwrap();
+        assert!(Arc::ptr_eq(
+            &key,
+            &config.certs.get("foo.example.com").unwrap()
+        ));
+
+        dir.write("foo.example.com", "ca2.pem", "ca2-key.pem");
+        dir.set_modified("foo.example.com", 2);
+        config.certs.reload().unwrap();
+        assert_eq!(served(&config, "foo.example.com"), Some(cert("ca2.pem")));
+
+        // New servers are loaded.
+        dir.write("bar.example.com", "ca1.pem", "ca1-key.pem");
+        config.certs.reload().unwrap();
+        assert_eq!(served(&config, "bar.example.com"), Some(cert("ca1.pem")));
+    }
+
+    #[test]
+    fn keeps_prior_certificate_when_reload_fails() {
+        let dir = CertsDir::new("invalid");
+        dir.write("foo.example.com", "ca1.pem", "ca1-key.pem");
+        dir.set_modified("foo.example.com", 1);
+        let config = load(&dir);
+        let key = config.certs.get("foo.example.com").unwrap();
+
+        fs::write(dir.0.join("foo.example.com").join(KEY_FILE), "invalid").unwrap();
+        dir.set_modified("foo.example.com", 2);
+        config.certs.reload().unwrap();
+        assert!(Arc::ptr_eq(
+            &key,
+            &config.certs.get("foo.example.com").unwrap()
+        ));
+    }
+}
@@ -8,7 +8,7 @@ pub use linkerd_identity::LocalId;
 
 pub use self::{
     client::{Client, ClientTls, ConditionalClientTls, ConnectMeta, NoClientTls, ServerId},
-    server::{ClientId, ConditionalServerTls, NewDetectTls, NoServerTls, ServerTls},
+    server::{ClientId, ConditionalServerTls, NewDetectSni, NewDetectTls, NoServerTls, ServerTls},
 };
 
 #[derive(Clone, Eq, PartialEq, Hash)]
@@ -21,7 +21,7 @@ use tracing::{debug, trace, warn};
 
 /// A newtype for remote client idenities.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
-pub struct ClientId(pub id::Name);
+pub struct ClientId(pub id::Id);
 
 /// Indicates a server-side connection's TLS status.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -73,6 +73,25 @@ pub struct Timeout(pub Duration);
 #[error("TLS detection timed out")]
 pub struct ServerTlsTimeoutError(());
 
+/// Detects the SNI of a TLS ClientHello without terminating TLS.
+///
+/// The ClientHello is peeked when possible; otherwise, it is buffered and the
+/// inner service is provided I/O that replays the buffered bytes. If the client
+/// sends nothing before the timeout elapses (e.g. because it speaks a
+/// server-first protocol), the inner service is built without an SNI.
+#[derive(Clone, Debug)]
+pub struct NewDetectSni<N> {
+    inner: N,
+    timeout: Timeout,
+}
+
+#[derive(Clone, Debug)]
+pub struct DetectSni<T, N> {
+    target: T,
+    timeout: Timeout,
+    inner: N,
+}
+
 #[derive(Clone, Debug)]
 pub struct DetectTls<T, L, P, N> {
     target: T,
@@ -191,6 +210,69 @@ where
     }
 }
 
+// === impl NewDetectSni ===
+
+impl<N> NewDetectSni<N> {
+    pub fn layer(timeout: Timeout) -> impl layer::Layer<N, Service = Self> + Clone {
+        layer::mk(move |inner| Self { inner, timeout })
+    }
+}
+
+impl<T, N: Clone> NewService<T> for NewDetectSni<N> {
+    type Service = DetectSni<T, N>;
+
+    fn new_service(&self, target: T) -> Self::Service {
+        DetectSni {
+            target,
+            timeout: self.timeout,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<I, T, N, NSvc> Service<I> for DetectSni<T, N>
+where
+    I: io::Peek + io::AsyncRead + io::AsyncWrite + Send + Sync + Unpin + 'static,
+    T: Clone + Send + 'static,
+    N: NewService<(Option<ServerId>, T), Service = NSvc> + Clone + Send + 'static,
+    NSvc: Service<DetectIo<I>, Response = ()> + Send + 'static,
+    NSvc::Error: Into<Error>,
+    NSvc::Future: Send,
+{
+    type Response = ();
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, io: I) -> Self::Future {
+        let target = self.target.clone();
+        let inner = self.inner.clone();
+        let Timeout(timeout) = self.timeout;
+        Box::pin(async move {
+            // Clients of server-first protocols won't send anything, so a
+            // timeout is not an error.
+            let mut byte = [0u8; 1];
+            if time::timeout(timeout, io.peek(&mut byte)).await.is_err() {
+                debug!("No data peeked before timeout");
+                let svc = inner.new_service((None, target));
+                return svc.oneshot(EitherIo::Left(io)).err_into::<Error>().await;
+            }
+
+            // Once the client has started writing, it must finish writing its
+            // ClientHello (or whatever it's writing) before the timeout.
+            let (sni, io) = time::timeout(timeout, detect_sni(io))
+                .await
+                .map_err(|_| ServerTlsTimeoutError(()))??;
+
+            let svc = inner.new_service((sni, target));
+            svc.oneshot(io).err_into::<Error>().await
+        })
+    }
+}
+
 /// Peek or buffer the provided stream to determine an SNI value.
 async fn detect_sni<I>(mut io: I) -> io::Result<(Option<ServerId>, DetectIo<I>)>
 where
@@ -250,20 +332,26 @@ where
 
 impl From<id::Name> for ClientId {
     fn from(n: id::Name) -> Self {
-        Self(n)
+        Self(n.into())
     }
 }
 
-impl From<ClientId> for id::Name {
-    fn from(ClientId(name): ClientId) -> id::Name {
-        name
+impl From<id::Id> for ClientId {
+    fn from(id: id::Id) -> Self {
+        Self(id)
+    }
+}
+
+impl From<ClientId> for id::Id {
+    fn from(ClientId(id): ClientId) -> id::Id {
+        id
     }
 }
 
 impl Deref for ClientId {
-    type Target = id::Name;
+    type Target = id::Id;
 
-    fn deref(&self) -> &id::Name {
+    fn deref(&self) -> &id::Id {
         &self.0
     }
 }
@@ -277,7 +365,7 @@ impl fmt::Display for ClientId {
 impl FromStr for ClientId {
     type Err = id::InvalidName;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        id::Name::from_str(s).map(Self)
+        id::Id::from_str(s).map(Self)
     }
 }
 
@@ -339,6 +427,46 @@ mod tests {
 
         client_task.await.expect("Client must not fail");
     }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn detect_sni_buffers_unpeekable_client_hello() {
+        let _trace = linkerd_tracing::test::trace_init();
+
+        // Duplex streams can't be peeked, so the ClientHello must be buffered.
+        let (mut client_io, server_io) = linkerd_io::duplex(1024);
+        let input = include_bytes!("server/testdata/curl-example-com-client-hello.bin");
+        let client_task = tokio::spawn(async move {
+            client_io
+                .write_all(&*input)
+                .await
+                .expect("Write must succeed");
+        });
+
+        let new_svc = move |(sni, ()): (Option<ServerId>, ())| {
+            let identity = id::Name::from_str("example.com").unwrap();
+            assert_eq!(sni, Some(ServerId(identity)));
+            linkerd_stack::service_fn(move |io: DetectIo<linkerd_io::DuplexStream>| {
+                match io {
+                    EitherIo::Left(_) => panic!("Detected IO should be buffered"),
+                    EitherIo::Right(io) => {
+                        assert_eq!(io.prefix().len(), input.len(), "All data must be buffered")
+                    }
+                }
+                future::ok::<_, Error>(())
+            })
+        };
+        let detect = NewDetectSni {
+            inner: new_svc,
+            timeout: Timeout(Duration::from_secs(10)),
+        };
+        detect
+            .new_service(())
+            .oneshot(server_io)
+            .await
+            .expect("SNI detection must not fail");
+
+        client_task.await.expect("Client must not fail");
+    }
 }
 
 #[cfg(fuzzing)]
@@ -22,6 +22,15 @@ pub static FOO_NS1: Entity = Entity {
     key: include_bytes!("testdata/foo-ns1-ca1/key.p8"),
 };
 
+/// Like `FOO_NS1`, but its certificate also has the URI SAN
+/// `spiffe://cluster.local/ns/ns1/sa/foo`.
+pub static FOO_NS1_SPIFFE: Entity = Entity {
+    name: "foo.ns1.serviceaccount.identity.linkerd.cluster.local",
+    trust_anchors: include_bytes!("testdata/ca1.pem"),
+    crt: include_bytes!("testdata/foo-ns1-spiffe-ca1/crt.der"),
+    key: include_bytes!("testdata/foo-ns1-spiffe-ca1/key.p8"),
+};
+
 pub static FOO_NS1_CA2: Entity = Entity {
     name: "foo.ns1.serviceaccount.identity.linkerd.cluster.local",
     trust_anchors: include_bytes!("testdata/ca2.pem"),
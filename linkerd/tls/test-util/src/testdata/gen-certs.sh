@@ -48,6 +48,39 @@ ee() {
   mv "${ee}.csr" "${ee}/csr.pem"
 }
 
+# Like `ee`, but the certificate also includes a SPIFFE ID as a URI SAN.
+ee_spiffe() {
+  ca_name=$1
+  ee_name=$2
+  ee_ns=$3
+  cp_ns=$4
+
+  hostname="${ee_name}.${ee_ns}.serviceaccount.identity.${cp_ns}.cluster.local"
+  spiffe_id="spiffe://cluster.local/ns/${ee_ns}/sa/${ee_name}"
+
+  ee="${ee_name}-${ee_ns}-spiffe-${ca_name}"
+  mkdir -p "${ee}"
+
+  openssl ecparam -name prime256v1 -genkey -noout -out "${ee}-key.pem"
+  openssl req -new -key "${ee}-key.pem" -subj "/CN=${hostname}" -out "${ee}/csr.pem"
+  printf '%s\n' \
+    'keyUsage = critical, digitalSignature, keyEncipherment' \
+    'extendedKeyUsage = serverAuth, clientAuth' \
+    'basicConstraints = critical, CA:FALSE' \
+    "subjectAltName = critical, URI:${spiffe_id}, DNS:${hostname}" \
+    > "${ee}.ext"
+  openssl x509 -req -days 3650 -sha256 \
+    -CA "${ca_name}.pem" -CAkey "${ca_name}-key.pem" -CAcreateserial \
+    -in "${ee}/csr.pem" -extfile "${ee}.ext" \
+    -outform der -out "${ee}/crt.der"
+  rm "${ee}.ext" "${ca_name}.srl"
+
+  openssl pkcs8 -topk8 -nocrypt -inform pem -outform der \
+    -in "${ee}-key.pem" \
+    -out "${ee}/key.p8"
+  rm "${ee}-key.pem"
+}
+
 ca 'Cluster-local CA 1' ca1
 ca 'Cluster-local CA 1' ca2 # Same name, different key pair.
 
@@ -58,3 +91,4 @@ ee ca1 default default linkerd
 ee ca1 foo ns1 linkerd
 ee ca2 foo ns1 linkerd # Same, but different CA
 ee ca1 bar ns1 linkerd # Different service.
+ee_spiffe ca1 foo ns1 linkerd # Same, but with a SPIFFE ID.
@@ -11,7 +11,10 @@ compile_error!(
 );
 
 use linkerd_app::{
-    core::{telemetry::StartTime, transport::BindTcp},
+    core::{
+        telemetry::StartTime,
+        transport::{BindTcp, BindUnixOrTcp, BindWithProxyProtocol, UnixListenPath},
+    },
     trace, Config,
 };
 use linkerd_signal as signal;
@@ -52,12 +55,14 @@ fn main() {
         let (shutdown_tx, mut shutdown_rx) = mpsc::unbounded_channel();
         let shutdown_grace_period = config.shutdown_grace_period;
 
-        let bind = BindTcp::with_orig_dst();
+        // Inbound and outbound listeners read PROXY protocol headers from
+        // trusted networks, when configured.
+        let bind = BindWithProxyProtocol::from(BindTcp::with_orig_dst());
         let app = match config
             .build(
                 bind,
                 bind,
-                BindTcp::default(),
+                BindUnixOrTcp::default(),
                 shutdown_tx,
                 trace,
                 start_time,
@@ -71,7 +76,10 @@ fn main() {
             }
         };
 
-        info!("Admin interface on {}", app.admin_addr());
+        match app.admin_listen_path() {
+            Some(UnixListenPath(path)) => info!("Admin interface on {}", path.display()),
+            None => info!("Admin interface on {}", app.admin_addr()),
+        }
         info!("Inbound interface on {}", app.inbound_addr());
         info!("Outbound interface on {}", app.outbound_addr());
 